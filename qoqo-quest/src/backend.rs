@@ -10,21 +10,34 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{MeasurementStreamWrapper, SessionWrapper};
 use bincode::{deserialize, serialize};
+use numpy::ndarray::Array2;
+use numpy::{Complex64, IntoPyArray, PyArray2};
 use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyByteArray, PyType};
-use qoqo::convert_into_circuit;
+use pyo3::types::{PyByteArray, PyBytes, PyDict, PyType};
+use qoqo::measurements::PauliZProductWrapper;
+use qoqo::operations::convert_pyany_to_operation;
 use qoqo::QoqoBackendError;
+use qoqo::{convert_into_circuit, convert_into_quantum_program};
 use roqoqo::backends::EvaluatingBackend;
 use roqoqo::registers::{BitOutputRegister, ComplexOutputRegister, FloatOutputRegister};
-use roqoqo::Circuit;
-use std::collections::HashMap;
+use roqoqo::RoqoqoBackendError;
+use roqoqo::{Circuit, QuantumProgram};
+use roqoqo_quest::{circuit_statistics, circuit_to_qasm, ignored_circuit_warnings};
+use roqoqo_quest::{OutputRegisters, ReadoutModel};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 /// QuEST backend
 ///
 /// provides functions to run circuits and measurements on with the QuEST quantum simulator.
-#[pyclass(name = "Backend", module = "qoqo_quest")]
+#[pyclass(
+    name = "Backend",
+    module = "qoqo_quest",
+    text_signature = "(number_qubits)"
+)]
 #[derive(Clone, Debug, PartialEq)]
 pub struct BackendWrapper {
     /// Internal storage of [roqoqo_quest::Backend]
@@ -32,10 +45,13 @@ pub struct BackendWrapper {
 }
 
 /// Type of registers returned from a run of a Circuit.
+///
+/// The registers are sorted by name so that the returned dictionaries
+/// have the same key order in every run.
 pub type Registers = (
-    HashMap<String, BitOutputRegister>,
-    HashMap<String, FloatOutputRegister>,
-    HashMap<String, ComplexOutputRegister>,
+    BTreeMap<String, BitOutputRegister>,
+    BTreeMap<String, FloatOutputRegister>,
+    BTreeMap<String, ComplexOutputRegister>,
 );
 
 #[pymethods]
@@ -58,6 +74,7 @@ impl BackendWrapper {
     ///
     /// Returns:
     ///     Backend: A deep copy of self.
+    #[pyo3(text_signature = "($self)")]
     pub fn __copy__(&self) -> BackendWrapper {
         self.clone()
     }
@@ -66,6 +83,7 @@ impl BackendWrapper {
     ///
     /// Returns:
     ///     Backend: A deep copy of self.
+    #[pyo3(text_signature = "($self, memodict)")]
     pub fn __deepcopy__(&self, _memodict: Py<PyAny>) -> BackendWrapper {
         self.clone()
     }
@@ -77,8 +95,9 @@ impl BackendWrapper {
     ///
     /// Raises:
     ///     ValueError: Cannot serialize Backend to bytes.
+    #[pyo3(text_signature = "($self)")]
     pub fn to_bincode(&self) -> PyResult<Py<PyByteArray>> {
-        let serialized = serialize(&self.internal)
+        let serialized = serialize_backend(&self.internal)
             .map_err(|_| PyValueError::new_err("Cannot serialize Backend to bytes"))?;
         let b: Py<PyByteArray> = Python::with_gil(|py| -> Py<PyByteArray> {
             PyByteArray::new(py, &serialized[..]).into()
@@ -96,16 +115,89 @@ impl BackendWrapper {
     ///
     /// Raises:
     ///     TypeError: Input cannot be converted to byte array.
-    ///     ValueError: Input cannot be deserialized to Backend.
+    ///     ValueError: Input was serialized with an incompatible version or cannot be deserialized to Backend.
     #[classmethod]
+    #[pyo3(text_signature = "($cls, input)")]
     pub fn from_bincode(_cls: &PyType, input: &PyAny) -> PyResult<BackendWrapper> {
         let bytes = input
             .extract::<Vec<u8>>()
             .map_err(|_| PyTypeError::new_err("Input cannot be converted to byte array"))?;
 
         Ok(BackendWrapper {
-            internal: deserialize(&bytes[..])
-                .map_err(|_| PyValueError::new_err("Input cannot be deserialized to Backend"))?,
+            internal: deserialize_backend(&bytes[..]).map_err(PyValueError::new_err)?,
+        })
+    }
+
+    /// Return the bincode representation of the Backend used to pass it between separately compiled packages.
+    ///
+    /// Returns:
+    ///     ByteArray: The serialized Backend (in [bincode] form).
+    ///
+    /// Raises:
+    ///     ValueError: Cannot serialize Backend to bytes.
+    #[pyo3(text_signature = "($self)")]
+    pub fn _enum_to_bincode(&self) -> PyResult<Py<PyByteArray>> {
+        self.to_bincode()
+    }
+
+    /// Create a Backend that starts each simulation in the equal superposition state |+...+>.
+    ///
+    /// Circuits run on this Backend do not need to start with a Hadamard gate on every qubit.
+    ///
+    /// Args:
+    ///     number_qubits (int): The number of qubits supported by the backend.
+    ///
+    /// Returns:
+    ///     Backend: The Backend starting in the plus state.
+    #[classmethod]
+    #[pyo3(text_signature = "($cls, number_qubits)")]
+    pub fn new_plus(_cls: &PyType, number_qubits: usize) -> BackendWrapper {
+        BackendWrapper {
+            internal: roqoqo_quest::Backend::new_plus(number_qubits),
+        }
+    }
+
+    /// Create a Backend from configuration options.
+    ///
+    /// Options that are not given keep the defaults of the Backend constructor.
+    ///
+    /// Args:
+    ///     number_qubits (int): The number of qubits supported by the backend.
+    ///     repetitions (Optional[int]): The number of repetitions of stochastic simulations.
+    ///     random_seed (Optional[List[int]]): The seed for the random number generators.
+    ///     imperfect_readout_model (Optional[Dict[int, List[List[float]]]]): The 2x2 readout confusion matrix of each qubit,
+    ///                                                                       entry [measured][detected].
+    ///
+    /// Returns:
+    ///     Backend: The configured Backend.
+    ///
+    /// Raises:
+    ///     ValueError: Invalid configuration.
+    #[classmethod]
+    #[pyo3(
+        text_signature = "($cls, number_qubits, repetitions=None, random_seed=None, imperfect_readout_model=None)"
+    )]
+    pub fn from_config(
+        _cls: &PyType,
+        number_qubits: usize,
+        repetitions: Option<usize>,
+        random_seed: Option<Vec<u64>>,
+        imperfect_readout_model: Option<ReadoutModel>,
+    ) -> PyResult<BackendWrapper> {
+        let mut builder = roqoqo_quest::Backend::builder().number_qubits(number_qubits);
+        if let Some(repetitions) = repetitions {
+            builder = builder.repetitions(repetitions);
+        }
+        if let Some(random_seed) = random_seed {
+            builder = builder.random_seed(random_seed);
+        }
+        if let Some(readout_model) = imperfect_readout_model {
+            builder = builder.imperfect_readout_model(readout_model);
+        }
+        Ok(BackendWrapper {
+            internal: builder
+                .build()
+                .map_err(|err| PyValueError::new_err(format!("Invalid configuration {:?}", err)))?,
         })
     }
 
@@ -116,6 +208,7 @@ impl BackendWrapper {
     ///
     /// Raises:
     ///     ValueError: Cannot serialize Backend to json.
+    #[pyo3(text_signature = "($self)")]
     fn to_json(&self) -> PyResult<String> {
         let serialized = serde_json::to_string(&self.internal)
             .map_err(|_| PyValueError::new_err("Cannot serialize Backend to json"))?;
@@ -133,6 +226,7 @@ impl BackendWrapper {
     /// Raises:
     ///     ValueError: Input cannot be deserialized to Backend.
     #[classmethod]
+    #[pyo3(text_signature = "($cls, input)")]
     fn from_json(_cls: &PyType, input: &str) -> PyResult<BackendWrapper> {
         Ok(BackendWrapper {
             internal: serde_json::from_str(input)
@@ -140,6 +234,129 @@ impl BackendWrapper {
         })
     }
 
+    /// Return a copy of the Backend with an override for the number of measurements.
+    ///
+    /// When set, the number of measurements of every PragmaRepeatedMeasurement and
+    /// PragmaSetNumberOfMeasurements is replaced by the override when running a circuit.
+    /// The circuit itself is not changed.
+    ///
+    /// Args:
+    ///     measurement_override (Optional[int]): The number of measurements, None to use the numbers in the circuits.
+    ///
+    /// Returns:
+    ///     Backend: The Backend with the measurement override.
+    #[pyo3(text_signature = "($self, measurement_override=None)")]
+    pub fn with_measurement_override(&self, measurement_override: Option<usize>) -> BackendWrapper {
+        BackendWrapper {
+            internal: self
+                .internal
+                .clone()
+                .with_measurement_override(measurement_override),
+        }
+    }
+
+    /// Return a copy of the Backend that starts simulations in a classical basis state.
+    ///
+    /// The quantum register is initialized to the basis state with the given index,
+    /// the n-th bit of the index being the state of the n-th qubit.
+    ///
+    /// Args:
+    ///     initial_state (Optional[int]): The index of the initial basis state, None to start in the all-zero state.
+    ///
+    /// Returns:
+    ///     Backend: The Backend with the initial state.
+    #[pyo3(text_signature = "($self, initial_state=None)")]
+    pub fn with_initial_state(&self, initial_state: Option<usize>) -> BackendWrapper {
+        BackendWrapper {
+            internal: self.internal.clone().with_initial_state(initial_state),
+        }
+    }
+
+    /// Set the seed for the random number generators used in the simulation.
+    ///
+    /// Running the same circuit with the same seed reproduces all repetitions.
+    /// A single integer is equivalent to a list containing only that integer.
+    ///
+    /// Args:
+    ///     random_seed (Union[int, List[int]]): The seed for the random number generators.
+    ///
+    /// Raises:
+    ///     TypeError: The seed is neither a non-negative integer nor a list of non-negative integers.
+    #[pyo3(text_signature = "($self, random_seed)")]
+    pub fn set_random_seed(&mut self, random_seed: &PyAny) -> PyResult<()> {
+        self.internal = if let Ok(seed) = random_seed.extract::<u64>() {
+            self.internal.clone().set_random_seed_single(seed)
+        } else {
+            let seed: Vec<u64> = random_seed.extract().map_err(|_| {
+                PyTypeError::new_err(
+                    "Random seed must be a non-negative integer or a list of non-negative integers",
+                )
+            })?;
+            self.internal.clone().set_random_seed(seed)
+        };
+        Ok(())
+    }
+
+    /// Set the density matrix every simulation of the Backend starts in.
+    ///
+    /// The density matrix is loaded at the start of each run before the circuit is applied,
+    /// so a mixed initial state can be reused for many circuits.
+    /// Setting an initial density matrix forces a density matrix simulation.
+    ///
+    /// Args:
+    ///     matrix (Optional[np.ndarray]): The 2^N x 2^N initial density matrix, None to start in the configured pure state.
+    ///
+    /// Raises:
+    ///     ValueError: The matrix is not square or does not match the number of qubits of the Backend.
+    #[pyo3(text_signature = "($self, matrix)")]
+    pub fn set_initial_density_matrix(
+        &mut self,
+        matrix: Option<Vec<Vec<Complex64>>>,
+    ) -> PyResult<()> {
+        let initial_density_matrix = match matrix {
+            Some(rows) => Some(square_matrix(rows).ok_or_else(|| {
+                PyValueError::new_err("Initial density matrix must be a square matrix")
+            })?),
+            None => None,
+        };
+        self.internal = self
+            .internal
+            .clone()
+            .set_initial_density_matrix(initial_density_matrix)
+            .map_err(|err| PyValueError::new_err(format!("{:?}", err)))?;
+        Ok(())
+    }
+
+    /// Set the number of OpenMP threads QuEST uses in simulations of the Backend.
+    ///
+    /// By default QuEST uses the number of threads of the OMP_NUM_THREADS environment variable.
+    /// The number of threads is applied before the QuEST environment and quantum register
+    /// of each simulation are created.
+    ///
+    /// Args:
+    ///     number_threads (int): The number of OpenMP threads.
+    ///
+    /// Raises:
+    ///     ValueError: The number of threads is zero, or larger than one when QuEST is not multithreaded.
+    #[pyo3(text_signature = "($self, number_threads)")]
+    pub fn set_num_threads(&mut self, number_threads: usize) -> PyResult<()> {
+        self.internal = self
+            .internal
+            .clone()
+            .set_num_threads(number_threads)
+            .map_err(|err| PyValueError::new_err(format!("{:?}", err)))?;
+        Ok(())
+    }
+
+    /// Return the number of OpenMP threads QuEST uses in simulations of the Backend.
+    ///
+    /// Returns:
+    ///     int: The number of threads set on the Backend or the OpenMP default.
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_num_threads(&self) -> usize {
+        self.internal.get_num_threads()
+    }
+
     /// Run a circuit with the QuEST backend.
     ///
     /// A circuit is passed to the backend and executed.
@@ -150,7 +367,8 @@ impl BackendWrapper {
     /// The results of each repetition are concatenated in OutputRegisters
     /// (List[List[bool]], List[List[float]], List[List[complex]]).
     /// As a simulater Backend the QuEST backend also allows to direclty read out
-    /// the statevector, density matrix or the expectation values of products of PauliOperators
+    /// the statevector, density matrix or the expectation values of products of PauliOperators.
+    /// The registers of each type are returned sorted by name, so the key order is the same in every run.
     ///
     ///
     /// Args:
@@ -162,145 +380,884 @@ impl BackendWrapper {
     /// Raises:
     ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
     ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit)")]
     pub fn run_circuit(&self, circuit: &PyAny) -> PyResult<Registers> {
+        let py = circuit.py();
         let circuit = convert_into_circuit(circuit).map_err(|err| {
             PyTypeError::new_err(format!(
                 "Circuit argument cannot be converted to qoqo Circuit {:?}",
                 err
             ))
         })?;
-        EvaluatingBackend::run_circuit(&self.internal, &circuit)
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| EvaluatingBackend::run_circuit(&self.internal, &circuit))
+            .map(sorted_registers)
             .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
     }
 
-    /// Run all circuits corresponding to one measurement with the QuEST backend.
-    ///
-    /// An expectation value measurement in general involves several circuits.
-    /// Each circuit is passes to the backend and executed separately.
-    /// A circuit is passed to the backend and executed.
-    /// During execution values are written to and read from classical registers
-    /// (List[bool], List[float], List[complex]).
-    /// To produce sufficient statistics for evaluating expectation values,
-    /// circuits have to be run multiple times.
-    /// The results of each repetition are concatenated in OutputRegisters
-    /// (List[List[bool]], List[List[float]], List[List[complex]]).  
+    /// Run a circuit with the QuEST backend and return the output registers and the timing of the run.
     ///
+    /// The run is identical to run_circuit. The timing dictionary contains the wall-clock times
+    /// in seconds of the whole run (`total`), of applying gate operations (`gate_operations`)
+    /// and of measuring and sampling measurement outcomes (`measurements`),
+    /// summed over all repetitions of the circuit.
     ///
     /// Args:
-    ///     measurement (Measurement): The measurement that is run on the backend.
+    ///     circuit (Circuit): The circuit that is run on the backend.
     ///
     /// Returns:
-    ///     Tuple[Dict[str, List[List[bool]]], Dict[str, List[List[float]]]], Dict[str, List[List[complex]]]]: The output registers written by the evaluated circuits.
+    ///     Tuple[Tuple[Dict[str, List[List[bool]]], Dict[str, List[List[float]]]], Dict[str, List[List[complex]]]], Dict[str, float]]: The output registers and the timing of the run.
     ///
     /// Raises:
-    ///     TypeError: Cannot extract constant circuit from measurement
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
     ///     RuntimeError: Running Circuit failed
-    pub fn run_measurement_registers(&self, measurement: &PyAny) -> PyResult<Registers> {
-        let mut run_circuits: Vec<Circuit> = Vec::new();
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn run_circuit_timed(
+        &self,
+        circuit: &PyAny,
+    ) -> PyResult<(Registers, HashMap<String, f64>)> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        let (registers, timing) = py
+            .allow_threads(|| self.internal.run_circuit_timed(&circuit))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))?;
+        let timing_seconds: HashMap<String, f64> = [
+            ("total", timing.total),
+            ("gate_operations", timing.gate_operations),
+            ("measurements", timing.measurements),
+        ]
+        .into_iter()
+        .map(|(name, duration)| (name.to_string(), duration.as_secs_f64()))
+        .collect();
+        Ok((sorted_registers(registers), timing_seconds))
+    }
 
-        let get_constant_circuit = measurement
-            .call_method0("constant_circuit")
-            .map_err(|err| {
-                PyTypeError::new_err(format!(
-                    "Cannot extract constant circuit from measurement {:?}",
-                    err
-                ))
-            })?;
-        let const_circuit = get_constant_circuit
-            .extract::<Option<&PyAny>>()
-            .map_err(|err| {
-                PyTypeError::new_err(format!(
-                    "Cannot extract constant circuit from measurement {:?}",
-                    err
-                ))
-            })?;
+    /// Run a circuit with the QuEST backend and return the output registers as a JSON string.
+    ///
+    /// The JSON string is an array of the bit, float and complex output registers,
+    /// each a map from the register name to the list of results of the repetitions.
+    /// Complex numbers are encoded as [real, imaginary] arrays.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     str: The output registers written by the evaluated circuit in JSON form.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    ///     ValueError: Cannot serialize output registers to json.
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn run_circuit_json(&self, circuit: &PyAny) -> PyResult<String> {
+        let (bit_registers, float_registers, complex_registers) = self.run_circuit(circuit)?;
+        let complex_registers: BTreeMap<String, Vec<Vec<[f64; 2]>>> = complex_registers
+            .into_iter()
+            .map(|(name, register)| {
+                let encoded = register
+                    .iter()
+                    .map(|values| values.iter().map(|value| [value.re, value.im]).collect())
+                    .collect();
+                (name, encoded)
+            })
+            .collect();
+        serde_json::to_string(&(bit_registers, float_registers, complex_registers))
+            .map_err(|_| PyValueError::new_err("Cannot serialize output registers to json"))
+    }
 
-        let constant_circuit = match const_circuit {
-            Some(x) => convert_into_circuit(x).map_err(|err| {
-                PyTypeError::new_err(format!(
-                    "Cannot extract constant circuit from measurement {:?}",
-                    err
-                ))
-            })?,
-            None => Circuit::new(),
-        };
+    /// Run a circuit with the QuEST backend and return the complex output registers as raw buffers.
+    ///
+    /// Each repetition of a complex register is returned as a bytes object containing the
+    /// real and imaginary parts of all entries as interleaved 64-bit floats in native byte order.
+    /// No Python object is created per entry, so large state vectors can be read quickly and
+    /// viewed without copying with np.frombuffer(buffer, dtype=np.complex128).
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     Dict[str, List[bytes]]: The buffers of the complex output registers for each repetition.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn run_circuit_complex_buffers(
+        &self,
+        circuit: &PyAny,
+    ) -> PyResult<BTreeMap<String, Vec<Py<PyBytes>>>> {
+        let py = circuit.py();
+        let (_, _, complex_registers) = self.run_circuit(circuit)?;
+        Ok(complex_registers
+            .into_iter()
+            .map(|(name, register)| {
+                let buffers = register
+                    .iter()
+                    .map(|values| {
+                        let interleaved: Vec<u8> = values
+                            .iter()
+                            .flat_map(|value| [value.re.to_ne_bytes(), value.im.to_ne_bytes()])
+                            .flatten()
+                            .collect();
+                        PyBytes::new(py, &interleaved).into()
+                    })
+                    .collect();
+                (name, buffers)
+            })
+            .collect())
+    }
 
-        let get_circuit_list = measurement.call_method0("circuits").map_err(|err| {
+    /// Run a circuit with the QuEST backend and return the density matrix of the final state.
+    ///
+    /// The density matrix is returned as a 2^N x 2^N numpy array
+    /// where the row index corresponds to the ket and the column index to the bra.
+    /// When the simulation is repeated (stochastic unravelling or overrotations),
+    /// the density matrices of all repetitions are averaged.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     np.ndarray: The density matrix of the final state.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn density_matrix(&self, circuit: &PyAny) -> PyResult<Py<PyArray2<Complex64>>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
             PyTypeError::new_err(format!(
-                "Cannot extract circuit list from measurement {:?}",
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
                 err
             ))
         })?;
-        let circuit_list = get_circuit_list.extract::<Vec<&PyAny>>().map_err(|err| {
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        let density_matrix = py
+            .allow_threads(|| self.internal.density_matrix(&circuit))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))?;
+        Ok(density_matrix.into_pyarray(py).to_owned())
+    }
+
+    /// Return True if the Backend can simulate the operation.
+    ///
+    /// Operations the Backend ignores without an error are supported.
+    ///
+    /// Args:
+    ///     operation (Operation): The operation that is checked.
+    ///
+    /// Returns:
+    ///     bool: Whether the operation is supported.
+    ///
+    /// Raises:
+    ///     TypeError: Operation argument cannot be converted to qoqo Operation
+    #[pyo3(text_signature = "($self, operation)")]
+    pub fn supports_operation(&self, operation: &PyAny) -> PyResult<bool> {
+        let operation = convert_pyany_to_operation(operation).map_err(|err| {
             PyTypeError::new_err(format!(
-                "Cannot extract circuit list from measurement {:?}",
+                "Operation argument cannot be converted to qoqo Operation {:?}",
                 err
             ))
         })?;
+        Ok(self.internal.supports_operation(&operation))
+    }
 
-        for c in circuit_list {
-            run_circuits.push(
-                constant_circuit.clone()
-                    + convert_into_circuit(c).map_err(|err| {
-                        PyTypeError::new_err(format!(
-                            "Cannot extract circuit of circuit list from measurement {:?}",
-                            err
-                        ))
-                    })?,
-            )
-        }
-
-        let mut bit_registers: HashMap<String, BitOutputRegister> = HashMap::new();
-        let mut float_registers: HashMap<String, FloatOutputRegister> = HashMap::new();
-        let mut complex_registers: HashMap<String, ComplexOutputRegister> = HashMap::new();
+    /// Run a circuit and return how often each bitstring was measured in each bit output register.
+    ///
+    /// Circuits that prepare their state deterministically and end with a PragmaRepeatedMeasurement
+    /// are counted while sampling, without storing every shot.
+    /// The n-th character of a bitstring is the n-th bit of the register, "1" for True and "0" for False.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     Dict[str, Dict[str, int]]: For each bit output register the number of times each bitstring was measured.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn run_circuit_counts(
+        &self,
+        circuit: &PyAny,
+    ) -> PyResult<HashMap<String, HashMap<String, usize>>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        let counts = py
+            .allow_threads(|| self.internal.run_circuit_counts(&circuit))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))?;
+        Ok(bitstring_counts(counts))
+    }
 
-        for circuit in run_circuits {
-            let (tmp_bit_reg, tmp_float_reg, tmp_complex_reg) = self
-                .internal
-                .run_circuit_iterator(circuit.iter())
-                .map_err(|err| {
-                    PyRuntimeError::new_err(format!("Running a circuit failed {:?}", err))
-                })?;
+    /// Run a circuit once and return the measured counts together with the probabilities of the final state.
+    ///
+    /// The shots of the final PragmaRepeatedMeasurement are sampled from the same simulated state
+    /// whose probabilities are returned. The circuit must prepare its state deterministically
+    /// and end with a PragmaRepeatedMeasurement.
+    /// The n-th character of a bitstring is the n-th bit of the register, "1" for True and "0" for False.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     Tuple[Dict[str, Dict[str, int]], List[float]]: The counts of the measured bitstrings in the readout register and the probabilities of all basis states.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn run_circuit_counts_and_probabilities(
+        &self,
+        circuit: &PyAny,
+    ) -> PyResult<(BitstringCounts, Vec<f64>)> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        let (counts, probabilities) = py
+            .allow_threads(|| self.internal.run_circuit_counts_and_probabilities(&circuit))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))?;
+        Ok((bitstring_counts(counts), probabilities))
+    }
 
-            for (key, mut val) in tmp_bit_reg.into_iter() {
-                if let Some(x) = bit_registers.get_mut(&key) {
-                    x.append(&mut val);
-                } else {
-                    let _ = bit_registers.insert(key, val);
-                }
-            }
-            for (key, mut val) in tmp_float_reg.into_iter() {
-                if let Some(x) = float_registers.get_mut(&key) {
-                    x.append(&mut val);
-                } else {
-                    let _ = float_registers.insert(key, val);
-                }
-            }
-            for (key, mut val) in tmp_complex_reg.into_iter() {
-                if let Some(x) = complex_registers.get_mut(&key) {
-                    x.append(&mut val);
-                } else {
-                    let _ = complex_registers.insert(key, val);
-                }
-            }
-        }
-        Ok((bit_registers, float_registers, complex_registers))
+    /// Run a circuit once and return a generator of measured shots of all qubits.
+    ///
+    /// The shots are sampled lazily from the final state of the circuit,
+    /// so large numbers of shots can be aggregated without storing them.
+    /// The n-th entry of each shot is the measured value of the n-th qubit.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit preparing the measured state.
+    ///     number_measurements (int): The number of shots yielded by the generator.
+    ///
+    /// Returns:
+    ///     MeasurementStream: The iterator yielding each shot as a List[bool].
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, number_measurements)")]
+    pub fn measure_stream(
+        &self,
+        circuit: &PyAny,
+        number_measurements: usize,
+    ) -> PyResult<MeasurementStreamWrapper> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        let stream = self
+            .internal
+            .measure_stream(&circuit)
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))?;
+        Ok(MeasurementStreamWrapper::new(stream, number_measurements))
     }
 
-    /// Evaluates expectation values of a measurement with the backend.
+    /// Run two circuits and return the inner product <psi_a|psi_b> of their final states.
     ///
+    /// Both circuits are simulated as state vectors,
+    /// circuits that require a density matrix simulation are not supported.
     ///
     /// Args:
-    ///     measurement (Measurement): The measurement that is run on the backend.
+    ///     circuit_a (Circuit): The circuit preparing the bra state.
+    ///     circuit_b (Circuit): The circuit preparing the ket state.
     ///
     /// Returns:
-    ///     Optional[Dict[str, float]]: The  dictionary of expectation values.
+    ///     complex: The inner product of the two final states.
     ///
     /// Raises:
-    ///     TypeError: Measurement evaluate function could not be used
-    ///     RuntimeError: Internal error measurement.evaluation returned unknown type
-    pub fn run_measurement(&self, measurement: &PyAny) -> PyResult<Option<HashMap<String, f64>>> {
-        let (bit_registers, float_registers, complex_registers) =
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit_a, circuit_b)")]
+    pub fn inner_product(&self, circuit_a: &PyAny, circuit_b: &PyAny) -> PyResult<Complex64> {
+        let py = circuit_a.py();
+        let circuit_a = convert_into_circuit(circuit_a).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let circuit_b = convert_into_circuit(circuit_b).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit_a)?;
+        warn_ignored_circuit_parameters(py, &circuit_b)?;
+        py.allow_threads(|| self.internal.inner_product(&circuit_a, &circuit_b))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run two circuits and return the squared Hilbert-Schmidt distance of their final states.
+    ///
+    /// The distance Tr[(rho_a - rho_b)^2] is calculated by QuEST on density matrices,
+    /// it is zero for identical states and two for orthogonal pure states.
+    ///
+    /// Args:
+    ///     circuit_a (Circuit): The first circuit that is run on the backend.
+    ///     circuit_b (Circuit): The second circuit that is run on the backend.
+    ///
+    /// Returns:
+    ///     float: The squared Hilbert-Schmidt distance of the two final states.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit_a, circuit_b)")]
+    pub fn hilbert_schmidt_distance(&self, circuit_a: &PyAny, circuit_b: &PyAny) -> PyResult<f64> {
+        let py = circuit_a.py();
+        let circuit_a = convert_into_circuit(circuit_a).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let circuit_b = convert_into_circuit(circuit_b).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit_a)?;
+        warn_ignored_circuit_parameters(py, &circuit_b)?;
+        py.allow_threads(|| {
+            self.internal
+                .hilbert_schmidt_distance(&circuit_a, &circuit_b)
+        })
+        .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit and return the second Rényi entropy of a subsystem of its final state.
+    ///
+    /// The entropy -ln Tr(rho_A^2) is calculated from the reduced density matrix of the subsystem,
+    /// which is the quantity estimated by a swap test on two copies of the state.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     qubits (List[int]): The qubits of the subsystem.
+    ///
+    /// Returns:
+    ///     float: The second Rényi entropy of the subsystem.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, qubits)")]
+    pub fn renyi_2_entropy(&self, circuit: &PyAny, qubits: Vec<usize>) -> PyResult<f64> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| self.internal.renyi_2_entropy(&circuit, &qubits))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit several times and return the final state vector of each run.
+    ///
+    /// Each run is an independent stochastic trajectory of the circuit, for example
+    /// PragmaRandomNoise applies different Pauli flips and measurements collapse the state differently in each run.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     number_trajectories (int): The number of runs of the circuit.
+    ///
+    /// Returns:
+    ///     List[List[complex]]: The amplitudes of the final state of each trajectory.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, number_trajectories)")]
+    pub fn run_circuit_trajectories(
+        &self,
+        circuit: &PyAny,
+        number_trajectories: usize,
+    ) -> PyResult<Vec<Vec<Complex64>>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| {
+            self.internal
+                .run_circuit_trajectories(&circuit, number_trajectories)
+        })
+        .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit and return the non-zero amplitudes of the final state vector.
+    ///
+    /// Only amplitudes with a magnitude above the threshold are returned,
+    /// the dense state vector is never copied.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     threshold (float): Amplitudes with a magnitude not above the threshold are skipped.
+    ///
+    /// Returns:
+    ///     List[Tuple[int, complex]]: The basis state indices and amplitudes above the threshold.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, threshold)")]
+    pub fn sparse_statevector(
+        &self,
+        circuit: &PyAny,
+        threshold: f64,
+    ) -> PyResult<Vec<(usize, Complex64)>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| self.internal.sparse_state_vector(&circuit, threshold))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit, apply a diagonal phase operator and return the final state vector.
+    ///
+    /// Each basis state of the final state of the circuit is multiplied with exp(i * phases[k]).
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     phases (List[float]): The phases of all 2^N basis states.
+    ///
+    /// Returns:
+    ///     List[complex]: The state vector after the diagonal operator.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, phases)")]
+    pub fn apply_diagonal(&self, circuit: &PyAny, phases: Vec<f64>) -> PyResult<Vec<Complex64>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| self.internal.apply_diagonal(&circuit, &phases))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit, apply a custom unitary matrix and return the final state vector.
+    ///
+    /// The unitary is applied to the final state of the circuit, so custom gates can be tried out
+    /// without defining a qoqo operation. The n-th qubit corresponds to the n-th bit of the matrix indices.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     qubits (List[int]): The one or two qubits the unitary acts on.
+    ///     matrix (np.ndarray): The 2x2 or 4x4 complex unitary matrix.
+    ///
+    /// Returns:
+    ///     List[complex]: The state vector after the unitary.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     ValueError: Matrix is not a square matrix
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, qubits, matrix)")]
+    pub fn apply_unitary(
+        &self,
+        circuit: &PyAny,
+        qubits: Vec<usize>,
+        matrix: Vec<Vec<Complex64>>,
+    ) -> PyResult<Vec<Complex64>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let unitary = square_matrix(matrix)
+            .ok_or_else(|| PyValueError::new_err("Matrix is not a square matrix"))?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| self.internal.apply_unitary(&circuit, &qubits, &unitary))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Run a circuit and return the expectation value of a real diagonal observable in its final state.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     diagonal (List[float]): The real diagonal entries of the observable for all 2^N basis states.
+    ///
+    /// Returns:
+    ///     float: The expectation value of the observable.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, diagonal)")]
+    pub fn expectation_value_diagonal(&self, circuit: &PyAny, diagonal: Vec<f64>) -> PyResult<f64> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| {
+            self.internal
+                .expectation_value_diagonal(&circuit, &diagonal)
+        })
+        .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Start an interactive session that keeps its quantum register alive between calls.
+    ///
+    /// The session starts in the all-zero state and uses the random seed of the backend.
+    /// It can be used as a context manager, the quantum register is released on exit.
+    ///
+    /// Args:
+    ///     density_matrix (Optional[bool]): Simulate a density matrix instead of a state vector. Defaults to False.
+    ///
+    /// Returns:
+    ///     Session: The interactive simulation session.
+    ///
+    /// Raises:
+    ///     ValueError: The backend has no qubits
+    #[pyo3(text_signature = "($self, density_matrix=None)")]
+    pub fn session(&self, density_matrix: Option<bool>) -> PyResult<SessionWrapper> {
+        if self.internal.number_qubits == 0 {
+            return Err(PyValueError::new_err(
+                "A session needs a backend with at least one qubit",
+            ));
+        }
+        Ok(SessionWrapper::new(
+            self.internal.number_qubits,
+            density_matrix.unwrap_or(false),
+            self.internal.random_seed.clone(),
+        ))
+    }
+
+    /// Return information about the build of the QuEST library used by the backend.
+    ///
+    /// The precision is 1 when QuEST is compiled in single precision (`single-precision` feature),
+    /// where results are only accurate to about 1e-6, and 2 for double precision.
+    ///
+    /// Returns:
+    ///     Dict[str, Union[int, bool]]: The floating point precision, whether QuEST is multithreaded and the number of threads.
+    #[staticmethod]
+    #[pyo3(text_signature = "()")]
+    pub fn environment_info(py: Python) -> PyResult<Py<PyDict>> {
+        let info = roqoqo_quest::environment_info();
+        let dict = PyDict::new(py);
+        dict.set_item("precision", info.precision)?;
+        dict.set_item("multithreaded", info.multithreaded)?;
+        dict.set_item("number_threads", info.number_threads)?;
+        Ok(dict.into())
+    }
+
+    /// Return gate counts, depth and simulation mode of a circuit.
+    ///
+    /// The statistics can be used to estimate the runtime and memory of a simulation
+    /// (2^N complex numbers for a state vector and 4^N for a density matrix).
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is analysed.
+    ///
+    /// Returns:
+    ///     Dict[str, Union[int, bool]]: The statistics of the circuit.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn circuit_statistics(&self, circuit: &PyAny) -> PyResult<Py<PyDict>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let statistics = circuit_statistics(&circuit);
+        let dict = PyDict::new(py);
+        dict.set_item(
+            "number_single_qubit_gates",
+            statistics.number_single_qubit_gates,
+        )?;
+        dict.set_item("number_two_qubit_gates", statistics.number_two_qubit_gates)?;
+        dict.set_item(
+            "number_three_qubit_gates",
+            statistics.number_three_qubit_gates,
+        )?;
+        dict.set_item(
+            "number_multi_qubit_gates",
+            statistics.number_multi_qubit_gates,
+        )?;
+        dict.set_item("number_noise_pragmas", statistics.number_noise_pragmas)?;
+        dict.set_item("depth", statistics.depth)?;
+        dict.set_item("number_qubits", statistics.number_qubits)?;
+        dict.set_item("is_density_matrix", statistics.is_density_matrix)?;
+        Ok(dict.into())
+    }
+
+    /// Run a circuit and write the final state vector to a file in the format of QuEST's reportState.
+    ///
+    /// The file starts with the header line `real, imag` followed by the real and imaginary part
+    /// of one amplitude per line, so it can be read by standalone QuEST programs.
+    /// The circuit must not require a density matrix simulation.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is run on the backend.
+    ///     path (str): The path of the file that is written.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, circuit, path)")]
+    pub fn write_state_to_file(&self, circuit: &PyAny, path: PathBuf) -> PyResult<()> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        warn_ignored_circuit_parameters(py, &circuit)?;
+        py.allow_threads(|| self.internal.write_state_to_file(&circuit, &path))
+            .map_err(|err| PyRuntimeError::new_err(format!("Running Circuit failed {:?}", err)))
+    }
+
+    /// Read a state vector from a file in the format of QuEST's reportState.
+    ///
+    /// The file must contain the 2^N amplitudes of a state vector of the qubits of the backend.
+    /// Lines starting with `#` and the header line `real, imag` are skipped.
+    ///
+    /// Args:
+    ///     path (str): The path of the file that is read.
+    ///
+    /// Returns:
+    ///     List[complex]: The amplitudes of the state vector in the file.
+    ///
+    /// Raises:
+    ///     ValueError: State cannot be read from file
+    #[pyo3(text_signature = "($self, path)")]
+    pub fn read_state_from_file(&self, path: PathBuf) -> PyResult<Vec<Complex64>> {
+        self.internal.read_state_from_file(&path).map_err(|err| {
+            PyValueError::new_err(format!("State cannot be read from file {:?}", err))
+        })
+    }
+
+    /// Serialize a circuit to OpenQASM 2.0 for the qubits of the backend.
+    ///
+    /// Gates are mapped to the gates of qelib1.inc that implement the same unitary,
+    /// bit registers are declared as classical registers. Float and complex registers
+    /// are left out. Operations without a QASM equivalent raise an error.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is serialized.
+    ///
+    /// Returns:
+    ///     str: The OpenQASM 2.0 program.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     ValueError: Circuit cannot be serialized to QASM
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn to_qasm(&self, circuit: &PyAny) -> PyResult<String> {
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        circuit_to_qasm(&circuit, self.internal.number_qubits).map_err(|err| {
+            PyValueError::new_err(format!("Circuit cannot be serialized to QASM {:?}", err))
+        })
+    }
+
+    /// Return the names and lengths of the output registers a circuit writes to, without running it.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is checked.
+    ///
+    /// Returns:
+    ///     Dict[str, Dict[str, int]]: The lengths of the output registers by name, under the keys "bit", "float" and "complex".
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    #[pyo3(text_signature = "($self, circuit)")]
+    pub fn output_registers(&self, circuit: &PyAny) -> PyResult<Py<PyDict>> {
+        let py = circuit.py();
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let shapes = self.internal.output_registers(&circuit);
+        let dict = PyDict::new(py);
+        dict.set_item("bit", shapes.bit_registers)?;
+        dict.set_item("float", shapes.float_registers)?;
+        dict.set_item("complex", shapes.complex_registers)?;
+        Ok(dict.into())
+    }
+
+    /// Run all circuits corresponding to one measurement with the QuEST backend.
+    ///
+    /// An expectation value measurement in general involves several circuits.
+    /// Each circuit is passes to the backend and executed separately.
+    /// A circuit is passed to the backend and executed.
+    /// During execution values are written to and read from classical registers
+    /// (List[bool], List[float], List[complex]).
+    /// To produce sufficient statistics for evaluating expectation values,
+    /// circuits have to be run multiple times.
+    /// The results of each repetition are concatenated in OutputRegisters
+    /// (List[List[bool]], List[List[float]], List[List[complex]]).  
+    /// The registers of each type are returned sorted by name, so the key order is the same in every run.
+    ///
+    ///
+    /// Args:
+    ///     measurement (Measurement): The measurement that is run on the backend.
+    ///
+    /// Returns:
+    ///     Tuple[Dict[str, List[List[bool]]], Dict[str, List[List[float]]]], Dict[str, List[List[complex]]]]: The output registers written by the evaluated circuits.
+    ///
+    /// Raises:
+    ///     TypeError: Cannot extract constant circuit from measurement
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, measurement)")]
+    pub fn run_measurement_registers(&self, measurement: &PyAny) -> PyResult<Registers> {
+        let run_circuits = measurement_circuits(measurement)?;
+
+        let mut bit_registers: BTreeMap<String, BitOutputRegister> = BTreeMap::new();
+        let mut float_registers: BTreeMap<String, FloatOutputRegister> = BTreeMap::new();
+        let mut complex_registers: BTreeMap<String, ComplexOutputRegister> = BTreeMap::new();
+
+        for circuit in run_circuits {
+            warn_ignored_circuit_parameters(measurement.py(), &circuit)?;
+            let (tmp_bit_reg, tmp_float_reg, tmp_complex_reg) = measurement
+                .py()
+                .allow_threads(|| self.internal.run_circuit_iterator(circuit.iter()))
+                .map_err(|err| {
+                    PyRuntimeError::new_err(format!("Running a circuit failed {:?}", err))
+                })?;
+
+            for (key, mut val) in tmp_bit_reg.into_iter() {
+                if let Some(x) = bit_registers.get_mut(&key) {
+                    x.append(&mut val);
+                } else {
+                    let _ = bit_registers.insert(key, val);
+                }
+            }
+            for (key, mut val) in tmp_float_reg.into_iter() {
+                if let Some(x) = float_registers.get_mut(&key) {
+                    x.append(&mut val);
+                } else {
+                    let _ = float_registers.insert(key, val);
+                }
+            }
+            for (key, mut val) in tmp_complex_reg.into_iter() {
+                if let Some(x) = complex_registers.get_mut(&key) {
+                    x.append(&mut val);
+                } else {
+                    let _ = complex_registers.insert(key, val);
+                }
+            }
+        }
+        Ok((bit_registers, float_registers, complex_registers))
+    }
+
+    /// Run all circuits of a measurement and return the output registers of each circuit separately.
+    ///
+    /// In contrast to run_measurement_registers, the registers of different circuits are not
+    /// concatenated, so that circuits writing to the same register can be told apart.
+    ///
+    /// Args:
+    ///     measurement (Measurement): The measurement that is run on the backend.
+    ///
+    /// Returns:
+    ///     List[Tuple[Dict[str, List[List[bool]]], Dict[str, List[List[float]]]], Dict[str, List[List[complex]]]]]: The output registers of each circuit, in the order of the circuits of the measurement.
+    ///
+    /// Raises:
+    ///     TypeError: Cannot extract constant circuit from measurement
+    ///     RuntimeError: Running Circuit failed
+    #[pyo3(text_signature = "($self, measurement)")]
+    pub fn run_measurement_registers_indexed(
+        &self,
+        measurement: &PyAny,
+    ) -> PyResult<Vec<Registers>> {
+        let run_circuits = measurement_circuits(measurement)?;
+        let mut registers: Vec<Registers> = Vec::with_capacity(run_circuits.len());
+        for circuit in run_circuits {
+            warn_ignored_circuit_parameters(measurement.py(), &circuit)?;
+            registers.push(
+                measurement
+                    .py()
+                    .allow_threads(|| self.internal.run_circuit_iterator(circuit.iter()))
+                    .map(sorted_registers)
+                    .map_err(|err| {
+                        PyRuntimeError::new_err(format!("Running a circuit failed {:?}", err))
+                    })?,
+            );
+        }
+        Ok(registers)
+    }
+
+    /// Abort the simulation that is currently running on the Backend.
+    ///
+    /// The simulation stops before the next operation and the running method raises a RuntimeError.
+    /// A call while no simulation is running has no effect on later simulations.
+    #[pyo3(text_signature = "($self)")]
+    pub fn cancel(&self) {
+        self.internal.cancellation_flag.cancel()
+    }
+
+    /// Evaluates expectation values of a measurement with the backend.
+    ///
+    ///
+    /// Args:
+    ///     measurement (Measurement): The measurement that is run on the backend.
+    ///
+    /// Returns:
+    ///     Optional[Dict[str, float]]: The  dictionary of expectation values.
+    ///
+    /// Raises:
+    ///     TypeError: Measurement evaluate function could not be used
+    ///     RuntimeError: Internal error measurement.evaluation returned unknown type
+    #[pyo3(text_signature = "($self, measurement)")]
+    pub fn run_measurement(&self, measurement: &PyAny) -> PyResult<Option<HashMap<String, f64>>> {
+        let (bit_registers, float_registers, complex_registers) =
             self.run_measurement_registers(measurement)?;
         let get_expectation_values = measurement
             .call_method1(
@@ -321,11 +1278,311 @@ impl BackendWrapper {
                 )
             })
     }
+
+    /// Evaluates the expectation values of a PauliZProduct measurement together with their standard errors.
+    ///
+    /// The standard errors are estimated from the shot statistics of the measured bit registers,
+    /// so the measurement needs to be sampled with at least two shots, for example with a PragmaRepeatedMeasurement.
+    ///
+    /// Args:
+    ///     measurement (PauliZProduct): The measurement that is run on the backend.
+    ///
+    /// Returns:
+    ///     Dict[str, Tuple[float, float]]: The expectation values and their standard errors.
+    ///
+    /// Raises:
+    ///     TypeError: Measurement is not a PauliZProduct measurement
+    ///     RuntimeError: Running measurement failed
+    #[pyo3(text_signature = "($self, measurement)")]
+    pub fn run_measurement_with_uncertainty(
+        &self,
+        py: Python,
+        measurement: &PyAny,
+    ) -> PyResult<HashMap<String, (f64, f64)>> {
+        let measurement = measurement
+            .extract::<PauliZProductWrapper>()
+            .map_err(|err| {
+                PyTypeError::new_err(format!(
+                    "Measurement is not a PauliZProduct measurement {:?}",
+                    err
+                ))
+            })?
+            .internal;
+        py.allow_threads(|| {
+            self.internal
+                .run_measurement_with_uncertainty(&measurement)
+                .map_err(|err| {
+                    PyRuntimeError::new_err(format!("Running measurement failed {:?}", err))
+                })
+        })
+    }
+
+    /// Run a QuantumProgram with the backend and return its expectation values.
+    ///
+    /// The number of parameters is checked against the input parameter names of the program
+    /// before the program is run.
+    ///
+    /// Args:
+    ///     program (QuantumProgram): The quantum program that is run on the backend.
+    ///     parameters (Optional[List[float]]): The values of the free parameters in the order of `input_parameter_names`.
+    ///
+    /// Returns:
+    ///     Optional[Dict[str, float]]: The dictionary of expectation values.
+    ///
+    /// Raises:
+    ///     TypeError: Program argument cannot be converted to qoqo QuantumProgram
+    ///     ValueError: Wrong number of parameters
+    ///     RuntimeError: Running QuantumProgram failed
+    #[pyo3(text_signature = "($self, program, parameters=None)")]
+    pub fn run_program(
+        &self,
+        program: &PyAny,
+        parameters: Option<Vec<f64>>,
+    ) -> PyResult<Option<HashMap<String, f64>>> {
+        let program = convert_into_quantum_program(program).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Program argument cannot be converted to qoqo QuantumProgram {:?}",
+                err
+            ))
+        })?;
+        let parameters = parameters.unwrap_or_default();
+        let input_parameter_names = program_input_parameter_names(&program);
+        if parameters.len() != input_parameter_names.len() {
+            return Err(PyValueError::new_err(format!(
+                "Wrong number of parameters: the QuantumProgram expects {} parameters {:?}, {} parameters were provided",
+                input_parameter_names.len(),
+                input_parameter_names,
+                parameters.len()
+            )));
+        }
+        program
+            .run(self.internal.clone(), &parameters)
+            .map_err(|err| {
+                PyRuntimeError::new_err(format!("Running QuantumProgram failed {:?}", err))
+            })
+    }
+
+    /// Return the gradients of the expectation values of a measurement with the parameter-shift rule.
+    ///
+    /// For each symbolic parameter the measurement is evaluated with the parameter shifted by +pi/2
+    /// and -pi/2 and the derivative is (E(theta + pi/2) - E(theta - pi/2)) / 2.
+    /// The rule is exact when each parameter is the angle of a single rotation exp(-i theta/2 P)
+    /// with a Pauli product P.
+    ///
+    /// Args:
+    ///     measurement (Measurement): The measurement with symbolic parameters.
+    ///     parameters (Dict[str, float]): The values of all symbolic parameters at which the gradient is evaluated.
+    ///
+    /// Returns:
+    ///     Dict[str, Dict[str, float]]: For each expectation value the derivatives by each parameter.
+    ///
+    /// Raises:
+    ///     TypeError: Parameters cannot be substituted in measurement
+    ///     RuntimeError: Measurement did not return expectation values
+    #[pyo3(text_signature = "($self, measurement, parameters)")]
+    pub fn parameter_shift_gradient(
+        &self,
+        measurement: &PyAny,
+        parameters: HashMap<String, f64>,
+    ) -> PyResult<HashMap<String, HashMap<String, f64>>> {
+        let mut gradients: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        for parameter in parameters.keys() {
+            let mut shifted_expectation_values: Vec<HashMap<String, f64>> = Vec::new();
+            for shift in [std::f64::consts::FRAC_PI_2, -std::f64::consts::FRAC_PI_2] {
+                let mut shifted_parameters = parameters.clone();
+                if let Some(value) = shifted_parameters.get_mut(parameter) {
+                    *value += shift;
+                }
+                let shifted_measurement = measurement
+                    .call_method1("substitute_parameters", (shifted_parameters,))
+                    .map_err(|err| {
+                        PyTypeError::new_err(format!(
+                            "Parameters cannot be substituted in measurement {:?}",
+                            err
+                        ))
+                    })?;
+                let expectation_values =
+                    self.run_measurement(shifted_measurement)?.ok_or_else(|| {
+                        PyRuntimeError::new_err("Measurement did not return expectation values")
+                    })?;
+                shifted_expectation_values.push(expectation_values);
+            }
+            for (name, plus_value) in shifted_expectation_values[0].iter() {
+                let minus_value = shifted_expectation_values[1].get(name).ok_or_else(|| {
+                    PyRuntimeError::new_err(format!(
+                        "Expectation value {} missing in shifted measurement",
+                        name
+                    ))
+                })?;
+                gradients
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(parameter.clone(), (plus_value - minus_value) / 2.0);
+            }
+        }
+        Ok(gradients)
+    }
+}
+
+// Returns the circuits of a measurement with the constant circuit prepended
+fn measurement_circuits(measurement: &PyAny) -> PyResult<Vec<Circuit>> {
+    let mut run_circuits: Vec<Circuit> = Vec::new();
+
+    let get_constant_circuit = measurement
+        .call_method0("constant_circuit")
+        .map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Cannot extract constant circuit from measurement {:?}",
+                err
+            ))
+        })?;
+    let const_circuit = get_constant_circuit
+        .extract::<Option<&PyAny>>()
+        .map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Cannot extract constant circuit from measurement {:?}",
+                err
+            ))
+        })?;
+
+    let constant_circuit = match const_circuit {
+        Some(x) => convert_into_circuit(x).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Cannot extract constant circuit from measurement {:?}",
+                err
+            ))
+        })?,
+        None => Circuit::new(),
+    };
+
+    let get_circuit_list = measurement.call_method0("circuits").map_err(|err| {
+        PyTypeError::new_err(format!(
+            "Cannot extract circuit list from measurement {:?}",
+            err
+        ))
+    })?;
+    let circuit_list = get_circuit_list.extract::<Vec<&PyAny>>().map_err(|err| {
+        PyTypeError::new_err(format!(
+            "Cannot extract circuit list from measurement {:?}",
+            err
+        ))
+    })?;
+
+    for c in circuit_list {
+        run_circuits.push(
+            constant_circuit.clone()
+                + convert_into_circuit(c).map_err(|err| {
+                    PyTypeError::new_err(format!(
+                        "Cannot extract circuit of circuit list from measurement {:?}",
+                        err
+                    ))
+                })?,
+        )
+    }
+    Ok(run_circuits)
+}
+
+// Returns the names of the free parameters of a quantum program in the order they are passed to run
+fn program_input_parameter_names(program: &QuantumProgram) -> &[String] {
+    match program {
+        QuantumProgram::PauliZProduct {
+            input_parameter_names,
+            ..
+        }
+        | QuantumProgram::CheatedPauliZProduct {
+            input_parameter_names,
+            ..
+        }
+        | QuantumProgram::Cheated {
+            input_parameter_names,
+            ..
+        }
+        | QuantumProgram::ClassicalRegister {
+            input_parameter_names,
+            ..
+        } => input_parameter_names,
+    }
+}
+
+// Converts nested lists of complex numbers to a square matrix, None if the rows do not form a square matrix
+fn square_matrix(rows: Vec<Vec<Complex64>>) -> Option<Array2<Complex64>> {
+    let dimension = rows.len();
+    if rows.iter().any(|row| row.len() != dimension) {
+        return None;
+    }
+    Array2::from_shape_vec((dimension, dimension), rows.into_iter().flatten().collect()).ok()
+}
+
+// Sorts the output registers of a run by name so that their order is the same in every run
+fn sorted_registers(registers: OutputRegisters) -> Registers {
+    let (bit_registers, float_registers, complex_registers) = registers;
+    (
+        bit_registers.into_iter().collect(),
+        float_registers.into_iter().collect(),
+        complex_registers.into_iter().collect(),
+    )
+}
+
+// Counts of the measured bitstrings of each readout register with bitstrings like "010" as keys
+type BitstringCounts = HashMap<String, HashMap<String, usize>>;
+
+// Converts the measured bit vectors of the counts to bitstrings like "010"
+fn bitstring_counts(counts: HashMap<String, HashMap<Vec<bool>, usize>>) -> BitstringCounts {
+    counts
+        .into_iter()
+        .map(|(name, register_counts)| {
+            let register_counts = register_counts
+                .into_iter()
+                .map(|(bits, count)| {
+                    let bitstring: String = bits
+                        .iter()
+                        .map(|bit| if *bit { '1' } else { '0' })
+                        .collect();
+                    (bitstring, count)
+                })
+                .collect();
+            (name, register_counts)
+        })
+        .collect()
+}
+
+// Surfaces the warnings for circuit parameters that are ignored by the backend as Python warnings
+fn warn_ignored_circuit_parameters(py: Python, circuit: &Circuit) -> PyResult<()> {
+    let user_warning = py.import("builtins")?.getattr("UserWarning")?;
+    for warning in ignored_circuit_warnings(circuit.iter()) {
+        PyErr::warn(py, user_warning, &warning, 0)?;
+    }
+    Ok(())
+}
+
+/// Version written in front of the bincode representation of a [roqoqo_quest::Backend].
+const SERIALIZATION_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Serializes the Backend behind the version tag so that payloads of other versions can be recognised
+fn serialize_backend(backend: &roqoqo_quest::Backend) -> bincode::Result<Vec<u8>> {
+    serialize(&(SERIALIZATION_VERSION, backend))
+}
+
+// Reads the version tag first, the Backend is only deserialized when the versions match
+fn deserialize_backend(bytes: &[u8]) -> Result<roqoqo_quest::Backend, String> {
+    let cannot_deserialize = |_| "Input cannot be deserialized to Backend".to_string();
+    let version: String = deserialize(bytes).map_err(cannot_deserialize)?;
+    if version != SERIALIZATION_VERSION {
+        return Err(format!(
+            "Backend was serialized with incompatible version {}, expected {}",
+            version, SERIALIZATION_VERSION
+        ));
+    }
+    let (_, backend): (String, roqoqo_quest::Backend) =
+        deserialize(bytes).map_err(cannot_deserialize)?;
+    Ok(backend)
 }
 
 /// Convert generic python object to [roqoqo_quest::Backend].
 ///
 /// Fallible conversion of generic python object to [roqoqo_quest::Backend].
+/// Backends from separately compiled packages are only converted when they were
+/// serialized with the same version of qoqo-quest.
 pub fn convert_into_backend(input: &PyAny) -> Result<roqoqo_quest::Backend, QoqoBackendError> {
     if let Ok(try_downcast) = input.extract::<BackendWrapper>() {
         Ok(try_downcast.internal)
@@ -338,6 +1595,7 @@ pub fn convert_into_backend(input: &PyAny) -> Result<roqoqo_quest::Backend, Qoqo
         let bytes = get_bytes
             .extract::<Vec<u8>>()
             .map_err(|_| QoqoBackendError::CannotExtractObject)?;
-        deserialize(&bytes[..]).map_err(|_| QoqoBackendError::CannotExtractObject)
+        deserialize_backend(&bytes[..])
+            .map_err(|msg| RoqoqoBackendError::GenericError { msg }.into())
     }
 }
@@ -22,6 +22,10 @@
 use pyo3::prelude::*;
 mod backend;
 pub use backend::{convert_into_backend, BackendWrapper};
+mod session;
+pub use session::SessionWrapper;
+mod measurement_stream;
+pub use measurement_stream::MeasurementStreamWrapper;
 
 /// QuEST Simulator backend to the qoqo quantum computing toolkit.
 ///
@@ -32,10 +36,14 @@ pub use backend::{convert_into_backend, BackendWrapper};
 ///     :toctree: generated/
 ///
 ///     Backend
+///     Session
+///     MeasurementStream
 ///
 #[pymodule]
 fn qoqo_quest(_py: Python, module: &PyModule) -> PyResult<()> {
     module.add_class::<BackendWrapper>()?;
+    module.add_class::<SessionWrapper>()?;
+    module.add_class::<MeasurementStreamWrapper>()?;
     // Adding nice imports corresponding to maturin example
     Ok(())
 }
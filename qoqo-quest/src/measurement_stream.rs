@@ -0,0 +1,66 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pyo3::prelude::*;
+use roqoqo_quest::MeasurementStream;
+
+/// Iterator over measured shots of all qubits.
+///
+/// Shots are sampled lazily from the final state of a circuit,
+/// so large numbers of shots can be aggregated without storing them.
+/// Streams are created with Backend.measure_stream().
+#[pyclass(name = "MeasurementStream", module = "qoqo_quest", unsendable)]
+#[derive(Debug)]
+pub struct MeasurementStreamWrapper {
+    /// Internal storage of [roqoqo_quest::MeasurementStream]
+    internal: MeasurementStream,
+    /// Number of shots that are still yielded by the stream
+    remaining: usize,
+}
+
+impl MeasurementStreamWrapper {
+    /// Creates a stream yielding a limited number of shots.
+    ///
+    /// # Arguments
+    ///
+    /// `internal` - The infinite stream of shots
+    /// `number_measurements` - The number of shots that are yielded
+    pub fn new(internal: MeasurementStream, number_measurements: usize) -> Self {
+        Self {
+            internal,
+            remaining: number_measurements,
+        }
+    }
+}
+
+#[pymethods]
+impl MeasurementStreamWrapper {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<Vec<bool>> {
+        if slf.remaining == 0 {
+            return None;
+        }
+        slf.remaining -= 1;
+        slf.internal.next()
+    }
+
+    /// Return the number of shots that are still yielded by the stream.
+    ///
+    /// Returns:
+    ///     int: The number of remaining shots.
+    fn __len__(&self) -> usize {
+        self.remaining
+    }
+}
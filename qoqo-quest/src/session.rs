@@ -0,0 +1,370 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numpy::Complex64;
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use qoqo::convert_into_circuit;
+use roqoqo::operations::MeasureQubit;
+use roqoqo::registers::{BitOutputRegister, BitRegister, ComplexRegister, FloatRegister};
+use roqoqo_quest::{call_circuit, call_operation, call_parity_conditional, Qureg};
+use std::collections::HashMap;
+
+/// Interactive QuEST simulation session.
+///
+/// A session keeps its quantum register alive between calls, so that circuits can be
+/// applied incrementally and the state can be inspected in between.
+/// Sessions are created with Backend.session() and can be used as context managers;
+/// the quantum register is released when the context is left.
+#[pyclass(name = "Session", module = "qoqo_quest", unsendable)]
+#[derive(Debug)]
+pub struct SessionWrapper {
+    /// Quantum register of the session, None after the session has been closed
+    qureg: Option<Qureg>,
+    /// Number of qubits of the quantum register
+    number_qubits: usize,
+    /// Simulate a density matrix instead of a state vector
+    is_density_matrix: bool,
+    /// Optional seed for the random number generators of the quantum register
+    random_seed: Option<Vec<u64>>,
+    /// Bit registers written by the applied circuits
+    bit_registers: HashMap<String, BitRegister>,
+    /// Float registers written by the applied circuits
+    float_registers: HashMap<String, FloatRegister>,
+    /// Complex registers written by the applied circuits
+    complex_registers: HashMap<String, ComplexRegister>,
+    /// Bit output registers written by the applied circuits
+    bit_registers_output: HashMap<String, BitOutputRegister>,
+}
+
+impl SessionWrapper {
+    /// Creates a new session with a quantum register in the all-zero state.
+    ///
+    /// # Arguments
+    ///
+    /// `number_qubits` - The number of qubits of the quantum register
+    /// `is_density_matrix` - Simulate a density matrix instead of a state vector
+    /// `random_seed` - The optional seed for the random number generators
+    pub fn new(
+        number_qubits: usize,
+        is_density_matrix: bool,
+        random_seed: Option<Vec<u64>>,
+    ) -> Self {
+        let mut session = Self {
+            qureg: None,
+            number_qubits,
+            is_density_matrix,
+            random_seed,
+            bit_registers: HashMap::new(),
+            float_registers: HashMap::new(),
+            complex_registers: HashMap::new(),
+            bit_registers_output: HashMap::new(),
+        };
+        session.reset();
+        session
+    }
+
+    // Returns the quantum register or an error when the session has been closed
+    fn qureg_mut(&mut self) -> PyResult<&mut Qureg> {
+        self.qureg
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("Session has been closed"))
+    }
+}
+
+#[pymethods]
+impl SessionWrapper {
+    /// Apply a circuit to the current state of the session.
+    ///
+    /// The operations are applied directly to the quantum register,
+    /// registers written by the circuit are kept until the session is reset.
+    ///
+    /// Args:
+    ///     circuit (Circuit): The circuit that is applied.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Applying Circuit failed
+    pub fn apply(&mut self, circuit: &PyAny) -> PyResult<()> {
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let qureg = self
+            .qureg
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("Session has been closed"))?;
+        call_circuit(
+            &circuit,
+            qureg,
+            &mut self.bit_registers,
+            &mut self.float_registers,
+            &mut self.complex_registers,
+            &mut self.bit_registers_output,
+        )
+        .map_err(|err| PyRuntimeError::new_err(format!("Applying Circuit failed {:?}", err)))
+    }
+
+    /// Apply a circuit when the parity of several bits of a bit register is one.
+    ///
+    /// The parity (XOR) of the entries `condition_indices` of the bit register `condition_register`
+    /// is calculated from the registers of the session, for example syndrome bits written by
+    /// MeasureQubit. The circuit is only applied when the parity is one.
+    ///
+    /// Args:
+    ///     condition_register (str): The name of the bit register containing the condition bits.
+    ///     condition_indices (List[int]): The indices of the condition bits in the register.
+    ///     circuit (Circuit): The circuit that is applied when the parity is one.
+    ///
+    /// Raises:
+    ///     TypeError: Circuit argument cannot be converted to qoqo Circuit
+    ///     RuntimeError: Session is closed, the register is missing, an index is out of range or applying Circuit failed
+    pub fn apply_parity_conditional(
+        &mut self,
+        condition_register: &str,
+        condition_indices: Vec<usize>,
+        circuit: &PyAny,
+    ) -> PyResult<()> {
+        let circuit = convert_into_circuit(circuit).map_err(|err| {
+            PyTypeError::new_err(format!(
+                "Circuit argument cannot be converted to qoqo Circuit {:?}",
+                err
+            ))
+        })?;
+        let qureg = self
+            .qureg
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("Session has been closed"))?;
+        call_parity_conditional(
+            condition_register,
+            &condition_indices,
+            &circuit,
+            qureg,
+            &mut self.bit_registers,
+            &mut self.float_registers,
+            &mut self.complex_registers,
+            &mut self.bit_registers_output,
+        )
+        .map_err(|err| PyRuntimeError::new_err(format!("Applying Circuit failed {:?}", err)))
+    }
+
+    /// Return the current state vector of the session.
+    ///
+    /// Returns:
+    ///     List[complex]: The amplitudes of all basis states.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed or simulates a density matrix
+    pub fn statevector(&mut self) -> PyResult<Vec<Complex64>> {
+        let qureg = self.qureg_mut()?;
+        qureg
+            .number_amplitudes()
+            .and_then(|number_amplitudes| qureg.amplitudes_range(0, number_amplitudes))
+            .map_err(|err| PyRuntimeError::new_err(format!("{:?}", err)))
+    }
+
+    /// Measure a single qubit of the current state.
+    ///
+    /// The state of the session collapses according to the measurement outcome.
+    ///
+    /// Args:
+    ///     qubit (int): The qubit that is measured.
+    ///
+    /// Returns:
+    ///     bool: The measurement outcome.
+    ///
+    /// Raises:
+    ///     ValueError: Qubit is not part of the session
+    ///     RuntimeError: Measurement failed
+    pub fn measure(&mut self, qubit: usize) -> PyResult<bool> {
+        if qubit >= self.number_qubits {
+            return Err(PyValueError::new_err(format!(
+                "Qubit {} is not part of the session with {} qubits",
+                qubit, self.number_qubits
+            )));
+        }
+        let readout = "__session_measurement__".to_string();
+        let mut bit_registers: HashMap<String, BitRegister> =
+            HashMap::from([(readout.clone(), vec![false])]);
+        call_operation(
+            &MeasureQubit::new(qubit, readout.clone(), 0).into(),
+            self.qureg_mut()?,
+            &mut bit_registers,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+        )
+        .map_err(|err| PyRuntimeError::new_err(format!("Measurement failed {:?}", err)))?;
+        Ok(bit_registers[&readout][0])
+    }
+
+    /// Measure all qubits of the current state jointly and write the outcome to a bit register.
+    ///
+    /// One basis state is sampled from the probabilities of the whole quantum register and
+    /// the state of the session collapses onto it, so the measured values of all qubits are
+    /// correlated like in a single shot of the device. The n-th entry of the register is the
+    /// measured value of the n-th qubit, an existing register with the same name is replaced.
+    ///
+    /// Args:
+    ///     readout (str): The name of the bit register the outcome is written to.
+    ///
+    /// Returns:
+    ///     List[bool]: The measured values of all qubits.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed or the measurement failed
+    pub fn measure_all(&mut self, readout: String) -> PyResult<Vec<bool>> {
+        let measured = self
+            .qureg_mut()?
+            .snapshot_measurement()
+            .map_err(|err| PyRuntimeError::new_err(format!("Measurement failed {:?}", err)))?;
+        self.bit_registers.insert(readout, measured.clone());
+        Ok(measured)
+    }
+
+    /// Measure a subset of qubits and write the outcome as an integer to a float register.
+    ///
+    /// The qubits are measured one after the other and the state of the session collapses.
+    /// The k-th bit of the outcome is the measured value of the k-th qubit in `qubits`,
+    /// so the first qubit is the least significant bit. The outcome is written to the float
+    /// register `readout` with a single entry, an existing register with the same name is replaced.
+    ///
+    /// Args:
+    ///     qubits (List[int]): The measured qubits, at most 64.
+    ///     readout (str): The name of the float register the outcome is written to.
+    ///
+    /// Returns:
+    ///     int: The index of the measured basis state of the qubits.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed or the measurement failed
+    pub fn measure_subset_to_int(&mut self, qubits: Vec<usize>, readout: String) -> PyResult<u64> {
+        let outcome = self
+            .qureg_mut()?
+            .measure_subset_to_int(&qubits)
+            .map_err(|err| PyRuntimeError::new_err(format!("Measurement failed {:?}", err)))?;
+        self.float_registers.insert(readout, vec![outcome as f64]);
+        Ok(outcome)
+    }
+
+    /// Write the marginal probabilities of qubits to a float register without collapsing the state.
+    ///
+    /// The k-th entry of the float register `readout` is the probability of the k-th qubit
+    /// in `qubits` to be measured in |1>, an existing register with the same name is replaced.
+    ///
+    /// Args:
+    ///     qubits (List[int]): The qubits whose marginal probabilities are recorded.
+    ///     readout (str): The name of the float register the probabilities are written to.
+    ///
+    /// Returns:
+    ///     List[float]: The probabilities of the qubits to be in |1>.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed or a qubit is not part of the session
+    pub fn marginal_probabilities_to_float(
+        &mut self,
+        qubits: Vec<usize>,
+        readout: String,
+    ) -> PyResult<Vec<f64>> {
+        let probabilities = self
+            .qureg_mut()?
+            .marginal_probabilities(&qubits)
+            .map_err(|err| PyRuntimeError::new_err(format!("{:?}", err)))?;
+        self.float_registers.insert(readout, probabilities.clone());
+        Ok(probabilities)
+    }
+
+    /// Convert the state vector of the session into the density matrix of the same pure state.
+    ///
+    /// Noise can be applied to the state afterwards, for example to check how a noise model
+    /// acts on a given pure state. The session keeps simulating a density matrix until it is reset,
+    /// a session that already simulates a density matrix is not changed.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed
+    pub fn promote_to_density_matrix(&mut self) -> PyResult<()> {
+        self.qureg_mut()?.promote_to_density_matrix();
+        Ok(())
+    }
+
+    /// Depolarise the whole quantum register of the session.
+    ///
+    /// The state is replaced by the maximally mixed state with the given probability,
+    /// a probability of 1 fully depolarises the register.
+    /// Only possible for sessions simulating a density matrix.
+    ///
+    /// Args:
+    ///     probability (float): The probability of replacing the state with the maximally mixed state.
+    ///
+    /// Raises:
+    ///     RuntimeError: Session is closed, simulates a state vector or the probability is not between 0 and 1
+    pub fn mix_global_depolarising(&mut self, probability: f64) -> PyResult<()> {
+        self.qureg_mut()?
+            .mix_global_depolarising(probability)
+            .map_err(|err| PyRuntimeError::new_err(format!("{:?}", err)))
+    }
+
+    /// Return the registers written by the circuits applied in the session.
+    ///
+    /// Returns:
+    ///     Tuple[Dict[str, List[bool]], Dict[str, List[float]], Dict[str, List[complex]]]: bit, float and complex registers
+    pub fn registers(&self) -> Registers {
+        (
+            self.bit_registers.clone(),
+            self.float_registers.clone(),
+            self.complex_registers.clone(),
+        )
+    }
+
+    /// Reset the session to the all-zero state and clear all registers.
+    pub fn reset(&mut self) {
+        let mut qureg = Qureg::new(self.number_qubits as u32, self.is_density_matrix);
+        if let Some(seed) = self.random_seed.as_ref() {
+            qureg.set_random_seed(seed);
+        }
+        self.qureg = Some(qureg);
+        self.bit_registers.clear();
+        self.float_registers.clear();
+        self.complex_registers.clear();
+        self.bit_registers_output.clear();
+    }
+
+    /// Release the quantum register of the session.
+    ///
+    /// A closed session can be reopened with reset().
+    pub fn close(&mut self) {
+        self.qureg = None;
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> bool {
+        self.close();
+        false
+    }
+}
+
+/// Registers of a session returned to Python.
+type Registers = (
+    HashMap<String, BitRegister>,
+    HashMap<String, FloatRegister>,
+    HashMap<String, ComplexRegister>,
+);
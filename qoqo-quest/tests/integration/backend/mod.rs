@@ -12,14 +12,23 @@
 
 //! Integration test for public API of Basis rotation measurement
 
+use numpy::{array, Complex64, PyArray2};
 use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
 use pyo3::Python;
-use qoqo::measurements::ClassicalRegisterWrapper;
-use qoqo::CircuitWrapper;
-use qoqo_quest::BackendWrapper;
-use roqoqo::measurements::ClassicalRegister;
+use qoqo::measurements::{
+    CheatedPauliZProductWrapper, ClassicalRegisterWrapper, PauliZProductWrapper,
+};
+use qoqo::operations::convert_operation_to_pyobject;
+use qoqo::{CircuitWrapper, QuantumProgramWrapper};
+use qoqo_quest::{convert_into_backend, BackendWrapper};
+use roqoqo::measurements::{
+    CheatedPauliZProduct, CheatedPauliZProductInput, ClassicalRegister, PauliZProduct,
+    PauliZProductInput,
+};
 use roqoqo::operations;
-use roqoqo::Circuit;
+use roqoqo::{Circuit, QuantumProgram};
+use std::collections::HashMap;
 
 #[test]
 fn test_creating_backend() {
@@ -97,3 +106,1774 @@ fn test_running_measurement() {
             .unwrap();
     })
 }
+
+#[test]
+fn test_run_circuit_complex_buffers() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::RotateX::new(0, 0.7.into());
+    circuit += operations::Hadamard::new(1);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let complex_registers: HashMap<String, Vec<Vec<Complex64>>> = backend
+            .call_method1("run_circuit", (circuit_wrapper.clone(),))
+            .unwrap()
+            .get_item(2)
+            .unwrap()
+            .extract()
+            .unwrap();
+        let buffers: HashMap<String, Vec<Vec<u8>>> = backend
+            .call_method1("run_circuit_complex_buffers", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(buffers["state"].len(), 1);
+        let buffer = &buffers["state"][0];
+        assert_eq!(buffer.len(), 4 * 16);
+        // Reconstruct the complex values from the interleaved real and imaginary parts
+        let reconstructed: Vec<Complex64> = buffer
+            .chunks_exact(16)
+            .map(|chunk| {
+                Complex64::new(
+                    f64::from_ne_bytes(chunk[..8].try_into().unwrap()),
+                    f64::from_ne_bytes(chunk[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+        assert_eq!(reconstructed, complex_registers["state"][0]);
+    })
+}
+
+#[test]
+fn test_run_circuit_timed() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 20, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let result = backend
+            .call_method1("run_circuit_timed", (circuit_wrapper,))
+            .unwrap();
+        let bit_registers: HashMap<String, Vec<Vec<bool>>> = result
+            .get_item(0)
+            .unwrap()
+            .get_item(0)
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(bit_registers["ro"].len(), 20);
+        let timing: HashMap<String, f64> = result.get_item(1).unwrap().extract().unwrap();
+        assert!(timing["total"] > 0.0);
+        assert!(timing["gate_operations"] >= 0.0);
+        assert!(timing["measurements"] >= 0.0);
+        assert!(timing["gate_operations"] + timing["measurements"] <= timing["total"]);
+    })
+}
+
+#[test]
+fn test_output_registers() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::DefinitionFloat::new("pauli".to_string(), 1, true);
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::DefinitionComplex::new("internal".to_string(), 4, false);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let shapes: HashMap<String, HashMap<String, usize>> = backend
+            .call_method1("output_registers", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(shapes["bit"], [("ro".to_string(), 2)].into_iter().collect());
+        assert_eq!(
+            shapes["float"],
+            [("pauli".to_string(), 1)].into_iter().collect()
+        );
+        assert_eq!(
+            shapes["complex"],
+            [("state".to_string(), 4)].into_iter().collect()
+        );
+    })
+}
+
+#[test]
+fn test_register_order() {
+    pyo3::prepare_freethreaded_python();
+    let names = [
+        "zeta", "alpha", "mu", "beta", "omega", "gamma", "kappa", "delta",
+    ];
+    let mut circuit = Circuit::new();
+    for (index, name) in names.iter().enumerate() {
+        circuit += operations::DefinitionBit::new(name.to_string(), 1, true);
+        circuit += operations::MeasureQubit::new(0, name.to_string(), 0);
+        circuit += operations::DefinitionFloat::new(format!("{}_float", name), 1, true);
+        circuit += operations::PragmaGetPauliProduct::new(
+            [(0, 3)].into_iter().collect(),
+            format!("{}_float", name),
+            Circuit::new(),
+        );
+        if index % 2 == 0 {
+            circuit += operations::PauliX::new(0);
+        }
+    }
+    let mut sorted_names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    sorted_names.sort();
+    let sorted_float_names: Vec<String> = sorted_names
+        .iter()
+        .map(|name| format!("{}_float", name))
+        .collect();
+    let crm_wrapper = ClassicalRegisterWrapper {
+        internal: ClassicalRegister {
+            constant_circuit: None,
+            circuits: vec![circuit.clone(), circuit.clone()],
+        },
+    };
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let register_keys = |registers: &PyAny, index: usize| -> Vec<String> {
+            registers
+                .get_item(index)
+                .unwrap()
+                .call_method0("keys")
+                .unwrap()
+                .iter()
+                .unwrap()
+                .map(|key| key.unwrap().extract().unwrap())
+                .collect()
+        };
+        for _ in 0..5 {
+            let registers = backend
+                .call_method1("run_circuit", (circuit_wrapper.clone(),))
+                .unwrap();
+            assert_eq!(register_keys(registers, 0), sorted_names);
+            assert_eq!(register_keys(registers, 1), sorted_float_names);
+            let registers = backend
+                .call_method1("run_measurement_registers", (crm_wrapper.clone(),))
+                .unwrap();
+            assert_eq!(register_keys(registers, 0), sorted_names);
+            assert_eq!(register_keys(registers, 1), sorted_float_names);
+        }
+    })
+}
+
+#[test]
+fn test_run_measurement_registers_indexed() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit_a += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 2, None);
+    let mut circuit_b = Circuit::new();
+    circuit_b += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit_b += operations::PauliX::new(0);
+    circuit_b += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 2, None);
+    let crm_wrapper = ClassicalRegisterWrapper {
+        internal: ClassicalRegister {
+            constant_circuit: None,
+            circuits: vec![circuit_a, circuit_b],
+        },
+    };
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let registers: Vec<std::collections::HashMap<String, Vec<Vec<bool>>>> = backend
+            .call_method1("run_measurement_registers_indexed", (crm_wrapper,))
+            .unwrap()
+            .extract::<Vec<&PyAny>>()
+            .unwrap()
+            .into_iter()
+            .map(|circuit_registers| circuit_registers.get_item(0).unwrap().extract().unwrap())
+            .collect();
+        assert_eq!(registers.len(), 2);
+        assert_eq!(registers[0].get("ro").unwrap(), &vec![vec![false]; 2]);
+        assert_eq!(registers[1].get("ro").unwrap(), &vec![vec![true]; 2]);
+    })
+}
+
+#[test]
+fn test_parameter_shift_gradient() {
+    pyo3::prepare_freethreaded_python();
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::RotateX::new(0, "theta".into());
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("z0".to_string(), 1, true);
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "z0".to_string(),
+        Circuit::new(),
+    );
+    let mut input = CheatedPauliZProductInput::new();
+    let z0 = input.add_pauliz_product("z0".to_string());
+    input
+        .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+        .unwrap();
+    let measurement_wrapper = CheatedPauliZProductWrapper {
+        internal: CheatedPauliZProduct {
+            constant_circuit: Some(constant_circuit),
+            circuits: vec![circuit],
+            input,
+        },
+    };
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let theta = 0.7;
+        let parameters: HashMap<String, f64> = [("theta".to_string(), theta)].into_iter().collect();
+        let gradients: HashMap<String, HashMap<String, f64>> = backend
+            .call_method1(
+                "parameter_shift_gradient",
+                (measurement_wrapper, parameters),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        // <Z> = cos(theta), so the derivative is -sin(theta)
+        assert!((gradients["z0"]["theta"] + f64::sin(theta)).abs() < 1e-10);
+    })
+}
+
+#[test]
+fn test_run_program() {
+    pyo3::prepare_freethreaded_python();
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::RotateX::new(0, "theta".into());
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("z0".to_string(), 1, true);
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "z0".to_string(),
+        Circuit::new(),
+    );
+    let mut input = CheatedPauliZProductInput::new();
+    let z0 = input.add_pauliz_product("z0".to_string());
+    input
+        .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+        .unwrap();
+    let program_wrapper = QuantumProgramWrapper {
+        internal: QuantumProgram::CheatedPauliZProduct {
+            measurement: CheatedPauliZProduct {
+                constant_circuit: Some(constant_circuit),
+                circuits: vec![circuit],
+                input,
+            },
+            input_parameter_names: vec!["theta".to_string()],
+        },
+    };
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let expectation_values: HashMap<String, f64> = backend
+            .call_method1("run_program", (program_wrapper.clone(), vec![0.7]))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((expectation_values["z0"] - f64::cos(0.7)).abs() < 1e-10);
+
+        let error = backend
+            .call_method1("run_program", (program_wrapper, Vec::<f64>::new()))
+            .unwrap_err();
+        assert!(error.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        assert_eq!(
+            error.value(py).to_string(),
+            "Wrong number of parameters: the QuantumProgram expects 1 parameters [\"theta\"], 0 parameters were provided"
+        );
+    })
+}
+
+#[test]
+fn test_cancel() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("readout".to_string(), 2, true);
+    circuit += operations::RotateX::new(0, 1.0.into());
+    circuit += operations::PragmaRepeatedMeasurement::new("readout".to_string(), 100, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        // A cancellation while no simulation is running does not abort the next simulation
+        backend.call_method0("cancel").unwrap();
+        let result = backend.call_method1("run_circuit", (circuit_wrapper,));
+        assert!(result.is_ok());
+    })
+}
+
+#[test]
+fn test_density_matrix() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(0);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let density_matrix = backend
+            .call_method1("density_matrix", (circuit_wrapper,))
+            .unwrap()
+            .extract::<&PyArray2<Complex64>>()
+            .unwrap()
+            .to_owned_array();
+        let expected = array![
+            [Complex64::new(0.0, 0.0), Complex64::new(0.0, 0.0)],
+            [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)]
+        ];
+        assert_eq!(density_matrix, expected);
+    });
+
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let density_matrix = backend
+            .call_method1("density_matrix", (circuit_wrapper,))
+            .unwrap()
+            .extract::<&PyArray2<Complex64>>()
+            .unwrap()
+            .to_owned_array();
+        let half = Complex64::new(0.5, 0.0);
+        let zero = Complex64::new(0.0, 0.0);
+        let expected = array![
+            [half, zero, zero, half],
+            [zero, zero, zero, zero],
+            [zero, zero, zero, zero],
+            [half, zero, zero, half]
+        ];
+        for (value, expected_value) in density_matrix.iter().zip(expected.iter()) {
+            assert!((value - expected_value).norm() < 1e-10);
+        }
+    })
+}
+
+#[test]
+fn test_ignored_circuit_warning() {
+    pyo3::prepare_freethreaded_python();
+    let mut preparation_circuit = Circuit::new();
+    preparation_circuit += operations::PauliX::new(0);
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 2, true);
+    circuit +=
+        operations::PragmaGetStateVector::new("state".to_string(), Some(preparation_circuit));
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let locals = pyo3::types::PyDict::new(py);
+        locals.set_item("backend", backend).unwrap();
+        locals
+            .set_item("circuit", Py::new(py, circuit_wrapper).unwrap())
+            .unwrap();
+        py.run(
+            "import warnings\nwith warnings.catch_warnings(record=True) as caught:\n    warnings.simplefilter('always')\n    backend.run_circuit(circuit)\nmessages = [str(warning.message) for warning in caught]",
+            None,
+            Some(locals),
+        )
+        .unwrap();
+        let messages: Vec<String> = locals.get_item("messages").unwrap().extract().unwrap();
+        assert_eq!(
+            messages,
+            vec!["Circuit parameter of PragmaGetStateVector with readout state is ignored by the QuEST backend".to_string()]
+        );
+    })
+}
+
+#[test]
+fn test_measurement_override() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("readout".to_string(), 2, true);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("readout".to_string(), 10, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let backend = backend
+            .call_method1("with_measurement_override", (Some(25),))
+            .unwrap();
+        let bit_registers: std::collections::HashMap<String, Vec<Vec<bool>>> = backend
+            .call_method1("run_circuit", (circuit_wrapper,))
+            .unwrap()
+            .get_item(0)
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(bit_registers.get("readout").unwrap().len(), 25);
+    })
+}
+
+#[test]
+fn test_initial_state() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("readout".to_string(), 2, true);
+    circuit += operations::PragmaRepeatedMeasurement::new("readout".to_string(), 5, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let backend = backend
+            .call_method1("with_initial_state", (Some(2),))
+            .unwrap();
+        let bit_registers: std::collections::HashMap<String, Vec<Vec<bool>>> = backend
+            .call_method1("run_circuit", (circuit_wrapper,))
+            .unwrap()
+            .get_item(0)
+            .unwrap()
+            .extract()
+            .unwrap();
+        for repetition in bit_registers.get("readout").unwrap() {
+            assert_eq!(repetition, &vec![false, true]);
+        }
+    })
+}
+
+#[test]
+fn test_text_signatures() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let signature = |name: &str| -> String {
+            backend_type
+                .getattr(name)
+                .unwrap()
+                .getattr("__text_signature__")
+                .unwrap()
+                .extract()
+                .unwrap()
+        };
+        assert_eq!(signature("run_circuit"), "($self, circuit)");
+        assert_eq!(signature("run_measurement"), "($self, measurement)");
+        assert_eq!(signature("from_bincode"), "($cls, input)");
+        assert_eq!(signature("session"), "($self, density_matrix=None)");
+        let class_signature: String = backend_type
+            .getattr("__text_signature__")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(class_signature, "(number_qubits)");
+    })
+}
+
+#[test]
+fn test_from_json_version_0_7() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        // Backends serialized by version 0.7 only contain the number of qubits and repetitions
+        let deserialized = backend_type
+            .call_method1("from_json", (r#"{"number_qubits":2,"repetitions":3}"#,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        assert_eq!(
+            deserialized.borrow().internal,
+            roqoqo_quest::Backend::new(2).set_repetitions(3)
+        );
+        assert!(deserialized.borrow().internal.group_diagonal_gates);
+
+        let serialized = deserialized.call_method0("to_json").unwrap();
+        let roundtrip = backend_type
+            .call_method1("from_json", (serialized,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        assert_eq!(roundtrip.borrow().internal, deserialized.borrow().internal);
+    })
+}
+
+#[test]
+fn test_bincode_version() {
+    pyo3::prepare_freethreaded_python();
+    let bumped_version = "999.0.0";
+    let bumped_payload =
+        bincode::serialize(&(bumped_version, roqoqo_quest::Backend::new(2))).unwrap();
+    let expected_error = format!(
+        "Backend was serialized with incompatible version {}, expected {}",
+        bumped_version,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let serialized = backend.call_method0("to_bincode").unwrap();
+        let deserialized = backend_type
+            .call_method1("from_bincode", (serialized,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        assert_eq!(
+            deserialized.borrow().internal,
+            roqoqo_quest::Backend::new(2)
+        );
+
+        let error = backend_type
+            .call_method1("from_bincode", (bumped_payload.clone(),))
+            .unwrap_err();
+        assert!(error.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        assert_eq!(error.value(py).to_string(), expected_error);
+
+        // A backend from a separately compiled package is only reachable through its bincode form
+        let globals = pyo3::types::PyDict::new(py);
+        globals.set_item("payload", bumped_payload).unwrap();
+        py.run(
+            "class ForeignBackend:\n    def _enum_to_bincode(self):\n        return bytearray(payload)\nforeign_backend = ForeignBackend()",
+            Some(globals),
+            None,
+        )
+        .unwrap();
+        let foreign_backend = globals.get_item("foreign_backend").unwrap();
+        let error = convert_into_backend(foreign_backend).unwrap_err();
+        assert!(error.to_string().contains(&expected_error));
+        assert_eq!(
+            convert_into_backend(deserialized.as_ref()).unwrap(),
+            roqoqo_quest::Backend::new(2)
+        );
+    })
+}
+
+#[test]
+fn test_circuit_statistics() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::PragmaDamping::new(1, 0.01.into(), 2.0.into());
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let statistics = backend
+            .call_method1("circuit_statistics", (circuit_wrapper,))
+            .unwrap();
+        let get_usize =
+            |key: &str| -> usize { statistics.get_item(key).unwrap().extract().unwrap() };
+        assert_eq!(get_usize("number_single_qubit_gates"), 1);
+        assert_eq!(get_usize("number_two_qubit_gates"), 1);
+        assert_eq!(get_usize("number_noise_pragmas"), 1);
+        assert_eq!(get_usize("depth"), 2);
+        assert_eq!(get_usize("number_qubits"), 2);
+        let is_density_matrix: bool = statistics
+            .get_item("is_density_matrix")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(is_density_matrix);
+    })
+}
+
+#[test]
+fn test_hilbert_schmidt_distance() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::PauliX::new(0);
+    let circuit_a_wrapper = CircuitWrapper {
+        internal: circuit_a,
+    };
+    let circuit_b_wrapper = CircuitWrapper {
+        internal: Circuit::new(),
+    };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let identical: f64 = backend
+            .call_method1(
+                "hilbert_schmidt_distance",
+                (circuit_a_wrapper.clone(), circuit_a_wrapper.clone()),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(identical.abs() < 1e-10);
+        let orthogonal: f64 = backend
+            .call_method1(
+                "hilbert_schmidt_distance",
+                (circuit_a_wrapper, circuit_b_wrapper),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((orthogonal - 2.0).abs() < 1e-10);
+    })
+}
+
+#[test]
+fn test_renyi_2_entropy() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let entropy: f64 = backend
+            .call_method1("renyi_2_entropy", (circuit_wrapper.clone(), vec![0]))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((entropy - 2.0_f64.ln()).abs() < 1e-10);
+        assert!(backend
+            .call_method1("renyi_2_entropy", (circuit_wrapper, vec![2]))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_to_qasm() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::MeasureQubit::new(1, "ro".to_string(), 0);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+    let mut unsupported = Circuit::new();
+    unsupported += operations::PragmaDamping::new(0, 1.0.into(), 0.1.into());
+    let unsupported_wrapper = CircuitWrapper {
+        internal: unsupported,
+    };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let qasm: String = backend
+            .call_method1("to_qasm", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(qasm.starts_with("OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\n"));
+        assert!(qasm.contains("h q[0];\ncx q[0],q[1];\nmeasure q[1] -> ro[0];\n"));
+        assert!(backend
+            .call_method1("to_qasm", (unsupported_wrapper,))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_state_file() {
+    pyo3::prepare_freethreaded_python();
+    let path = std::env::temp_dir().join(format!("qoqo_quest_state_{}.csv", std::process::id()));
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        backend
+            .call_method1(
+                "write_state_to_file",
+                (circuit_wrapper, path.to_str().unwrap()),
+            )
+            .unwrap();
+        let state_vector: Vec<Complex64> = backend
+            .call_method1("read_state_from_file", (path.to_str().unwrap(),))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let amplitude = std::f64::consts::FRAC_1_SQRT_2;
+        let expected = [amplitude, 0.0, 0.0, amplitude];
+        for (value, expected) in state_vector.iter().zip(expected.iter()) {
+            assert!((value - expected).norm() < 1e-12);
+        }
+        let larger_backend = backend_type.call1((3,)).unwrap();
+        assert!(larger_backend
+            .call_method1("read_state_from_file", (path.to_str().unwrap(),))
+            .is_err());
+    });
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_inner_product() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::PauliX::new(0);
+    let circuit_a_wrapper = CircuitWrapper {
+        internal: circuit_a,
+    };
+    let circuit_b_wrapper = CircuitWrapper {
+        internal: Circuit::new(),
+    };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let identical: Complex64 = backend
+            .call_method1(
+                "inner_product",
+                (circuit_a_wrapper.clone(), circuit_a_wrapper.clone()),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((identical - Complex64::new(1.0, 0.0)).norm() < 1e-10);
+        let orthogonal: Complex64 = backend
+            .call_method1("inner_product", (circuit_a_wrapper, circuit_b_wrapper))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(orthogonal.norm() < 1e-10);
+    })
+}
+
+#[test]
+fn test_apply_unitary() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateX::new(0, 0.4.into());
+    circuit += operations::Hadamard::new(1);
+    circuit += operations::RotateZ::new(1, 0.7.into());
+    let mut swap_circuit = circuit.clone();
+    swap_circuit += operations::SWAP::new(0, 1);
+    swap_circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    swap_circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+    let swap_circuit_wrapper = CircuitWrapper {
+        internal: swap_circuit,
+    };
+    let one = Complex64::new(1.0, 0.0);
+    let zero = Complex64::new(0.0, 0.0);
+    let swap = vec![
+        vec![one, zero, zero, zero],
+        vec![zero, zero, one, zero],
+        vec![zero, one, zero, zero],
+        vec![zero, zero, zero, one],
+    ];
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let state_vector: Vec<Complex64> = backend
+            .call_method1(
+                "apply_unitary",
+                (circuit_wrapper.clone(), vec![0, 1], swap.clone()),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        let complex_registers: HashMap<String, Vec<Vec<Complex64>>> = backend
+            .call_method1("run_circuit", (swap_circuit_wrapper,))
+            .unwrap()
+            .get_item(2)
+            .unwrap()
+            .extract()
+            .unwrap();
+        for (value, expected) in state_vector
+            .iter()
+            .zip(complex_registers["state"][0].iter())
+        {
+            assert!((value - expected).norm() < 1e-10);
+        }
+
+        // Wrong number of qubits and a non-square matrix
+        assert!(backend
+            .call_method1(
+                "apply_unitary",
+                (circuit_wrapper.clone(), vec![0], swap.clone()),
+            )
+            .is_err());
+        assert!(backend
+            .call_method1(
+                "apply_unitary",
+                (circuit_wrapper, vec![0, 1], vec![vec![one, zero]]),
+            )
+            .is_err());
+    })
+}
+
+#[test]
+fn test_expectation_value_diagonal() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let parity: f64 = backend
+            .call_method1(
+                "expectation_value_diagonal",
+                (circuit_wrapper.clone(), vec![1.0, -1.0, -1.0, 1.0]),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((parity - 1.0).abs() < 1e-10);
+        assert!(backend
+            .call_method1(
+                "expectation_value_diagonal",
+                (circuit_wrapper, vec![1.0, -1.0]),
+            )
+            .is_err());
+    })
+}
+
+#[test]
+fn test_supports_operation() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let hadamard = convert_operation_to_pyobject(operations::Hadamard::new(0).into()).unwrap();
+        let supported: bool = backend
+            .call_method1("supports_operation", (hadamard,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(supported);
+        let sleep =
+            convert_operation_to_pyobject(operations::PragmaSleep::new(vec![0], 0.1.into()).into())
+                .unwrap();
+        let supported: bool = backend
+            .call_method1("supports_operation", (sleep,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(!supported);
+        assert!(backend.call_method1("supports_operation", (1,)).is_err());
+    })
+}
+
+#[test]
+fn test_run_circuit_counts() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 50, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((3,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let counts: HashMap<String, HashMap<String, usize>> = backend
+            .call_method1("run_circuit_counts", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected: HashMap<String, usize> = [("010".to_string(), 50)].into_iter().collect();
+        assert_eq!(counts["ro"], expected);
+    })
+}
+
+#[test]
+fn test_run_circuit_counts_and_probabilities() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 50, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let (counts, probabilities): (HashMap<String, HashMap<String, usize>>, Vec<f64>) = backend
+            .call_method1("run_circuit_counts_and_probabilities", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected: HashMap<String, usize> = [("01".to_string(), 50)].into_iter().collect();
+        assert_eq!(counts["ro"], expected);
+        assert_eq!(probabilities, vec![0.0, 0.0, 1.0, 0.0]);
+    })
+}
+
+#[test]
+fn test_measure_stream() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let stream = backend
+            .call_method1("measure_stream", (circuit_wrapper, 10))
+            .unwrap();
+        assert_eq!(stream.len().unwrap(), 10);
+        let mut number_shots = 0;
+        for shot in stream.iter().unwrap() {
+            let shot: Vec<bool> = shot.unwrap().extract().unwrap();
+            assert_eq!(shot, vec![false, true]);
+            number_shots += 1;
+        }
+        assert_eq!(number_shots, 10);
+        assert_eq!(stream.len().unwrap(), 0);
+    })
+}
+
+#[test]
+fn test_run_circuit_trajectories() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let trajectories: Vec<Vec<Complex64>> = backend
+            .call_method1("run_circuit_trajectories", (circuit_wrapper, 5))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(trajectories.len(), 5);
+        for trajectory in trajectories {
+            assert!((trajectory[0].norm() * trajectory[1].norm()).abs() < 1e-10);
+        }
+    })
+}
+
+#[test]
+fn test_run_circuit_json() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::DefinitionFloat::new("pauli".to_string(), 1, true);
+    circuit += operations::DefinitionComplex::new("state".to_string(), 2, true);
+    circuit += operations::PauliY::new(0);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "pauli".to_string(),
+        Circuit::new(),
+    );
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let json: String = backend
+            .call_method1("run_circuit_json", (circuit_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        type JsonRegisters = (
+            HashMap<String, Vec<Vec<bool>>>,
+            HashMap<String, Vec<Vec<f64>>>,
+            HashMap<String, Vec<Vec<[f64; 2]>>>,
+        );
+        let (bit_registers, float_registers, complex_registers): JsonRegisters =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(bit_registers.get("ro").unwrap(), &vec![vec![true]]);
+        assert_eq!(float_registers.get("pauli").unwrap(), &vec![vec![-1.0]]);
+        let state = &complex_registers.get("state").unwrap()[0];
+        assert_eq!(state.len(), 2);
+        assert!(state[0][0].abs() < 1e-10 && state[0][1].abs() < 1e-10);
+        assert!(state[1][0].abs() < 1e-10 && (state[1][1] - 1.0).abs() < 1e-10);
+    })
+}
+
+#[test]
+fn test_new_plus() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call_method1("new_plus", (2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        assert_eq!(
+            backend.borrow().internal,
+            roqoqo_quest::Backend::new_plus(2)
+        );
+        assert!(backend.borrow().internal.initial_plus_state);
+    })
+}
+
+#[test]
+fn test_from_config() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let readout_errors: HashMap<usize, Vec<Vec<f64>>> =
+            [(0, vec![vec![0.9, 0.1], vec![0.2, 0.8]])]
+                .into_iter()
+                .collect();
+        let kwargs = [
+            ("repetitions", 5.into_py(py)),
+            ("random_seed", vec![3_u64].into_py(py)),
+            ("imperfect_readout_model", readout_errors.into_py(py)),
+        ]
+        .into_py_dict(py);
+        let backend = backend_type
+            .call_method("from_config", (2,), Some(kwargs))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let internal = backend.borrow().internal.clone();
+        assert_eq!(
+            internal,
+            roqoqo_quest::Backend::new(2)
+                .set_repetitions(5)
+                .set_random_seed(vec![3])
+                .set_readout_error(0, [[0.9, 0.1], [0.2, 0.8]])
+                .unwrap()
+        );
+
+        let default_backend = backend_type
+            .call_method1("from_config", (2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        assert_eq!(
+            default_backend.borrow().internal,
+            roqoqo_quest::Backend::new(2)
+        );
+
+        let invalid_errors: HashMap<usize, Vec<Vec<f64>>> =
+            [(0, vec![vec![0.9, 0.9], vec![0.2, 0.8]])]
+                .into_iter()
+                .collect();
+        let kwargs = [("imperfect_readout_model", invalid_errors.into_py(py))].into_py_dict(py);
+        assert!(backend_type
+            .call_method("from_config", (2,), Some(kwargs))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_sparse_statevector() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let sparse: Vec<(usize, Complex64)> = backend
+            .call_method1("sparse_statevector", (circuit_wrapper, 1e-10))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(sparse.len(), 2);
+        assert_eq!(sparse[0].0, 0);
+        assert_eq!(sparse[1].0, 2);
+    })
+}
+
+#[test]
+fn test_session_without_qubits() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((0,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let error = backend.call_method0("session").unwrap_err();
+        assert!(error.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+    })
+}
+
+#[test]
+fn test_session() {
+    pyo3::prepare_freethreaded_python();
+    let mut first_circuit = Circuit::new();
+    first_circuit += operations::Hadamard::new(0);
+    let mut second_circuit = Circuit::new();
+    second_circuit += operations::CNOT::new(0, 1);
+    second_circuit += operations::RotateZ::new(1, 0.3.into());
+    let combined_circuit = first_circuit.clone() + second_circuit.clone();
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        session
+            .call_method1(
+                "apply",
+                (CircuitWrapper {
+                    internal: first_circuit,
+                },),
+            )
+            .unwrap();
+        session
+            .call_method1(
+                "apply",
+                (CircuitWrapper {
+                    internal: second_circuit,
+                },),
+            )
+            .unwrap();
+        let cumulative: Vec<Complex64> = session
+            .call_method0("statevector")
+            .unwrap()
+            .extract()
+            .unwrap();
+
+        let combined_session = backend.call_method0("session").unwrap();
+        combined_session
+            .call_method1(
+                "apply",
+                (CircuitWrapper {
+                    internal: combined_circuit,
+                },),
+            )
+            .unwrap();
+        let combined: Vec<Complex64> = combined_session
+            .call_method0("statevector")
+            .unwrap()
+            .extract()
+            .unwrap();
+        for (value, combined_value) in cumulative.iter().zip(combined.iter()) {
+            assert!((value - combined_value).norm() < 1e-10);
+        }
+
+        let outcome: bool = session
+            .call_method1("measure", (0,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let collapsed: Vec<Complex64> = session
+            .call_method0("statevector")
+            .unwrap()
+            .extract()
+            .unwrap();
+        let collapsed_index = if outcome { 3 } else { 0 };
+        assert!((collapsed[collapsed_index].norm() - 1.0).abs() < 1e-10);
+        assert!(session.call_method1("measure", (2,)).is_err());
+
+        session.call_method0("reset").unwrap();
+        let reset: Vec<Complex64> = session
+            .call_method0("statevector")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((reset[0] - Complex64::new(1.0, 0.0)).norm() < 1e-10);
+
+        session.call_method0("close").unwrap();
+        assert!(session.call_method0("statevector").is_err());
+    })
+}
+
+#[test]
+fn test_session_measure_all() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::CNOT::new(1, 2);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((3,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        for _ in 0..20 {
+            session.call_method0("reset").unwrap();
+            session
+                .call_method1("apply", (circuit_wrapper.clone(),))
+                .unwrap();
+            let measured: Vec<bool> = session
+                .call_method1("measure_all", ("snapshot",))
+                .unwrap()
+                .extract()
+                .unwrap();
+            // The GHZ state only produces all-zeros or all-ones bitstrings
+            assert!(measured == vec![false; 3] || measured == vec![true; 3]);
+            let bit_registers: HashMap<String, Vec<bool>> = session
+                .call_method0("registers")
+                .unwrap()
+                .get_item(0)
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(bit_registers["snapshot"], measured);
+        }
+    })
+}
+
+#[test]
+fn test_session_apply_parity_conditional() {
+    pyo3::prepare_freethreaded_python();
+    let mut syndrome_circuit = Circuit::new();
+    syndrome_circuit += operations::DefinitionBit::new("syndrome".to_string(), 2, true);
+    syndrome_circuit += operations::PauliX::new(1);
+    syndrome_circuit += operations::MeasureQubit::new(1, "syndrome".to_string(), 0);
+    syndrome_circuit += operations::MeasureQubit::new(2, "syndrome".to_string(), 1);
+    let mut correction = Circuit::new();
+    correction += operations::PauliX::new(0);
+    let correction = CircuitWrapper {
+        internal: correction,
+    };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((3,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        session
+            .call_method1(
+                "apply",
+                (CircuitWrapper {
+                    internal: syndrome_circuit,
+                },),
+            )
+            .unwrap();
+        // The parity of the syndrome bits 1 and 0 is one, so the X correction is applied
+        session
+            .call_method1(
+                "apply_parity_conditional",
+                ("syndrome", vec![0_usize, 1], correction.clone()),
+            )
+            .unwrap();
+        let outcome: bool = session
+            .call_method1("measure", (0,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(outcome);
+
+        let error = session
+            .call_method1(
+                "apply_parity_conditional",
+                ("syndrome", vec![0_usize, 2], correction),
+            )
+            .unwrap_err();
+        assert!(error.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+    })
+}
+
+#[test]
+fn test_session_measure_subset_to_int() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::CNOT::new(1, 2);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((3,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        for _ in 0..20 {
+            session.call_method0("reset").unwrap();
+            session
+                .call_method1("apply", (circuit_wrapper.clone(),))
+                .unwrap();
+            let outcome: u64 = session
+                .call_method1("measure_subset_to_int", (vec![0, 1, 2], "outcome"))
+                .unwrap()
+                .extract()
+                .unwrap();
+            // The GHZ state only produces the all-zeros or the all-ones integer
+            assert!(outcome == 0 || outcome == 0b111);
+            let float_registers: HashMap<String, Vec<f64>> = session
+                .call_method0("registers")
+                .unwrap()
+                .get_item(1)
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(float_registers["outcome"], vec![outcome as f64]);
+        }
+        assert!(session
+            .call_method1("measure_subset_to_int", (vec![3], "outcome"))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_session_marginal_probabilities_to_float() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PauliX::new(2);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((3,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        session.call_method1("apply", (circuit_wrapper,)).unwrap();
+        let probabilities: Vec<f64> = session
+            .call_method1(
+                "marginal_probabilities_to_float",
+                (vec![0, 1, 2], "marginals"),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        for (probability, expected) in probabilities.iter().zip([0.5, 0.0, 1.0].iter()) {
+            assert!((probability - expected).abs() < 1e-10);
+        }
+        let float_registers: HashMap<String, Vec<f64>> = session
+            .call_method0("registers")
+            .unwrap()
+            .get_item(1)
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(float_registers["marginals"], probabilities);
+        // The state does not collapse
+        let statevector: Vec<Complex64> = session
+            .call_method0("statevector")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!((statevector[4].norm_sqr() - 0.5).abs() < 1e-10);
+        assert!((statevector[5].norm_sqr() - 0.5).abs() < 1e-10);
+        assert!(session
+            .call_method1("marginal_probabilities_to_float", (vec![3], "marginals"))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_session_promote_to_density_matrix() {
+    pyo3::prepare_freethreaded_python();
+    let mut preparation = Circuit::new();
+    preparation += operations::Hadamard::new(0);
+    let mut readout = Circuit::new();
+    readout += operations::DefinitionComplex::new("density_matrix".to_string(), 4, true);
+    readout += operations::PragmaGetDensityMatrix::new("density_matrix".to_string(), None);
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method0("session").unwrap();
+        session
+            .call_method1(
+                "apply",
+                (CircuitWrapper {
+                    internal: preparation,
+                },),
+            )
+            .unwrap();
+        session.call_method0("promote_to_density_matrix").unwrap();
+        assert!(session.call_method0("statevector").is_err());
+        session
+            .call_method1("apply", (CircuitWrapper { internal: readout },))
+            .unwrap();
+        let complex_registers: HashMap<String, Vec<Complex64>> = session
+            .call_method0("registers")
+            .unwrap()
+            .get_item(2)
+            .unwrap()
+            .extract()
+            .unwrap();
+        // |+><+| has all entries 1/2
+        for entry in complex_registers["density_matrix"].iter() {
+            assert!((entry - Complex64::new(0.5, 0.0)).norm() < 1e-12);
+        }
+
+        // Reset returns to a state vector simulation
+        session.call_method0("reset").unwrap();
+        assert!(session.call_method0("statevector").is_ok());
+    })
+}
+
+#[test]
+fn test_session_mix_global_depolarising() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("density_matrix".to_string(), 16, true);
+    circuit += operations::PragmaGetDensityMatrix::new("density_matrix".to_string(), None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let session = backend.call_method1("session", (true,)).unwrap();
+        session
+            .call_method1("mix_global_depolarising", (1.0,))
+            .unwrap();
+        session.call_method1("apply", (circuit_wrapper,)).unwrap();
+        let complex_registers: HashMap<String, Vec<Complex64>> = session
+            .call_method0("registers")
+            .unwrap()
+            .get_item(2)
+            .unwrap()
+            .extract()
+            .unwrap();
+        // The maximally mixed state has 1/4 on the diagonal of the flattened density matrix
+        for (index, value) in complex_registers["density_matrix"].iter().enumerate() {
+            let expected = if index % 5 == 0 { 0.25 } else { 0.0 };
+            assert!((value - Complex64::new(expected, 0.0)).norm() < 1e-10);
+        }
+
+        let state_vector_session = backend.call_method0("session").unwrap();
+        assert!(state_vector_session
+            .call_method1("mix_global_depolarising", (1.0,))
+            .is_err());
+    })
+}
+
+#[test]
+fn test_session_context_manager() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(1);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((2,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let locals = pyo3::types::PyDict::new(py);
+        locals.set_item("backend", backend).unwrap();
+        locals
+            .set_item("circuit", circuit_wrapper.into_py(py))
+            .unwrap();
+        py.run(
+            "with backend.session() as session:\n    session.apply(circuit)\n    state = session.statevector()\n",
+            None,
+            Some(locals),
+        )
+        .unwrap();
+        let state: Vec<Complex64> = locals.get_item("state").unwrap().extract().unwrap();
+        assert!((state[2] - Complex64::new(1.0, 0.0)).norm() < 1e-10);
+        let session = locals.get_item("session").unwrap();
+        assert!(session.call_method0("statevector").is_err());
+    })
+}
+
+#[test]
+fn test_set_random_seed() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    for qubit in 0..3 {
+        circuit += operations::Hadamard::new(qubit);
+    }
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 50, None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let run_with_seed = |seed: PyObject| -> Vec<Vec<bool>> {
+            let backend = backend_type.call1((3,)).unwrap();
+            backend.call_method1("set_random_seed", (seed,)).unwrap();
+            let bit_registers: HashMap<String, Vec<Vec<bool>>> = backend
+                .call_method1("run_circuit", (circuit_wrapper.clone(),))
+                .unwrap()
+                .get_item(0)
+                .unwrap()
+                .extract()
+                .unwrap();
+            bit_registers["ro"].clone()
+        };
+        let single_result = run_with_seed(5_u64.into_py(py));
+        let list_result = run_with_seed(vec![5_u64].into_py(py));
+        assert_eq!(single_result, list_result);
+
+        let backend = backend_type.call1((3,)).unwrap();
+        assert!(backend.call_method1("set_random_seed", ("seed",)).is_err());
+        assert!(backend.call_method1("set_random_seed", (-1,)).is_err());
+    })
+}
+
+#[test]
+fn test_initial_density_matrix() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("density_matrix".to_string(), 4, true);
+    circuit += operations::PragmaGetDensityMatrix::new("density_matrix".to_string(), None);
+    let circuit_wrapper = CircuitWrapper { internal: circuit };
+    let matrix = vec![
+        vec![Complex64::new(0.75, 0.0), Complex64::new(0.0, 0.25)],
+        vec![Complex64::new(0.0, -0.25), Complex64::new(0.25, 0.0)],
+    ];
+
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        backend
+            .call_method1("set_initial_density_matrix", (matrix.clone(),))
+            .unwrap();
+        let complex_registers: HashMap<String, Vec<Vec<Complex64>>> = backend
+            .call_method1("run_circuit", (circuit_wrapper,))
+            .unwrap()
+            .get_item(2)
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected: Vec<Complex64> = matrix.iter().flatten().cloned().collect();
+        for (value, expected) in complex_registers["density_matrix"][0].iter().zip(expected) {
+            assert!((value - expected).norm() < 1e-10);
+        }
+
+        let wrong_shape = vec![vec![Complex64::new(1.0, 0.0)]];
+        assert!(backend
+            .call_method1("set_initial_density_matrix", (wrong_shape,))
+            .is_err());
+        let ragged = vec![vec![Complex64::new(1.0, 0.0)], vec![]];
+        assert!(backend
+            .call_method1("set_initial_density_matrix", (ragged,))
+            .is_err());
+        backend
+            .call_method1("set_initial_density_matrix", (None::<Vec<Vec<Complex64>>>,))
+            .unwrap();
+    })
+}
+
+#[test]
+fn test_run_measurement_with_uncertainty() {
+    pyo3::prepare_freethreaded_python();
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 400, None);
+    let mut input = PauliZProductInput::new(1, false);
+    let z0 = input.add_pauliz_product("ro".to_string(), vec![0]).unwrap();
+    input
+        .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+        .unwrap();
+    let measurement_wrapper = PauliZProductWrapper {
+        internal: PauliZProduct {
+            constant_circuit: None,
+            circuits: vec![circuit],
+            input,
+        },
+    };
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        let results: HashMap<String, (f64, f64)> = backend
+            .call_method1("run_measurement_with_uncertainty", (measurement_wrapper,))
+            .unwrap()
+            .extract()
+            .unwrap();
+        // <Z> = 0 with a standard error of 1 / sqrt(400)
+        let (value, uncertainty) = results["z0"];
+        assert!((uncertainty - 0.05).abs() < 0.01);
+        assert!(value.abs() < 5.0 * 0.05);
+
+        let not_pauli_z_product =
+            backend.call_method1("run_measurement_with_uncertainty", (CircuitWrapper::new(),));
+        assert!(not_pauli_z_product.is_err());
+    })
+}
+
+#[test]
+fn test_set_num_threads() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| -> () {
+        let backend_type = py.get_type::<BackendWrapper>();
+        let backend = backend_type
+            .call1((1,))
+            .unwrap()
+            .cast_as::<PyCell<BackendWrapper>>()
+            .unwrap();
+        backend.call_method1("set_num_threads", (1,)).unwrap();
+        let number_threads: usize = backend
+            .call_method0("get_num_threads")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(number_threads, 1);
+
+        let mut circuit = Circuit::new();
+        circuit += operations::PauliX::new(0);
+        let circuit_wrapper = CircuitWrapper { internal: circuit };
+        backend
+            .call_method1("run_circuit", (circuit_wrapper,))
+            .unwrap();
+        let info: HashMap<String, usize> = backend_type
+            .call_method0("environment_info")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(info["number_threads"], 1);
+
+        assert!(backend.call_method1("set_num_threads", (0,)).is_err());
+    })
+}
@@ -1,38 +1,97 @@
+#[cfg(not(feature = "system-quest"))]
 use cmake::Config;
-#[cfg(feature = "rebuild")]
+#[cfg(any(feature = "rebuild", feature = "system-quest"))]
 use std::env;
 #[cfg(feature = "rebuild")]
 use std::path::PathBuf;
+#[cfg(feature = "system-quest")]
+use std::process::Command;
+
+// QuEST precision passed to CMake, 1 for single and 2 for double precision
+#[cfg(not(feature = "single-precision"))]
+#[cfg_attr(
+    all(feature = "system-quest", not(feature = "rebuild")),
+    allow(dead_code)
+)]
+const PRECISION: &str = "2";
+#[cfg(feature = "single-precision")]
+#[cfg_attr(
+    all(feature = "system-quest", not(feature = "rebuild")),
+    allow(dead_code)
+)]
+const PRECISION: &str = "1";
+
+// Environment variable with the directory of a system-installed QuEST library
+#[cfg(feature = "system-quest")]
+const QUEST_LIB_DIR: &str = "QUEST_LIB_DIR";
+
+// Links against a system-installed QuEST library instead of building the vendored source.
+// The library is searched in the directory given by QUEST_LIB_DIR, otherwise it is found with pkg-config.
+#[cfg(feature = "system-quest")]
+fn link_system_quest() {
+    println!("cargo:rerun-if-env-changed={}", QUEST_LIB_DIR);
+    if let Ok(library_directory) = env::var(QUEST_LIB_DIR) {
+        println!("cargo:rustc-link-search=native={}", library_directory);
+        println!("cargo:rustc-link-lib=QuEST");
+        return;
+    }
+    let output = Command::new("pkg-config")
+        .args(["--libs", "QuEST"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .unwrap_or_else(|| {
+            panic!(
+                "The system-quest feature requires the directory of libQuEST in {} or a QuEST pkg-config file",
+                QUEST_LIB_DIR
+            )
+        });
+    for flag in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+        if let Some(library_directory) = flag.strip_prefix("-L") {
+            println!("cargo:rustc-link-search=native={}", library_directory);
+        } else if let Some(library) = flag.strip_prefix("-l") {
+            println!("cargo:rustc-link-lib={}", library);
+        }
+    }
+}
 
 fn main() {
     #[cfg(feature = "rebuild")]
     let out_dir_path = PathBuf::from(env::var("OUT_DIR").unwrap());
 
+    // a system-installed QuEST is linked directly and the CMake build is skipped
+    #[cfg(feature = "system-quest")]
+    link_system_quest();
+
     // use CMake to build quest and return path where the static library is placed
-    #[cfg(feature = "openmp")]
+    #[cfg(all(feature = "openmp", not(feature = "system-quest")))]
     let quest_library_path = Config::new("QuEST/QuEST")
         .no_build_target(true)
         .very_verbose(true)
         .always_configure(true)
         // activated openmp mulit-threading
         .define("MULTITHREADED", "1")
+        .define("PRECISION", PRECISION)
         // .define("CMAKE_C_COMPILER", "clang")
         .build()
         .join("build/");
 
-    #[cfg(not(feature = "openmp"))]
+    #[cfg(not(any(feature = "openmp", feature = "system-quest")))]
     let quest_library_path = Config::new("QuEST/QuEST")
         .no_build_target(true)
         .very_verbose(true)
         .always_configure(true)
         // deactivates multi-threading
         .define("MULTITHREADED", "0")
+        .define("PRECISION", PRECISION)
         .build()
         .join("build/");
+    #[cfg(not(feature = "system-quest"))]
     println!(
         "cargo:rustc-link-search=native={}",
         quest_library_path.display()
     );
+    #[cfg(not(feature = "system-quest"))]
     println!("cargo:rustc-link-lib=static=QuEST");
     println!("cargo:rerun-if-changed=wrapper.h");
 
@@ -40,6 +99,7 @@ fn main() {
     #[cfg(feature = "rebuild")]
     let builder = bindgen::Builder::default()
         .header("wrapper.h")
+        .clang_arg(format!("-DQuEST_PREC={}", PRECISION))
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
         .allowlist_function("create.*")
         .allowlist_function("cloneQureg")
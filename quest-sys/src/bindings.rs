@@ -73,8 +73,8 @@ fn bindgen_test_layout_QASMLogger() {
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct ComplexArray {
-    pub real: *mut f64,
-    pub imag: *mut f64,
+    pub real: *mut qreal,
+    pub imag: *mut qreal,
 }
 #[test]
 fn bindgen_test_layout_ComplexArray() {
@@ -125,8 +125,8 @@ pub type pauliOpType = ::std::os::raw::c_uint;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct Complex {
-    pub real: f64,
-    pub imag: f64,
+    pub real: qreal,
+    pub imag: qreal,
 }
 #[test]
 fn bindgen_test_layout_Complex() {
@@ -191,8 +191,8 @@ fn bindgen_test_layout_Complex() {
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct ComplexMatrix2 {
-    pub real: [[f64; 2usize]; 2usize],
-    pub imag: [[f64; 2usize]; 2usize],
+    pub real: [[qreal; 2usize]; 2usize],
+    pub imag: [[qreal; 2usize]; 2usize],
 }
 #[test]
 fn bindgen_test_layout_ComplexMatrix2() {
@@ -261,8 +261,8 @@ fn bindgen_test_layout_ComplexMatrix2() {
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct ComplexMatrix4 {
-    pub real: [[f64; 4usize]; 4usize],
-    pub imag: [[f64; 4usize]; 4usize],
+    pub real: [[qreal; 4usize]; 4usize],
+    pub imag: [[qreal; 4usize]; 4usize],
 }
 #[test]
 fn bindgen_test_layout_ComplexMatrix4() {
@@ -305,8 +305,8 @@ fn bindgen_test_layout_ComplexMatrix4() {
 #[derive(Debug, Copy, Clone)]
 pub struct ComplexMatrixN {
     pub numQubits: ::std::os::raw::c_int,
-    pub real: *mut *mut f64,
-    pub imag: *mut *mut f64,
+    pub real: *mut *mut qreal,
+    pub imag: *mut *mut qreal,
 }
 #[test]
 fn bindgen_test_layout_ComplexMatrixN() {
@@ -358,9 +358,9 @@ fn bindgen_test_layout_ComplexMatrixN() {
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct Vector {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: qreal,
+    pub y: qreal,
+    pub z: qreal,
 }
 #[test]
 fn bindgen_test_layout_Vector() {
@@ -479,7 +479,7 @@ pub struct PauliHamil {
     #[doc = "! This is a flat array of length PauliHamil.numSumTerms * PauliHamil.numQubits."]
     pub pauliCodes: *mut pauliOpType,
     #[doc = "! The real coefficient of each Pauli product. This is an array of length PauliHamil.numSumTerms;"]
-    pub termCoeffs: *mut f64,
+    pub termCoeffs: *mut qreal,
     #[doc = "! The number of terms in the weighted sum, or the number of Pauli products."]
     pub numSumTerms: ::std::os::raw::c_int,
     #[doc = "! The number of qubits informing the Hilbert dimension of the Hamiltonian."]
@@ -556,9 +556,9 @@ pub struct DiagonalOp {
     #[doc = "! The position of the chunk of the operator held by this process in the full operator"]
     pub chunkId: ::std::os::raw::c_int,
     #[doc = "! The real values of the 2^numQubits complex elements"]
-    pub real: *mut f64,
+    pub real: *mut qreal,
     #[doc = "! The imaginary values of the 2^numQubits complex elements"]
-    pub imag: *mut f64,
+    pub imag: *mut qreal,
     #[doc = "! A copy of the elements stored persistently on the GPU"]
     pub deviceOperator: ComplexArray,
 }
@@ -676,9 +676,9 @@ pub struct Qureg {
     #[doc = "! Storage for wavefunction amplitudes in the GPU version"]
     pub deviceStateVec: ComplexArray,
     #[doc = "! Storage for reduction of probabilities on GPU"]
-    pub firstLevelReduction: *mut f64,
+    pub firstLevelReduction: *mut qreal,
     #[doc = "! Storage for reduction of probabilities on GPU"]
-    pub secondLevelReduction: *mut f64,
+    pub secondLevelReduction: *mut qreal,
     #[doc = "! Storage for generated QASM output"]
     pub qasmLog: *mut QASMLogger,
 }
@@ -1282,7 +1282,7 @@ extern "C" {
     #[doc = " @throws invalidQuESTInputError()"]
     #[doc = " - if \\p m has not been allocated (e.g. with createComplexMatrixN())"]
     #[doc = " @author Tyson Jones"]
-    pub fn initComplexMatrixN(m: ComplexMatrixN, real: *mut *mut f64, imag: *mut *mut f64);
+    pub fn initComplexMatrixN(m: ComplexMatrixN, real: *mut *mut qreal, imag: *mut *mut qreal);
 }
 extern "C" {
     #[doc = " Dynamically allocates a Hamiltonian expressed as a real-weighted sum of products of Pauli operators."]
@@ -1417,7 +1417,7 @@ extern "C" {
     #[doc = " - if \\p hamil has invalid parameters (\\p numQubits <= 0, \\p numSumTerms <= 0)"]
     #[doc = " - if any code in \\p codes is not a valid Pauli code (::pauliOpType)"]
     #[doc = " @author Tyson Jones"]
-    pub fn initPauliHamil(hamil: PauliHamil, coeffs: *mut f64, codes: *mut pauliOpType);
+    pub fn initPauliHamil(hamil: PauliHamil, coeffs: *mut qreal, codes: *mut pauliOpType);
 }
 extern "C" {
     #[doc = " Creates a ::DiagonalOp representing a diagonal operator on the"]
@@ -1539,7 +1539,7 @@ extern "C" {
     #[doc = " @throws segmentation-fault"]
     #[doc = " - if either \\p real or \\p imag have length smaller than <b>pow(2, </b>`op.numQubits`<b>)</b>"]
     #[doc = " @author Tyson Jones"]
-    pub fn initDiagonalOp(op: DiagonalOp, real: *mut f64, imag: *mut f64);
+    pub fn initDiagonalOp(op: DiagonalOp, real: *mut qreal, imag: *mut qreal);
 }
 extern "C" {
     #[doc = " Populates the diagonal operator \\p op to be equivalent to the given Pauli"]
@@ -1691,8 +1691,8 @@ extern "C" {
     pub fn setDiagonalOpElems(
         op: DiagonalOp,
         startInd: ::std::os::raw::c_longlong,
-        real: *mut f64,
-        imag: *mut f64,
+        real: *mut qreal,
+        imag: *mut qreal,
         numElems: ::std::os::raw::c_longlong,
     );
 }
@@ -1999,7 +1999,7 @@ extern "C" {
     #[doc = " @throws segmentation-fault"]
     #[doc = " - if either \\p reals or \\p imags have fewer than `qureg.numAmpsTotal` elements"]
     #[doc = " @author Tyson Jones"]
-    pub fn initStateFromAmps(qureg: Qureg, reals: *mut f64, imags: *mut f64);
+    pub fn initStateFromAmps(qureg: Qureg, reals: *mut qreal, imags: *mut qreal);
 }
 extern "C" {
     #[doc = " Overwrites a subset of the amplitudes in state-vector \\p qureg, with those passed in \\p reals and \\p imags."]
@@ -2049,8 +2049,8 @@ extern "C" {
     pub fn setAmps(
         qureg: Qureg,
         startInd: ::std::os::raw::c_longlong,
-        reals: *mut f64,
-        imags: *mut f64,
+        reals: *mut qreal,
+        imags: *mut qreal,
         numAmps: ::std::os::raw::c_longlong,
     );
 }
@@ -2110,7 +2110,7 @@ extern "C" {
     #[doc = " @throws invalidQuESTInputError()"]
     #[doc = " - \\p targetQubit is outside [0, \\p qureg.numQubitsRepresented)."]
     #[doc = " @author Tyson Jones"]
-    pub fn phaseShift(qureg: Qureg, targetQubit: ::std::os::raw::c_int, angle: f64);
+    pub fn phaseShift(qureg: Qureg, targetQubit: ::std::os::raw::c_int, angle: qreal);
 }
 extern "C" {
     #[doc = " Introduce a phase factor \\f$ \\exp(i \\theta) \\f$ on state \\f$ |11\\rangle \\f$ of qubits"]
@@ -2159,7 +2159,7 @@ extern "C" {
         qureg: Qureg,
         idQubit1: ::std::os::raw::c_int,
         idQubit2: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -2207,7 +2207,7 @@ extern "C" {
         qureg: Qureg,
         controlQubits: *mut ::std::os::raw::c_int,
         numControlQubits: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -2466,7 +2466,7 @@ extern "C" {
     #[doc = " - if \\p qureg is a density matrix"]
     #[doc = " - if \\p index is outside [0, \\f$2^{N}\\f$) where \\f$N = \\f$ \\p qureg.numQubitsRepresented"]
     #[doc = " @author Ania Brown"]
-    pub fn getRealAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> f64;
+    pub fn getRealAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> qreal;
 }
 extern "C" {
     #[doc = " Get the imaginary component of the complex probability amplitude at an index in the state vector."]
@@ -2487,7 +2487,7 @@ extern "C" {
     #[doc = " - if \\p qureg is a density matrix"]
     #[doc = " - if \\p index is outside [0, \\f$2^{N}\\f$) where \\f$N = \\f$ \\p qureg.numQubitsRepresented"]
     #[doc = " @author Ania Brown"]
-    pub fn getImagAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> f64;
+    pub fn getImagAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> qreal;
 }
 extern "C" {
     #[doc = " Get the probability of a state-vector at an index in the full state vector."]
@@ -2508,7 +2508,7 @@ extern "C" {
     #[doc = " - if \\p qureg is a density matrix"]
     #[doc = " - if \\p index is outside [0, \\f$2^{N}\\f$) where \\f$N = \\f$ \\p qureg.numQubitsRepresented"]
     #[doc = " @author Ania Brown"]
-    pub fn getProbAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> f64;
+    pub fn getProbAmp(qureg: Qureg, index: ::std::os::raw::c_longlong) -> qreal;
 }
 extern "C" {
     #[doc = " Get an amplitude from a density matrix at a given row and column."]
@@ -2561,7 +2561,7 @@ extern "C" {
     #[doc = " @return the total probability of the qubits in \\p qureg being in any state"]
     #[doc = " @author Ania Brown (state-vector)"]
     #[doc = " @author Tyson Jones (density matrix, doc)"]
-    pub fn calcTotalProb(qureg: Qureg) -> f64;
+    pub fn calcTotalProb(qureg: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Apply a single-qubit unitary parameterised by two given complex scalars."]
@@ -2687,7 +2687,7 @@ extern "C" {
     #[doc = " - if \\p rotQubit is outside [0, \\p qureg.numQubitsRepresented)"]
     #[doc = " @author Ania Brown (state-vector)"]
     #[doc = " @author Tyson Jones (density matrix, doc)"]
-    pub fn rotateX(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: f64);
+    pub fn rotateX(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: qreal);
 }
 extern "C" {
     #[doc = " Rotate a single qubit by a given angle around the Y-axis of the Bloch-sphere."]
@@ -2726,7 +2726,7 @@ extern "C" {
     #[doc = "      if \\p rotQubit is outside [0, \\p qureg.numQubitsRepresented)."]
     #[doc = " @author Ania Brown (state-vector)"]
     #[doc = " @author Tyson Jones (density matrix, doc, debug)"]
-    pub fn rotateY(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: f64);
+    pub fn rotateY(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: qreal);
 }
 extern "C" {
     #[doc = " Rotate a single qubit by a given angle around the Z-axis of the Bloch-sphere (also known as a phase shift gate)."]
@@ -2766,7 +2766,7 @@ extern "C" {
     #[doc = " - if \\p rotQubit is outside [0, \\p qureg.numQubitsRepresented)"]
     #[doc = " @author Ania Brown (state-vector)"]
     #[doc = " @author Tyson Jones (density matrix, doc)"]
-    pub fn rotateZ(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: f64);
+    pub fn rotateZ(qureg: Qureg, rotQubit: ::std::os::raw::c_int, angle: qreal);
 }
 extern "C" {
     #[doc = " Rotate a single qubit by a given angle around a given \\ref Vector on the Bloch-sphere."]
@@ -2797,7 +2797,7 @@ extern "C" {
     pub fn rotateAroundAxis(
         qureg: Qureg,
         rotQubit: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
         axis: Vector,
     );
 }
@@ -2843,7 +2843,7 @@ extern "C" {
         qureg: Qureg,
         controlQubit: ::std::os::raw::c_int,
         targetQubit: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -2887,7 +2887,7 @@ extern "C" {
         qureg: Qureg,
         controlQubit: ::std::os::raw::c_int,
         targetQubit: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -2932,7 +2932,7 @@ extern "C" {
         qureg: Qureg,
         controlQubit: ::std::os::raw::c_int,
         targetQubit: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -2977,7 +2977,7 @@ extern "C" {
         qureg: Qureg,
         controlQubit: ::std::os::raw::c_int,
         targetQubit: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
         axis: Vector,
     );
 }
@@ -3518,7 +3518,7 @@ extern "C" {
         qureg: Qureg,
         measureQubit: ::std::os::raw::c_int,
         outcome: ::std::os::raw::c_int,
-    ) -> f64;
+    ) -> qreal;
 }
 extern "C" {
     #[doc = " Populates \\p outcomeProbs with the probabilities of every outcome of the sub-register"]
@@ -3608,7 +3608,7 @@ extern "C" {
     #[doc = " - if \\p outcomeProbs contains space for fewer than <b>1<<</b>\\p numQubits elements"]
     #[doc = " @author Tyson Jones"]
     pub fn calcProbOfAllOutcomes(
-        outcomeProbs: *mut f64,
+        outcomeProbs: *mut qreal,
         qureg: Qureg,
         qubits: *mut ::std::os::raw::c_int,
         numQubits: ::std::os::raw::c_int,
@@ -3717,7 +3717,7 @@ extern "C" {
     #[doc = " - if \\p rho1 and \\p rho2 have mismatching dimensions"]
     #[doc = " @author Balint Koczor (CPU)"]
     #[doc = " @author Tyson Jones (GPU)"]
-    pub fn calcDensityInnerProduct(rho1: Qureg, rho2: Qureg) -> f64;
+    pub fn calcDensityInnerProduct(rho1: Qureg, rho2: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Obtain the seeds presently used in random number generation."]
@@ -3796,7 +3796,7 @@ extern "C" {
     #[doc = " - if \\p prob is not in [0, 1/2]"]
     #[doc = " @author Tyson Jones (GPU, doc)"]
     #[doc = " @author Ania Brown (CPU, distributed)"]
-    pub fn mixDephasing(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: f64);
+    pub fn mixDephasing(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: qreal);
 }
 extern "C" {
     #[doc = " Mixes a density matrix \\p qureg to induce two-qubit dephasing noise."]
@@ -3832,7 +3832,7 @@ extern "C" {
         qureg: Qureg,
         qubit1: ::std::os::raw::c_int,
         qubit2: ::std::os::raw::c_int,
-        prob: f64,
+        prob: qreal,
     );
 }
 extern "C" {
@@ -3876,7 +3876,7 @@ extern "C" {
     #[doc = " - if \\p prob is not in [0, 3/4]"]
     #[doc = " @author Tyson Jones (GPU, doc)"]
     #[doc = " @author Ania Brown (CPU, distributed)"]
-    pub fn mixDepolarising(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: f64);
+    pub fn mixDepolarising(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: qreal);
 }
 extern "C" {
     #[doc = " Mixes a density matrix \\p qureg to induce single-qubit amplitude damping (decay to 0 state)."]
@@ -3914,7 +3914,7 @@ extern "C" {
     #[doc = " @author Nicolas Vogt of HQS (local CPU)"]
     #[doc = " @author Ania Brown (GPU, patched local CPU)"]
     #[doc = " @author Tyson Jones (distributed, doc)"]
-    pub fn mixDamping(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: f64);
+    pub fn mixDamping(qureg: Qureg, targetQubit: ::std::os::raw::c_int, prob: qreal);
 }
 extern "C" {
     #[doc = " Mixes a density matrix \\p qureg to induce two-qubit homogeneous depolarising noise."]
@@ -3985,7 +3985,7 @@ extern "C" {
         qureg: Qureg,
         qubit1: ::std::os::raw::c_int,
         qubit2: ::std::os::raw::c_int,
-        prob: f64,
+        prob: qreal,
     );
 }
 extern "C" {
@@ -4030,9 +4030,9 @@ extern "C" {
     pub fn mixPauli(
         qureg: Qureg,
         targetQubit: ::std::os::raw::c_int,
-        probX: f64,
-        probY: f64,
-        probZ: f64,
+        probX: qreal,
+        probY: qreal,
+        probZ: qreal,
     );
 }
 extern "C" {
@@ -4055,7 +4055,7 @@ extern "C" {
     #[doc = " - if the dimensions of \\p combineQureg and \\p otherQureg do not match"]
     #[doc = " - if \\p prob is not in [0, 1]"]
     #[doc = " @author Tyson Jones"]
-    pub fn mixDensityMatrix(combineQureg: Qureg, prob: f64, otherQureg: Qureg);
+    pub fn mixDensityMatrix(combineQureg: Qureg, prob: qreal, otherQureg: Qureg);
 }
 extern "C" {
     #[doc = " Calculates the purity of a density matrix, by the trace of the density matrix squared."]
@@ -4083,7 +4083,7 @@ extern "C" {
     #[doc = " - if the dimensions of \\p combineQureg and \\p otherQureg do not match"]
     #[doc = " - if \\p prob is not in [0, 1]"]
     #[doc = " @author Tyson Jones"]
-    pub fn calcPurity(qureg: Qureg) -> f64;
+    pub fn calcPurity(qureg: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Calculates the fidelity of \\p qureg (a state-vector or density matrix) against"]
@@ -4115,7 +4115,7 @@ extern "C" {
     #[doc = " - if the second argument (\\p pureState) is not a state-vector"]
     #[doc = " - if the number of qubits in \\p qureg and \\p pureState do not match"]
     #[doc = " @author Tyson Jones"]
-    pub fn calcFidelity(qureg: Qureg, pureState: Qureg) -> f64;
+    pub fn calcFidelity(qureg: Qureg, pureState: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Performs a SWAP gate between \\p qubit1 and \\p qubit2."]
@@ -4235,7 +4235,7 @@ extern "C" {
         numControls: ::std::os::raw::c_int,
         targetQubits: *mut ::std::os::raw::c_int,
         numTargets: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -4344,7 +4344,7 @@ extern "C" {
         targetQubits: *mut ::std::os::raw::c_int,
         targetPaulis: *mut pauliOpType,
         numTargets: ::std::os::raw::c_int,
-        angle: f64,
+        angle: qreal,
     );
 }
 extern "C" {
@@ -4402,7 +4402,7 @@ extern "C" {
         pauliCodes: *mut pauliOpType,
         numTargets: ::std::os::raw::c_int,
         workspace: Qureg,
-    ) -> f64;
+    ) -> qreal;
 }
 extern "C" {
     #[doc = " Computes the expected value of a sum of products of Pauli operators."]
@@ -4460,10 +4460,10 @@ extern "C" {
     pub fn calcExpecPauliSum(
         qureg: Qureg,
         allPauliCodes: *mut pauliOpType,
-        termCoeffs: *mut f64,
+        termCoeffs: *mut qreal,
         numSumTerms: ::std::os::raw::c_int,
         workspace: Qureg,
-    ) -> f64;
+    ) -> qreal;
 }
 extern "C" {
     #[doc = " Computes the expected value of \\p qureg under Hermitian operator \\p hamil."]
@@ -4504,7 +4504,7 @@ extern "C" {
     #[doc = " - if \\p hamil.numSumTerms <= 0"]
     #[doc = " - if \\p workspace is not of the same type and dimensions as \\p qureg and \\p hamil"]
     #[doc = " @author Tyson Jones"]
-    pub fn calcExpecPauliHamil(qureg: Qureg, hamil: PauliHamil, workspace: Qureg) -> f64;
+    pub fn calcExpecPauliHamil(qureg: Qureg, hamil: PauliHamil, workspace: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Apply a general two-qubit unitary (including a global phase factor)."]
@@ -5183,7 +5183,7 @@ extern "C" {
     #[doc = " - if \\p a and \\p have mismatching dimensions"]
     #[doc = " @author Balint Koczor"]
     #[doc = " @author Tyson Jones (refactored, doc)"]
-    pub fn calcHilbertSchmidtDistance(a: Qureg, b: Qureg) -> f64;
+    pub fn calcHilbertSchmidtDistance(a: Qureg, b: Qureg) -> qreal;
 }
 extern "C" {
     #[doc = " Modifies qureg \\p out to the result of (\\p facOut \\p out + \\p fac1 \\p qureg1 + \\p fac2 \\p qureg2),"]
@@ -5277,7 +5277,7 @@ extern "C" {
     pub fn applyPauliSum(
         inQureg: Qureg,
         allPauliCodes: *mut pauliOpType,
-        termCoeffs: *mut f64,
+        termCoeffs: *mut qreal,
         numSumTerms: ::std::os::raw::c_int,
         outQureg: Qureg,
     );
@@ -5407,7 +5407,7 @@ extern "C" {
     pub fn applyTrotterCircuit(
         qureg: Qureg,
         hamil: PauliHamil,
-        time: f64,
+        time: qreal,
         order: ::std::os::raw::c_int,
         reps: ::std::os::raw::c_int,
     );
@@ -5734,8 +5734,8 @@ extern "C" {
         qubits: *mut ::std::os::raw::c_int,
         numQubits: ::std::os::raw::c_int,
         encoding: bitEncoding,
-        coeffs: *mut f64,
-        exponents: *mut f64,
+        coeffs: *mut qreal,
+        exponents: *mut qreal,
         numTerms: ::std::os::raw::c_int,
     );
 }
@@ -5853,11 +5853,11 @@ extern "C" {
         qubits: *mut ::std::os::raw::c_int,
         numQubits: ::std::os::raw::c_int,
         encoding: bitEncoding,
-        coeffs: *mut f64,
-        exponents: *mut f64,
+        coeffs: *mut qreal,
+        exponents: *mut qreal,
         numTerms: ::std::os::raw::c_int,
         overrideInds: *mut ::std::os::raw::c_longlong,
-        overridePhases: *mut f64,
+        overridePhases: *mut qreal,
         numOverrides: ::std::os::raw::c_int,
     );
 }
@@ -6026,8 +6026,8 @@ extern "C" {
         numQubitsPerReg: *mut ::std::os::raw::c_int,
         numRegs: ::std::os::raw::c_int,
         encoding: bitEncoding,
-        coeffs: *mut f64,
-        exponents: *mut f64,
+        coeffs: *mut qreal,
+        exponents: *mut qreal,
         numTermsPerReg: *mut ::std::os::raw::c_int,
     );
 }
@@ -6117,11 +6117,11 @@ extern "C" {
         numQubitsPerReg: *mut ::std::os::raw::c_int,
         numRegs: ::std::os::raw::c_int,
         encoding: bitEncoding,
-        coeffs: *mut f64,
-        exponents: *mut f64,
+        coeffs: *mut qreal,
+        exponents: *mut qreal,
         numTermsPerReg: *mut ::std::os::raw::c_int,
         overrideInds: *mut ::std::os::raw::c_longlong,
-        overridePhases: *mut f64,
+        overridePhases: *mut qreal,
         numOverrides: ::std::os::raw::c_int,
     );
 }
@@ -6351,7 +6351,7 @@ extern "C" {
         encoding: bitEncoding,
         functionNameCode: phaseFunc,
         overrideInds: *mut ::std::os::raw::c_longlong,
-        overridePhases: *mut f64,
+        overridePhases: *mut qreal,
         numOverrides: ::std::os::raw::c_int,
     );
 }
@@ -6478,7 +6478,7 @@ extern "C" {
         numRegs: ::std::os::raw::c_int,
         encoding: bitEncoding,
         functionNameCode: phaseFunc,
-        params: *mut f64,
+        params: *mut qreal,
         numParams: ::std::os::raw::c_int,
     );
 }
@@ -6562,10 +6562,10 @@ extern "C" {
         numRegs: ::std::os::raw::c_int,
         encoding: bitEncoding,
         functionNameCode: phaseFunc,
-        params: *mut f64,
+        params: *mut qreal,
         numParams: ::std::os::raw::c_int,
         overrideInds: *mut ::std::os::raw::c_longlong,
-        overridePhases: *mut f64,
+        overridePhases: *mut qreal,
         numOverrides: ::std::os::raw::c_int,
     );
 }
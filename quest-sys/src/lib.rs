@@ -26,6 +26,52 @@ use std::include;
 #[cfg(feature = "openmp")]
 extern crate openmp_sys;
 
+/// Floating point type of QuEST (`qreal`), single precision with the `single-precision` feature.
+#[cfg(not(feature = "single-precision"))]
+pub type qreal = f64;
+/// Floating point type of QuEST (`qreal`), single precision with the `single-precision` feature.
+#[cfg(feature = "single-precision")]
+pub type qreal = f32;
+
+/// The QuEST precision the library is compiled with (`QuEST_PREC`), 1 for single and 2 for double precision.
+#[cfg(not(feature = "single-precision"))]
+pub const QUEST_PRECISION: u32 = 2;
+/// The QuEST precision the library is compiled with (`QuEST_PREC`), 1 for single and 2 for double precision.
+#[cfg(feature = "single-precision")]
+pub const QUEST_PRECISION: u32 = 1;
+
+#[cfg(feature = "openmp")]
+extern "C" {
+    /// Sets the number of OpenMP threads of the parallel regions started by the calling thread.
+    pub fn omp_set_num_threads(num_threads: ::std::os::raw::c_int);
+    /// Returns the number of OpenMP threads of the parallel regions started by the calling thread.
+    pub fn omp_get_max_threads() -> ::std::os::raw::c_int;
+}
+
+// QuEST functions that are declared by hand and are not part of the bindgen allowlist in build.rs
+extern "C" {
+    /// Seeds the random number generator of QuEST with the `numSeeds` keys in `seedArray`.
+    pub fn seedQuEST(
+        env: *mut QuESTEnv,
+        seedArray: *mut ::std::os::raw::c_ulong,
+        numSeeds: ::std::os::raw::c_int,
+    );
+    /// Applies the rotation exp(-i angle/2 P) of the Pauli product P of `targetPaulis` on `targetQubits`.
+    pub fn multiRotatePauli(
+        qureg: Qureg,
+        targetQubits: *mut ::std::os::raw::c_int,
+        targetPaulis: *mut pauliOpType,
+        numTargets: ::std::os::raw::c_int,
+        angle: qreal,
+    );
+    /// Projects `measureQubit` onto `outcome`, renormalises the state and returns the probability of the outcome.
+    pub fn collapseToOutcome(
+        qureg: Qureg,
+        measureQubit: ::std::os::raw::c_int,
+        outcome: ::std::os::raw::c_int,
+    ) -> qreal;
+}
+
 #[cfg(feature = "rebuild")]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
@@ -1,5 +1,6 @@
 use criterion::{criterion_group, Criterion};
 use criterion::{BenchmarkId, Throughput};
+use roqoqo::measurements::ClassicalRegister;
 use roqoqo::operations;
 use roqoqo::prelude::EvaluatingBackend;
 use roqoqo::Circuit;
@@ -32,4 +33,109 @@ fn bench_run_long_circuit(c: &mut Criterion) {
     subgroup.finish();
 }
 
-criterion_group!(benches, bench_run_long_circuit,);
+fn bench_run_phase_circuit(c: &mut Criterion) {
+    let mut subgroup = c.benchmark_group("run_phase_circuit_density_matrix");
+    let number = 8;
+    let mut circuit = Circuit::new();
+    for i in 0..number {
+        circuit += operations::Hadamard::new(i);
+    }
+    circuit += operations::PragmaDamping::new(0, 0.01.into(), 1.0.into());
+    for j in 0..100 {
+        for i in 0..3 {
+            circuit += operations::RotateZ::new(i, (0.01_f64 * j as f64).into());
+            circuit +=
+                operations::ControlledPhaseShift::new(i, i + 1, (0.02_f64 * j as f64).into());
+            circuit += operations::PhaseShiftState1::new(i + 1, (0.03_f64 * j as f64).into());
+        }
+        circuit += operations::RotateX::new(number - 1, (0.01_f64 * j as f64).into());
+    }
+    for group_diagonal_gates in [true, false] {
+        subgroup.bench_with_input(
+            BenchmarkId::from_parameter(group_diagonal_gates),
+            &group_diagonal_gates,
+            |bench, &group_diagonal_gates| {
+                let backend = Backend::new(number).set_group_diagonal_gates(group_diagonal_gates);
+                bench.iter(|| {
+                    let _res = backend.run_circuit(&circuit);
+                });
+            },
+        );
+    }
+    subgroup.finish();
+}
+
+fn bench_run_pm_interaction_circuit(c: &mut Criterion) {
+    let mut subgroup = c.benchmark_group("run_pm_interaction_circuit");
+    let number = 16;
+    // PMInteraction is applied with its analytic matrix, the equivalent XY gate with the generic two-qubit unitary
+    for native in [true, false] {
+        let mut circuit = Circuit::new();
+        circuit += operations::PauliX::new(0);
+        for j in 0..200 {
+            for i in 0..number - 1 {
+                let t = 0.01_f64 * j as f64;
+                if native {
+                    circuit += operations::PMInteraction::new(i, i + 1, t.into());
+                } else {
+                    circuit += operations::XY::new(i, i + 1, (-2.0 * t).into());
+                }
+            }
+        }
+        subgroup.bench_with_input(BenchmarkId::from_parameter(native), &native, |bench, _| {
+            let backend = Backend::new(number);
+            bench.iter(|| {
+                let _res = backend.run_circuit(&circuit);
+            });
+        });
+    }
+    subgroup.finish();
+}
+
+fn bench_run_measurement_constant_circuit(c: &mut Criterion) {
+    let mut subgroup = c.benchmark_group("run_measurement_constant_circuit");
+    let number = 12;
+    let mut constant_circuit = Circuit::new();
+    for j in 0..200 {
+        for i in 0..number {
+            constant_circuit += operations::RotateX::new(i, (0.01_f64 * j as f64).into());
+        }
+        for i in 0..number - 1 {
+            constant_circuit += operations::CNOT::new(i, i + 1);
+        }
+    }
+    let mut circuits: Vec<Circuit> = Vec::new();
+    for i in 0..number {
+        let mut circuit = Circuit::new();
+        circuit += operations::DefinitionBit::new("ro".to_string(), number, true);
+        circuit += operations::Hadamard::new(i);
+        circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 100, None);
+        circuits.push(circuit);
+    }
+    let measurement = ClassicalRegister {
+        constant_circuit: Some(constant_circuit),
+        circuits,
+    };
+    for reuse_constant_circuit in [true, false] {
+        subgroup.bench_with_input(
+            BenchmarkId::from_parameter(reuse_constant_circuit),
+            &reuse_constant_circuit,
+            |bench, &reuse_constant_circuit| {
+                let backend =
+                    Backend::new(number).set_reuse_constant_circuit(reuse_constant_circuit);
+                bench.iter(|| {
+                    let _res = backend.run_measurement_registers(&measurement);
+                });
+            },
+        );
+    }
+    subgroup.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_run_long_circuit,
+    bench_run_phase_circuit,
+    bench_run_pm_interaction_circuit,
+    bench_run_measurement_constant_circuit,
+);
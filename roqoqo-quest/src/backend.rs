@@ -10,57 +10,2045 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::interface::call_operation_with_device;
-use qoqo_calculator::CalculatorFloat;
+use crate::interface::{
+    call_operation_with_device, cumulative_measurement_probabilities, index_to_qubits,
+    is_supported_operation, map_measured_qubits, sample_inverse_cdf,
+};
+use qoqo_calculator::{Calculator, CalculatorError, CalculatorFloat};
 use roqoqo::backends::EvaluatingBackend;
 // use roqoqo::measurements::Measure;
-use crate::Qureg;
+use crate::{
+    check_number_threads, number_amplitudes, number_threads, set_number_threads, Qureg,
+    ReadoutModel, DEFAULT_PROBABILITY_TOLERANCE,
+};
+use ndarray::Array2;
+use num_complex::Complex64;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
 use roqoqo::backends::RegisterResult;
+use roqoqo::devices::Device;
+use roqoqo::measurements::{
+    Measure, MeasureExpectationValues, PauliProductsToExpVal, PauliZProduct,
+};
 use roqoqo::operations::*;
 use roqoqo::registers::{
     BitOutputRegister, BitRegister, ComplexOutputRegister, ComplexRegister, FloatOutputRegister,
     FloatRegister,
 };
-use roqoqo::RoqoqoBackendError;
+use roqoqo::Circuit;
+use roqoqo::{RoqoqoBackendError, RoqoqoError};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Output registers (bit, float and complex) written by running a circuit
+pub type OutputRegisters = (
+    HashMap<String, BitOutputRegister>,
+    HashMap<String, FloatOutputRegister>,
+    HashMap<String, ComplexOutputRegister>,
+);
+
+/// Counts of the measured bitstrings of each readout register and the probabilities of the basis states
+pub type CountsAndProbabilities = (HashMap<String, HashMap<Vec<bool>, usize>>, Vec<f64>);
+
+// Counts of the measured bitstrings of each readout register
+type RegisterCounts = HashMap<String, HashMap<Vec<bool>, usize>>;
+
+/// Flag to abort a running simulation from another thread
+///
+/// Clones of the flag share the same state, so a clone can be kept by another thread
+/// and cancel the simulations while the backend is running a circuit.
+/// A call to [CancellationFlag::cancel] aborts all simulations that are running with the flag,
+/// a call made while no simulation is running does not abort a later one.
+/// The flag can also watch a shared boolean owned by the caller, see [Backend::set_cancellation_flag].
+/// The boolean is only read by the backend and every simulation is aborted while it is set.
+/// The flag is not part of the backend configuration and is not serialized.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationFlag {
+    // Shared boolean of the caller, never written by the backend
+    token: Option<Arc<AtomicBool>>,
+    // Number of calls to cancel
+    cancel_requests: Arc<AtomicUsize>,
+    // Number of calls to cancel when the currently running simulations started
+    cancel_requests_at_start: Arc<AtomicUsize>,
+    // Number of simulations that are currently running with the flag
+    running_simulations: Arc<AtomicUsize>,
+}
+
+impl CancellationFlag {
+    /// Creates a cancellation flag watching a shared atomic boolean.
+    ///
+    /// # Arguments
+    ///
+    /// `token` - The shared boolean that cancels the simulations while it is set to true
+    pub fn new(token: Arc<AtomicBool>) -> Self {
+        Self {
+            token: Some(token),
+            ..Self::default()
+        }
+    }
+
+    /// Requests the cancellation of the currently running simulations.
+    pub fn cancel(&self) {
+        self.cancel_requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns true if the running simulations have been cancelled or the shared boolean is set.
+    pub fn is_cancelled(&self) -> bool {
+        self.token
+            .as_ref()
+            .map_or(false, |token| token.load(Ordering::SeqCst))
+            || self.cancel_requests.load(Ordering::SeqCst)
+                != self.cancel_requests_at_start.load(Ordering::SeqCst)
+    }
+
+    // Marks a simulation as running until the returned guard is dropped,
+    // requests made before the first running simulation started are ignored
+    fn start_simulation(&self) -> RunningSimulation<'_> {
+        if self.running_simulations.fetch_add(1, Ordering::SeqCst) == 0 {
+            self.cancel_requests_at_start.store(
+                self.cancel_requests.load(Ordering::SeqCst),
+                Ordering::SeqCst,
+            );
+        }
+        RunningSimulation(self)
+    }
+}
+
+// Guard of a running simulation created by CancellationFlag::start_simulation
+struct RunningSimulation<'a>(&'a CancellationFlag);
+
+impl Drop for RunningSimulation<'_> {
+    fn drop(&mut self) {
+        self.0.running_simulations.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Device stored in the backend that is used for availability checks
+///
+/// Clones of the backend share the same device.
+/// The device is not serialized and two stored devices are equal when they are the same shared device.
+#[derive(Clone, Default)]
+pub struct BackendDevice(pub Option<Arc<Mutex<Box<dyn Device + Send>>>>);
+
+impl std::fmt::Debug for BackendDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(device) => match device.lock() {
+                Ok(device) => write!(
+                    f,
+                    "BackendDevice(number_qubits: {})",
+                    device.number_qubits()
+                ),
+                Err(_) => write!(f, "BackendDevice(<inaccessible>)"),
+            },
+            None => write!(f, "BackendDevice(None)"),
+        }
+    }
+}
+
+impl PartialEq for BackendDevice {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(device), Some(other_device)) => Arc::ptr_eq(device, other_device),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Infinite iterator over measurements of all qubits sampled from a fixed final state.
+///
+/// Created with [Backend::measure_stream].
+#[derive(Debug)]
+pub struct MeasurementStream {
+    /// Quantum register holding the final state, its random number generator samples the shots
+    qureg: Qureg,
+    /// Cumulative probabilities of the basis states of the final state
+    cumulative_probabilities: Vec<f64>,
+}
+
+impl MeasurementStream {
+    /// Returns the probabilities of the basis states of the final state the shots are sampled from.
+    ///
+    /// The n-th entry is the probability of the basis state with index n,
+    /// qubit 0 being the least significant bit. Readout errors are not included.
+    pub fn probabilities(&self) -> Result<Vec<f64>, RoqoqoBackendError> {
+        let mut probabilities = vec![0.0; self.qureg.number_amplitudes()?];
+        self.qureg.probabilities_into(&mut probabilities)?;
+        Ok(probabilities)
+    }
+}
+
+impl Iterator for MeasurementStream {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        let index = sample_inverse_cdf(&self.cumulative_probabilities, &mut self.qureg.rng);
+        let mut shot = index_to_qubits(index, self.qureg.number_qubits());
+        self.qureg.apply_readout_errors(&mut shot);
+        Some(shot)
+    }
+}
+
+/// Names and lengths of the output registers a circuit writes to, see [Backend::output_registers]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegisterShapes {
+    /// Lengths of the bit output registers
+    pub bit_registers: HashMap<String, usize>,
+    /// Lengths of the float output registers
+    pub float_registers: HashMap<String, usize>,
+    /// Lengths of the complex output registers
+    pub complex_registers: HashMap<String, usize>,
+}
+
+/// Wall-clock timing of a simulation run with [Backend::run_circuit_timed].
+///
+/// The time in gate operations and measurements is summed over all repetitions of the circuit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationTiming {
+    /// Total time of the run including the preparation of the circuit and the output registers
+    pub total: Duration,
+    /// Time spent applying gate operations with QuEST
+    pub gate_operations: Duration,
+    /// Time spent measuring and sampling measurement outcomes
+    pub measurements: Duration,
+}
+
+impl SimulationTiming {
+    // Adds the time of an operation to the gate or measurement time, other operations are not counted
+    fn record(&mut self, operation: &Operation, elapsed: Duration) {
+        match operation {
+            Operation::MeasureQubit(_) | Operation::PragmaRepeatedMeasurement(_) => {
+                self.measurements += elapsed
+            }
+            _ if GateOperation::try_from(operation).is_ok() => self.gate_operations += elapsed,
+            _ => (),
+        }
+    }
+}
+
 /// QuEST backend
 ///
 /// provides functions to run circuits and measurements on with the QuEST quantum simulator.
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+/// Fields that were added after version 0.7 default to the values of [Backend::new] when deserializing.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Backend {
     /// Number of qubits supported by the backend
     pub number_qubits: usize,
     /// Number of repetitions
     pub repetitions: usize,
+    /// Flag that aborts a running simulation when set
+    #[serde(skip)]
+    pub cancellation_flag: CancellationFlag,
+    /// Apply PragmaGlobalPhase to the state vector instead of ignoring it
+    #[serde(default)]
+    pub track_global_phase: bool,
+    /// Optional seed for the random number generators used in the simulation
+    #[serde(default)]
+    pub random_seed: Option<Vec<u64>>,
+    /// Rescale state vectors and density matrices of set-state pragmas to norm one
+    #[serde(default)]
+    pub renormalize_set_state: bool,
+    /// Optional number of measurements that replaces the number of measurements in the circuit
+    #[serde(default)]
+    pub measurement_override: Option<usize>,
+    /// Optional device that is used for availability checks when running circuits
+    #[serde(skip)]
+    pub device: BackendDevice,
+    /// Insert damping and dephasing on the idle qubits of the device after each PragmaStopParallelBlock
+    #[serde(default)]
+    pub auto_idle_noise: bool,
+    /// Apply damping and dephasing to all qubits of the device for the duration of each gate
+    #[serde(default)]
+    pub continuous_decoherence: bool,
+    /// Apply runs of consecutive diagonal gates as a single diagonal operator on density matrices
+    #[serde(default = "default_true")]
+    pub group_diagonal_gates: bool,
+    /// Optional index of the classical basis state the simulation starts in instead of |0...0>
+    #[serde(default)]
+    pub initial_state: Option<usize>,
+    /// Start the simulation in the equal superposition state |+...+> instead of |0...0>
+    #[serde(default)]
+    pub initial_plus_state: bool,
+    /// Largest magnitude of a negative probability that is treated as zero when sampling measurements
+    #[serde(default = "default_probability_tolerance")]
+    pub probability_tolerance: f64,
+    /// Simulate the constant circuit of a measurement once and copy its final state for each circuit
+    #[serde(default)]
+    pub reuse_constant_circuit: bool,
+    /// Readout confusion matrices of qubits, entry [measured][detected] is the detection probability
+    #[serde(default)]
+    pub readout_errors: ReadoutModel,
+    /// Skip operations that are not supported by the backend with a warning instead of aborting the run
+    #[serde(default)]
+    pub skip_unsupported: bool,
+    /// Always simulate a state vector, circuits with operations that require a density matrix return an error
+    #[serde(default)]
+    pub force_statevector: bool,
+    /// Sample MeasureQubit from the probability of the qubit without collapsing the state
+    #[serde(default)]
+    pub non_collapsing_measurement: bool,
+    /// Collapse MeasureQubit to the most likely value of the qubit instead of sampling it
+    #[serde(default)]
+    pub deterministic_measurement: bool,
+    /// Optional density matrix every simulation starts in, forces a density matrix simulation
+    #[serde(default)]
+    pub initial_density_matrix: Option<Array2<Complex64>>,
+    /// Optional number of shots PragmaGetPauliProduct is estimated from instead of returning the exact value
+    #[serde(default)]
+    pub pauli_product_shots: Option<usize>,
+    /// Optional number of OpenMP threads QuEST uses, None keeps the OpenMP default (`OMP_NUM_THREADS`)
+    #[serde(default)]
+    pub number_threads: Option<usize>,
+}
+
+// Defaults of the serialized fields that were added after version 0.7 and are not false or empty,
+// so that serialized backends of older versions can still be deserialized
+fn default_true() -> bool {
+    true
+}
+
+fn default_probability_tolerance() -> f64 {
+    DEFAULT_PROBABILITY_TOLERANCE
 }
 
-impl Backend {
-    /// Creates a new QuEST backend.
-    ///
-    /// # Arguments
-    ///
-    /// `number_qubits` - The number of qubits supported by the backend
-    pub fn new(number_qubits: usize) -> Self {
-        Self {
-            number_qubits,
-            repetitions: 1,
+// The cancellation flag is not part of the configuration and is left out of the comparison
+impl PartialEq for Backend {
+    fn eq(&self, other: &Self) -> bool {
+        let Backend {
+            number_qubits,
+            repetitions,
+            cancellation_flag: _,
+            track_global_phase,
+            random_seed,
+            renormalize_set_state,
+            measurement_override,
+            device,
+            auto_idle_noise,
+            continuous_decoherence,
+            group_diagonal_gates,
+            initial_state,
+            initial_plus_state,
+            probability_tolerance,
+            reuse_constant_circuit,
+            readout_errors,
+            skip_unsupported,
+            force_statevector,
+            non_collapsing_measurement,
+            deterministic_measurement,
+            initial_density_matrix,
+            pauli_product_shots,
+            number_threads,
+        } = self;
+        *number_qubits == other.number_qubits
+            && *repetitions == other.repetitions
+            && *track_global_phase == other.track_global_phase
+            && *random_seed == other.random_seed
+            && *renormalize_set_state == other.renormalize_set_state
+            && *measurement_override == other.measurement_override
+            && *device == other.device
+            && *auto_idle_noise == other.auto_idle_noise
+            && *continuous_decoherence == other.continuous_decoherence
+            && *group_diagonal_gates == other.group_diagonal_gates
+            && *initial_state == other.initial_state
+            && *initial_plus_state == other.initial_plus_state
+            && *probability_tolerance == other.probability_tolerance
+            && *reuse_constant_circuit == other.reuse_constant_circuit
+            && *readout_errors == other.readout_errors
+            && *skip_unsupported == other.skip_unsupported
+            && *force_statevector == other.force_statevector
+            && *non_collapsing_measurement == other.non_collapsing_measurement
+            && *deterministic_measurement == other.deterministic_measurement
+            && *initial_density_matrix == other.initial_density_matrix
+            && *pauli_product_shots == other.pauli_product_shots
+            && *number_threads == other.number_threads
+    }
+}
+
+/// Builder for the configuration of a [Backend].
+///
+/// Collects the configuration with chainable methods and creates the backend with [BackendBuilder::build].
+/// Options that are not set keep the defaults of [Backend::new].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackendBuilder {
+    number_qubits: Option<usize>,
+    repetitions: Option<usize>,
+    random_seed: Option<Vec<u64>>,
+    readout_model: ReadoutModel,
+}
+
+impl BackendBuilder {
+    /// Creates a new builder without any configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of qubits of the backend, required to build the backend.
+    ///
+    /// # Arguments
+    ///
+    /// `number_qubits` - The number of qubits supported by the backend
+    pub fn number_qubits(mut self, number_qubits: usize) -> Self {
+        self.number_qubits = Some(number_qubits);
+        self
+    }
+
+    /// Sets the number of repetitions of stochastic simulations.
+    ///
+    /// # Arguments
+    ///
+    /// `repetitions` - The number of repetitions
+    pub fn repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = Some(repetitions);
+        self
+    }
+
+    /// Sets the seed of the random number generators used in the simulation.
+    ///
+    /// # Arguments
+    ///
+    /// `random_seed` - The seed array for the random number generators
+    pub fn random_seed(mut self, random_seed: Vec<u64>) -> Self {
+        self.random_seed = Some(random_seed);
+        self
+    }
+
+    /// Adds the readout error of a qubit as a confusion matrix, see [Backend::set_readout_error].
+    ///
+    /// # Arguments
+    ///
+    /// `qubit` - The qubit the readout error applies to
+    /// `confusion_matrix` - The 2x2 confusion matrix of the qubit
+    pub fn readout_error(mut self, qubit: usize, confusion_matrix: [[f64; 2]; 2]) -> Self {
+        self.readout_model.insert(qubit, confusion_matrix);
+        self
+    }
+
+    /// Sets the readout errors of all qubits, replacing readout errors that were added before.
+    ///
+    /// # Arguments
+    ///
+    /// `readout_model` - The confusion matrix of each qubit with a readout error
+    pub fn imperfect_readout_model(mut self, readout_model: ReadoutModel) -> Self {
+        self.readout_model = readout_model;
+        self
+    }
+
+    /// Creates the configured backend.
+    ///
+    /// Returns an error if the number of qubits is not set or a readout error is invalid.
+    pub fn build(self) -> Result<Backend, RoqoqoBackendError> {
+        let number_qubits = self
+            .number_qubits
+            .ok_or_else(|| RoqoqoBackendError::GenericError {
+                msg: "Number of qubits must be set to build a Backend".to_string(),
+            })?;
+        let mut backend = Backend::new(number_qubits);
+        backend.check_number_qubits()?;
+        if let Some(repetitions) = self.repetitions {
+            backend = backend.set_repetitions(repetitions);
+        }
+        backend.random_seed = self.random_seed;
+        backend.set_imperfect_readout_model(self.readout_model)
+    }
+}
+
+impl Backend {
+    /// Returns a [BackendBuilder] to configure a new backend.
+    pub fn builder() -> BackendBuilder {
+        BackendBuilder::new()
+    }
+
+    /// Creates a new QuEST backend.
+    ///
+    /// # Arguments
+    ///
+    /// `number_qubits` - The number of qubits supported by the backend
+    pub fn new(number_qubits: usize) -> Self {
+        Self {
+            number_qubits,
+            repetitions: 1,
+            cancellation_flag: CancellationFlag::default(),
+            track_global_phase: false,
+            random_seed: None,
+            renormalize_set_state: false,
+            measurement_override: None,
+            device: BackendDevice::default(),
+            auto_idle_noise: false,
+            continuous_decoherence: false,
+            group_diagonal_gates: true,
+            initial_state: None,
+            initial_plus_state: false,
+            probability_tolerance: DEFAULT_PROBABILITY_TOLERANCE,
+            reuse_constant_circuit: false,
+            readout_errors: HashMap::new(),
+            skip_unsupported: false,
+            force_statevector: false,
+            non_collapsing_measurement: false,
+            deterministic_measurement: false,
+            initial_density_matrix: None,
+            pauli_product_shots: None,
+            number_threads: None,
+        }
+    }
+
+    /// Creates a new QuEST backend that starts each simulation in the state |+...+>.
+    ///
+    /// The quantum register is initialized with QuEST's initPlusState at the start of each repetition,
+    /// so circuits do not need to start with a Hadamard gate on every qubit.
+    ///
+    /// # Arguments
+    ///
+    /// `number_qubits` - The number of qubits supported by the backend
+    pub fn new_plus(number_qubits: usize) -> Self {
+        Self {
+            initial_plus_state: true,
+            ..Self::new(number_qubits)
+        }
+    }
+
+    /// Creates a new QuEST backend sized to a device.
+    ///
+    /// The number of qubits of the backend is the number of qubits of the device.
+    /// The device is stored in the backend and the availability of each operation
+    /// on the device is checked when running circuits.
+    ///
+    /// # Arguments
+    ///
+    /// `device` - The device the backend simulates
+    /// `random_seed` - The optional seed for the random number generators used in the simulation
+    pub fn from_device(device: Box<dyn Device + Send>, random_seed: Option<Vec<u64>>) -> Self {
+        let mut backend = Self::new(device.number_qubits());
+        backend.random_seed = random_seed;
+        backend.device = BackendDevice(Some(Arc::new(Mutex::new(device))));
+        backend
+    }
+
+    /// Sets whether idle noise is inserted automatically after parallel blocks.
+    ///
+    /// When set and the backend is run with a device, a PragmaDamping and a PragmaDephasing
+    /// with the execution time of the block are inserted after each PragmaStopParallelBlock
+    /// for every qubit of the device that is not part of the block.
+    /// The damping and dephasing rates are taken from the diagonal of the decoherence rates of the device.
+    /// The rates are read from the device at each block, so a PragmaChangeDevice changes the noise of the following blocks.
+    ///
+    /// # Arguments
+    ///
+    /// `auto_idle_noise` - Insert idle noise after each PragmaStopParallelBlock
+    pub fn set_auto_idle_noise(mut self, auto_idle_noise: bool) -> Self {
+        self.auto_idle_noise = auto_idle_noise;
+        self
+    }
+
+    /// Sets whether always-on decoherence is applied during every gate.
+    ///
+    /// When set and the backend is run with a device, a PragmaDamping and a PragmaDephasing
+    /// with the gate time of the device are inserted after each gate operation
+    /// for every qubit of the device, so qubits decay while gates are applied to any qubit.
+    /// The damping and dephasing rates are taken from the diagonal of the decoherence rates of the device.
+    /// Gates without a gate time on the device do not add decoherence.
+    /// Gate times and rates are read from the device at each gate, so a PragmaChangeDevice changes the noise of the following gates.
+    ///
+    /// # Arguments
+    ///
+    /// `continuous_decoherence` - Apply decoherence to all qubits for the duration of each gate
+    pub fn set_continuous_decoherence(mut self, continuous_decoherence: bool) -> Self {
+        self.continuous_decoherence = continuous_decoherence;
+        self
+    }
+
+    /// Sets whether runs of consecutive diagonal gates are applied together.
+    ///
+    /// In density matrix mode, consecutive RotateZ, PhaseShiftState1 and ControlledPhaseShift gates
+    /// acting on at most four qubits are combined into a single diagonal operator,
+    /// which is applied in one pass over the density matrix. Grouping is enabled by default
+    /// and is not used when the availability of operations is checked on a device.
+    ///
+    /// # Arguments
+    ///
+    /// `group_diagonal_gates` - Apply runs of diagonal gates as a single diagonal operator
+    pub fn set_group_diagonal_gates(mut self, group_diagonal_gates: bool) -> Self {
+        self.group_diagonal_gates = group_diagonal_gates;
+        self
+    }
+
+    /// Sets the number of repetitions used for stochastic circuit simulations
+    ///
+    /// The number of repetitions of the actual simulation is set to one by default.
+    /// The repetitions are not to be confused with the number of simulated measurements per simulation run
+    /// set with PragmaRepeatedMeasurement or PragmaSetNumberMeasurements.
+    /// Should only be different from one if a stochastic unravelling of a noisy simulation is used
+    /// with PragmaRandomNoise or PragmaOverrotation
+    ///
+    /// # Arguments
+    ///
+    /// `repetitions` - The number of repetitions that is set
+    pub fn set_repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = repetitions;
+        self
+    }
+
+    /// Sets the flag that is used to abort running simulations
+    ///
+    /// The flag is checked before each operation and each repetition of the simulation.
+    /// While it is set every simulation returns an error. The backend never resets the flag,
+    /// the caller clears it to run further simulations, see [CancellationFlag].
+    ///
+    /// # Arguments
+    ///
+    /// `cancellation_flag` - The shared flag that can be set from another thread
+    pub fn set_cancellation_flag(mut self, cancellation_flag: Arc<AtomicBool>) -> Self {
+        self.cancellation_flag = CancellationFlag::new(cancellation_flag);
+        self
+    }
+
+    /// Sets whether PragmaGlobalPhase is applied to the simulated state
+    ///
+    /// By default PragmaGlobalPhase is ignored.
+    /// When the global phase is tracked, the state vector is multiplied with exp(i * phase)
+    /// for each PragmaGlobalPhase, so that PragmaGetStateVector returns the state including the phase.
+    /// A density matrix is not affected by a global phase.
+    ///
+    /// # Arguments
+    ///
+    /// `track_global_phase` - Whether PragmaGlobalPhase is applied
+    pub fn set_track_global_phase(mut self, track_global_phase: bool) -> Self {
+        self.track_global_phase = track_global_phase;
+        self
+    }
+
+    /// Sets the seed for the random number generators used in the simulation
+    ///
+    /// By default the simulation is not seeded and stochastic results (measurement outcomes
+    /// and the rotation offsets of PragmaOverrotation)
+    /// differ between runs.
+    /// When a seed is set, a separate seed is derived for each repetition of the simulation
+    /// from the seed and the index of the repetition.
+    /// Running the same circuit with the same seed reproduces all repetitions.
+    ///
+    /// # Arguments
+    ///
+    /// `random_seed` - The seed array for the random number generators
+    pub fn set_random_seed(mut self, random_seed: Vec<u64>) -> Self {
+        self.random_seed = Some(random_seed);
+        self
+    }
+
+    /// Sets a single integer seed for the random number generators used in the simulation
+    ///
+    /// Equivalent to [Backend::set_random_seed] with a seed array containing only `random_seed`.
+    ///
+    /// # Arguments
+    ///
+    /// `random_seed` - The seed for the random number generators
+    pub fn set_random_seed_single(self, random_seed: u64) -> Self {
+        self.set_random_seed(vec![random_seed])
+    }
+
+    /// Sets whether states set with PragmaSetStateVector and PragmaSetDensityMatrix are renormalized
+    ///
+    /// By default, setting a state vector that is not normalized or a density matrix
+    /// with a trace different from one returns an error.
+    /// When renormalization is active, the state vector is rescaled to norm one
+    /// and the density matrix to trace one before the state is set.
+    ///
+    /// # Arguments
+    ///
+    /// `renormalize_set_state` - Whether set states are renormalized
+    pub fn set_renormalize_set_state(mut self, renormalize_set_state: bool) -> Self {
+        self.renormalize_set_state = renormalize_set_state;
+        self
+    }
+
+    /// Sets a number of measurements that overrides the number of measurements in the circuits
+    ///
+    /// When set, the number of measurements of every PragmaRepeatedMeasurement and
+    /// PragmaSetNumberOfMeasurements is replaced by the override when running a circuit.
+    /// The circuit itself is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// `measurement_override` - The number of measurements, None to use the numbers in the circuits
+    pub fn with_measurement_override(mut self, measurement_override: Option<usize>) -> Self {
+        self.measurement_override = measurement_override;
+        self
+    }
+
+    /// Sets the classical basis state every simulation starts in.
+    ///
+    /// By default the simulation starts in the all-zero state |0...0>.
+    /// When an initial state is set, the quantum register is initialized to the basis state
+    /// with the given index at the start of each repetition, the n-th bit of the index
+    /// being the state of the n-th qubit. This is cheaper than setting a state vector
+    /// with a single non-zero amplitude with PragmaSetStateVector.
+    ///
+    /// # Arguments
+    ///
+    /// `initial_state` - The index of the initial basis state, None to start in the all-zero state
+    ///
+    /// The plus state of [Backend::new_plus] takes precedence over the initial basis state.
+    pub fn with_initial_state(mut self, initial_state: Option<usize>) -> Self {
+        self.initial_state = initial_state;
+        self
+    }
+
+    /// Sets the density matrix every simulation starts in.
+    ///
+    /// The density matrix is loaded into the quantum register at the start of each repetition,
+    /// before the circuit is applied, so a mixed initial state can be reused for many circuits
+    /// without a PragmaSetDensityMatrix in each of them. Setting an initial density matrix
+    /// forces a density matrix simulation and takes precedence over the plus state and the initial basis state.
+    /// The density matrix must be normalized, this is checked when a circuit is run.
+    ///
+    /// # Arguments
+    ///
+    /// `initial_density_matrix` - The 2^N x 2^N initial density matrix, None to start in the configured pure state
+    pub fn set_initial_density_matrix(
+        mut self,
+        initial_density_matrix: Option<Array2<Complex64>>,
+    ) -> Result<Self, RoqoqoBackendError> {
+        if let Some(density_matrix) = &initial_density_matrix {
+            let dimension = number_amplitudes(self.number_qubits as u32)?;
+            if density_matrix.dim() != (dimension, dimension) {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Initial density matrix has shape {:?}, a backend with {} qubits needs shape ({}, {})",
+                        density_matrix.dim(),
+                        self.number_qubits,
+                        dimension,
+                        dimension
+                    ),
+                });
+            }
+        }
+        self.initial_density_matrix = initial_density_matrix;
+        Ok(self)
+    }
+
+    /// Sets the tolerance for negative probabilities when sampling repeated measurements.
+    ///
+    /// Numerical errors can produce slightly negative probabilities, for example in deep circuits
+    /// or in single precision. Negative probabilities with a magnitude up to the tolerance
+    /// are set to zero before sampling, larger ones return an error.
+    /// The default tolerance is 1e-14.
+    ///
+    /// # Arguments
+    ///
+    /// `probability_tolerance` - The largest magnitude of a negative probability that is treated as zero
+    pub fn set_probability_tolerance(mut self, probability_tolerance: f64) -> Self {
+        self.probability_tolerance = probability_tolerance;
+        self
+    }
+
+    /// Sets whether the final state of the constant circuit of a measurement is reused.
+    ///
+    /// By default the constant circuit of a measurement is prepended to each circuit of the
+    /// measurement and simulated again for every circuit.
+    /// When reuse is active, the constant circuit is simulated once and its final state
+    /// is copied into the quantum register at the start of each circuit.
+    /// The state is only reused when the constant circuit prepares it deterministically
+    /// (gates, definitions, noise pragmas and set-state pragmas) and no device is set,
+    /// otherwise the constant circuit is prepended as before.
+    ///
+    /// # Arguments
+    ///
+    /// `reuse_constant_circuit` - Whether the final state of the constant circuit is reused
+    pub fn set_reuse_constant_circuit(mut self, reuse_constant_circuit: bool) -> Self {
+        self.reuse_constant_circuit = reuse_constant_circuit;
+        self
+    }
+
+    /// Sets whether operations that are not supported by the backend are skipped.
+    ///
+    /// By default running a circuit with an unsupported operation returns an
+    /// OperationNotInBackend error. When skipping is active, the operation is skipped
+    /// and a warning is emitted with [log::warn] for every skipped operation.
+    /// Skipped operations can change the results of the simulation.
+    ///
+    /// # Arguments
+    ///
+    /// `skip_unsupported` - Skip unsupported operations with a warning instead of aborting the run
+    pub fn set_skip_unsupported(mut self, skip_unsupported: bool) -> Self {
+        self.skip_unsupported = skip_unsupported;
+        self
+    }
+
+    /// Sets whether the backend always simulates a state vector.
+    ///
+    /// By default the backend switches to a density matrix simulation when a circuit contains
+    /// noise pragmas like PragmaDamping, doubling the number of simulated qubits.
+    /// When forced, circuits are always simulated as state vectors and circuits containing
+    /// operations that require a density matrix return an error. Stochastic noise like
+    /// PragmaOverrotation is sampled in each repetition and keeps the state vector simulation.
+    ///
+    /// # Arguments
+    ///
+    /// `force_statevector` - Always simulate a state vector instead of switching to a density matrix
+    pub fn set_force_statevector(mut self, force_statevector: bool) -> Self {
+        self.force_statevector = force_statevector;
+        self
+    }
+
+    /// Sets whether MeasureQubit collapses the state of the simulation.
+    ///
+    /// By default MeasureQubit measures the qubit with QuEST and collapses the state.
+    /// When set, the measured value is sampled from the probability of the qubit to be in |1>
+    /// and the state is left unchanged, so later operations act on the unmeasured state.
+    /// This is useful for estimators that read out many qubits of the same state, like classical shadows.
+    /// The readout errors of the backend are applied to the sampled value.
+    ///
+    /// # Arguments
+    ///
+    /// `non_collapsing_measurement` - Sample MeasureQubit without collapsing the state
+    pub fn set_non_collapsing_measurement(mut self, non_collapsing_measurement: bool) -> Self {
+        self.non_collapsing_measurement = non_collapsing_measurement;
+        self
+    }
+
+    /// Sets whether MeasureQubit collapses to the most likely outcome instead of a random one.
+    ///
+    /// When set, each MeasureQubit collapses the qubit to |1> if its probability to be in |1>
+    /// is larger than one half and to |0> otherwise, and renormalizes the state.
+    /// This makes the measurements of a circuit reproducible for debugging.
+    /// The readout errors of the backend are still applied to the measured value.
+    /// Non-collapsing measurements (see [Backend::set_non_collapsing_measurement]) take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// `deterministic_measurement` - Collapse MeasureQubit to the most likely outcome
+    pub fn set_deterministic_measurement(mut self, deterministic_measurement: bool) -> Self {
+        self.deterministic_measurement = deterministic_measurement;
+        self
+    }
+
+    /// Sets the number of shots PragmaGetPauliProduct is estimated from.
+    ///
+    /// By default PragmaGetPauliProduct returns the exact expectation value calculated by QuEST.
+    /// When a number of shots is set, the Pauli product is instead measured that many times
+    /// in its eigenbasis and the empirical mean of the outcomes is returned,
+    /// mimicking the finite statistics of a measurement on hardware.
+    /// The outcomes are sampled with the random number generator of the backend seed.
+    ///
+    /// # Arguments
+    ///
+    /// `pauli_product_shots` - The number of shots, None returns the exact expectation value
+    ///
+    /// # Returns
+    ///
+    /// `Err(RoqoqoBackendError)` - The number of shots is zero.
+    pub fn set_pauli_product_shots(
+        mut self,
+        pauli_product_shots: Option<usize>,
+    ) -> Result<Self, RoqoqoBackendError> {
+        if pauli_product_shots == Some(0) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Number of shots for PragmaGetPauliProduct must be larger than zero"
+                    .to_string(),
+            });
+        }
+        self.pauli_product_shots = pauli_product_shots;
+        Ok(self)
+    }
+
+    /// Sets the number of OpenMP threads QuEST uses in simulations of the backend.
+    ///
+    /// By default QuEST uses the number of threads of the `OMP_NUM_THREADS` environment variable.
+    /// The number of threads is only checked and stored here, it is applied in the simulating thread
+    /// before the QuEST environment and quantum register of each simulation are created.
+    ///
+    /// # Arguments
+    ///
+    /// `number_threads` - The number of OpenMP threads
+    ///
+    /// # Returns
+    ///
+    /// `Err(RoqoqoBackendError)` - The number of threads is zero, or larger than one without the `openmp` feature.
+    pub fn set_num_threads(mut self, number_threads: usize) -> Result<Self, RoqoqoBackendError> {
+        check_number_threads(number_threads)?;
+        self.number_threads = Some(number_threads);
+        Ok(self)
+    }
+
+    /// Returns the number of OpenMP threads QuEST uses in simulations of the backend.
+    ///
+    /// Without a number of threads set on the backend, this is the OpenMP default of the calling thread.
+    pub fn get_num_threads(&self) -> usize {
+        self.number_threads.unwrap_or_else(number_threads)
+    }
+
+    /// Sets the readout error of a qubit as a confusion matrix.
+    ///
+    /// Entry [measured][detected] of the confusion matrix is the probability that the measured
+    /// value of the qubit is detected as the detected value, so each row sums to one.
+    /// The readout error is applied to the results of MeasureQubit and PragmaRepeatedMeasurement.
+    ///
+    /// # Arguments
+    ///
+    /// `qubit` - The qubit the readout error applies to
+    /// `confusion_matrix` - The 2x2 confusion matrix of the qubit
+    pub fn set_readout_error(
+        mut self,
+        qubit: usize,
+        confusion_matrix: [[f64; 2]; 2],
+    ) -> Result<Self, RoqoqoBackendError> {
+        for row in confusion_matrix.iter() {
+            if row
+                .iter()
+                .any(|probability| !(0.0..=1.0).contains(probability))
+                || (row[0] + row[1] - 1.0).abs() > 1e-10
+            {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Rows of the readout confusion matrix of qubit {} must be probabilities summing to one, got {:?}",
+                        qubit, confusion_matrix
+                    ),
+                });
+            }
+        }
+        self.readout_errors.insert(qubit, confusion_matrix);
+        Ok(self)
+    }
+
+    /// Sets the readout error of a qubit from the two flip probabilities.
+    ///
+    /// Special case of [Backend::set_readout_error] for the confusion matrix
+    /// [[1 - prob_detect_0_as_1, prob_detect_0_as_1], [prob_detect_1_as_0, 1 - prob_detect_1_as_0]].
+    ///
+    /// # Arguments
+    ///
+    /// `qubit` - The qubit the readout error applies to
+    /// `prob_detect_0_as_1` - The probability to detect a measured 0 as 1
+    /// `prob_detect_1_as_0` - The probability to detect a measured 1 as 0
+    pub fn set_readout_error_probabilities(
+        self,
+        qubit: usize,
+        prob_detect_0_as_1: f64,
+        prob_detect_1_as_0: f64,
+    ) -> Result<Self, RoqoqoBackendError> {
+        self.set_readout_error(
+            qubit,
+            [
+                [1.0 - prob_detect_0_as_1, prob_detect_0_as_1],
+                [prob_detect_1_as_0, 1.0 - prob_detect_1_as_0],
+            ],
+        )
+    }
+
+    /// Sets the readout errors of all qubits, replacing the readout errors that were set before.
+    ///
+    /// The confusion matrices are checked like in [Backend::set_readout_error].
+    ///
+    /// # Arguments
+    ///
+    /// `readout_model` - The confusion matrix of each qubit with a readout error
+    pub fn set_imperfect_readout_model(
+        mut self,
+        readout_model: ReadoutModel,
+    ) -> Result<Self, RoqoqoBackendError> {
+        self.readout_errors = ReadoutModel::new();
+        let mut qubits: Vec<usize> = readout_model.keys().copied().collect();
+        qubits.sort_unstable();
+        for qubit in qubits {
+            self = self.set_readout_error(qubit, readout_model[&qubit])?;
+        }
+        Ok(self)
+    }
+
+    /// Returns the names and lengths of the output registers a circuit writes to, without running it.
+    ///
+    /// Each output register of a run is a list with one entry of the returned length per repetition.
+    /// Registers that are not defined as outputs are not returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is checked.
+    ///
+    /// # Returns
+    ///
+    /// `RegisterShapes` - The lengths of the bit, float and complex output registers by name.
+    pub fn output_registers(&self, circuit: &Circuit) -> RegisterShapes {
+        let mut shapes = RegisterShapes::default();
+        for op in circuit.iter() {
+            match op {
+                Operation::DefinitionBit(def) if *def.is_output() => {
+                    shapes
+                        .bit_registers
+                        .insert(def.name().clone(), *def.length());
+                }
+                Operation::DefinitionFloat(def) if *def.is_output() => {
+                    shapes
+                        .float_registers
+                        .insert(def.name().clone(), *def.length());
+                }
+                Operation::DefinitionComplex(def) if *def.is_output() => {
+                    shapes
+                        .complex_registers
+                        .insert(def.name().clone(), *def.length());
+                }
+                _ => (),
+            }
+        }
+        shapes
+    }
+
+    /// Returns true if the backend can simulate the operation.
+    ///
+    /// Operations the backend ignores without an error, for example PragmaGlobalPhase
+    /// when the global phase is not tracked, are supported.
+    /// Running an unsupported operation returns an OperationNotInBackend error
+    /// (or skips it with a warning, see [Backend::set_skip_unsupported]).
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation that is checked.
+    pub fn supports_operation(&self, operation: &Operation) -> bool {
+        is_supported_operation(operation)
+    }
+
+    /// Runs a circuit and returns the density matrix of the final state.
+    ///
+    /// A [roqoqo::operations::PragmaGetDensityMatrix] is appended to the circuit and the
+    /// flattened (row-major) register is reshaped into a 2^N x 2^N matrix.
+    /// When the simulation is repeated (stochastic unravelling or overrotations),
+    /// the density matrices of all repetitions are averaged.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `Array2<Complex64>` - The density matrix of the final state.
+    pub fn density_matrix(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<Array2<Complex64>, RoqoqoBackendError> {
+        let readout = "__density_matrix__".to_string();
+        let dimension = number_amplitudes(self.number_qubits as u32)?;
+        let number_elements =
+            dimension
+                .checked_mul(dimension)
+                .ok_or_else(|| RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Number of density matrix elements of a backend with {} qubits exceeds the addressable size",
+                        self.number_qubits
+                    ),
+                })?;
+        let mut density_matrix_circuit = circuit.clone();
+        density_matrix_circuit += DefinitionComplex::new(readout.clone(), number_elements, true);
+        density_matrix_circuit += PragmaGetDensityMatrix::new(readout.clone(), None);
+        let (_, _, complex_registers) = self.run_circuit(&density_matrix_circuit)?;
+        let repetitions =
+            complex_registers
+                .get(&readout)
+                .ok_or_else(|| RoqoqoBackendError::GenericError {
+                    msg: "Density matrix could not be read out".to_string(),
+                })?;
+        let mut density_matrix = Array2::<Complex64>::zeros((dimension, dimension));
+        for flattened_density_matrix in repetitions.iter() {
+            let repetition_density_matrix =
+                Array2::from_shape_vec((dimension, dimension), flattened_density_matrix.clone())
+                    .map_err(|err| RoqoqoBackendError::GenericError {
+                        msg: format!("Density matrix has the wrong size {:?}", err),
+                    })?;
+            density_matrix = density_matrix + repetition_density_matrix;
+        }
+        Ok(density_matrix / Complex64::new(repetitions.len() as f64, 0.0))
+    }
+
+    /// Runs a circuit and returns the non-zero amplitudes of the final state vector.
+    ///
+    /// Only amplitudes with a magnitude above the threshold are returned as
+    /// (basis state index, amplitude) pairs, the dense state vector is never copied.
+    /// The circuit must not require a density matrix simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `threshold` - Amplitudes with a magnitude not above the threshold are skipped.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<(usize, Complex64)>` - The basis state indices and amplitudes above the threshold.
+    pub fn sparse_state_vector(
+        &self,
+        circuit: &Circuit,
+        threshold: f64,
+    ) -> Result<Vec<(usize, Complex64)>, RoqoqoBackendError> {
+        if requires_density_matrix(circuit.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not return a sparse state vector for a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        self.prepare_qureg(circuit, false)?
+            .sparse_state_vector(threshold)
+    }
+
+    /// Runs a circuit and writes the final state vector to a file in the format of QuEST's reportState.
+    ///
+    /// The file can be read by standalone QuEST programs and by [Backend::read_state_from_file],
+    /// see [Qureg::write_state_to_file]. The circuit must not require a density matrix simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `path` - The path of the file that is written.
+    pub fn write_state_to_file(
+        &self,
+        circuit: &Circuit,
+        path: &Path,
+    ) -> Result<(), RoqoqoBackendError> {
+        if requires_density_matrix(circuit.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not write the state vector of a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        self.prepare_qureg(circuit, false)?
+            .write_state_to_file(path)
+    }
+
+    /// Reads a state vector from a file in the format of QuEST's reportState.
+    ///
+    /// The file must contain the 2^N amplitudes of a state vector of the qubits of the backend,
+    /// see [Qureg::read_state_from_file]. The state vector can be loaded into a circuit
+    /// with a [roqoqo::operations::PragmaSetStateVector].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file that is read.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<Complex64>` - The amplitudes of the state vector in the file.
+    pub fn read_state_from_file(&self, path: &Path) -> Result<Vec<Complex64>, RoqoqoBackendError> {
+        self.check_number_qubits()?;
+        let mut qureg = self.create_qureg(false)?;
+        qureg.read_state_from_file(path)?;
+        qureg.amplitudes_range(0, qureg.number_amplitudes()?)
+    }
+
+    /// Runs a circuit, applies a diagonal phase operator and returns the final state vector.
+    ///
+    /// Each basis state of the final state of the circuit is multiplied with exp(i * phases[k]),
+    /// see [Qureg::apply_diagonal_op]. The circuit must not require a density matrix simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `phases` - The phases of all 2^N basis states.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<Complex64>` - The state vector after the diagonal operator.
+    pub fn apply_diagonal(
+        &self,
+        circuit: &Circuit,
+        phases: &[f64],
+    ) -> Result<Vec<Complex64>, RoqoqoBackendError> {
+        if requires_density_matrix(circuit.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not return the state vector for a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        let mut qureg = self.prepare_qureg(circuit, false)?;
+        qureg.apply_diagonal_op(phases)?;
+        qureg.amplitudes_range(0, qureg.number_amplitudes()?)
+    }
+
+    /// Runs a circuit, applies a user provided unitary matrix and returns the final state vector.
+    ///
+    /// The 2x2 or 4x4 unitary is applied to one or two qubits of the final state of the circuit,
+    /// see [Qureg::apply_unitary]. Custom gates can be tried out this way without defining a roqoqo operation.
+    /// The circuit must not require a density matrix simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `qubits` - The one or two qubits the unitary acts on, the n-th qubit being the n-th bit of the matrix indices.
+    /// * `unitary` - The 2x2 or 4x4 unitary matrix.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<Complex64>` - The state vector after the unitary.
+    pub fn apply_unitary(
+        &self,
+        circuit: &Circuit,
+        qubits: &[usize],
+        unitary: &Array2<Complex64>,
+    ) -> Result<Vec<Complex64>, RoqoqoBackendError> {
+        if requires_density_matrix(circuit.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not return the state vector for a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        let mut qureg = self.prepare_qureg(circuit, false)?;
+        qureg.apply_unitary(qubits, unitary)?;
+        qureg.amplitudes_range(0, qureg.number_amplitudes()?)
+    }
+
+    /// Runs a circuit and returns the probabilities of single qubits to be measured in |1> in its final state.
+    ///
+    /// The marginal probabilities are calculated with QuEST's calcProbOfOutcome without collapsing the state,
+    /// which is much cheaper than reading out the probabilities of all 2^N basis states,
+    /// see [Qureg::marginal_probabilities].
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `qubits` - The qubits whose marginal probabilities are returned.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<f64>` - The probability of `qubits[k]` to be in |1> at index k.
+    pub fn marginal_probabilities(
+        &self,
+        circuit: &Circuit,
+        qubits: &[usize],
+    ) -> Result<Vec<f64>, RoqoqoBackendError> {
+        self.prepare_qureg(circuit, self.is_density_matrix_simulation(circuit.iter())?)?
+            .marginal_probabilities(qubits)
+    }
+
+    /// Runs a circuit and returns the expectation value of a real diagonal observable in its final state.
+    ///
+    /// Diagonal observables such as QAOA cost functions are evaluated directly with
+    /// QuEST's calcExpecDiagonalOp instead of being decomposed into a sum of Pauli products,
+    /// see [Qureg::expectation_value_diagonal].
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `diagonal` - The real diagonal entries of the observable for all 2^N basis states.
+    ///
+    /// # Returns
+    ///
+    /// `f64` - The expectation value of the observable.
+    pub fn expectation_value_diagonal(
+        &self,
+        circuit: &Circuit,
+        diagonal: &[f64],
+    ) -> Result<f64, RoqoqoBackendError> {
+        let number_states = number_amplitudes(self.number_qubits as u32)?;
+        if diagonal.len() != number_states {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Diagonal observable on {} qubits requires {} entries, got {}",
+                    self.number_qubits,
+                    number_states,
+                    diagonal.len()
+                ),
+            });
+        }
+        self.prepare_qureg(circuit, self.is_density_matrix_simulation(circuit.iter())?)?
+            .expectation_value_diagonal(diagonal)
+    }
+
+    /// Runs a circuit once and returns a stream of measurements of all qubits in its final state.
+    ///
+    /// Each item of the stream is one shot, the n-th entry being the measured value of the n-th qubit.
+    /// Shots are sampled lazily from the probabilities of the final state, so large numbers of shots
+    /// can be aggregated without storing them. The stream is infinite, use [Iterator::take]
+    /// to limit the number of shots. Readout errors of the backend are applied to each shot.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit preparing the measured state.
+    ///
+    /// # Returns
+    ///
+    /// `MeasurementStream` - The iterator over the measured shots.
+    pub fn measure_stream(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<MeasurementStream, RoqoqoBackendError> {
+        let qureg =
+            self.prepare_qureg(circuit, self.is_density_matrix_simulation(circuit.iter())?)?;
+        let cumulative_probabilities = cumulative_measurement_probabilities(&qureg)?;
+        Ok(MeasurementStream {
+            qureg,
+            cumulative_probabilities,
+        })
+    }
+
+    /// Runs two circuits and returns the inner product <psi_a|psi_b> of their final states.
+    ///
+    /// Both circuits are run in separate state vector quantum registers and the overlap
+    /// is calculated with QuEST's calcInnerProduct.
+    /// Circuits that require a density matrix simulation are not supported,
+    /// use [Backend::hilbert_schmidt_distance] to compare mixed states.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit_a` - The circuit preparing the bra state.
+    /// * `circuit_b` - The circuit preparing the ket state.
+    ///
+    /// # Returns
+    ///
+    /// `Complex64` - The inner product of the two final states.
+    pub fn inner_product(
+        &self,
+        circuit_a: &Circuit,
+        circuit_b: &Circuit,
+    ) -> Result<Complex64, RoqoqoBackendError> {
+        let _simulation = self.cancellation_flag.start_simulation();
+        if requires_density_matrix(circuit_a.iter()) || requires_density_matrix(circuit_b.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not calculate the inner product for a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        let qureg_a = self.prepare_qureg(circuit_a, false)?;
+        let qureg_b = self.prepare_qureg(circuit_b, false)?;
+        qureg_a.inner_product(&qureg_b)
+    }
+
+    /// Runs two circuits and returns the squared Hilbert-Schmidt distance of their final states.
+    ///
+    /// The final states of both circuits are loaded into density matrix quantum registers
+    /// and compared with QuEST's calcHilbertSchmidtDistance.
+    /// The returned value is Tr[(rho_a - rho_b)^2], which is zero for identical states
+    /// and two for orthogonal pure states.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit_a` - The first circuit that is run on the backend.
+    /// * `circuit_b` - The second circuit that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `f64` - The squared Hilbert-Schmidt distance of the two final states.
+    pub fn hilbert_schmidt_distance(
+        &self,
+        circuit_a: &Circuit,
+        circuit_b: &Circuit,
+    ) -> Result<f64, RoqoqoBackendError> {
+        let _simulation = self.cancellation_flag.start_simulation();
+        let qureg_a = self.density_matrix_qureg(circuit_a)?;
+        let qureg_b = self.density_matrix_qureg(circuit_b)?;
+        let distance = qureg_a.hilbert_schmidt_distance(&qureg_b)?;
+        Ok(distance * distance)
+    }
+
+    /// Runs a circuit and returns the second Rényi entropy of a subsystem of its final state.
+    ///
+    /// The final state is loaded into a density matrix quantum register and the entropy
+    /// -ln Tr(rho_A^2) is calculated from the reduced density matrix of the subsystem,
+    /// which is the quantity estimated by a swap test on two copies of the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `qubits` - The qubits of the subsystem A.
+    ///
+    /// # Returns
+    ///
+    /// `f64` - The second Rényi entropy of the subsystem.
+    pub fn renyi_2_entropy(
+        &self,
+        circuit: &Circuit,
+        qubits: &[usize],
+    ) -> Result<f64, RoqoqoBackendError> {
+        self.density_matrix_qureg(circuit)?.renyi_2_entropy(qubits)
+    }
+
+    /// Runs a circuit and returns how often each bitstring was measured in each bit output register.
+    ///
+    /// When the circuit prepares its state deterministically and ends with a
+    /// [roqoqo::operations::PragmaRepeatedMeasurement], the shots are sampled from the final state
+    /// and counted directly without storing every shot. Other circuits are run with
+    /// [EvaluatingBackend::run_circuit] and their bit output registers are counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `HashMap<String, HashMap<Vec<bool>, usize>>` - For each bit output register the number of times each bitstring was measured.
+    pub fn run_circuit_counts(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<HashMap<String, HashMap<Vec<bool>, usize>>, RoqoqoBackendError> {
+        if let Some((preparation, measurement)) = self.sampled_measurement(circuit) {
+            let (counts, _) = self.sample_counts(&preparation, measurement)?;
+            return Ok(counts);
+        }
+        let (bit_registers, _, _) = self.run_circuit(circuit)?;
+        Ok(bit_registers
+            .into_iter()
+            .map(|(name, shots)| {
+                let mut counts: HashMap<Vec<bool>, usize> = HashMap::new();
+                for shot in shots {
+                    *counts.entry(shot).or_default() += 1;
+                }
+                (name, counts)
+            })
+            .collect())
+    }
+
+    /// Runs a circuit once and returns the measured counts together with the probabilities of the final state.
+    ///
+    /// The shots of the final [roqoqo::operations::PragmaRepeatedMeasurement] are sampled from the
+    /// same simulated state whose probabilities are returned, so both are obtained from a single run.
+    /// This requires a circuit that prepares its state deterministically and ends with a
+    /// [roqoqo::operations::PragmaRepeatedMeasurement], on a backend without device and measurement override.
+    /// The probabilities do not include readout errors, the counts do.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `CountsAndProbabilities` - The counts of the measured bitstrings in the readout register and the probabilities of all 2^N basis states.
+    pub fn run_circuit_counts_and_probabilities(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<CountsAndProbabilities, RoqoqoBackendError> {
+        let (preparation, measurement) = self.sampled_measurement(circuit).ok_or_else(|| {
+            RoqoqoBackendError::GenericError {
+                msg: "Counts and probabilities can only be returned from a single run for circuits with a deterministic state preparation ending in a PragmaRepeatedMeasurement".to_string(),
+            }
+        })?;
+        let (counts, stream) = self.sample_counts(&preparation, measurement)?;
+        Ok((counts, stream.probabilities()?))
+    }
+
+    // Splits a circuit ending in a repeated measurement into its state preparation and the measurement
+    // when the shots can be sampled from a single run of the preparation
+    fn sampled_measurement<'a>(
+        &self,
+        circuit: &'a Circuit,
+    ) -> Option<(Circuit, &'a PragmaRepeatedMeasurement)> {
+        let last_operation = circuit
+            .iter()
+            .filter(|op| !op.tags().contains(&"Definition"))
+            .last();
+        if let Some(Operation::PragmaRepeatedMeasurement(measurement)) = last_operation {
+            let mut preparation = Circuit::new();
+            for op in circuit.iter() {
+                if !matches!(op, Operation::PragmaRepeatedMeasurement(_)) {
+                    preparation += op.clone();
+                }
+            }
+            if self.device.0.is_none()
+                && self.measurement_override.is_none()
+                && is_deterministic_preparation(&preparation)
+            {
+                return Some((preparation, measurement));
+            }
+        }
+        None
+    }
+
+    // Samples the shots of a repeated measurement from the final state of a deterministic preparation
+    // and counts the measured bitstrings, the stream holding the final state is returned with the counts
+    fn sample_counts(
+        &self,
+        preparation: &Circuit,
+        measurement: &PragmaRepeatedMeasurement,
+    ) -> Result<(RegisterCounts, MeasurementStream), RoqoqoBackendError> {
+        let is_output = preparation.iter().any(|op| {
+            matches!(op, Operation::DefinitionBit(def) if def.name() == measurement.readout() && *def.is_output())
+        });
+        if !is_output {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Trying to write readout to non-existent register {}",
+                    measurement.readout()
+                ),
+            });
+        }
+        let mut stream = self.measure_stream(preparation)?;
+        let mut counts: HashMap<Vec<bool>, usize> = HashMap::new();
+        for _ in 0..*measurement.number_measurements() {
+            let mut shot = stream.next().expect("Measurement stream is infinite");
+            if let Some(mapping) = measurement.qubit_mapping() {
+                shot = map_measured_qubits(&shot, mapping);
+            }
+            *counts.entry(shot).or_default() += 1;
+        }
+        Ok((
+            [(measurement.readout().clone(), counts)]
+                .into_iter()
+                .collect(),
+            stream,
+        ))
+    }
+
+    /// Runs a circuit several times and returns the final state vector of each run.
+    ///
+    /// Each run is an independent stochastic trajectory of the circuit, for example
+    /// PragmaRandomNoise applies different Pauli flips and measurements collapse the state differently in each run.
+    /// A [roqoqo::operations::PragmaGetStateVector] is appended to the circuit.
+    /// When a random seed is set, the seed of each trajectory is derived from the seed
+    /// and the index of the trajectory, so that all trajectories are reproducible.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run on the backend.
+    /// * `number_trajectories` - The number of runs of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<Vec<Complex64>>` - The amplitudes of the final state of each trajectory.
+    pub fn run_circuit_trajectories(
+        &self,
+        circuit: &Circuit,
+        number_trajectories: usize,
+    ) -> Result<Vec<Vec<Complex64>>, RoqoqoBackendError> {
+        let _simulation = self.cancellation_flag.start_simulation();
+        if requires_density_matrix(circuit.iter()) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "State vector trajectories can not be obtained for a circuit that requires a density matrix".to_string(),
+            });
+        }
+        let readout = "__trajectory__".to_string();
+        let mut trajectory_circuit = circuit.clone();
+        trajectory_circuit += DefinitionComplex::new(
+            readout.clone(),
+            number_amplitudes(self.number_qubits as u32)?,
+            true,
+        );
+        trajectory_circuit += PragmaGetStateVector::new(readout.clone(), None);
+        let mut trajectory_backend = self.clone().set_repetitions(1);
+        let mut trajectories: Vec<Vec<Complex64>> = Vec::with_capacity(number_trajectories);
+        for trajectory in 0..number_trajectories {
+            if let Some(seed) = self.random_seed.as_ref() {
+                trajectory_backend.random_seed = Some(repetition_seed(seed, trajectory));
+            }
+            let (_, _, mut complex_registers) =
+                trajectory_backend.run_circuit(&trajectory_circuit)?;
+            let states = complex_registers.remove(&readout).ok_or_else(|| {
+                RoqoqoBackendError::GenericError {
+                    msg: "State vector could not be read out".to_string(),
+                }
+            })?;
+            trajectories.extend(states);
+        }
+        Ok(trajectories)
+    }
+
+    /// Runs a circuit as a noisy simulation of a device.
+    ///
+    /// Combines the noise settings of the backend into a single call: the device is used for
+    /// availability checks, decoherence with the damping and dephasing rates of the device is applied
+    /// to all qubits for the gate time of each gate ([Backend::set_continuous_decoherence]) and to idle
+    /// qubits after parallel blocks ([Backend::set_auto_idle_noise]), and the readout errors are applied
+    /// to the measured values. Circuits with decoherence are simulated as density matrices.
+    /// The other settings of the backend are kept, the backend itself is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The circuit that is run.
+    /// * `device` - The device whose gate times and decoherence rates are simulated.
+    /// * `readout_model` - The readout confusion matrices of the qubits, see [Backend::set_imperfect_readout_model].
+    /// * `repetitions` - The number of shots of a circuit with PragmaRepeatedMeasurement or PragmaSetNumberOfMeasurements,
+    ///   or with PragmaRandomNoise or PragmaOverrotation, which sample a new trajectory for every shot.
+    ///
+    /// # Returns
+    ///
+    /// `RegisterResult` - The output registers of all shots.
+    pub fn simulate_noisy(
+        &self,
+        circuit: &Circuit,
+        device: Box<dyn Device + Send>,
+        readout_model: ReadoutModel,
+        repetitions: usize,
+    ) -> RegisterResult {
+        // PragmaRandomNoise and PragmaOverrotation are sampled once per repetition, so each shot is measured
+        // in its own repetition, otherwise all shots are measured from a single simulation. The run falls back
+        // to one repetition per measurement when the measurements can not be replaced, giving the same number
+        // of shots.
+        let is_stochastic = circuit.iter().any(|op| {
+            matches!(
+                op,
+                Operation::PragmaRandomNoise(_) | Operation::PragmaOverrotation(_)
+            )
+        });
+        let (simulation_repetitions, number_measurements) = if is_stochastic {
+            (repetitions, 1)
+        } else {
+            (1, repetitions)
+        };
+        let mut backend = self
+            .clone()
+            .set_continuous_decoherence(true)
+            .set_auto_idle_noise(true)
+            .set_repetitions(simulation_repetitions)
+            .with_measurement_override(Some(number_measurements));
+        backend.device = BackendDevice(Some(Arc::new(Mutex::new(device))));
+        backend = backend.set_imperfect_readout_model(readout_model)?;
+        backend.run_circuit(circuit)
+    }
+
+    /// Returns the gradients of the expectation values of a measurement with the parameter-shift rule.
+    ///
+    /// For each symbolic parameter the measurement is evaluated with the parameter shifted by +pi/2
+    /// and -pi/2 and the derivative is (E(theta + pi/2) - E(theta - pi/2)) / 2.
+    /// The rule is exact when each parameter is the angle of a single rotation exp(-i theta/2 P)
+    /// with a Pauli product P.
+    ///
+    /// # Arguments
+    ///
+    /// * `measurement` - The measurement with symbolic parameters.
+    /// * `parameters` - The values of all symbolic parameters at which the gradient is evaluated.
+    ///
+    /// # Returns
+    ///
+    /// `HashMap<String, HashMap<String, f64>>` - For each expectation value the derivatives by each parameter.
+    pub fn parameter_shift_gradient<T>(
+        &self,
+        measurement: &T,
+        parameters: &HashMap<String, f64>,
+    ) -> Result<HashMap<String, HashMap<String, f64>>, RoqoqoBackendError>
+    where
+        T: MeasureExpectationValues,
+    {
+        let _simulation = self.cancellation_flag.start_simulation();
+        let mut gradients: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        for parameter in parameters.keys() {
+            let mut shifted_expectation_values: Vec<HashMap<String, f64>> = Vec::new();
+            for shift in [std::f64::consts::FRAC_PI_2, -std::f64::consts::FRAC_PI_2] {
+                let mut shifted_parameters = parameters.clone();
+                if let Some(value) = shifted_parameters.get_mut(parameter) {
+                    *value += shift;
+                }
+                let shifted_measurement = measurement.substitute_parameters(shifted_parameters)?;
+                let expectation_values =
+                    self.run_measurement(&shifted_measurement)?.ok_or_else(|| {
+                        RoqoqoBackendError::GenericError {
+                            msg: "Measurement did not return expectation values".to_string(),
+                        }
+                    })?;
+                shifted_expectation_values.push(expectation_values);
+            }
+            for (name, plus_value) in shifted_expectation_values[0].iter() {
+                let minus_value = shifted_expectation_values[1].get(name).ok_or_else(|| {
+                    RoqoqoBackendError::GenericError {
+                        msg: format!("Expectation value {} missing in shifted measurement", name),
+                    }
+                })?;
+                gradients
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(parameter.clone(), (plus_value - minus_value) / 2.0);
+            }
+        }
+        Ok(gradients)
+    }
+
+    /// Evaluates the expectation values of a PauliZProduct measurement together with their standard errors.
+    ///
+    /// The standard errors are estimated from the shot statistics of the measured bit registers.
+    /// For each register the covariance of the single-shot Pauli products is divided by the number of shots,
+    /// registers of different circuits are independent. The variance of an expectation value is the
+    /// variance of the linear combination of Pauli products it is built from.
+    /// The measurement needs to be sampled with at least two shots per register, for example
+    /// with a PragmaRepeatedMeasurement.
+    ///
+    /// # Arguments
+    ///
+    /// * `measurement` - The PauliZProduct measurement that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `HashMap<String, (f64, f64)>` - The expectation values and their standard errors.
+    pub fn run_measurement_with_uncertainty(
+        &self,
+        measurement: &PauliZProduct,
+    ) -> Result<HashMap<String, (f64, f64)>, RoqoqoBackendError> {
+        let (bit_registers, float_registers, complex_registers) =
+            self.run_measurement_registers(measurement)?;
+        let input = &measurement.input;
+        let number_pauli_products = input.number_pauli_products;
+        // Normal and flipped readouts are averaged, which scales the covariance by 1/4
+        let (extensions, weight) = if input.use_flipped_measurement {
+            (vec![(false, ""), (true, "_flipped")], 0.25)
+        } else {
+            (vec![(false, "")], 1.0)
+        };
+        let mut covariance: Array2<f64> =
+            Array2::zeros((number_pauli_products, number_pauli_products));
+        for (register_name, masks) in input.pauli_product_qubit_masks.iter() {
+            for (flip_measurement, extension) in extensions.iter() {
+                let name = format!("{}{}", register_name, extension);
+                let register =
+                    bit_registers
+                        .get(&name)
+                        .ok_or_else(|| RoqoqoBackendError::GenericError {
+                            msg: format!("Bit register {} not found", name),
+                        })?;
+                let number_shots = register.len();
+                if number_shots < 2 {
+                    return Err(RoqoqoBackendError::GenericError {
+                        msg: format!(
+                            "Uncertainty needs at least two shots, register {} has {}",
+                            name, number_shots
+                        ),
+                    });
+                }
+                let indices: Vec<usize> = masks.keys().copied().collect();
+                let single_shot_pauli_products: Vec<Vec<f64>> = indices
+                    .iter()
+                    .map(|index| {
+                        register
+                            .iter()
+                            .map(|shot| {
+                                let parity = masks[index]
+                                    .iter()
+                                    .filter(|qubit| shot[**qubit] ^ flip_measurement)
+                                    .count();
+                                if parity % 2 == 0 {
+                                    1.0
+                                } else {
+                                    -1.0
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let means: Vec<f64> = single_shot_pauli_products
+                    .iter()
+                    .map(|values| values.iter().sum::<f64>() / number_shots as f64)
+                    .collect();
+                for (a, index_a) in indices.iter().enumerate() {
+                    for (b, index_b) in indices.iter().enumerate() {
+                        let sample_covariance = single_shot_pauli_products[a]
+                            .iter()
+                            .zip(single_shot_pauli_products[b].iter())
+                            .map(|(value_a, value_b)| (value_a - means[a]) * (value_b - means[b]))
+                            .sum::<f64>()
+                            / (number_shots - 1) as f64;
+                        covariance[[*index_a, *index_b]] +=
+                            weight * sample_covariance / number_shots as f64;
+                    }
+                }
+            }
+        }
+        let expectation_values = measurement
+            .evaluate(bit_registers, float_registers, complex_registers)?
+            .unwrap_or_default();
+        let mut results: HashMap<String, (f64, f64)> = HashMap::new();
+        for (name, evaluation) in input.measured_exp_vals.iter() {
+            let coefficients = match evaluation {
+                PauliProductsToExpVal::Linear(coefficients) => coefficients,
+                PauliProductsToExpVal::Symbolic(_) => {
+                    return Err(RoqoqoBackendError::GenericError {
+                        msg: format!(
+                            "Uncertainty of symbolic expectation value {} is not supported",
+                            name
+                        ),
+                    })
+                }
+            };
+            let mut variance = 0.0;
+            for (index_a, coefficient_a) in coefficients.iter() {
+                for (index_b, coefficient_b) in coefficients.iter() {
+                    variance += coefficient_a * coefficient_b * covariance[[*index_a, *index_b]];
+                }
+            }
+            let value = expectation_values.get(name).copied().ok_or_else(|| {
+                RoqoqoBackendError::GenericError {
+                    msg: format!("Expectation value {} missing in measurement", name),
+                }
+            })?;
+            results.insert(name.clone(), (value, variance.max(0.0).sqrt()));
+        }
+        Ok(results)
+    }
+
+    /// Runs all circuits of a measurement and returns the output registers of each circuit separately.
+    ///
+    /// In contrast to [roqoqo::backends::EvaluatingBackend::run_measurement_registers],
+    /// the registers of different circuits are not concatenated, so that circuits writing
+    /// to the same register can be told apart.
+    /// The constant circuit of the measurement is prepended to each circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `measurement` - The measurement that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<OutputRegisters>` - The output registers of each circuit, in the order of `measurement.circuits()`.
+    pub fn run_measurement_registers_indexed<T>(
+        &self,
+        measurement: &T,
+    ) -> Result<Vec<OutputRegisters>, RoqoqoBackendError>
+    where
+        T: Measure,
+    {
+        let circuits: Vec<&Circuit> = measurement.circuits().collect();
+        self.run_circuits_indexed(measurement.constant_circuit().as_ref(), &circuits)
+    }
+
+    // Runs each circuit with the optional constant circuit prepended and returns the output registers of each circuit,
+    // the final state of the constant circuit is reused when the backend is configured to
+    fn run_circuits_indexed(
+        &self,
+        constant_circuit: Option<&Circuit>,
+        circuits: &[&Circuit],
+    ) -> Result<Vec<OutputRegisters>, RoqoqoBackendError> {
+        let _simulation = self.cancellation_flag.start_simulation();
+        match constant_circuit {
+            Some(constant_circuit)
+                if self.reuse_constant_circuit
+                    && self.device.0.is_none()
+                    && is_deterministic_preparation(constant_circuit) =>
+            {
+                self.run_circuits_from_prepared_state(constant_circuit, circuits)
+            }
+            _ => circuits
+                .iter()
+                .map(|circuit| match constant_circuit {
+                    Some(x) => self.run_circuit_iterator(x.iter().chain(circuit.iter())),
+                    None => self.run_circuit_iterator(circuit.iter()),
+                })
+                .collect(),
+        }
+    }
+
+    // Simulates the constant circuit once and runs each circuit on a copy of its final state
+    fn run_circuits_from_prepared_state(
+        &self,
+        constant_circuit: &Circuit,
+        circuits: &[&Circuit],
+    ) -> Result<Vec<OutputRegisters>, RoqoqoBackendError> {
+        // The register definitions of the constant circuit are still needed for the outputs
+        let definitions: Vec<Operation> = constant_circuit
+            .iter()
+            .filter(|op| op.tags().contains(&"Definition"))
+            .cloned()
+            .collect();
+        // Prepared states for state vector and density matrix simulations, created on first use
+        let mut prepared_quregs: HashMap<bool, Qureg> = HashMap::new();
+        let mut results: Vec<OutputRegisters> = Vec::new();
+        for circuit in circuits {
+            let is_density_matrix =
+                self.is_density_matrix_simulation(constant_circuit.iter().chain(circuit.iter()))?;
+            let prepared_qureg = match prepared_quregs.entry(is_density_matrix) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(self.prepare_qureg(constant_circuit, is_density_matrix)?)
+                }
+            };
+            results.push(self.run_circuit_iterator_with_device_reference(
+                definitions.iter().chain(circuit.iter()),
+                &mut None,
+                Some(prepared_qureg),
+                &mut SimulationTiming::default(),
+            )?);
+        }
+        Ok(results)
+    }
+
+    // Simulates a single repetition of a circuit with the stored device on a new quantum register
+    // and returns the register holding the final state.
+    // The circuit is run through the same simulation loop as run_circuit, so the final state
+    // includes the global phase, overrotations and device noise of a run
+    fn prepare_qureg(
+        &self,
+        circuit: &Circuit,
+        is_density_matrix: bool,
+    ) -> Result<Qureg, RoqoqoBackendError> {
+        let (_, qureg) = self.with_stored_device(|device| {
+            self.simulate_circuit(
+                circuit.iter(),
+                device,
+                None,
+                Some(is_density_matrix),
+                &mut SimulationTiming::default(),
+            )
+        })?;
+        Ok(qureg)
+    }
+
+    // Returns an error for a backend without qubits, QuEST aborts the process when creating an empty quantum register.
+    // Circuits that use no qubits are simulated on all qubits of the backend.
+    fn check_number_qubits(&self) -> Result<(), RoqoqoBackendError> {
+        if self.number_qubits == 0 {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "The QuEST backend needs at least one qubit to run a circuit".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // Returns true if the circuit is simulated as a density matrix,
+    // when a state vector simulation is forced circuits that require a density matrix return an error
+    fn is_density_matrix_simulation<'a>(
+        &self,
+        mut circuit: impl Iterator<Item = &'a Operation>,
+    ) -> Result<bool, RoqoqoBackendError> {
+        if self.initial_density_matrix.is_some() {
+            if self.force_statevector {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: "The backend has an initial density matrix but is forced to simulate a state vector".to_string(),
+                });
+            }
+            return Ok(true);
+        }
+        if !self.force_statevector {
+            return Ok(requires_density_matrix(circuit));
+        }
+        match circuit.find(|op| is_density_matrix_operation(op)) {
+            Some(op) => Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Operation {} requires a density matrix simulation but the backend is forced to simulate a state vector",
+                    op.hqslang()
+                ),
+            }),
+            None => Ok(false),
         }
     }
 
-    /// Sets the number of repetitions used for stochastic circuit simulations
+    // Initializes the quantum register in the configured initial state of the simulation,
+    // discarding the final state of a previous repetition
+    fn initialize_state(&self, qureg: &mut Qureg) -> Result<(), RoqoqoBackendError> {
+        if let Some(density_matrix) = &self.initial_density_matrix {
+            call_operation_with_device(
+                &PragmaSetDensityMatrix::new(density_matrix.clone()).into(),
+                qureg,
+                &mut HashMap::new(),
+                &mut HashMap::new(),
+                &mut HashMap::new(),
+                &mut HashMap::new(),
+                &mut None,
+            )?;
+        } else if self.initial_plus_state {
+            qureg.init_plus_state();
+        } else if let Some(index) = self.initial_state {
+            qureg.init_classical_state(index)?;
+        } else {
+            qureg.reset_to_zero();
+        }
+        Ok(())
+    }
+
+    // Runs a circuit and returns a density matrix quantum register holding the final state
+    fn density_matrix_qureg(&self, circuit: &Circuit) -> Result<Qureg, RoqoqoBackendError> {
+        let density_matrix = self.density_matrix(circuit)?;
+        let mut qureg = self.create_qureg(true)?;
+        call_operation_with_device(
+            &PragmaSetDensityMatrix::new(density_matrix).into(),
+            &mut qureg,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut None,
+        )?;
+        Ok(qureg)
+    }
+
+    /// Runs a circuit and returns the output registers together with the timing of the run.
     ///
-    /// The number of repetitions of the actual simulation is set to one by default.
-    /// The repetitions are not to be confused with the number of simulated measurements per simulation run
-    /// set with PragmaRepeatedMeasurement or PragmaSetNumberMeasurements.
-    /// Should only be different from one if a stochastic unravelling of a noisy simulation is used
-    /// with PragmaRandomNoise or PragmaOverrotation
+    /// The run is identical to [EvaluatingBackend::run_circuit]. The time spent in gate operations
+    /// and in measurements is measured around each operation with [std::time::Instant],
+    /// which adds a negligible overhead compared to the simulation of the operation.
     ///
     /// # Arguments
     ///
-    /// `repetitions` - The number of repetitions that is set
-    pub fn set_repetitions(mut self, repetitions: usize) -> Self {
-        self.repetitions = repetitions;
-        self
+    /// * `circuit` - The circuit that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `(OutputRegisters, SimulationTiming)` - The output registers and the timing of the run.
+    pub fn run_circuit_timed(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<(OutputRegisters, SimulationTiming), RoqoqoBackendError> {
+        let mut timing = SimulationTiming::default();
+        let registers = self.run_circuit_iterator_timed(circuit.iter(), &mut timing)?;
+        Ok((registers, timing))
+    }
+
+    // Runs the operations of a circuit with the stored device and adds the timing of the run
+    fn run_circuit_iterator_timed<'a>(
+        &self,
+        circuit: impl Iterator<Item = &'a Operation>,
+        timing: &mut SimulationTiming,
+    ) -> RegisterResult {
+        self.with_stored_device(|device| {
+            self.run_circuit_iterator_with_device_reference(circuit, device, None, timing)
+        })
+    }
+
+    // Calls a function with a mutable reference to the stored device of the backend,
+    // the device stays locked while the function runs
+    fn with_stored_device<T>(
+        &self,
+        function: impl FnOnce(&mut Option<&mut dyn Device>) -> Result<T, RoqoqoBackendError>,
+    ) -> Result<T, RoqoqoBackendError> {
+        match &self.device.0 {
+            Some(device) => {
+                let mut device_guard =
+                    device
+                        .lock()
+                        .map_err(|_| RoqoqoBackendError::GenericError {
+                            msg: "Device of the backend can not be accessed".to_string(),
+                        })?;
+                let mut device_reference: Option<&mut dyn Device> =
+                    Some(&mut **device_guard as &mut dyn Device);
+                function(&mut device_reference)
+            }
+            None => function(&mut None),
+        }
+    }
+
+    // Returns the noise of the device that is applied after an operation:
+    // with auto idle noise, damping and dephasing of the idle qubits for the execution time of a PragmaStopParallelBlock,
+    // with continuous decoherence, damping and dephasing of all qubits for the gate time of a gate
+    fn device_noise(&self, op: &Operation, device: Option<&dyn Device>) -> Vec<Operation> {
+        let device = match device {
+            Some(device) => device,
+            None => return Vec::new(),
+        };
+        let mut noise: Vec<Operation> = Vec::new();
+        if self.auto_idle_noise {
+            if let Operation::PragmaStopParallelBlock(block) = op {
+                let idle_qubits =
+                    (0..device.number_qubits()).filter(|qubit| !block.qubits().contains(qubit));
+                noise.extend(decoherence_operations(
+                    idle_qubits,
+                    block.execution_time(),
+                    device,
+                ));
+            }
+        }
+        if self.continuous_decoherence {
+            if let Some(gate_time) = gate_time(op, device) {
+                noise.extend(decoherence_operations(
+                    0..device.number_qubits(),
+                    &gate_time.into(),
+                    device,
+                ));
+            }
+        }
+        noise
+    }
+
+    // Creates a quantum register after applying the number of OpenMP threads of the backend
+    fn create_qureg(&self, is_density_matrix: bool) -> Result<Qureg, RoqoqoBackendError> {
+        if let Some(number_threads) = self.number_threads {
+            set_number_threads(number_threads)?;
+        }
+        Ok(Qureg::new(self.number_qubits as u32, is_density_matrix))
+    }
+
+    // Returns an error when the cancellation of the simulation was requested
+    fn check_cancellation(&self) -> Result<(), RoqoqoBackendError> {
+        if self.cancellation_flag.is_cancelled() {
+            Err(RoqoqoBackendError::GenericError {
+                msg: "cancelled".to_string(),
+            })
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -69,7 +2057,16 @@ impl EvaluatingBackend for Backend {
         &self,
         circuit: impl Iterator<Item = &'a Operation>,
     ) -> RegisterResult {
-        self.run_circuit_iterator_with_device(circuit, &mut None)
+        self.run_circuit_iterator_timed(circuit, &mut SimulationTiming::default())
+    }
+
+    fn run_measurement_registers<T>(&self, measurement: &T) -> RegisterResult
+    where
+        T: Measure,
+    {
+        Ok(concatenate_registers(
+            self.run_measurement_registers_indexed(measurement)?,
+        ))
     }
 }
 
@@ -100,22 +2097,87 @@ impl Backend {
     pub fn run_circuit_iterator_with_device<'a>(
         &self,
         circuit: impl Iterator<Item = &'a Operation>,
-        device: &mut Option<Box<dyn roqoqo::devices::Device>>,
+        device: &mut Option<Box<dyn Device>>,
+    ) -> RegisterResult {
+        let mut device_reference: Option<&mut dyn Device> = match device {
+            Some(device_box) => Some(&mut **device_box),
+            None => None,
+        };
+        self.run_circuit_iterator_with_device_reference(
+            circuit,
+            &mut device_reference,
+            None,
+            &mut SimulationTiming::default(),
+        )
+    }
+
+    // Runs the operations of a circuit with an optional mutable reference to the device,
+    // each repetition starts from a copy of the prepared state when one is given
+    fn run_circuit_iterator_with_device_reference<'a>(
+        &self,
+        circuit: impl Iterator<Item = &'a Operation>,
+        device: &mut Option<&mut dyn Device>,
+        prepared_state: Option<&Qureg>,
+        timing: &mut SimulationTiming,
     ) -> RegisterResult {
-        let circuit_vec: Vec<&'a Operation> = circuit.into_iter().collect();
+        self.simulate_circuit(circuit, device, prepared_state, None, timing)
+            .map(|(registers, _)| registers)
+    }
+
+    // Simulates the repetitions of a circuit and returns the output registers together with
+    // the quantum register holding the final state of the last repetition.
+    // With `final_state` set to Some(is_density_matrix) only a single repetition is simulated
+    // to prepare a final state, which is a density matrix when is_density_matrix is true
+    fn simulate_circuit<'a>(
+        &self,
+        circuit: impl Iterator<Item = &'a Operation>,
+        device: &mut Option<&mut dyn Device>,
+        prepared_state: Option<&Qureg>,
+        final_state: Option<bool>,
+        timing: &mut SimulationTiming,
+    ) -> Result<(OutputRegisters, Qureg), RoqoqoBackendError> {
+        let _simulation = self.cancellation_flag.start_simulation();
+        let start = Instant::now();
+        self.check_number_qubits()?;
+        let mut circuit_vec: Vec<Operation> = apply_decomposition_block_reordering(circuit)?;
+        check_symbolic_parameters(&circuit_vec)?;
+        if self.renormalize_set_state {
+            circuit_vec = renormalize_set_state_operations(circuit_vec);
+        }
+        if let Some(number_measurements) = self.measurement_override {
+            circuit_vec = override_number_measurements(circuit_vec, number_measurements);
+        }
+        // The noise of the device is applied after each operation during the simulation, so that it is
+        // read from the current calibration of the device after a PragmaChangeDevice.
+        // The noise of the initial calibration and any noise after a device change require a density matrix
+        let initial_device_noise: Vec<Operation> = circuit_vec
+            .iter()
+            .flat_map(|op| self.device_noise(op, device.as_deref()))
+            .collect();
+        let device_noise_may_change = device.is_some()
+            && (self.auto_idle_noise || self.continuous_decoherence)
+            && circuit_vec
+                .iter()
+                .any(|op| matches!(op, Operation::PragmaChangeDevice(_)));
+        if device_noise_may_change && self.force_statevector {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "The noise of a changed device requires a density matrix simulation but the backend is forced to simulate a state vector".to_string(),
+            });
+        }
 
         // Automatically switch to density matrix mode if operations are present in the
         // circuit that require density matrix mode
-        let is_density_matrix = circuit_vec.iter().any(|x| {
-            matches!(
-                x,
-                Operation::PragmaDamping(_)
-                    | Operation::PragmaDephasing(_)
-                    | Operation::PragmaDepolarising(_)
-                    | Operation::PragmaGeneralNoise(_)
-                    | Operation::PragmaSetDensityMatrix(_)
-            )
-        });
+        let is_density_matrix = self
+            .is_density_matrix_simulation(circuit_vec.iter().chain(initial_device_noise.iter()))?
+            || device_noise_may_change
+            || prepared_state.map_or(false, |state| state.is_density_matrix);
+        if final_state == Some(false) && is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Can not prepare a state vector for a circuit that requires a density matrix simulation".to_string(),
+            });
+        }
+        let is_density_matrix = is_density_matrix || final_state == Some(true);
+        check_state_vector_readout(&circuit_vec, is_density_matrix)?;
 
         // Calculatre total global phase of the circuit
         let mut global_phase: CalculatorFloat = CalculatorFloat::ZERO;
@@ -144,7 +2206,26 @@ impl Backend {
             None => 1,
         };
 
-        let mut qureg = Qureg::new(self.number_qubits as u32, is_density_matrix);
+        let mut qureg = self.create_qureg(is_density_matrix)?;
+        qureg.probability_tolerance = self.probability_tolerance;
+        qureg.readout_errors = self.readout_errors.clone();
+        qureg.skip_unsupported = self.skip_unsupported;
+        qureg.non_collapsing_measurement = self.non_collapsing_measurement;
+        qureg.deterministic_measurement = self.deterministic_measurement;
+        qureg.pauli_product_shots = self.pauli_product_shots;
+        // Overrotated gates differ between repetitions, so they are not grouped into diagonal blocks
+        let uses_overrotation = circuit_vec
+            .iter()
+            .any(|op| matches!(op, Operation::PragmaOverrotation(_)));
+        let diagonal_blocks: HashMap<usize, DiagonalBlock> = if is_density_matrix
+            && self.group_diagonal_gates
+            && device.is_none()
+            && !uses_overrotation
+        {
+            diagonal_blocks(&circuit_vec)
+        } else {
+            HashMap::new()
+        };
 
         // Set up output registers
         let mut bit_registers_output: HashMap<String, BitOutputRegister> = HashMap::new();
@@ -172,6 +2253,9 @@ impl Backend {
                 _ => ()
             }
         }
+        for warning in ignored_circuit_warnings(circuit_vec.iter()) {
+            log::warn!("{}", warning);
+        }
         let mut measured_qubits: Vec<usize> = Vec::new();
         for op in circuit_vec.iter() {
             match op {
@@ -229,14 +2313,50 @@ impl Backend {
             } else {
                 None
             };
-        for _ in 0..repetitions {
+        if final_state.is_some() {
+            repetitions = 1;
+        }
+        for repetition in 0..repetitions {
+            self.check_cancellation()?;
+            if let Some(seed) = self.random_seed.as_ref() {
+                qureg.set_random_seed(&repetition_seed(seed, repetition));
+            }
+            if let Some(state) = prepared_state {
+                qureg.copy_state_from(state)?;
+            } else {
+                self.initialize_state(&mut qureg)?;
+            }
+            // The overrotations are drawn from the random number generator of the repetition,
+            // so seeded runs reproduce them
+            let overrotated_circuit: Vec<Operation>;
+            let repetition_circuit: &[Operation] = if uses_overrotation {
+                overrotated_circuit = overrotate_operations(&circuit_vec, &mut qureg.rng)?;
+                &overrotated_circuit
+            } else {
+                &circuit_vec
+            };
+            qureg.noise_boost = 1.0;
+            qureg.gate_repetitions = 1;
             let mut bit_registers_internal: HashMap<String, BitRegister> = HashMap::new();
             let mut float_registers_internal: HashMap<String, FloatRegister> = HashMap::new();
             let mut complex_registers_internal: HashMap<String, ComplexRegister> = HashMap::new();
             // If the SetNumberMeasurements pragma is used go through operations and replace first
             // instance of MeasureQubit with matching
+            let mut next_index: usize = 0;
             if replace_measurements {
-                for op in circuit_vec.iter() {
+                for (index, op) in repetition_circuit.iter().enumerate() {
+                    if index < next_index {
+                        continue;
+                    }
+                    self.check_cancellation()?;
+                    if let Some(block) = diagonal_blocks.get(&index) {
+                        let operation_start = Instant::now();
+                        qureg.apply_diagonal_phases(&block.qubits, &block.phases)?;
+                        timing.gate_operations += operation_start.elapsed();
+                        next_index = index + block.number_operations;
+                        continue;
+                    }
+                    let operation_start = Instant::now();
                     match op {
                         // Find measurement operation
                         Operation::MeasureQubit(measure_op) => {
@@ -271,6 +2391,11 @@ impl Backend {
                                 )?;
                             }
                         }
+                        Operation::PragmaGlobalPhase(global_phase_op)
+                            if self.track_global_phase =>
+                        {
+                            qureg.apply_global_phase(*global_phase_op.phase().float()?);
+                        }
                         // Normal Operation call for non-measurements
                         _ => {
                             call_operation_with_device(
@@ -284,10 +2409,36 @@ impl Backend {
                             )?;
                         }
                     }
+                    timing.record(op, operation_start.elapsed());
+                    for noise_op in self.device_noise(op, device.as_deref()).iter() {
+                        let operation_start = Instant::now();
+                        call_operation_with_device(
+                            noise_op,
+                            &mut qureg,
+                            &mut bit_registers_internal,
+                            &mut float_registers_internal,
+                            &mut complex_registers_internal,
+                            &mut bit_registers_output,
+                            device,
+                        )?;
+                        timing.record(noise_op, operation_start.elapsed());
+                    }
                 }
                 // Standard path when not using PragmaSetRepeatedMeasurements
             } else {
-                for op in circuit_vec.iter() {
+                for (index, op) in repetition_circuit.iter().enumerate() {
+                    if index < next_index {
+                        continue;
+                    }
+                    self.check_cancellation()?;
+                    if let Some(block) = diagonal_blocks.get(&index) {
+                        let operation_start = Instant::now();
+                        qureg.apply_diagonal_phases(&block.qubits, &block.phases)?;
+                        timing.gate_operations += operation_start.elapsed();
+                        next_index = index + block.number_operations;
+                        continue;
+                    }
+                    let operation_start = Instant::now();
                     match op {
                         Operation::PragmaRepeatedMeasurement(rm) => {
                             for qb in 0..self.number_qubits {
@@ -309,6 +2460,11 @@ impl Backend {
                                 )?;
                             }
                         }
+                        Operation::PragmaGlobalPhase(global_phase_op)
+                            if self.track_global_phase =>
+                        {
+                            qureg.apply_global_phase(*global_phase_op.phase().float()?);
+                        }
                         _ => {
                             call_operation_with_device(
                                 op,
@@ -321,6 +2477,20 @@ impl Backend {
                             )?;
                         }
                     }
+                    timing.record(op, operation_start.elapsed());
+                    for noise_op in self.device_noise(op, device.as_deref()).iter() {
+                        let operation_start = Instant::now();
+                        call_operation_with_device(
+                            noise_op,
+                            &mut qureg,
+                            &mut bit_registers_internal,
+                            &mut float_registers_internal,
+                            &mut complex_registers_internal,
+                            &mut bit_registers_output,
+                            device,
+                        )?;
+                        timing.record(noise_op, operation_start.elapsed());
+                    }
                 }
             }
 
@@ -343,10 +2513,648 @@ impl Backend {
                 }
             }
         }
+        timing.total += start.elapsed();
         Ok((
-            bit_registers_output,
-            float_registers_output,
-            complex_registers_output,
+            (
+                bit_registers_output,
+                float_registers_output,
+                complex_registers_output,
+            ),
+            qureg,
+        ))
+    }
+}
+
+/// Returns true if the circuit contains operations that require a density matrix quantum register.
+///
+/// The [Backend] automatically switches to a density matrix simulation for these circuits.
+///
+/// # Arguments
+///
+/// * `circuit` - The iterator over operations that is checked (corresponds to a circuit).
+pub fn requires_density_matrix<'a>(mut circuit: impl Iterator<Item = &'a Operation>) -> bool {
+    circuit.any(is_density_matrix_operation)
+}
+
+// Returns true if the operation can only be simulated on a density matrix quantum register
+fn is_density_matrix_operation(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::PragmaDamping(_)
+            | Operation::PragmaDephasing(_)
+            | Operation::PragmaDepolarising(_)
+            | Operation::PragmaGeneralNoise(_)
+            | Operation::PragmaSetDensityMatrix(_)
+    )
+}
+
+// Returns an error if a circuit reads out the state vector but is simulated as a density matrix,
+// so that the simulation does not fail only when the readout is reached
+fn check_state_vector_readout(
+    circuit_vec: &[Operation],
+    is_density_matrix: bool,
+) -> Result<(), RoqoqoBackendError> {
+    if !is_density_matrix
+        || !circuit_vec
+            .iter()
+            .any(|op| matches!(op, Operation::PragmaGetStateVector(_)))
+    {
+        return Ok(());
+    }
+    let reason = match circuit_vec
+        .iter()
+        .find(|op| is_density_matrix_operation(op))
+    {
+        Some(op) => format!("operation {}", op.hqslang()),
+        None => "the prepared state".to_string(),
+    };
+    Err(RoqoqoBackendError::GenericError {
+        msg: format!(
+            "Circuit contains PragmaGetStateVector but is simulated as a density matrix because of {}, use PragmaGetDensityMatrix instead",
+            reason
+        ),
+    })
+}
+
+/// Returns warnings for the parameters of operations in a circuit that are ignored by the backend.
+///
+/// The QuEST backend does not apply the optional preparation circuit of
+/// [roqoqo::operations::PragmaGetStateVector] and [roqoqo::operations::PragmaGetDensityMatrix]
+/// before reading out the state.
+/// The warnings are emitted with [log::warn] when running a circuit on the [Backend].
+///
+/// # Arguments
+///
+/// * `circuit` - The iterator over operations that is checked (corresponds to a circuit).
+///
+/// # Returns
+///
+/// `Vec<String>` - The warning messages.
+pub fn ignored_circuit_warnings<'a>(circuit: impl Iterator<Item = &'a Operation>) -> Vec<String> {
+    circuit
+        .filter_map(|op| match op {
+            Operation::PragmaGetStateVector(get_op) if get_op.circuit().is_some() => Some(format!(
+                "Circuit parameter of PragmaGetStateVector with readout {} is ignored by the QuEST backend",
+                get_op.readout()
+            )),
+            Operation::PragmaGetDensityMatrix(get_op) if get_op.circuit().is_some() => Some(format!(
+                "Circuit parameter of PragmaGetDensityMatrix with readout {} is ignored by the QuEST backend",
+                get_op.readout()
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+// Returns an error naming the operation and the symbol if an operation still has symbolic parameters,
+// symbolic parameters must be substituted before a circuit is simulated
+fn check_symbolic_parameters(circuit_vec: &[Operation]) -> Result<(), RoqoqoBackendError> {
+    if let Some(op) = circuit_vec.iter().find(|op| op.is_parametrized()) {
+        // Substituting with an empty calculator fails on the first unresolved symbol
+        let symbol = match op.substitute_parameters(&Calculator::new()) {
+            Err(RoqoqoError::CalculatorError(CalculatorError::VariableNotSet { name })) => name,
+            _ => "unknown".to_string(),
+        };
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Operation {} has the unresolved symbolic parameter {}, substitute symbolic parameters before running the circuit",
+                op.hqslang(),
+                symbol
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Applies the statistical overrotations of all PragmaOverrotation operations in a circuit.
+// Each pragma adds amplitude times a normally distributed offset with standard deviation variance
+// to the angle of the next rotation gate with the given name acting on the same qubits.
+// The pragmas are kept in place so the indices of the operations do not change.
+fn overrotate_operations(
+    circuit_vec: &[Operation],
+    rng: &mut impl Rng,
+) -> Result<Vec<Operation>, RoqoqoBackendError> {
+    let mut operations = circuit_vec.to_vec();
+    for (index, op) in circuit_vec.iter().enumerate() {
+        if let Operation::PragmaOverrotation(overrotation) = op {
+            let target = operations[index + 1..].iter().position(|gate| {
+                gate.hqslang() == overrotation.gate_hqslang()
+                    && gate.involved_qubits() == overrotation.involved_qubits()
+            });
+            if let Some(position) = target {
+                let distribution = Normal::new(0.0, *overrotation.variance()).map_err(|err| {
+                    RoqoqoBackendError::GenericError {
+                        msg: format!("Invalid variance of PragmaOverrotation {:?}", err),
+                    }
+                })?;
+                let offset = overrotation.amplitude() * distribution.sample(rng);
+                let gate_index = index + 1 + position;
+                operations[gate_index] = overrotated_gate(&operations[gate_index], offset)?;
+            }
+        }
+    }
+    Ok(operations)
+}
+
+// Returns a copy of a rotation gate with the offset added to its rotation angle
+fn overrotated_gate(gate: &Operation, offset: f64) -> Result<Operation, RoqoqoBackendError> {
+    let overrotated: Operation = match gate {
+        Operation::RotateX(op) => RotateX::new(*op.qubit(), op.theta() + offset).into(),
+        Operation::RotateY(op) => RotateY::new(*op.qubit(), op.theta() + offset).into(),
+        Operation::RotateZ(op) => RotateZ::new(*op.qubit(), op.theta() + offset).into(),
+        Operation::PhaseShiftState0(op) => {
+            PhaseShiftState0::new(*op.qubit(), op.theta() + offset).into()
+        }
+        Operation::PhaseShiftState1(op) => {
+            PhaseShiftState1::new(*op.qubit(), op.theta() + offset).into()
+        }
+        Operation::RotateAroundSphericalAxis(op) => RotateAroundSphericalAxis::new(
+            *op.qubit(),
+            op.theta() + offset,
+            op.spherical_theta().clone(),
+            op.spherical_phi().clone(),
+        )
+        .into(),
+        Operation::RotateXY(op) => {
+            RotateXY::new(*op.qubit(), op.theta() + offset, op.phi().clone()).into()
+        }
+        Operation::ControlledPhaseShift(op) => {
+            ControlledPhaseShift::new(*op.control(), *op.target(), op.theta() + offset).into()
+        }
+        Operation::XY(op) => XY::new(*op.control(), *op.target(), op.theta() + offset).into(),
+        Operation::VariableMSXX(op) => {
+            VariableMSXX::new(*op.control(), *op.target(), op.theta() + offset).into()
+        }
+        Operation::GivensRotation(op) => GivensRotation::new(
+            *op.control(),
+            *op.target(),
+            op.theta() + offset,
+            op.phi().clone(),
+        )
+        .into(),
+        Operation::GivensRotationLittleEndian(op) => GivensRotationLittleEndian::new(
+            *op.control(),
+            *op.target(),
+            op.theta() + offset,
+            op.phi().clone(),
+        )
+        .into(),
+        Operation::MultiQubitMS(op) => {
+            MultiQubitMS::new(op.qubits().clone(), op.theta() + offset).into()
+        }
+        Operation::MultiQubitZZ(op) => {
+            MultiQubitZZ::new(op.qubits().clone(), op.theta() + offset).into()
+        }
+        _ => {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "PragmaOverrotation can only be applied to rotation gates, not to {}",
+                    gate.hqslang()
+                ),
+            })
+        }
+    };
+    Ok(overrotated)
+}
+
+// Returns true if a circuit only prepares a state deterministically,
+// so that its final state can be simulated once and copied
+fn is_deterministic_preparation(circuit: &Circuit) -> bool {
+    circuit.iter().all(|op| {
+        op.tags().contains(&"GateOperation")
+            || op.tags().contains(&"Definition")
+            || matches!(
+                op,
+                Operation::PragmaDamping(_)
+                    | Operation::PragmaDephasing(_)
+                    | Operation::PragmaDepolarising(_)
+                    | Operation::PragmaGeneralNoise(_)
+                    | Operation::PragmaSetStateVector(_)
+                    | Operation::PragmaSetDensityMatrix(_)
+            )
+    })
+}
+
+// Concatenates the output registers of several circuits, registers with the same name are appended
+fn concatenate_registers(results: Vec<OutputRegisters>) -> OutputRegisters {
+    let mut bit_registers: HashMap<String, BitOutputRegister> = HashMap::new();
+    let mut float_registers: HashMap<String, FloatOutputRegister> = HashMap::new();
+    let mut complex_registers: HashMap<String, ComplexOutputRegister> = HashMap::new();
+    for (tmp_bit_reg, tmp_float_reg, tmp_complex_reg) in results {
+        for (key, mut val) in tmp_bit_reg.into_iter() {
+            if let Some(x) = bit_registers.get_mut(&key) {
+                x.append(&mut val);
+            } else {
+                let _ = bit_registers.insert(key, val);
+            }
+        }
+        for (key, mut val) in tmp_float_reg.into_iter() {
+            if let Some(x) = float_registers.get_mut(&key) {
+                x.append(&mut val);
+            } else {
+                let _ = float_registers.insert(key, val);
+            }
+        }
+        for (key, mut val) in tmp_complex_reg.into_iter() {
+            if let Some(x) = complex_registers.get_mut(&key) {
+                x.append(&mut val);
+            } else {
+                let _ = complex_registers.insert(key, val);
+            }
+        }
+    }
+    (bit_registers, float_registers, complex_registers)
+}
+
+// Derives the seed of one repetition by appending the index of the repetition to the seed of the backend
+fn repetition_seed(seed: &[u64], repetition: usize) -> Vec<u64> {
+    let mut repetition_seed = seed.to_vec();
+    repetition_seed.push(repetition as u64);
+    repetition_seed
+}
+
+// Replaces the number of measurements of PragmaRepeatedMeasurement and PragmaSetNumberOfMeasurements
+fn override_number_measurements(
+    circuit_vec: Vec<Operation>,
+    number_measurements: usize,
+) -> Vec<Operation> {
+    circuit_vec
+        .into_iter()
+        .map(|op| match op {
+            Operation::PragmaRepeatedMeasurement(measure_op) => PragmaRepeatedMeasurement::new(
+                measure_op.readout().clone(),
+                number_measurements,
+                measure_op.qubit_mapping().clone(),
+            )
+            .into(),
+            Operation::PragmaSetNumberOfMeasurements(measure_op) => {
+                PragmaSetNumberOfMeasurements::new(
+                    number_measurements,
+                    measure_op.readout().clone(),
+                )
+                .into()
+            }
+            _ => op,
+        })
+        .collect()
+}
+
+// Rescales the state vectors of PragmaSetStateVector to norm one
+// and the density matrices of PragmaSetDensityMatrix to trace one
+fn renormalize_set_state_operations(circuit_vec: Vec<Operation>) -> Vec<Operation> {
+    circuit_vec
+        .into_iter()
+        .map(|op| match op {
+            Operation::PragmaSetStateVector(set_op) => {
+                let norm = set_op
+                    .statevector()
+                    .iter()
+                    .map(|x| x.norm_sqr())
+                    .sum::<f64>()
+                    .sqrt();
+                if norm > 0.0 {
+                    PragmaSetStateVector::new(set_op.statevector() / Complex64::new(norm, 0.0))
+                        .into()
+                } else {
+                    Operation::from(set_op)
+                }
+            }
+            Operation::PragmaSetDensityMatrix(set_op) => {
+                let trace: Complex64 = set_op.density_matrix().diag().sum();
+                if trace.norm() > 0.0 {
+                    PragmaSetDensityMatrix::new(set_op.density_matrix() / trace).into()
+                } else {
+                    Operation::from(set_op)
+                }
+            }
+            _ => op,
+        })
+        .collect()
+}
+
+// Returns PragmaDamping and PragmaDephasing with the decoherence rates of the device
+// on each of the qubits for the given duration
+fn decoherence_operations(
+    qubits: impl Iterator<Item = usize>,
+    duration: &CalculatorFloat,
+    device: &dyn Device,
+) -> Vec<Operation> {
+    let mut operations: Vec<Operation> = Vec::new();
+    for qubit in qubits {
+        if let Some(rates) = device.qubit_decoherence_rates(&qubit) {
+            let damping_rate = rates[[1, 1]];
+            let dephasing_rate = rates[[2, 2]];
+            if damping_rate > 0.0 {
+                operations
+                    .push(PragmaDamping::new(qubit, duration.clone(), damping_rate.into()).into());
+            }
+            if dephasing_rate > 0.0 {
+                operations.push(
+                    PragmaDephasing::new(qubit, duration.clone(), dephasing_rate.into()).into(),
+                );
+            }
+        }
+    }
+    operations
+}
+
+// Returns the gate time of a gate operation on the device, None for other operations
+fn gate_time(op: &Operation, device: &dyn Device) -> Option<f64> {
+    if let Ok(gate) = SingleQubitGateOperation::try_from(op) {
+        device.single_qubit_gate_time(gate.hqslang(), gate.qubit())
+    } else if let Ok(gate) = TwoQubitGateOperation::try_from(op) {
+        device.two_qubit_gate_time(gate.hqslang(), gate.control(), gate.target())
+    } else if let Ok(gate) = MultiQubitGateOperation::try_from(op) {
+        device.multi_qubit_gate_time(gate.hqslang(), gate.qubits())
+    } else {
+        None
+    }
+}
+
+// Maximum number of qubits a run of grouped diagonal gates may act on
+const MAX_DIAGONAL_BLOCK_QUBITS: usize = 4;
+
+// A run of consecutive diagonal gates that is applied as a single diagonal operator
+struct DiagonalBlock {
+    // Number of operations in the run
+    number_operations: usize,
+    // Qubits the run acts on, the n-th qubit is the n-th bit of the index of the phases
+    qubits: Vec<usize>,
+    // Phases of all basis states of the qubits
+    phases: Vec<f64>,
+}
+
+// Returns the phases of a diagonal gate as terms (qubits, phase),
+// where the phase is applied when all qubits of the term are in state one.
+// Returns None for non-diagonal gates and gates with symbolic parameters.
+fn diagonal_phase_terms(op: &Operation) -> Option<Vec<(Vec<usize>, f64)>> {
+    match op {
+        Operation::RotateZ(gate) => {
+            let theta = *gate.theta().float().ok()?;
+            Some(vec![(vec![], -theta / 2.0), (vec![*gate.qubit()], theta)])
+        }
+        Operation::PhaseShiftState1(gate) => {
+            Some(vec![(vec![*gate.qubit()], *gate.theta().float().ok()?)])
+        }
+        Operation::ControlledPhaseShift(gate) => Some(vec![(
+            vec![*gate.control(), *gate.target()],
+            *gate.theta().float().ok()?,
+        )]),
+        _ => None,
+    }
+}
+
+// Finds runs of at least two consecutive diagonal gates acting on at most
+// MAX_DIAGONAL_BLOCK_QUBITS qubits, keyed by the index of the first operation of the run
+fn diagonal_blocks(circuit_vec: &[Operation]) -> HashMap<usize, DiagonalBlock> {
+    let mut blocks: HashMap<usize, DiagonalBlock> = HashMap::new();
+    let mut start = 0;
+    while start < circuit_vec.len() {
+        let mut qubits: Vec<usize> = Vec::new();
+        let mut terms: Vec<(Vec<usize>, f64)> = Vec::new();
+        // A gate following a PragmaRepeatGate is repeated and can not be grouped
+        if start > 0 && matches!(circuit_vec[start - 1], Operation::PragmaRepeatGate(_)) {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while let Some(op_terms) = circuit_vec.get(end).and_then(diagonal_phase_terms) {
+            let mut block_qubits = qubits.clone();
+            for (term_qubits, _) in op_terms.iter() {
+                for qubit in term_qubits {
+                    if !block_qubits.contains(qubit) {
+                        block_qubits.push(*qubit);
+                    }
+                }
+            }
+            if block_qubits.len() > MAX_DIAGONAL_BLOCK_QUBITS {
+                break;
+            }
+            qubits = block_qubits;
+            terms.extend(op_terms);
+            end += 1;
+        }
+        if end - start >= 2 {
+            let phases: Vec<f64> = (0..1_usize << qubits.len())
+                .map(|state| {
+                    terms
+                        .iter()
+                        .filter(|(term_qubits, _)| {
+                            term_qubits.iter().all(|qubit| {
+                                let bit = qubits.iter().position(|q| q == qubit).unwrap_or(0);
+                                (state >> bit) & 1 == 1
+                            })
+                        })
+                        .map(|(_, phase)| phase)
+                        .sum()
+                })
+                .collect();
+            blocks.insert(
+                start,
+                DiagonalBlock {
+                    number_operations: end - start,
+                    qubits,
+                    phases,
+                },
+            );
+            start = end;
+        } else {
+            start = end.max(start + 1);
+        }
+    }
+    blocks
+}
+
+// Remaps the qubits of all operations between a PragmaStartDecompositionBlock
+// and the following PragmaStopDecompositionBlock with the reordering dictionary of the block
+fn apply_decomposition_block_reordering<'a>(
+    circuit: impl Iterator<Item = &'a Operation>,
+) -> Result<Vec<Operation>, RoqoqoBackendError> {
+    let mut reordering: Option<&HashMap<usize, usize>> = None;
+    let mut circuit_vec: Vec<Operation> = Vec::new();
+    for op in circuit {
+        match op {
+            Operation::PragmaStartDecompositionBlock(block) => {
+                if !block.reordering_dictionary().is_empty() {
+                    reordering = Some(block.reordering_dictionary());
+                }
+                circuit_vec.push(op.clone());
+            }
+            Operation::PragmaStopDecompositionBlock(_) => {
+                reordering = None;
+                circuit_vec.push(op.clone());
+            }
+            _ => match reordering {
+                Some(mapping) => circuit_vec.push(op.remap_qubits(mapping)?),
+                None => circuit_vec.push(op.clone()),
+            },
+        }
+    }
+    Ok(circuit_vec)
+}
+
+#[cfg(feature = "async")]
+impl Backend {
+    /// Runs a circuit on the backend without blocking the calling async executor.
+    ///
+    /// The numerical simulation is moved to a separate thread and the returned future
+    /// resolves once the simulation has finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit` - The [roqoqo::Circuit] that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `RegisterResult` - The output registers written by the evaluated circuit.
+    pub async fn async_run_circuit(&self, circuit: &Circuit) -> RegisterResult {
+        let backend = self.clone();
+        let circuit = circuit.clone();
+        spawn_simulation(move || backend.run_circuit(&circuit)).await
+    }
+
+    /// Runs all circuits of a measurement on the backend without blocking the calling async executor.
+    ///
+    /// The circuits are simulated in the same way as in
+    /// [roqoqo::backends::EvaluatingBackend::run_measurement_registers] on a pool with one thread
+    /// per available CPU core, and the results are concatenated in the order of the circuits.
+    /// Without a random seed and without reusing the constant circuit the circuits are simulated in parallel.
+    /// Seeded backends simulate the circuits one after the other, so that the process-wide random number
+    /// generator of QuEST is used in the same order as in a synchronous run and the results are reproducible.
+    ///
+    /// # Arguments
+    ///
+    /// * `measurement` - The measurement that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `RegisterResult` - The output registers written by the evaluated circuits.
+    pub async fn async_run_measurement_registers<T>(&self, measurement: &T) -> RegisterResult
+    where
+        T: Measure,
+    {
+        let _simulation = self.cancellation_flag.start_simulation();
+        let constant_circuit = measurement.constant_circuit().clone();
+        let circuits: Vec<Circuit> = measurement.circuits().cloned().collect();
+        let circuit_groups: Vec<Vec<Circuit>> =
+            if self.random_seed.is_some() || self.reuse_constant_circuit {
+                vec![circuits]
+            } else {
+                circuits.into_iter().map(|circuit| vec![circuit]).collect()
+            };
+        let group_futures: Vec<_> = circuit_groups
+            .into_iter()
+            .map(|group| {
+                let backend = self.clone();
+                let constant_circuit = constant_circuit.clone();
+                spawn_simulation(move || {
+                    let circuits: Vec<&Circuit> = group.iter().collect();
+                    backend.run_circuits_indexed(constant_circuit.as_ref(), &circuits)
+                })
+            })
+            .collect();
+        // All simulations are awaited, so that they have stopped when the run returns an error
+        let group_results = futures::future::join_all(group_futures)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Vec<OutputRegisters>>, RoqoqoBackendError>>()?;
+        Ok(concatenate_registers(
+            group_results.into_iter().flatten().collect(),
         ))
     }
+
+    /// Evaluates the expectation values of a measurement without blocking the calling async executor.
+    ///
+    /// # Arguments
+    ///
+    /// * `measurement` - The measurement that is run on the backend.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Option<HashMap<String, f64>>)` - The dictionary of expectation values.
+    pub async fn async_run_measurement<T>(
+        &self,
+        measurement: &T,
+    ) -> Result<Option<HashMap<String, f64>>, RoqoqoBackendError>
+    where
+        T: MeasureExpectationValues,
+    {
+        let (bit_registers, float_registers, complex_registers) =
+            self.async_run_measurement_registers(measurement).await?;
+        Ok(measurement.evaluate(bit_registers, float_registers, complex_registers)?)
+    }
+}
+
+// A simulation that is run on the pool of simulation threads
+#[cfg(feature = "async")]
+type SimulationJob = Box<dyn FnOnce() + Send>;
+
+// Sender of the jobs for the pool of simulation threads, the pool is started on first use.
+// The async feature requires Rust 1.63
+#[cfg(feature = "async")]
+#[allow(clippy::incompatible_msrv)]
+static SIMULATION_POOL: Mutex<Option<std::sync::mpsc::Sender<SimulationJob>>> = Mutex::new(None);
+
+// Runs a simulation on the pool of simulation threads and returns a future resolving to its result
+#[cfg(feature = "async")]
+fn spawn_simulation<R>(
+    simulation: impl FnOnce() -> Result<R, RoqoqoBackendError> + Send + 'static,
+) -> impl std::future::Future<Output = Result<R, RoqoqoBackendError>>
+where
+    R: Send + 'static,
+{
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    let submitted = submit_simulation_job(Box::new(move || {
+        let _ = sender.send(simulation());
+    }));
+    async move {
+        submitted?;
+        receiver
+            .await
+            .map_err(|_| RoqoqoBackendError::GenericError {
+                msg: "Simulation thread terminated without returning a result".to_string(),
+            })?
+    }
+}
+
+// Sends a job to the pool of simulation threads, starting the pool if it is not running yet
+#[cfg(feature = "async")]
+fn submit_simulation_job(job: SimulationJob) -> Result<(), RoqoqoBackendError> {
+    let mut pool = SIMULATION_POOL
+        .lock()
+        .map_err(|_| RoqoqoBackendError::GenericError {
+            msg: "Pool of simulation threads can not be accessed".to_string(),
+        })?;
+    pool.get_or_insert_with(start_simulation_pool)
+        .send(job)
+        .map_err(|_| RoqoqoBackendError::GenericError {
+            msg: "Pool of simulation threads is not running".to_string(),
+        })
+}
+
+// Starts one simulation thread per available CPU core, the threads take jobs from a shared queue.
+// A panicking job drops the sender of its result, so that its future resolves to an error
+#[cfg(feature = "async")]
+#[allow(clippy::incompatible_msrv)]
+fn start_simulation_pool() -> std::sync::mpsc::Sender<SimulationJob> {
+    let (sender, receiver) = std::sync::mpsc::channel::<SimulationJob>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let number_threads = std::thread::available_parallelism().map_or(1, |number| number.get());
+    for _ in 0..number_threads {
+        let receiver = receiver.clone();
+        std::thread::spawn(move || loop {
+            // The queue is only locked while waiting for the next job
+            let job = match receiver.lock() {
+                Ok(queue) => queue.recv(),
+                Err(_) => return,
+            };
+            match job {
+                Ok(job) => {
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                }
+                Err(_) => return,
+            }
+        });
+    }
+    sender
 }
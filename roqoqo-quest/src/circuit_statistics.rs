@@ -0,0 +1,90 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::backend::requires_density_matrix;
+use roqoqo::operations::*;
+use roqoqo::Circuit;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// Statistics of a circuit used to estimate the resources of a simulation
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CircuitStats {
+    /// Number of single qubit gates
+    pub number_single_qubit_gates: usize,
+    /// Number of two qubit gates
+    pub number_two_qubit_gates: usize,
+    /// Number of multi qubit gates acting on three qubits
+    pub number_three_qubit_gates: usize,
+    /// Number of multi qubit gates acting on more than three qubits
+    pub number_multi_qubit_gates: usize,
+    /// Number of noise pragmas
+    pub number_noise_pragmas: usize,
+    /// Depth of the circuit counting only gate operations
+    pub depth: usize,
+    /// Number of qubits the circuit acts on (highest qubit index plus one)
+    pub number_qubits: usize,
+    /// Whether the simulation uses a density matrix (memory 4^N instead of 2^N)
+    pub is_density_matrix: bool,
+}
+
+/// Returns the gate counts, depth and simulation mode of a circuit.
+///
+/// # Arguments
+///
+/// * `circuit` - The circuit that is analysed.
+///
+/// # Returns
+///
+/// `CircuitStats` - The statistics of the circuit.
+pub fn circuit_statistics(circuit: &Circuit) -> CircuitStats {
+    let mut statistics = CircuitStats {
+        is_density_matrix: requires_density_matrix(circuit.iter()),
+        ..CircuitStats::default()
+    };
+    // Depth of the circuit on each qubit after the operations that have been counted
+    let mut qubit_depths: HashMap<usize, usize> = HashMap::new();
+    for op in circuit.iter() {
+        if let InvolvedQubits::Set(qubits) = op.involved_qubits() {
+            if let Some(max_qubit) = qubits.iter().max() {
+                statistics.number_qubits = statistics.number_qubits.max(max_qubit + 1);
+            }
+            if GateOperation::try_from(op).is_ok() {
+                let layer = qubits
+                    .iter()
+                    .map(|qubit| qubit_depths.get(qubit).cloned().unwrap_or(0))
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                for qubit in qubits.iter() {
+                    qubit_depths.insert(*qubit, layer);
+                }
+                statistics.depth = statistics.depth.max(layer);
+                count_gate(&mut statistics, &qubits);
+            }
+        }
+        if PragmaNoiseOperation::try_from(op).is_ok() {
+            statistics.number_noise_pragmas += 1;
+        }
+    }
+    statistics
+}
+
+// Increases the gate count corresponding to the number of qubits of a gate
+fn count_gate(statistics: &mut CircuitStats, qubits: &HashSet<usize>) {
+    match qubits.len() {
+        1 => statistics.number_single_qubit_gates += 1,
+        2 => statistics.number_two_qubit_gates += 1,
+        3 => statistics.number_three_qubit_gates += 1,
+        _ => statistics.number_multi_qubit_gates += 1,
+    }
+}
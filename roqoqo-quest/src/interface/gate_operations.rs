@@ -10,8 +10,10 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::quest_bindings::qreal_matrix;
 use crate::ComplexMatrixN;
 use crate::Qureg;
+use num_complex::Complex64;
 use roqoqo::operations::*;
 use roqoqo::RoqoqoBackendError;
 
@@ -23,14 +25,14 @@ pub fn execute_generic_single_qubit_operation(
     let unitary_matrix = operation.unitary_matrix()?;
     let complex_matrix = quest_sys::ComplexMatrix2 {
         // row major version only used for Complex2/4/N intio
-        real: [
+        real: qreal_matrix([
             [unitary_matrix[(0, 0)].re, unitary_matrix[(0, 1)].re],
             [unitary_matrix[(1, 0)].re, unitary_matrix[(1, 1)].re],
-        ],
-        imag: [
+        ]),
+        imag: qreal_matrix([
             [unitary_matrix[(0, 0)].im, unitary_matrix[(0, 1)].im],
             [unitary_matrix[(1, 0)].im, unitary_matrix[(1, 1)].im],
-        ],
+        ]),
         // column major version
         // real: [
         //     [unitary_matrix[(0, 0)].re, unitary_matrix[(1, 0)].re],
@@ -83,7 +85,7 @@ pub fn execute_generic_two_qubit_operation(
     let unitary_matrix = operation.unitary_matrix()?;
     let complex_matrix = quest_sys::ComplexMatrix4 {
         // row major version only used for Complex2/4/N intio
-        real: [
+        real: qreal_matrix([
             [
                 unitary_matrix[(0, 0)].re,
                 unitary_matrix[(0, 1)].re,
@@ -108,8 +110,8 @@ pub fn execute_generic_two_qubit_operation(
                 unitary_matrix[(3, 2)].re,
                 unitary_matrix[(3, 3)].re,
             ],
-        ],
-        imag: [
+        ]),
+        imag: qreal_matrix([
             [
                 unitary_matrix[(0, 0)].im,
                 unitary_matrix[(0, 1)].im,
@@ -134,7 +136,7 @@ pub fn execute_generic_two_qubit_operation(
                 unitary_matrix[(3, 2)].im,
                 unitary_matrix[(3, 3)].im,
             ],
-        ],
+        ]),
         // column major version
         // real: [
         //     [
@@ -200,6 +202,136 @@ pub fn execute_generic_two_qubit_operation(
     Ok(())
 }
 
+/// Simulate the Fsim gate with a unitary matrix assembled directly from the gate parameters
+pub fn execute_fsim(operation: &Fsim, qureg: &mut Qureg) -> Result<(), RoqoqoBackendError> {
+    let (sin_t, cos_t) = operation.t().float()?.sin_cos();
+    let (sin_u, cos_u) = operation.u().float()?.sin_cos();
+    let (sin_d, cos_d) = operation.delta().float()?.sin_cos();
+    let complex_matrix = quest_sys::ComplexMatrix4 {
+        // row major version
+        real: qreal_matrix([
+            [cos_d, 0.0, 0.0, 0.0],
+            [0.0, 0.0, cos_t, 0.0],
+            [0.0, cos_t, 0.0, 0.0],
+            [-sin_d * sin_u, 0.0, 0.0, -cos_d * cos_u],
+        ]),
+        imag: qreal_matrix([
+            [0.0, 0.0, 0.0, sin_d],
+            [0.0, -sin_t, 0.0, 0.0],
+            [0.0, 0.0, -sin_t, 0.0],
+            [-sin_d * cos_u, 0.0, 0.0, cos_d * sin_u],
+        ]),
+    };
+    unsafe {
+        quest_sys::twoQubitUnitary(
+            qureg.quest_qureg,
+            *operation.target() as i32,
+            *operation.control() as i32,
+            complex_matrix,
+        )
+    }
+    Ok(())
+}
+
+/// Simulate the Qsim gate with a unitary matrix assembled directly from the gate parameters
+pub fn execute_qsim(operation: &Qsim, qureg: &mut Qureg) -> Result<(), RoqoqoBackendError> {
+    let x = *operation.x().float()?;
+    let y = *operation.y().float()?;
+    let (sin_m, cos_m) = (x - y).sin_cos();
+    let (sin_p, cos_p) = (x + y).sin_cos();
+    let (sin_z, cos_z) = operation.z().float()?.sin_cos();
+    let complex_matrix = quest_sys::ComplexMatrix4 {
+        // row major version
+        real: qreal_matrix([
+            [cos_m * cos_z, 0.0, 0.0, -sin_m * sin_z],
+            [0.0, sin_p * sin_z, cos_p * cos_z, 0.0],
+            [0.0, cos_p * cos_z, sin_p * sin_z, 0.0],
+            [-sin_m * sin_z, 0.0, 0.0, cos_m * cos_z],
+        ]),
+        imag: qreal_matrix([
+            [-cos_m * sin_z, 0.0, 0.0, -sin_m * cos_z],
+            [0.0, -sin_p * cos_z, cos_p * sin_z, 0.0],
+            [0.0, cos_p * sin_z, -sin_p * cos_z, 0.0],
+            [-sin_m * cos_z, 0.0, 0.0, -cos_m * sin_z],
+        ]),
+    };
+    unsafe {
+        quest_sys::twoQubitUnitary(
+            qureg.quest_qureg,
+            *operation.target() as i32,
+            *operation.control() as i32,
+            complex_matrix,
+        )
+    }
+    Ok(())
+}
+
+/// Simulate the PMInteraction gate with a unitary matrix assembled directly from the gate parameters
+pub fn execute_pm_interaction(
+    operation: &PMInteraction,
+    qureg: &mut Qureg,
+) -> Result<(), RoqoqoBackendError> {
+    let (sin_t, cos_t) = operation.t().float()?.sin_cos();
+    let complex_matrix = quest_sys::ComplexMatrix4 {
+        // row major version, the gate only mixes the single-excitation states |01> and |10>
+        real: qreal_matrix([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos_t, 0.0, 0.0],
+            [0.0, 0.0, cos_t, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]),
+        imag: qreal_matrix([
+            [0.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, -sin_t, 0.0],
+            [0.0, -sin_t, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ]),
+    };
+    unsafe {
+        quest_sys::twoQubitUnitary(
+            qureg.quest_qureg,
+            *operation.target() as i32,
+            *operation.control() as i32,
+            complex_matrix,
+        )
+    }
+    Ok(())
+}
+
+/// Simulate the ComplexPMInteraction gate with a unitary matrix assembled directly from the gate parameters
+pub fn execute_complex_pm_interaction(
+    operation: &ComplexPMInteraction,
+    qureg: &mut Qureg,
+) -> Result<(), RoqoqoBackendError> {
+    let coupling = Complex64::new(*operation.t_real().float()?, *operation.t_imag().float()?);
+    let (sin_t, cos_t) = coupling.norm().sin_cos();
+    let (sin_phase, cos_phase) = coupling.arg().sin_cos();
+    let complex_matrix = quest_sys::ComplexMatrix4 {
+        // row major version, the gate only mixes the single-excitation states |01> and |10>
+        real: qreal_matrix([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos_t, -sin_t * sin_phase, 0.0],
+            [0.0, sin_t * sin_phase, cos_t, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]),
+        imag: qreal_matrix([
+            [0.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, -sin_t * cos_phase, 0.0],
+            [0.0, -sin_t * cos_phase, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ]),
+    };
+    unsafe {
+        quest_sys::twoQubitUnitary(
+            qureg.quest_qureg,
+            *operation.target() as i32,
+            *operation.control() as i32,
+            complex_matrix,
+        )
+    }
+    Ok(())
+}
+
 // pub fn execute_generic_single_qubit_noise(
 //     operation: &PragmaNoiseOperation,
 //     qureg: &mut Qureg,
@@ -13,6 +13,8 @@
 use crate::Qureg;
 use crate::Vector;
 use num_complex::Complex64;
+use qoqo_calculator::CalculatorFloat;
+use quest_sys::qreal;
 use roqoqo::operations::*;
 use roqoqo::registers::{BitOutputRegister, BitRegister, ComplexRegister, FloatRegister};
 use roqoqo::Circuit;
@@ -21,18 +23,19 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 mod pragma_operations;
 use pragma_operations::*;
+pub(crate) use pragma_operations::{
+    cumulative_measurement_probabilities, index_to_qubits, map_measured_qubits, sample_inverse_cdf,
+};
 mod gate_operations;
 use gate_operations::*;
 
 // Pragma operations that are ignored by backend and do not throw an error
-const ALLOWED_OPERATIONS: &[&str; 10] = &[
+const ALLOWED_OPERATIONS: &[&str; 8] = &[
     "PragmaSetNumberOfMeasurements",
-    "PragmaBoostNoise",
     "PragmaStopParallelBlock",
     "PragmaGlobalPhase",
     "DefinitionUsize",
     "InputSymbolic",
-    "PragmaRepeatGate",
     "PragmaStartDecompositionBlock",
     "PragmaStopDecompositionBlock",
     "PragmaOverrotation",
@@ -96,7 +99,7 @@ pub fn call_circuit_with_device(
     float_registers: &mut HashMap<String, FloatRegister>,
     complex_registers: &mut HashMap<String, ComplexRegister>,
     bit_registers_output: &mut HashMap<String, BitOutputRegister>,
-    device: &mut Option<Box<dyn roqoqo::devices::Device>>,
+    device: &mut Option<&mut dyn roqoqo::devices::Device>,
 ) -> Result<(), RoqoqoBackendError> {
     for op in circuit.iter() {
         call_operation_with_device(
@@ -112,6 +115,109 @@ pub fn call_circuit_with_device(
     Ok(())
 }
 
+/// Simulates a circuit conditioned on the parity of several entries of a bit register
+///
+/// The circuit is only simulated when the parity (XOR) of the entries `condition_indices`
+/// of the bit register `condition_register` is one. A [roqoqo::operations::PragmaConditional]
+/// is the special case of a single condition index.
+///
+/// # Arguments
+///
+/// `condition_register` - The name of the bit register containing the condition bits
+/// `condition_indices` - The indices of the condition bits in the bit register
+/// `circuit` - The [roqoqo::Circuit] that is simulated when the parity is one
+/// `qureg` - The wrapper around a QuEST quantum register on which the operations act
+/// `bit_registers` - The HashMap of bit registers ([Vec<bool>]) to write measurement results to
+/// `float_registers` - The HashMap of float registers ([Vec<f64>]) to write real values extracted from the simulator to
+/// `complex_registers` - The HashMap of complex registers ([Vec<Complex64>])
+///                     to write complex values extracted from the simulator to
+/// `bit_registers_output` - The HashMap of bit output registers ([Vec<Vec<bool>>])
+///                          to write measurements of simulated repetitions of circuit execution to
+#[allow(clippy::too_many_arguments)]
+pub fn call_parity_conditional(
+    condition_register: &str,
+    condition_indices: &[usize],
+    circuit: &Circuit,
+    qureg: &mut Qureg,
+    bit_registers: &mut HashMap<String, BitRegister>,
+    float_registers: &mut HashMap<String, FloatRegister>,
+    complex_registers: &mut HashMap<String, ComplexRegister>,
+    bit_registers_output: &mut HashMap<String, BitOutputRegister>,
+) -> Result<(), RoqoqoBackendError> {
+    call_parity_conditional_with_device(
+        condition_register,
+        condition_indices,
+        circuit,
+        qureg,
+        bit_registers,
+        float_registers,
+        complex_registers,
+        bit_registers_output,
+        &mut None,
+    )
+}
+
+// Simulates the circuit when the parity of the condition bits is one,
+// returns an error for a missing register, no condition indices or an index out of range
+#[allow(clippy::too_many_arguments)]
+fn call_parity_conditional_with_device(
+    condition_register: &str,
+    condition_indices: &[usize],
+    circuit: &Circuit,
+    qureg: &mut Qureg,
+    bit_registers: &mut HashMap<String, BitRegister>,
+    float_registers: &mut HashMap<String, FloatRegister>,
+    complex_registers: &mut HashMap<String, ComplexRegister>,
+    bit_registers_output: &mut HashMap<String, BitOutputRegister>,
+    device: &mut Option<&mut dyn roqoqo::devices::Device>,
+) -> Result<(), RoqoqoBackendError> {
+    let register =
+        bit_registers
+            .get(condition_register)
+            .ok_or_else(|| RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Conditional register {:?} not found in classical bit registers.",
+                    condition_register
+                ),
+            })?;
+    if condition_indices.is_empty() {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "No condition indices given for conditional register {:?}.",
+                condition_register
+            ),
+        });
+    }
+    let mut parity = false;
+    for index in condition_indices {
+        match register.get(*index) {
+            Some(condition) => parity ^= *condition,
+            None => {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Condition index {} out of range for conditional register {:?} of length {}.",
+                        index,
+                        condition_register,
+                        register.len()
+                    ),
+                });
+            }
+        }
+    }
+    if parity {
+        call_circuit_with_device(
+            circuit,
+            qureg,
+            bit_registers,
+            float_registers,
+            complex_registers,
+            bit_registers_output,
+            device,
+        )?;
+    }
+    Ok(())
+}
+
 /// Simulates a single operation ([roqoqo::operations::Operation]) acting on a quantum register
 ///
 /// # Arguments
@@ -169,8 +275,25 @@ pub fn call_operation_with_device(
     float_registers: &mut HashMap<String, FloatRegister>,
     complex_registers: &mut HashMap<String, ComplexRegister>,
     bit_registers_output: &mut HashMap<String, BitOutputRegister>,
-    device: &mut Option<Box<dyn roqoqo::devices::Device>>,
+    device: &mut Option<&mut dyn roqoqo::devices::Device>,
 ) -> Result<(), RoqoqoBackendError> {
+    // Apply a gate following a PragmaRepeatGate as often as requested by the pragma,
+    // a repetition coefficient of zero skips the gate
+    if qureg.gate_repetitions != 1 && operation.tags().contains(&"GateOperation") {
+        let repetitions = std::mem::replace(&mut qureg.gate_repetitions, 1);
+        for _ in 0..repetitions {
+            call_operation_with_device(
+                operation,
+                qureg,
+                bit_registers,
+                float_registers,
+                complex_registers,
+                bit_registers_output,
+                device,
+            )?;
+        }
+        return Ok(());
+    }
     match operation {
         Operation::DefinitionBit(def) => {
             if *def.is_output() {
@@ -197,15 +320,20 @@ pub fn call_operation_with_device(
             execute_pragma_repeated_measurement(op, qureg, bit_registers, bit_registers_output)
         }
         Operation::MeasureQubit(op) => {
-            unsafe {
-                let register = bit_registers.get_mut(op.readout()).ok_or(
-                    RoqoqoBackendError::GenericError {
+            let register =
+                bit_registers
+                    .get_mut(op.readout())
+                    .ok_or(RoqoqoBackendError::GenericError {
                         msg: format!("Bit register {} not found to write output to", op.readout()),
-                    },
-                )?;
-                register[*op.readout_index()] =
-                    quest_sys::measure(qureg.quest_qureg, *op.qubit() as i32) == 1;
-            }
+                    })?;
+            let measured = if qureg.non_collapsing_measurement {
+                qureg.sample_qubit(*op.qubit())?
+            } else if qureg.deterministic_measurement {
+                qureg.collapse_to_most_likely(*op.qubit())?
+            } else {
+                qureg.measure_qubit(*op.qubit())
+            };
+            register[*op.readout_index()] = qureg.readout_error(*op.qubit(), measured);
             Ok(())
         }
         Operation::PragmaSetStateVector(op) => execute_pragma_set_state_vector(op, qureg),
@@ -222,8 +350,8 @@ pub fn call_operation_with_device(
                 return Ok(());
             }
             unsafe {
-                let workspace = Qureg::new(qureg.number_qubits(), qureg.is_density_matrix);
-                let workspace_pp = Qureg::new(qureg.number_qubits(), qureg.is_density_matrix);
+                let workspace = qureg.new_workspace(qureg.is_density_matrix);
+                let workspace_pp = qureg.new_workspace(qureg.is_density_matrix);
                 if !op.circuit().is_empty() {
                     call_circuit_with_device(
                         op.circuit(),
@@ -257,13 +385,17 @@ pub fn call_operation_with_device(
                 );
                 drop(workspace);
                 drop(workspace_pp);
-                float_registers.insert(op.readout().clone(), vec![pp]);
+                let value = match qureg.pauli_product_shots {
+                    Some(shots) => qureg.estimate_pauli_product(pp as f64, shots)?,
+                    None => pp as f64,
+                };
+                float_registers.insert(op.readout().clone(), vec![value]);
             }
             Ok(())
         }
         Operation::PragmaGetOccupationProbability(op) => {
             unsafe {
-                let mut workspace = Qureg::new(qureg.number_qubits(), qureg.is_density_matrix);
+                let mut workspace = qureg.new_workspace(qureg.is_density_matrix);
                 match op.circuit() {
                     Some(x) => {
                         call_circuit_with_device(
@@ -310,46 +442,31 @@ pub fn call_operation_with_device(
             Ok(())
         }
         Operation::PragmaActiveReset(op) => {
-            unsafe {
-                if quest_sys::measure(qureg.quest_qureg, *op.qubit() as i32) == 1 {
+            if qureg.measure_qubit(*op.qubit()) {
+                unsafe {
                     quest_sys::pauliX(qureg.quest_qureg, *op.qubit() as ::std::os::raw::c_int)
                 }
             }
             Ok(())
         }
-        Operation::PragmaConditional(op) => {
-            match bit_registers.get(op.condition_register()) {
-                None => {
-                    return Err(RoqoqoBackendError::GenericError {
-                        msg: format!(
-                            "Conditional register {:?} not found in classical bit registers.",
-                            op.condition_register()
-                        ),
-                    });
-                }
-                Some(x) => {
-                    if x[*op.condition_index()] {
-                        call_circuit_with_device(
-                            op.circuit(),
-                            qureg,
-                            bit_registers,
-                            float_registers,
-                            complex_registers,
-                            bit_registers_output,
-                            device,
-                        )?;
-                    }
-                }
-            }
-            Ok(())
-        }
+        Operation::PragmaConditional(op) => call_parity_conditional_with_device(
+            op.condition_register(),
+            &[*op.condition_index()],
+            op.circuit(),
+            qureg,
+            bit_registers,
+            float_registers,
+            complex_registers,
+            bit_registers_output,
+            device,
+        ),
         Operation::RotateX(op) => {
             check_single_qubit_availability(op, device)?;
             unsafe {
                 quest_sys::rotateX(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                 )
             }
             Ok(())
@@ -360,7 +477,7 @@ pub fn call_operation_with_device(
                 quest_sys::rotateY(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                 )
             }
             Ok(())
@@ -371,29 +488,24 @@ pub fn call_operation_with_device(
                 quest_sys::rotateZ(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                 )
             }
             Ok(())
         }
         Operation::PhaseShiftState0(op) => {
             check_single_qubit_availability(op, device)?;
-            unsafe {
-                quest_sys::rotateZ(
-                    qureg.quest_qureg,
-                    *op.qubit() as ::std::os::raw::c_int,
-                    -*op.theta().float()?,
-                )
-            }
-            Ok(())
+            // Applied with its matrix so that the phase of |1> is unchanged instead of a rotateZ up to a global phase
+            execute_generic_single_qubit_operation(&op.clone().into(), qureg)
         }
         Operation::PhaseShiftState1(op) => {
             check_single_qubit_availability(op, device)?;
+            // phaseShift applies diag(1, exp(i theta)) exactly, used as a virtual Z gate
             unsafe {
-                quest_sys::rotateZ(
+                quest_sys::phaseShift(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                 )
             }
             Ok(())
@@ -434,7 +546,7 @@ pub fn call_operation_with_device(
                 quest_sys::rotateX(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    std::f64::consts::FRAC_PI_2,
+                    std::f64::consts::FRAC_PI_2 as qreal,
                 )
             }
             Ok(())
@@ -445,7 +557,7 @@ pub fn call_operation_with_device(
                 quest_sys::rotateX(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    std::f64::consts::FRAC_PI_2 * -1.0,
+                    -std::f64::consts::FRAC_PI_2 as qreal,
                 )
             }
             Ok(())
@@ -461,7 +573,7 @@ pub fn call_operation_with_device(
                 quest_sys::rotateAroundAxis(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                     vector.vector,
                 )
             }
@@ -485,7 +597,7 @@ pub fn call_operation_with_device(
                     qureg.quest_qureg,
                     *op.control() as ::std::os::raw::c_int,
                     *op.target() as ::std::os::raw::c_int,
-                    *op.theta().float()?,
+                    *op.theta().float()? as qreal,
                 )
             }
             Ok(())
@@ -512,6 +624,41 @@ pub fn call_operation_with_device(
             }
             Ok(())
         }
+        Operation::Fsim(op) => {
+            check_two_qubit_availability(op, device)?;
+            execute_fsim(op, qureg)
+        }
+        Operation::Qsim(op) => {
+            check_two_qubit_availability(op, device)?;
+            execute_qsim(op, qureg)
+        }
+        Operation::PMInteraction(op) => {
+            check_two_qubit_availability(op, device)?;
+            execute_pm_interaction(op, qureg)
+        }
+        Operation::ComplexPMInteraction(op) => {
+            check_two_qubit_availability(op, device)?;
+            execute_complex_pm_interaction(op, qureg)
+        }
+        Operation::MolmerSorensenXX(op) => {
+            check_two_qubit_availability(op, device)?;
+            qureg.multi_rotate_pauli(
+                &[*op.control(), *op.target()],
+                "XX",
+                std::f64::consts::FRAC_PI_2,
+            )
+        }
+        Operation::VariableMSXX(op) => {
+            check_two_qubit_availability(op, device)?;
+            qureg.multi_rotate_pauli(&[*op.control(), *op.target()], "XX", *op.theta().float()?)
+        }
+        Operation::XY(op) => {
+            check_two_qubit_availability(op, device)?;
+            // XX and YY commute, the unitary of XY is exp(i * theta/4 * (XX + YY))
+            let angle = -*op.theta().float()? / 2.0;
+            qureg.multi_rotate_pauli(&[*op.control(), *op.target()], "XX", angle)?;
+            qureg.multi_rotate_pauli(&[*op.control(), *op.target()], "YY", angle)
+        }
         Operation::SWAP(op) => {
             check_two_qubit_availability(op, device)?;
             unsafe {
@@ -523,32 +670,53 @@ pub fn call_operation_with_device(
             }
             Ok(())
         }
+        Operation::MultiQubitMS(op) => {
+            check_mulit_qubit_availability(op, device)?;
+            let paulis = "X".repeat(op.qubits().len());
+            qureg.multi_rotate_pauli(op.qubits(), &paulis, *op.theta().float()?)
+        }
+        Operation::MultiQubitZZ(op) => {
+            check_mulit_qubit_availability(op, device)?;
+            let paulis = "Z".repeat(op.qubits().len());
+            qureg.multi_rotate_pauli(op.qubits(), &paulis, *op.theta().float()?)
+        }
+        Operation::PragmaRepeatGate(op) => {
+            qureg.gate_repetitions = *op.repetition_coefficient();
+            Ok(())
+        }
+        Operation::PragmaBoostNoise(op) => {
+            qureg.noise_boost = *op.noise_coefficient().float()?;
+            Ok(())
+        }
         Operation::PragmaDamping(op) => {
+            let probability = boosted_probability(op.probability(), qureg, 1.0, op.hqslang())?;
             unsafe {
                 quest_sys::mixDamping(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    f64::try_from(op.probability())?,
+                    probability as qreal,
                 )
             }
             Ok(())
         }
         Operation::PragmaDephasing(op) => {
+            let probability = boosted_probability(op.probability(), qureg, 0.5, op.hqslang())?;
             unsafe {
                 quest_sys::mixDephasing(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    f64::try_from(op.probability())?,
+                    probability as qreal,
                 )
             }
             Ok(())
         }
         Operation::PragmaDepolarising(op) => {
+            let probability = boosted_probability(op.probability(), qureg, 0.75, op.hqslang())?;
             unsafe {
                 quest_sys::mixDepolarising(
                     qureg.quest_qureg,
                     *op.qubit() as ::std::os::raw::c_int,
-                    f64::try_from(op.probability())?,
+                    probability as qreal,
                 )
             }
             Ok(())
@@ -559,7 +727,7 @@ pub fn call_operation_with_device(
             }
             Ok(())
         }
-        // Operation::PragmaRandomNoise(op) => execute_pragma_random_noise(op, qureg),
+        Operation::PragmaRandomNoise(op) => execute_pragma_random_noise(op, qureg),
         _ => {
             if let Ok(op) = TwoQubitGateOperation::try_from(operation) {
                 check_two_qubit_availability(&op, device)?;
@@ -573,25 +741,87 @@ pub fn call_operation_with_device(
             } else if let Ok(_op) = PragmaNoiseOperation::try_from(operation) {
                 // Not working yet WIP
                 // execute_generic_single_qubit_noise(&_op, qureg)
-                Err(RoqoqoBackendError::OperationNotInBackend {
-                    backend: "QuEST",
-                    hqslang: operation.hqslang(),
-                })
+                unsupported_operation(operation, qureg)
             } else if ALLOWED_OPERATIONS.contains(&operation.hqslang()) {
                 Ok(())
             } else {
-                Err(RoqoqoBackendError::OperationNotInBackend {
-                    backend: "QuEST",
-                    hqslang: operation.hqslang(),
-                })
+                unsupported_operation(operation, qureg)
             }
         }
     }
 }
 
+// Returns true if call_operation_with_device simulates or ignores the operation,
+// false for operations that return an OperationNotInBackend error
+pub(crate) fn is_supported_operation(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::DefinitionBit(_)
+            | Operation::DefinitionFloat(_)
+            | Operation::DefinitionComplex(_)
+            | Operation::PragmaRepeatedMeasurement(_)
+            | Operation::MeasureQubit(_)
+            | Operation::PragmaSetStateVector(_)
+            | Operation::PragmaSetDensityMatrix(_)
+            | Operation::PragmaGetStateVector(_)
+            | Operation::PragmaGetDensityMatrix(_)
+            | Operation::PragmaGetPauliProduct(_)
+            | Operation::PragmaGetOccupationProbability(_)
+            | Operation::PragmaActiveReset(_)
+            | Operation::PragmaConditional(_)
+            | Operation::PragmaRepeatGate(_)
+            | Operation::PragmaBoostNoise(_)
+            | Operation::PragmaDamping(_)
+            | Operation::PragmaDephasing(_)
+            | Operation::PragmaDepolarising(_)
+            | Operation::PragmaRandomNoise(_)
+            | Operation::PragmaChangeDevice(_)
+    ) || SingleQubitGateOperation::try_from(operation).is_ok()
+        || TwoQubitGateOperation::try_from(operation).is_ok()
+        || MultiQubitGateOperation::try_from(operation).is_ok()
+        || ALLOWED_OPERATIONS.contains(&operation.hqslang())
+}
+
+// Returns an error for an operation that is not supported by the backend,
+// or skips the operation with a warning when the quantum register skips unsupported operations
+fn unsupported_operation(operation: &Operation, qureg: &Qureg) -> Result<(), RoqoqoBackendError> {
+    if qureg.skip_unsupported {
+        log::warn!(
+            "Skipping operation {} that is not supported by the QuEST backend",
+            operation.hqslang()
+        );
+        Ok(())
+    } else {
+        Err(RoqoqoBackendError::OperationNotInBackend {
+            backend: "QuEST",
+            hqslang: operation.hqslang(),
+        })
+    }
+}
+
+// Returns the probability of a noise pragma multiplied with the noise boost of the quantum register
+fn boosted_probability(
+    probability: CalculatorFloat,
+    qureg: &Qureg,
+    max_probability: f64,
+    hqslang: &str,
+) -> Result<f64, RoqoqoBackendError> {
+    let probability = f64::try_from(probability)? * qureg.noise_boost;
+    if (0.0..=max_probability).contains(&probability) {
+        Ok(probability)
+    } else {
+        Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Boosted probability {} of {} is outside of the allowed range [0, {}]",
+                probability, hqslang, max_probability
+            ),
+        })
+    }
+}
+
 fn check_single_qubit_availability<T>(
     op: &T,
-    device: &Option<Box<dyn roqoqo::devices::Device>>,
+    device: &Option<&mut dyn roqoqo::devices::Device>,
 ) -> Result<(), RoqoqoBackendError>
 where
     T: OperateSingleQubit,
@@ -614,7 +844,7 @@ where
 
 fn check_two_qubit_availability<T>(
     op: &T,
-    device: &Option<Box<dyn roqoqo::devices::Device>>,
+    device: &Option<&mut dyn roqoqo::devices::Device>,
 ) -> Result<(), RoqoqoBackendError>
 where
     T: OperateTwoQubit,
@@ -638,7 +868,7 @@ where
 
 fn check_mulit_qubit_availability<T>(
     op: &T,
-    device: &Option<Box<dyn roqoqo::devices::Device>>,
+    device: &Option<&mut dyn roqoqo::devices::Device>,
 ) -> Result<(), RoqoqoBackendError>
 where
     T: OperateMultiQubit,
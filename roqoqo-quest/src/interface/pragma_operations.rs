@@ -12,13 +12,16 @@
 
 use crate::Qureg;
 use num_complex::Complex64;
-use rand::distributions::WeightedIndex;
+use quest_sys::qreal;
 use rand::prelude::*;
 use roqoqo::operations::*;
 use roqoqo::registers::{BitOutputRegister, BitRegister, ComplexRegister};
 use roqoqo::RoqoqoBackendError;
 use std::collections::HashMap;
 
+// Allowed deviation of the squared norm of a state vector or the trace of a density matrix from one
+const NORMALIZATION_TOLERANCE: f64 = 1e-6;
+
 pub fn execute_pragma_repeated_measurement(
     operation: &PragmaRepeatedMeasurement,
     qureg: &mut Qureg,
@@ -27,12 +30,17 @@ pub fn execute_pragma_repeated_measurement(
 ) -> Result<(), RoqoqoBackendError> {
     let index_dict = operation.qubit_mapping();
     let number_qubits = qureg.number_qubits();
-    let probabilities = qureg.probabilites();
-    let distribution =
-        WeightedIndex::new(&probabilities).map_err(|err| RoqoqoBackendError::GenericError {
-            msg: format!("Probabilites from quantum register {:?}", err),
-        })?;
-    let mut rng = thread_rng();
+    let number_amplitudes = qureg.number_amplitudes()?;
+    // Reuse the probability buffer of the quantum register between calls
+    let mut probabilities = std::mem::take(&mut qureg.probability_buffer);
+    probabilities.resize(number_amplitudes, 0.0);
+    qureg.probabilities_into(&mut probabilities)?;
+    let cumulative = sanitize_probabilities(&mut probabilities, qureg.probability_tolerance)
+        .and_then(|_| cumulative_probabilities(&mut probabilities));
+    if let Err(err) = cumulative {
+        qureg.probability_buffer = probabilities;
+        return Err(err);
+    }
     let output_register: &mut BitOutputRegister = bit_registers_output
         .get_mut(operation.readout())
         .ok_or(RoqoqoBackendError::GenericError {
@@ -45,41 +53,127 @@ pub fn execute_pragma_repeated_measurement(
     match index_dict {
         None => {
             for _ in 0..*operation.number_measurements() {
-                let index = distribution.sample(&mut rng);
-                output_register.push(index_to_qubits(index, number_qubits))
+                let index = sample_inverse_cdf(&probabilities, &mut qureg.rng);
+                let mut output = index_to_qubits(index, number_qubits);
+                qureg.apply_readout_errors(&mut output);
+                output_register.push(output)
             }
         }
         Some(mapping) => {
             for _ in 0..*operation.number_measurements() {
-                let index = distribution.sample(&mut rng);
-                let tmp_output = index_to_qubits(index, number_qubits);
-                let mut new_output: Vec<bool> = vec![false; number_qubits as usize];
-                for (k, val) in tmp_output.iter().enumerate() {
-                    let tmp_index = match mapping.get(&k) {
-                        Some(ind) => ind,
-                        None => &k,
-                    };
-                    new_output[*tmp_index] = *val;
-                }
-                output_register.push(new_output);
+                let index = sample_inverse_cdf(&probabilities, &mut qureg.rng);
+                let mut tmp_output = index_to_qubits(index, number_qubits);
+                qureg.apply_readout_errors(&mut tmp_output);
+                output_register.push(map_measured_qubits(&tmp_output, mapping));
             }
         }
     }
+    qureg.probability_buffer = probabilities;
     Ok(())
 }
 
+// Moves the measured value of each qubit to the readout index given by the qubit mapping,
+// qubits without an entry in the mapping keep their index
+pub fn map_measured_qubits(measured: &[bool], mapping: &HashMap<usize, usize>) -> Vec<bool> {
+    let mut mapped: Vec<bool> = vec![false; measured.len()];
+    for (k, val) in measured.iter().enumerate() {
+        let tmp_index = match mapping.get(&k) {
+            Some(ind) => ind,
+            None => &k,
+        };
+        mapped[*tmp_index] = *val;
+    }
+    mapped
+}
+
+// Sets negative probabilities caused by numerical errors to zero,
+// returns an error for negative probabilities larger in magnitude than the tolerance
+fn sanitize_probabilities(
+    probabilities: &mut [f64],
+    tolerance: f64,
+) -> Result<(), RoqoqoBackendError> {
+    for (index, probability) in probabilities.iter_mut().enumerate() {
+        if *probability < 0.0 {
+            if *probability < -tolerance {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Negative probability {} of basis state {} exceeds the tolerance {}",
+                        probability, index, tolerance
+                    ),
+                });
+            }
+            *probability = 0.0;
+        }
+    }
+    Ok(())
+}
+
+// Returns the cumulative probabilities of the basis states of the quantum register for sampling measurements
+pub fn cumulative_measurement_probabilities(qureg: &Qureg) -> Result<Vec<f64>, RoqoqoBackendError> {
+    let mut probabilities = vec![0.0; qureg.number_amplitudes()?];
+    qureg.probabilities_into(&mut probabilities)?;
+    sanitize_probabilities(&mut probabilities, qureg.probability_tolerance)?;
+    cumulative_probabilities(&mut probabilities)?;
+    Ok(probabilities)
+}
+
+// Replaces the probabilities with their cumulative sums,
+// returns an error if the probabilities do not sum to a finite positive value
+fn cumulative_probabilities(probabilities: &mut [f64]) -> Result<(), RoqoqoBackendError> {
+    let mut total = 0.0;
+    for probability in probabilities.iter_mut() {
+        total += *probability;
+        *probability = total;
+    }
+    if !(total.is_finite() && total > 0.0) {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Probabilites from quantum register sum to {}, can not sample measurement outcomes",
+                total
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Samples a basis state by inverting the cumulative distribution with one u64 of the random number stream.
+// Takes the first basis state whose cumulative probability exceeds the uniform random number,
+// so basis states with zero probability are never sampled.
+pub fn sample_inverse_cdf(cumulative: &[f64], rng: &mut impl RngCore) -> usize {
+    let total = cumulative[cumulative.len() - 1];
+    // Uniform number in [0, 1) from the 53 most significant bits
+    let uniform = (rng.next_u64() >> 11) as f64 * (1.0 / (1_u64 << 53) as f64);
+    let target = uniform * total;
+    let index = cumulative.partition_point(|probability| *probability <= target);
+    if index < cumulative.len() {
+        index
+    } else {
+        // Rounding put the target on the total, take the last basis state with non-zero probability
+        cumulative.partition_point(|probability| *probability < total)
+    }
+}
+
 pub fn execute_pragma_set_state_vector(
     operation: &PragmaSetStateVector,
     qureg: &mut Qureg,
 ) -> Result<(), RoqoqoBackendError> {
     let statevec = operation.statevector();
     let num_amps: i64 = statevec.len() as i64;
-    if num_amps != 2_i64.pow(qureg.number_qubits()) {
+    if statevec.len() != qureg.number_amplitudes()? {
         return Err(RoqoqoBackendError::GenericError{msg: format!("Can not set state vector number of qubits of statevector {} differs from number of qubits in qubit register {}", num_amps, qureg.number_qubits())});
     }
+    let squared_norm: f64 = statevec.iter().map(|x| x.norm_sqr()).sum();
+    if (squared_norm - 1.0).abs() > NORMALIZATION_TOLERANCE {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Can not set state vector that is not normalized, squared norm of statevector is {}",
+                squared_norm
+            ),
+        });
+    }
     if qureg.is_density_matrix {
-        let mut reals: Vec<f64> = Vec::new();
-        let mut imags: Vec<f64> = Vec::new();
+        let mut reals: Vec<qreal> = Vec::new();
+        let mut imags: Vec<qreal> = Vec::new();
         // iterate over ket state vector to the left of the matrix product
         // to reconstruct density matrix
         // Variant for row major order
@@ -102,22 +196,24 @@ pub fn execute_pragma_set_state_vector(
             reals.extend(
                 statevec
                     .iter()
-                    .map(|value_left| (value_left * value_right.conj()).re),
+                    .map(|value_left| (value_left * value_right.conj()).re as qreal),
             );
             imags.extend(
                 statevec
                     .iter()
-                    .map(|value_left| (value_left * value_right.conj()).im),
+                    .map(|value_left| (value_left * value_right.conj()).im as qreal),
             );
         }
+        check_amplitude_buffers(&reals, &imags, qureg)?;
         unsafe {
             quest_sys::initStateFromAmps(qureg.quest_qureg, reals.as_mut_ptr(), imags.as_mut_ptr())
         }
         Ok(())
     } else {
         let startind: i64 = 0;
-        let mut reals: Vec<f64> = statevec.iter().map(|x| x.re).collect();
-        let mut imags: Vec<f64> = statevec.iter().map(|x| x.im).collect();
+        let mut reals: Vec<qreal> = statevec.iter().map(|x| x.re as qreal).collect();
+        let mut imags: Vec<qreal> = statevec.iter().map(|x| x.im as qreal).collect();
+        check_amplitude_buffers(&reals, &imags, qureg)?;
         unsafe {
             quest_sys::setAmps(
                 qureg.quest_qureg,
@@ -137,17 +233,27 @@ pub fn execute_pragma_set_density_matrix(
 ) -> Result<(), RoqoqoBackendError> {
     let density_matrix = operation.density_matrix();
     let (num_amps, _) = density_matrix.dim();
-    if num_amps != 2_i64.pow(qureg.number_qubits()) as usize {
+    if num_amps != qureg.number_amplitudes()? {
         return Err(RoqoqoBackendError::GenericError{msg: format!("Can not set state vector number of qubits of statevector {} differs from number of qubits in qubit register {}", num_amps, qureg.number_qubits())});
     }
     if qureg.is_density_matrix {
+        let trace: Complex64 = density_matrix.diag().sum();
+        if (trace - 1.0).norm() > NORMALIZATION_TOLERANCE {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Can not set density matrix that is not normalized, trace of density matrix is {}",
+                    trace
+                ),
+            });
+        }
         // Variant for row major order (ndarray default row major)
         // let mut reals: Vec<f64> = density_matrix.iter().map(|x| x.re).collect();
         // let mut imags: Vec<f64> = density_matrix.iter().map(|x| x.im).collect();
 
         // // Variant for column major order (transpose ndarray default row major)
-        let mut reals: Vec<f64> = density_matrix.t().iter().map(|x| x.re).collect();
-        let mut imags: Vec<f64> = density_matrix.t().iter().map(|x| x.im).collect();
+        let mut reals: Vec<qreal> = density_matrix.t().iter().map(|x| x.re as qreal).collect();
+        let mut imags: Vec<qreal> = density_matrix.t().iter().map(|x| x.im as qreal).collect();
+        check_amplitude_buffers(&reals, &imags, qureg)?;
 
         unsafe {
             quest_sys::initStateFromAmps(qureg.quest_qureg, reals.as_mut_ptr(), imags.as_mut_ptr())
@@ -160,67 +266,64 @@ pub fn execute_pragma_set_density_matrix(
     }
 }
 
-// pub fn execute_pragma_random_noise(
-//     operation: &PragmaRandomNoise,
-//     qureg: &mut Qureg,
-// ) -> Result<(), RoqoqoBackendError> {
-//     let mut rng = thread_rng();
-//     let r0 = rng.gen_range(0.0..1.0);
-//     let rates = [
-//         operation.depolarising_rate().float()? / 4.0,
-//         operation.depolarising_rate().float()? / 4.0,
-//         (operation.depolarising_rate().float()? / 4.0) + (operation.dephasing_rate().float()?),
-//     ];
-//     let gate_time = operation.gate_time().float()?;
-//     let mut probas: [f64; 3] = [0.0; 3];
-//     for (i, rate) in rates.iter().enumerate() {
-//         if rate >= &f64::EPSILON {
-//             probas[i] = *rate;
-//         }
-//     }
-//     let sum: f64 = rates.clone().iter().sum();
-//     if r0 < (gate_time * sum * -1.0) - 1.0 {
-//         Ok(())
-//     } else {
-//         let choices = [1, 2, 3];
-//         let distribution =
-//             WeightedIndex::new(&probas).map_err(|err| RoqoqoBackendError::GenericError {
-//                 msg: format!("Probabilites from quantum register {:?}", err),
-//             })?;
-//         match choices[distribution.sample(&mut rng)] {
-//             1 => {
-//                 unsafe {
-//                     quest_sys::pauliX(
-//                         qureg.quest_qureg,
-//                         *operation.qubit() as ::std::os::raw::c_int,
-//                     )
-//                 }
-//                 Ok(())
-//             }
-//             2 => {
-//                 unsafe {
-//                     quest_sys::pauliY(
-//                         qureg.quest_qureg,
-//                         *operation.qubit() as ::std::os::raw::c_int,
-//                     )
-//                 }
-//                 Ok(())
-//             }
-//             3 => {
-//                 unsafe {
-//                     quest_sys::pauliZ(
-//                         qureg.quest_qureg,
-//                         *operation.qubit() as ::std::os::raw::c_int,
-//                     )
-//                 }
-//                 Ok(())
-//             }
-//             x => Err(RoqoqoBackendError::GenericError {
-//                 msg: format!("Incorrect Pauli selected: {:?}", x),
-//             }),
-//         }
-//     }
-// }
+// Checks that the real and imaginary parts passed to QuEST hold exactly the amplitudes QuEST reads,
+// so a wrong length returns an error instead of an out-of-bounds read in the C library
+fn check_amplitude_buffers(
+    reals: &[qreal],
+    imags: &[qreal],
+    qureg: &Qureg,
+) -> Result<(), RoqoqoBackendError> {
+    let expected = qureg.quest_qureg.numAmpsTotal as usize;
+    if reals.len() != expected || imags.len() != expected {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Can not set {} real and {} imaginary parts on a quantum register with {} amplitudes",
+                reals.len(),
+                imags.len(),
+                expected
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Applies one stochastic trajectory of the noise of a PragmaRandomNoise.
+// The noise is unravelled into independent Pauli X, Y and Z flips with the rates depolarising_rate / 4
+// for X and Y and depolarising_rate / 4 + dephasing_rate for Z. A flip with rate r happens with
+// probability (1 - exp(-2 gate_time r)) / 2, so averaging over the repetitions of the simulation gives
+// the same state as PragmaDepolarising and PragmaDephasing with the rates of the operation.
+pub fn execute_pragma_random_noise(
+    operation: &PragmaRandomNoise,
+    qureg: &mut Qureg,
+) -> Result<(), RoqoqoBackendError> {
+    let gate_time = f64::try_from(operation.gate_time().clone())? * qureg.noise_boost;
+    let depolarising_rate = f64::try_from(operation.depolarising_rate().clone())?;
+    let dephasing_rate = f64::try_from(operation.dephasing_rate().clone())?;
+    if gate_time < 0.0 || depolarising_rate < 0.0 || dephasing_rate < 0.0 {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Gate time {} and rates {}, {} of PragmaRandomNoise must not be negative",
+                gate_time, depolarising_rate, dephasing_rate
+            ),
+        });
+    }
+    let qubit = *operation.qubit() as ::std::os::raw::c_int;
+    let flips: [(
+        f64,
+        unsafe extern "C" fn(quest_sys::Qureg, ::std::os::raw::c_int),
+    ); 3] = [
+        (depolarising_rate / 4.0, quest_sys::pauliX),
+        (depolarising_rate / 4.0, quest_sys::pauliY),
+        (depolarising_rate / 4.0 + dephasing_rate, quest_sys::pauliZ),
+    ];
+    for (rate, pauli) in flips {
+        let probability = (1.0 - (-2.0 * gate_time * rate).exp()) / 2.0;
+        if qureg.rng.gen::<f64>() < probability {
+            unsafe { pauli(qureg.quest_qureg, qubit) }
+        }
+    }
+    Ok(())
+}
 
 pub fn execute_pragma_get_state_vector(
     operation: &PragmaGetStateVector,
@@ -233,12 +336,12 @@ pub fn execute_pragma_get_state_vector(
             msg: "Trying to obtain state vector from density matrix quantum register".to_string(),
         })
     } else {
-        let mut statevector: Vec<Complex64> =
-            Vec::with_capacity(2_usize.pow(qureg.number_qubits()));
-        for i in 0..2_usize.pow(qureg.number_qubits()) as i64 {
+        let number_amplitudes = qureg.number_amplitudes()?;
+        let mut statevector: Vec<Complex64> = Vec::with_capacity(number_amplitudes);
+        for i in 0..number_amplitudes as i64 {
             statevector.push(Complex64::new(
-                unsafe { quest_sys::getRealAmp(qureg.quest_qureg, i) },
-                unsafe { quest_sys::getImagAmp(qureg.quest_qureg, i) },
+                unsafe { quest_sys::getRealAmp(qureg.quest_qureg, i) as f64 },
+                unsafe { quest_sys::getImagAmp(qureg.quest_qureg, i) as f64 },
             ))
         }
         complex_registers.insert(readout.clone(), statevector);
@@ -252,15 +355,25 @@ pub fn execute_pragma_get_density_matrix(
     complex_registers: &mut HashMap<String, ComplexRegister>,
 ) -> Result<(), RoqoqoBackendError> {
     let readout = operation.readout();
-    let dimension = 2_i64.pow(qureg.number_qubits());
+    let number_amplitudes = qureg.number_amplitudes()?;
+    let number_elements =
+        number_amplitudes
+            .checked_mul(number_amplitudes)
+            .ok_or_else(|| RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Number of density matrix elements of a quantum register with {} qubits exceeds the addressable size",
+                    qureg.number_qubits()
+                ),
+            })?;
+    let dimension = number_amplitudes as i64;
     let mut density_matrix_flattened_row_major: Vec<Complex64> =
-        Vec::with_capacity(4_usize.pow(qureg.number_qubits()));
+        Vec::with_capacity(number_elements);
     if qureg.is_density_matrix {
         for row in 0..dimension {
             for column in 0..dimension {
                 density_matrix_flattened_row_major.push(Complex64::new(
-                    unsafe { quest_sys::getDensityAmp(qureg.quest_qureg, row, column).real },
-                    unsafe { quest_sys::getDensityAmp(qureg.quest_qureg, row, column).imag },
+                    unsafe { quest_sys::getDensityAmp(qureg.quest_qureg, row, column).real as f64 },
+                    unsafe { quest_sys::getDensityAmp(qureg.quest_qureg, row, column).imag as f64 },
                 ))
             }
         }
@@ -268,11 +381,11 @@ pub fn execute_pragma_get_density_matrix(
         for row in 0..dimension {
             for column in 0..dimension {
                 let value = Complex64::new(
-                    unsafe { quest_sys::getRealAmp(qureg.quest_qureg, row) },
-                    unsafe { quest_sys::getImagAmp(qureg.quest_qureg, row) },
+                    unsafe { quest_sys::getRealAmp(qureg.quest_qureg, row) as f64 },
+                    unsafe { quest_sys::getImagAmp(qureg.quest_qureg, row) as f64 },
                 ) * Complex64::new(
-                    unsafe { quest_sys::getRealAmp(qureg.quest_qureg, column) },
-                    unsafe { quest_sys::getImagAmp(qureg.quest_qureg, column) },
+                    unsafe { quest_sys::getRealAmp(qureg.quest_qureg, column) as f64 },
+                    unsafe { quest_sys::getImagAmp(qureg.quest_qureg, column) as f64 },
                 )
                 .conj();
                 density_matrix_flattened_row_major.push(value);
@@ -284,7 +397,7 @@ pub fn execute_pragma_get_density_matrix(
 }
 
 #[inline]
-fn index_to_qubits(index: usize, number_qubits: u32) -> Vec<bool> {
+pub fn index_to_qubits(index: usize, number_qubits: u32) -> Vec<bool> {
     let mut binary_list: Vec<bool> = Vec::with_capacity(number_qubits as usize);
     for k in 0..number_qubits {
         // (index // 2**k) % 2 => 0 -> false 1 -> true
@@ -24,8 +24,16 @@
 //! roqoqo-quest provides a backend to simulate roqoqo quantum circuits with the QuEST simulator
 
 mod interface;
-pub use interface::{call_circuit, call_operation};
+pub use interface::{call_circuit, call_operation, call_parity_conditional};
 mod backend;
-pub use backend::Backend;
+pub use backend::{
+    ignored_circuit_warnings, requires_density_matrix, Backend, BackendBuilder, BackendDevice,
+    CancellationFlag, CountsAndProbabilities, MeasurementStream, OutputRegisters, RegisterShapes,
+    SimulationTiming,
+};
+mod circuit_statistics;
+pub use circuit_statistics::{circuit_statistics, CircuitStats};
+mod qasm;
+pub use qasm::circuit_to_qasm;
 mod quest_bindings;
 pub use quest_bindings::*;
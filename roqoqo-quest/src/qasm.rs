@@ -0,0 +1,160 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use qoqo_calculator::CalculatorFloat;
+use roqoqo::operations::*;
+use roqoqo::Circuit;
+use roqoqo::RoqoqoBackendError;
+use std::convert::TryFrom;
+
+// Operations that do not change the quantum state or the bit registers and are left out of the QASM output
+const IGNORED_OPERATIONS: &[&str; 8] = &[
+    "DefinitionFloat",
+    "DefinitionComplex",
+    "DefinitionUsize",
+    "InputSymbolic",
+    "PragmaSetNumberOfMeasurements",
+    "PragmaStopParallelBlock",
+    "PragmaStartDecompositionBlock",
+    "PragmaStopDecompositionBlock",
+];
+
+/// Serializes a circuit to OpenQASM 2.0.
+///
+/// All qubits are declared in the quantum register `q`, bit registers are declared as classical
+/// registers with the same name. Gates are mapped to the gates of qelib1.inc that implement
+/// the same unitary including the global phase. Float, complex and usize registers and pragmas
+/// without effect on the state are left out. All other operations return an error.
+///
+/// # Arguments
+///
+/// * `circuit` - The circuit that is serialized.
+/// * `number_qubits` - The number of qubits in the quantum register `q`.
+///
+/// # Returns
+///
+/// `Ok(String)` - The OpenQASM 2.0 program.
+/// `Err(RoqoqoBackendError)` - An operation has no QASM equivalent, has symbolic parameters
+///                             or acts on a qubit outside the quantum register.
+pub fn circuit_to_qasm(
+    circuit: &Circuit,
+    number_qubits: usize,
+) -> Result<String, RoqoqoBackendError> {
+    let mut qasm = format!(
+        "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[{}];\n",
+        number_qubits
+    );
+    for op in circuit.iter() {
+        if let InvolvedQubits::Set(qubits) = op.involved_qubits() {
+            if let Some(qubit) = qubits.iter().find(|qubit| **qubit >= number_qubits) {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Operation {} acts on qubit {} outside of the {} qubit register",
+                        op.hqslang(),
+                        qubit,
+                        number_qubits
+                    ),
+                });
+            }
+        }
+        if let Some(line) = operation_to_qasm(op, number_qubits)? {
+            qasm.push_str(&line);
+            qasm.push('\n');
+        }
+    }
+    Ok(qasm)
+}
+
+// Returns the QASM line of an operation or None for operations that are left out
+fn operation_to_qasm(
+    operation: &Operation,
+    number_qubits: usize,
+) -> Result<Option<String>, RoqoqoBackendError> {
+    let line = match operation {
+        Operation::DefinitionBit(op) => format!("creg {}[{}];", op.name(), op.length()),
+        Operation::RotateX(op) => format!("rx({}) q[{}];", qasm_angle(op.theta())?, op.qubit()),
+        Operation::RotateY(op) => format!("ry({}) q[{}];", qasm_angle(op.theta())?, op.qubit()),
+        Operation::RotateZ(op) => {
+            // rz of qelib1.inc is u1 and differs from exp(-i theta Z / 2) by the phase exp(-i theta / 2),
+            // conjugating rx with Hadamard gates gives the exact unitary
+            let qubit = op.qubit();
+            format!(
+                "h q[{}];\nrx({}) q[{}];\nh q[{}];",
+                qubit,
+                qasm_angle(op.theta())?,
+                qubit,
+                qubit
+            )
+        }
+        Operation::PhaseShiftState1(op) => {
+            format!("u1({}) q[{}];", qasm_angle(op.theta())?, op.qubit())
+        }
+        Operation::PauliX(op) => format!("x q[{}];", op.qubit()),
+        Operation::PauliY(op) => format!("y q[{}];", op.qubit()),
+        Operation::PauliZ(op) => format!("z q[{}];", op.qubit()),
+        Operation::Hadamard(op) => format!("h q[{}];", op.qubit()),
+        Operation::SGate(op) => format!("s q[{}];", op.qubit()),
+        Operation::TGate(op) => format!("t q[{}];", op.qubit()),
+        Operation::SqrtPauliX(op) => format!("rx(pi/2) q[{}];", op.qubit()),
+        Operation::InvSqrtPauliX(op) => format!("rx(-pi/2) q[{}];", op.qubit()),
+        Operation::CNOT(op) => format!("cx q[{}],q[{}];", op.control(), op.target()),
+        Operation::ControlledPauliY(op) => format!("cy q[{}],q[{}];", op.control(), op.target()),
+        Operation::ControlledPauliZ(op) => format!("cz q[{}],q[{}];", op.control(), op.target()),
+        Operation::ControlledPhaseShift(op) => format!(
+            "cu1({}) q[{}],q[{}];",
+            qasm_angle(op.theta())?,
+            op.control(),
+            op.target()
+        ),
+        Operation::SWAP(op) => format!("swap q[{}],q[{}];", op.control(), op.target()),
+        Operation::MeasureQubit(op) => format!(
+            "measure q[{}] -> {}[{}];",
+            op.qubit(),
+            op.readout(),
+            op.readout_index()
+        ),
+        Operation::PragmaRepeatedMeasurement(op) => {
+            // The number of measurements is a property of the execution and not of the QASM program
+            let mut qubit_mapping: Vec<(usize, usize)> = match op.qubit_mapping() {
+                Some(mapping) => mapping
+                    .iter()
+                    .map(|(qubit, index)| (*qubit, *index))
+                    .collect(),
+                None => (0..number_qubits).map(|qubit| (qubit, qubit)).collect(),
+            };
+            qubit_mapping.sort_unstable();
+            qubit_mapping
+                .iter()
+                .map(|(qubit, index)| {
+                    format!("measure q[{}] -> {}[{}];", qubit, op.readout(), index)
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        Operation::PragmaActiveReset(op) => format!("reset q[{}];", op.qubit()),
+        _ => {
+            if IGNORED_OPERATIONS.contains(&operation.hqslang()) {
+                return Ok(None);
+            }
+            return Err(RoqoqoBackendError::OperationNotInBackend {
+                backend: "QASM",
+                hqslang: operation.hqslang(),
+            });
+        }
+    };
+    Ok(Some(line))
+}
+
+// Returns the value of an angle, symbolic angles can not be serialized
+fn qasm_angle(angle: &CalculatorFloat) -> Result<f64, RoqoqoBackendError> {
+    Ok(f64::try_from(angle.clone())?)
+}
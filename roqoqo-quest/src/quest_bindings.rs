@@ -10,7 +10,47 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::interface::{cumulative_measurement_probabilities, index_to_qubits, sample_inverse_cdf};
+use ndarray::Array2;
 use num_complex::Complex64;
+use quest_sys::qreal;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Binomial, Distribution};
+use roqoqo::RoqoqoBackendError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// QuEST draws measurement outcomes from a single process-wide random number generator,
+// calls that seed or draw from it hold this lock so that simulations on different threads do not race
+static QUEST_RNG_LOCKED: AtomicBool = AtomicBool::new(false);
+
+// Holds the lock of the random number generator of QuEST until it is dropped
+struct QuestRngGuard;
+
+impl Drop for QuestRngGuard {
+    fn drop(&mut self) {
+        QUEST_RNG_LOCKED.store(false, Ordering::Release);
+    }
+}
+
+// Locks the random number generator of QuEST, the calls holding the lock are short so waiting threads spin
+fn lock_quest_rng() -> QuestRngGuard {
+    while QUEST_RNG_LOCKED
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::thread::yield_now();
+    }
+    QuestRngGuard
+}
+
+/// Imperfect readout model, the 2x2 readout confusion matrix of each qubit
+///
+/// Entry [measured][detected] of a confusion matrix is the probability to detect the measured value
+/// of the qubit as the detected value. Qubits without a confusion matrix are read out perfectly.
+pub type ReadoutModel = HashMap<usize, [[f64; 2]; 2]>;
 
 /// Wrapper around QuEST quantum register
 ///
@@ -20,7 +60,9 @@ use num_complex::Complex64;
 /// A density-matrix quantum register contains the full density matrix
 /// of a potentially mixed quantum state of the simulator.
 ///
-#[derive(Debug, Clone)]
+/// The Debug output only summarizes the quantum register and does not contain its amplitudes.
+///
+#[derive(Clone)]
 pub struct Qureg {
     /// Internally stored C QuEST environment.
     pub quest_env: quest_sys::QuESTEnv,
@@ -28,6 +70,130 @@ pub struct Qureg {
     pub quest_qureg: quest_sys::Qureg,
     /// Is a density matrix
     pub is_density_matrix: bool,
+    /// Random number generator used for sampling on the Rust side (e.g. repeated measurements)
+    pub rng: StdRng,
+    /// Buffer for the probabilities that is reused when sampling repeated measurements
+    pub probability_buffer: Vec<f64>,
+    /// Factor the probabilities of noise pragmas are multiplied with, set by PragmaBoostNoise
+    pub noise_boost: f64,
+    /// Number of times the next gate operation is applied, set by PragmaRepeatGate
+    pub gate_repetitions: usize,
+    /// Largest magnitude of a negative probability that is set to zero before sampling measurements
+    pub probability_tolerance: f64,
+    /// Readout confusion matrices of qubits, entry [measured][detected] is the probability
+    /// to detect the measured value as the detected value
+    pub readout_errors: ReadoutModel,
+    /// Skip operations that are not supported by the backend with a warning instead of returning an error
+    pub skip_unsupported: bool,
+    /// Sample the result of MeasureQubit from the probability of the qubit without collapsing the state
+    pub non_collapsing_measurement: bool,
+    /// Collapse MeasureQubit to the most likely value of the qubit instead of sampling it
+    pub deterministic_measurement: bool,
+    /// Number of shots PragmaGetPauliProduct is estimated from, None returns the exact expectation value
+    pub pauli_product_shots: Option<usize>,
+}
+
+/// Default largest magnitude of a negative probability that is treated as zero when sampling measurements
+pub const DEFAULT_PROBABILITY_TOLERANCE: f64 = 1e-14;
+
+// Allowed deviation of unitary matrices and of sum_k K_k^dagger K_k of Kraus maps from the identity,
+// the machine precision QuEST validates them with
+const VALIDATION_TOLERANCE: f64 = if quest_sys::QUEST_PRECISION == 1 {
+    1e-5
+} else {
+    1e-13
+};
+
+/// Information about the build of the QuEST library used by the simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvironmentInfo {
+    /// Floating point precision of QuEST (`QuEST_PREC`), 1 for single and 2 for double precision
+    pub precision: u32,
+    /// QuEST is compiled with OpenMP multi-threading
+    pub multithreaded: bool,
+    /// Number of OpenMP threads QuEST uses in the calling thread, always 1 without multi-threading
+    pub number_threads: usize,
+}
+
+/// Returns information about the build of the QuEST library used by the simulator.
+///
+/// QuEST is compiled in single precision when the `single-precision` feature is enabled.
+/// Single precision halves the memory of a quantum register, but amplitudes and probabilities
+/// are only accurate to about 1e-6 and values are converted to and from f64 at the boundary.
+pub fn environment_info() -> EnvironmentInfo {
+    EnvironmentInfo {
+        precision: quest_sys::QUEST_PRECISION,
+        multithreaded: cfg!(feature = "openmp"),
+        number_threads: number_threads(),
+    }
+}
+
+/// Returns the number of OpenMP threads QuEST uses for operations started from the calling thread.
+///
+/// Without the `openmp` feature QuEST is single-threaded and the number of threads is always 1.
+pub fn number_threads() -> usize {
+    #[cfg(feature = "openmp")]
+    unsafe {
+        quest_sys::omp_get_max_threads() as usize
+    }
+    #[cfg(not(feature = "openmp"))]
+    1
+}
+
+/// Sets the number of OpenMP threads QuEST uses for operations started from the calling thread.
+///
+/// The number of threads overrides the `OMP_NUM_THREADS` environment variable.
+/// It applies to quantum registers created afterwards in the calling thread,
+/// so it needs to be set before the QuEST environment and quantum register are created.
+///
+/// # Arguments
+///
+/// * `number_threads` - The number of OpenMP threads.
+///
+/// # Returns
+///
+/// `Err(RoqoqoBackendError)` - The number of threads is zero, or larger than one without the `openmp` feature.
+pub fn set_number_threads(number_threads: usize) -> Result<(), RoqoqoBackendError> {
+    check_number_threads(number_threads)?;
+    #[cfg(feature = "openmp")]
+    unsafe {
+        quest_sys::omp_set_num_threads(number_threads as ::std::os::raw::c_int);
+    }
+    Ok(())
+}
+
+// Returns an error if QuEST can not use the number of threads,
+// zero threads are invalid and without the `openmp` feature only a single thread is possible
+pub(crate) fn check_number_threads(number_threads: usize) -> Result<(), RoqoqoBackendError> {
+    if number_threads == 0 {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: "Number of threads must be larger than zero".to_string(),
+        });
+    }
+    if !cfg!(feature = "openmp") && number_threads > 1 {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: "QuEST is compiled without OpenMP and can only use a single thread".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the number of amplitudes 2^number_qubits of the state vector of a quantum register.
+///
+/// Returns an error instead of overflowing when the number of amplitudes exceeds usize::MAX.
+///
+/// # Arguments
+///
+/// * `number_qubits` - The number of qubits of the quantum register.
+pub fn number_amplitudes(number_qubits: u32) -> Result<usize, RoqoqoBackendError> {
+    2_usize
+        .checked_pow(number_qubits)
+        .ok_or_else(|| RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Number of amplitudes of a quantum register with {} qubits exceeds the addressable size",
+                number_qubits
+            ),
+        })
 }
 
 impl Qureg {
@@ -38,19 +204,305 @@ impl Qureg {
     /// * `number_qubits` - The number of qubits in the quantum register.
     /// * `is_density_matrix` - Create a
     pub fn new(number_qubits: u32, is_density_matrix: bool) -> Self {
-        unsafe {
-            let quest_env = quest_sys::createQuESTEnv();
-            let quest_qureg = if is_density_matrix {
+        // Creating the environment seeds the random number generator of QuEST
+        let quest_env = {
+            let _rng = lock_quest_rng();
+            unsafe { quest_sys::createQuESTEnv() }
+        };
+        Self::with_environment(number_qubits, is_density_matrix, quest_env)
+    }
+
+    /// Creates a temporary quantum register with the same number of qubits in the environment of the register.
+    ///
+    /// Unlike [Qureg::new] this does not reseed the random number generator of QuEST,
+    /// so workspaces do not change the results of a seeded simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_density_matrix` - Create the workspace as a density matrix.
+    pub fn new_workspace(&self, is_density_matrix: bool) -> Self {
+        // The seeds are owned by the environment of self and freed when self is dropped
+        let quest_env = quest_sys::QuESTEnv {
+            seeds: std::ptr::null_mut(),
+            numSeeds: 0,
+            ..self.quest_env
+        };
+        Self::with_environment(self.number_qubits(), is_density_matrix, quest_env)
+    }
+
+    // Creates the C quantum register in an environment that is destroyed together with the register
+    fn with_environment(
+        number_qubits: u32,
+        is_density_matrix: bool,
+        quest_env: quest_sys::QuESTEnv,
+    ) -> Self {
+        let quest_qureg = unsafe {
+            if is_density_matrix {
                 quest_sys::createDensityQureg(number_qubits as ::std::os::raw::c_int, quest_env)
             } else {
                 quest_sys::createQureg(number_qubits as ::std::os::raw::c_int, quest_env)
-            };
-            Qureg {
-                quest_env,
-                quest_qureg,
-                is_density_matrix,
+            }
+        };
+        Qureg {
+            quest_env,
+            quest_qureg,
+            is_density_matrix,
+            rng: StdRng::from_entropy(),
+            probability_buffer: Vec::new(),
+            noise_boost: 1.0,
+            gate_repetitions: 1,
+            probability_tolerance: DEFAULT_PROBABILITY_TOLERANCE,
+            readout_errors: HashMap::new(),
+            skip_unsupported: false,
+            non_collapsing_measurement: false,
+            deterministic_measurement: false,
+            pauli_product_shots: None,
+        }
+    }
+
+    /// Seeds the random number generators of QuEST and of the Rust side of the quantum register.
+    ///
+    /// After seeding, all stochastic results (e.g. measurement outcomes) are reproducible.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed array used to initialise the random number generators.
+    pub fn set_random_seed(&mut self, seed: &[u64]) {
+        let mut seed_array: Vec<::std::os::raw::c_ulong> =
+            seed.iter().map(|x| *x as ::std::os::raw::c_ulong).collect();
+        let _rng = lock_quest_rng();
+        unsafe {
+            quest_sys::seedQuEST(
+                &mut self.quest_env,
+                seed_array.as_mut_ptr(),
+                seed_array.len() as ::std::os::raw::c_int,
+            )
+        }
+        // Combine the seed array into a single u64 with a linear congruential step per entry
+        let combined_seed = seed.iter().fold(0_u64, |acc, x| {
+            acc.wrapping_mul(6364136223846793005).wrapping_add(*x)
+        });
+        self.rng = StdRng::seed_from_u64(combined_seed);
+    }
+
+    /// Returns the detected value of a measured qubit after applying its readout error.
+    ///
+    /// The detected value is sampled from the row of the confusion matrix of the qubit
+    /// that belongs to the measured value. Qubits without readout error are detected correctly.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit` - The qubit that was measured.
+    /// * `measured` - The measured value of the qubit.
+    pub fn readout_error(&mut self, qubit: usize, measured: bool) -> bool {
+        match self.readout_errors.get(&qubit) {
+            Some(confusion_matrix) => {
+                let probability_flip = confusion_matrix[measured as usize][!measured as usize];
+                if self.rng.gen::<f64>() < probability_flip {
+                    !measured
+                } else {
+                    measured
+                }
+            }
+            None => measured,
+        }
+    }
+
+    /// Returns the empirical estimate of a Pauli product from a finite number of shots.
+    ///
+    /// Measuring the Pauli product in its eigenbasis yields +1 with probability (1 + expectation) / 2
+    /// and -1 otherwise. The number of +1 outcomes of all shots is sampled from the corresponding
+    /// binomial distribution with the Rust side random number generator,
+    /// so the estimate has the variance (1 - expectation^2) / shots of a hardware measurement.
+    ///
+    /// # Arguments
+    ///
+    /// * `expectation` - The exact expectation value of the Pauli product.
+    /// * `shots` - The number of shots the estimate is calculated from.
+    pub fn estimate_pauli_product(
+        &mut self,
+        expectation: f64,
+        shots: usize,
+    ) -> Result<f64, RoqoqoBackendError> {
+        if shots == 0 {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Pauli product can not be estimated from zero shots".to_string(),
+            });
+        }
+        let probability_plus = ((1.0 + expectation) / 2.0).clamp(0.0, 1.0);
+        let binomial = Binomial::new(shots as u64, probability_plus).map_err(|err| {
+            RoqoqoBackendError::GenericError {
+                msg: format!("Pauli product can not be sampled: {}", err),
+            }
+        })?;
+        let number_plus = binomial.sample(&mut self.rng) as f64;
+        Ok(2.0 * number_plus / shots as f64 - 1.0)
+    }
+
+    /// Samples the measured value of a qubit without collapsing the state of the quantum register.
+    ///
+    /// The value is sampled with the Rust side random number generator from the probability
+    /// of the qubit to be in |1>, calculated with QuEST's calcProbOfOutcome.
+    /// The readout error of the qubit is not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit` - The qubit that is measured.
+    pub fn sample_qubit(&mut self, qubit: usize) -> Result<bool, RoqoqoBackendError> {
+        let probability_one = self.probability_of_one(qubit)?;
+        Ok(self.rng.gen::<f64>() < probability_one)
+    }
+
+    /// Measures a qubit with QuEST, collapsing the state onto the measured value.
+    ///
+    /// The outcome is drawn from the random number generator of QuEST.
+    /// The readout error of the qubit is not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit` - The qubit that is measured.
+    pub fn measure_qubit(&mut self, qubit: usize) -> bool {
+        let _rng = lock_quest_rng();
+        unsafe { quest_sys::measure(self.quest_qureg, qubit as ::std::os::raw::c_int) == 1 }
+    }
+
+    /// Measures a qubit deterministically, collapsing it to its most likely value.
+    ///
+    /// The qubit is projected onto |1> if its probability to be in |1> is larger than one half
+    /// and onto |0> otherwise, and the state is renormalized with QuEST's collapseToOutcome.
+    /// The readout error of the qubit is not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit` - The qubit that is measured.
+    pub fn collapse_to_most_likely(&mut self, qubit: usize) -> Result<bool, RoqoqoBackendError> {
+        let measured = self.probability_of_one(qubit)? > 0.5;
+        unsafe {
+            quest_sys::collapseToOutcome(
+                self.quest_qureg,
+                qubit as ::std::os::raw::c_int,
+                measured as ::std::os::raw::c_int,
+            );
+        }
+        Ok(measured)
+    }
+
+    /// Measures all qubits jointly, sampling one basis state from the current probabilities.
+    ///
+    /// A single basis state is drawn from the probability distribution of the whole quantum register
+    /// and the state is projected onto it with QuEST's collapseToOutcome,
+    /// so all qubits are measured in one go and correlations between them are kept.
+    /// The readout errors of the qubits are applied to the returned values,
+    /// the n-th entry being the measured value of the n-th qubit.
+    pub fn snapshot_measurement(&mut self) -> Result<Vec<bool>, RoqoqoBackendError> {
+        let cumulative_probabilities = cumulative_measurement_probabilities(self)?;
+        let index = sample_inverse_cdf(&cumulative_probabilities, &mut self.rng);
+        let mut measured = index_to_qubits(index, self.number_qubits());
+        for (qubit, outcome) in measured.iter().enumerate() {
+            unsafe {
+                quest_sys::collapseToOutcome(
+                    self.quest_qureg,
+                    qubit as ::std::os::raw::c_int,
+                    *outcome as ::std::os::raw::c_int,
+                );
+            }
+        }
+        self.apply_readout_errors(&mut measured);
+        Ok(measured)
+    }
+
+    /// Returns the probabilities of single qubits to be measured in |1> without collapsing the state.
+    ///
+    /// The marginal probability of each qubit is calculated with QuEST's calcProbOfOutcome,
+    /// which is much cheaper than reading out the probabilities of all 2^N basis states.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits whose marginal probabilities are returned.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<f64>` - The probability of `qubits[k]` to be in |1> at index k.
+    pub fn marginal_probabilities(&self, qubits: &[usize]) -> Result<Vec<f64>, RoqoqoBackendError> {
+        qubits
+            .iter()
+            .map(|qubit| self.probability_of_one(*qubit))
+            .collect()
+    }
+
+    /// Measures a subset of qubits and returns the outcome as the index of a basis state.
+    ///
+    /// The qubits are measured one after the other with QuEST's measure, collapsing the state.
+    /// The readout errors of the qubits are applied to the measured values.
+    /// The k-th bit of the returned integer is the measured value of `qubits[k]`,
+    /// so `qubits[0]` is the least significant bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The measured qubits, at most 64.
+    ///
+    /// # Returns
+    ///
+    /// `Err(RoqoqoBackendError)` - More than 64 qubits or a qubit outside of the quantum register.
+    pub fn measure_subset_to_int(&mut self, qubits: &[usize]) -> Result<u64, RoqoqoBackendError> {
+        if qubits.len() > u64::BITS as usize {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "{} qubits can not be packed into a 64 bit integer",
+                    qubits.len()
+                ),
+            });
+        }
+        if let Some(qubit) = qubits
+            .iter()
+            .find(|qubit| **qubit >= self.number_qubits() as usize)
+        {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Qubit {} is outside of the quantum register with {} qubits",
+                    qubit,
+                    self.number_qubits()
+                ),
+            });
+        }
+        let mut outcome = 0_u64;
+        for (bit, qubit) in qubits.iter().enumerate() {
+            let measured = self.measure_qubit(*qubit);
+            if self.readout_error(*qubit, measured) {
+                outcome |= 1 << bit;
             }
         }
+        Ok(outcome)
+    }
+
+    // Returns the probability of a qubit to be measured in |1>
+    fn probability_of_one(&self, qubit: usize) -> Result<f64, RoqoqoBackendError> {
+        if qubit >= self.number_qubits() as usize {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Qubit {} is outside of the quantum register with {} qubits",
+                    qubit,
+                    self.number_qubits()
+                ),
+            });
+        }
+        Ok(unsafe {
+            quest_sys::calcProbOfOutcome(self.quest_qureg, qubit as ::std::os::raw::c_int, 1)
+        } as f64)
+    }
+
+    /// Applies the readout errors to the measured values of all qubits in the quantum register.
+    ///
+    /// # Arguments
+    ///
+    /// * `measured` - The measured values, indexed by qubit, replaced by the detected values.
+    pub fn apply_readout_errors(&mut self, measured: &mut [bool]) {
+        if self.readout_errors.is_empty() {
+            return;
+        }
+        for (qubit, value) in measured.iter_mut().enumerate() {
+            *value = self.readout_error(qubit, *value);
+        }
     }
 
     /// Returns the number of qubits in the qureg.
@@ -58,27 +510,1231 @@ impl Qureg {
         self.quest_qureg.numQubitsRepresented as u32
     }
 
+    /// Returns true if the quantum register contains a density matrix instead of a state vector.
+    pub fn is_density_matrix(&self) -> bool {
+        self.is_density_matrix
+    }
+
+    /// Returns the number of amplitudes 2^number_qubits of the state vector of the quantum register.
+    ///
+    /// Returns an error instead of overflowing when the number of amplitudes exceeds usize::MAX.
+    pub fn number_amplitudes(&self) -> Result<usize, RoqoqoBackendError> {
+        number_amplitudes(self.number_qubits())
+    }
+
+    /// Initializes the quantum register in a classical basis state.
+    ///
+    /// The basis state is given by its index, the n-th bit of the index is the state of the n-th qubit.
+    /// Any previous state of the quantum register, including entanglement, is discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the basis state, must be smaller than 2^number_qubits.
+    pub fn init_classical_state(&mut self, index: usize) -> Result<(), RoqoqoBackendError> {
+        let number_amplitudes = self.number_amplitudes()?;
+        if index >= number_amplitudes {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Basis state {} does not exist in a quantum register with {} qubits",
+                    index,
+                    self.number_qubits()
+                ),
+            });
+        }
+        unsafe { quest_sys::initClassicalState(self.quest_qureg, index as i64) }
+        Ok(())
+    }
+
+    /// Resets the quantum register to the all-zero state |0...0>.
+    ///
+    /// Uses QuEST's initZeroState. All amplitudes, including entanglement between qubits
+    /// and mixedness of a density matrix, are discarded. The random number generators,
+    /// readout errors and other settings of the quantum register are kept.
+    pub fn reset_to_zero(&mut self) {
+        unsafe { quest_sys::initZeroState(self.quest_qureg) }
+    }
+
+    /// Initializes the quantum register in the equal superposition state |+>^n.
+    ///
+    /// Uses QuEST's initPlusState, which is cheaper than applying a Hadamard gate to each qubit.
+    /// Any previous state of the quantum register is discarded.
+    pub fn init_plus_state(&mut self) {
+        unsafe { quest_sys::initPlusState(self.quest_qureg) }
+    }
+
+    /// Converts a state vector quantum register into the density matrix of the same pure state.
+    ///
+    /// A density matrix quantum register is allocated and initialized to |psi><psi| from the current
+    /// amplitudes with QuEST's initPureState, then the state vector is released.
+    /// All other settings of the quantum register are kept.
+    /// A quantum register that already contains a density matrix is not changed.
+    pub fn promote_to_density_matrix(&mut self) {
+        if self.is_density_matrix {
+            return;
+        }
+        unsafe {
+            let density_matrix = quest_sys::createDensityQureg(
+                self.quest_qureg.numQubitsRepresented,
+                self.quest_env,
+            );
+            quest_sys::initPureState(density_matrix, self.quest_qureg);
+            quest_sys::destroyQureg(self.quest_qureg, self.quest_env);
+            self.quest_qureg = density_matrix;
+        }
+        self.is_density_matrix = true;
+    }
+
+    /// Copies the state of another quantum register into the quantum register.
+    ///
+    /// Both quantum registers must have the same number of qubits and
+    /// both must be state vectors or both density matrices.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The quantum register the state is copied from.
+    pub fn copy_state_from(&mut self, other: &Qureg) -> Result<(), RoqoqoBackendError> {
+        if self.is_density_matrix != other.is_density_matrix
+            || self.number_qubits() != other.number_qubits()
+        {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Can not copy the state of a quantum register with {} qubits (density matrix: {}) into a quantum register with {} qubits (density matrix: {})",
+                    other.number_qubits(),
+                    other.is_density_matrix,
+                    self.number_qubits(),
+                    self.is_density_matrix
+                ),
+            });
+        }
+        unsafe { quest_sys::cloneQureg(self.quest_qureg, other.quest_qureg) }
+        Ok(())
+    }
+
     /// Returns probability amplitudes for each state in the quantum register.
     ///
     /// Probability amplitudes give the probability that a quantum register collapses to the corresponding state after a measurement.
     pub fn probabilites(&self) -> Vec<f64> {
-        let number_qubits = self.number_qubits();
-        let dimension: u32 = 2u32.pow(number_qubits);
-        let mut probabilites: Vec<f64> = Vec::with_capacity(dimension as usize);
+        let number_amplitudes = self
+            .number_amplitudes()
+            .expect("Number of amplitudes of an allocated quantum register fits in usize");
+        let mut probabilites: Vec<f64> = vec![0.0; number_amplitudes];
+        self.probabilities_into(&mut probabilites)
+            .expect("Buffer for probabilities has the size of the quantum register");
+        probabilites
+    }
+
+    /// Writes the probability amplitudes for each state in the quantum register into a buffer.
+    ///
+    /// Same as [Qureg::probabilites] without allocating a new vector on each call.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer the probabilities are written to, must have length 2^number_qubits.
+    pub fn probabilities_into(&self, buf: &mut [f64]) -> Result<(), RoqoqoBackendError> {
+        let dimension = self.number_amplitudes()?;
+        if buf.len() != dimension {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Buffer of length {} can not hold the {} probabilities of the quantum register",
+                    buf.len(),
+                    dimension
+                ),
+            });
+        }
+        if self.is_density_matrix {
+            for (index, probability) in buf.iter_mut().enumerate() {
+                *probability = unsafe {
+                    quest_sys::getDensityAmp(self.quest_qureg, index as i64, index as i64).real
+                        as f64
+                };
+            }
+        } else {
+            for (index, probability) in buf.iter_mut().enumerate() {
+                *probability =
+                    unsafe { quest_sys::getProbAmp(self.quest_qureg, index as i64) as f64 };
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the expectation values of the Pauli operators X, Y and Z for each qubit.
+    ///
+    /// The entry of each qubit is its Bloch vector [<X>, <Y>, <Z>].
+    /// The expectation values are calculated with QuEST on a workspace register,
+    /// the state of the quantum register is left unchanged.
+    pub fn single_qubit_pauli_expectations(&self) -> Vec<[f64; 3]> {
+        let workspace = self.new_workspace(self.is_density_matrix);
+        let pauli_codes = [
+            quest_sys::pauliOpType_PAULI_X,
+            quest_sys::pauliOpType_PAULI_Y,
+            quest_sys::pauliOpType_PAULI_Z,
+        ];
+        (0..self.number_qubits())
+            .map(|qubit| {
+                let mut expectations = [0.0; 3];
+                for (expectation, pauli_code) in expectations.iter_mut().zip(pauli_codes) {
+                    let mut target = [qubit as ::std::os::raw::c_int];
+                    let mut code = [pauli_code];
+                    *expectation = unsafe {
+                        quest_sys::calcExpecPauliProd(
+                            self.quest_qureg,
+                            target.as_mut_ptr(),
+                            code.as_mut_ptr(),
+                            1,
+                            workspace.quest_qureg,
+                        ) as f64
+                    };
+                }
+                expectations
+            })
+            .collect()
+    }
+
+    /// Multiplies the quantum register with the global phase factor exp(i * phase).
+    ///
+    /// The global phase has no effect on a density matrix, which is left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The global phase that is applied.
+    pub fn apply_global_phase(&mut self, phase: f64) {
+        if !self.is_density_matrix {
+            let zero = quest_sys::Complex {
+                real: 0.0,
+                imag: 0.0,
+            };
+            let factor = quest_sys::Complex {
+                real: phase.cos() as qreal,
+                imag: phase.sin() as qreal,
+            };
+            unsafe {
+                quest_sys::setWeightedQureg(
+                    zero,
+                    self.quest_qureg,
+                    zero,
+                    self.quest_qureg,
+                    factor,
+                    self.quest_qureg,
+                )
+            }
+        }
+    }
+
+    /// Multiplies each basis state with a phase factor that depends on the state of a set of qubits.
+    ///
+    /// The basis state is multiplied with exp(i * phases[r]), where the n-th qubit in `qubits`
+    /// is the n-th bit of the index r. On a density matrix the diagonal operator is applied as a unitary.
+    /// All phases are applied in a single pass over the amplitudes.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits that determine the phase of a basis state.
+    /// * `phases` - The phases of all 2^len(qubits) states of the qubits.
+    pub fn apply_diagonal_phases(
+        &mut self,
+        qubits: &[usize],
+        phases: &[f64],
+    ) -> Result<(), RoqoqoBackendError> {
+        self.check_subsystem(qubits)?;
+        if qubits.is_empty() || phases.len() != 1 << qubits.len() {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Diagonal operator on {} qubits requires {} phases, got {}",
+                    qubits.len(),
+                    1_usize << qubits.len(),
+                    phases.len()
+                ),
+            });
+        }
+        let factors: Vec<Complex64> = phases
+            .iter()
+            .map(|phase| Complex64::new(phase.cos(), phase.sin()))
+            .collect();
+        let number_qubits = self.number_qubits() as usize;
+        let number_amplitudes = self.quest_qureg.numAmpsPerChunk as usize;
+        let offset = self.quest_qureg.chunkId as usize * number_amplitudes;
+        // The amplitudes are modified in place, the density matrix element (row, column)
+        // is stored at index row + column * 2^N
+        let (reals, imags) = unsafe {
+            (
+                std::slice::from_raw_parts_mut(self.quest_qureg.stateVec.real, number_amplitudes),
+                std::slice::from_raw_parts_mut(self.quest_qureg.stateVec.imag, number_amplitudes),
+            )
+        };
+        for (index, (real, imag)) in reals.iter_mut().zip(imags.iter_mut()).enumerate() {
+            let global_index = offset + index;
+            let mut factor = factors[gather_bits(global_index, qubits)];
+            if self.is_density_matrix {
+                factor *= factors[gather_bits(global_index >> number_qubits, qubits)].conj();
+            }
+            let amplitude = complex_amplitude(*real, *imag) * factor;
+            *real = amplitude.re as qreal;
+            *imag = amplitude.im as qreal;
+        }
+        Ok(())
+    }
+
+    /// Multiplies each basis state of the quantum register with the phase factor exp(i * phases[k]).
+    ///
+    /// State vectors are multiplied with the diagonal operator using QuEST's DiagonalOp API.
+    /// QuEST only multiplies density matrices from the left with a DiagonalOp,
+    /// so on density matrices the operator is applied as a unitary with [Qureg::apply_diagonal_phases].
+    ///
+    /// # Arguments
+    ///
+    /// * `phases` - The phases of all 2^N basis states of the quantum register.
+    pub fn apply_diagonal_op(&mut self, phases: &[f64]) -> Result<(), RoqoqoBackendError> {
+        let number_amplitudes = self.number_amplitudes()?;
+        if phases.len() != number_amplitudes {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Diagonal operator on a quantum register with {} qubits requires {} phases, got {}",
+                    self.number_qubits(),
+                    number_amplitudes,
+                    phases.len()
+                ),
+            });
+        }
         if self.is_density_matrix {
-            for index in 0..dimension {
+            let qubits: Vec<usize> = (0..self.number_qubits() as usize).collect();
+            return self.apply_diagonal_phases(&qubits, phases);
+        }
+        let mut reals: Vec<qreal> = phases.iter().map(|phase| phase.cos() as qreal).collect();
+        let mut imags: Vec<qreal> = phases.iter().map(|phase| phase.sin() as qreal).collect();
+        unsafe {
+            let diagonal_op = quest_sys::createDiagonalOp(
+                self.number_qubits() as ::std::os::raw::c_int,
+                self.quest_env,
+            );
+            quest_sys::initDiagonalOp(diagonal_op, reals.as_mut_ptr(), imags.as_mut_ptr());
+            quest_sys::applyDiagonalOp(self.quest_qureg, diagonal_op);
+            quest_sys::destroyDiagonalOp(diagonal_op, self.quest_env);
+        }
+        Ok(())
+    }
+
+    /// Returns the expectation value of a real diagonal observable.
+    ///
+    /// The observable is loaded into a QuEST DiagonalOp and evaluated with calcExpecDiagonalOp,
+    /// which works for both state vectors and density matrices.
+    ///
+    /// # Arguments
+    ///
+    /// * `diagonal` - The real diagonal entries of the observable for all 2^N basis states.
+    pub fn expectation_value_diagonal(&self, diagonal: &[f64]) -> Result<f64, RoqoqoBackendError> {
+        let number_states = self.number_amplitudes()?;
+        if diagonal.len() != number_states {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Diagonal observable on a quantum register with {} qubits requires {} entries, got {}",
+                    self.number_qubits(),
+                    number_states,
+                    diagonal.len()
+                ),
+            });
+        }
+        let mut reals: Vec<qreal> = diagonal.iter().map(|value| *value as qreal).collect();
+        let mut imags: Vec<qreal> = vec![0.0; number_states];
+        let expectation_value = unsafe {
+            let diagonal_op = quest_sys::createDiagonalOp(
+                self.number_qubits() as ::std::os::raw::c_int,
+                self.quest_env,
+            );
+            quest_sys::initDiagonalOp(diagonal_op, reals.as_mut_ptr(), imags.as_mut_ptr());
+            let expectation_value = quest_sys::calcExpecDiagonalOp(self.quest_qureg, diagonal_op);
+            quest_sys::destroyDiagonalOp(diagonal_op, self.quest_env);
+            expectation_value
+        };
+        Ok(expectation_value.real as f64)
+    }
+
+    /// Applies the quantum Fourier transform to a subset of qubits.
+    ///
+    /// The transform is applied natively with QuEST's applyQFT (applyFullQFT for all qubits in order),
+    /// without decomposing it into Hadamard, controlled phase and SWAP gates.
+    /// The n-th qubit in `qubits` is the n-th bit of the transformed index,
+    /// |x> -> 1/sqrt(2^n) sum_y exp(2 pi i x y / 2^n) |y>.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits the quantum Fourier transform acts on.
+    pub fn apply_qft(&mut self, qubits: &[usize]) -> Result<(), RoqoqoBackendError> {
+        self.check_subsystem(qubits)?;
+        if qubits.is_empty() {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Quantum Fourier transform requires at least one qubit".to_string(),
+            });
+        }
+        if qubits
+            .iter()
+            .enumerate()
+            .all(|(index, qubit)| index == *qubit)
+            && qubits.len() == self.number_qubits() as usize
+        {
+            unsafe { quest_sys::applyFullQFT(self.quest_qureg) }
+        } else {
+            let mut targets: Vec<i32> = qubits.iter().map(|qubit| *qubit as i32).collect();
+            unsafe {
+                quest_sys::applyQFT(
+                    self.quest_qureg,
+                    targets.as_mut_ptr(),
+                    targets.len() as ::std::os::raw::c_int,
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the rotation exp(-i * angle/2 * P) under a product P of Pauli operators.
+    ///
+    /// The rotation is applied directly with QuEST's multiRotatePauli,
+    /// without decomposing it into a ladder of CNOT gates.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits the Pauli operators act on.
+    /// * `paulis` - The Pauli operators ('I', 'X', 'Y' or 'Z'), the n-th operator acts on the n-th qubit.
+    /// * `angle` - The rotation angle.
+    pub fn multi_rotate_pauli(
+        &mut self,
+        qubits: &[usize],
+        paulis: &str,
+        angle: f64,
+    ) -> Result<(), RoqoqoBackendError> {
+        self.check_subsystem(qubits)?;
+        let mut pauli_codes: Vec<quest_sys::pauliOpType> = paulis
+            .chars()
+            .map(|pauli| match pauli {
+                'I' => Ok(quest_sys::pauliOpType_PAULI_I),
+                'X' => Ok(quest_sys::pauliOpType_PAULI_X),
+                'Y' => Ok(quest_sys::pauliOpType_PAULI_Y),
+                'Z' => Ok(quest_sys::pauliOpType_PAULI_Z),
+                _ => Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Unknown Pauli operator {} in Pauli string {}",
+                        pauli, paulis
+                    ),
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+        if pauli_codes.len() != qubits.len() || qubits.is_empty() {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Pauli string {} does not match the qubits {:?}",
+                    paulis, qubits
+                ),
+            });
+        }
+        let mut targets: Vec<i32> = qubits.iter().map(|qubit| *qubit as i32).collect();
+        unsafe {
+            quest_sys::multiRotatePauli(
+                self.quest_qureg,
+                targets.as_mut_ptr(),
+                pauli_codes.as_mut_ptr(),
+                targets.len() as i32,
+                angle as qreal,
+            )
+        }
+        Ok(())
+    }
+
+    /// Applies amplitude damping and dephasing of a qubit for a time evolution with given T1 and T2.
+    ///
+    /// The populations decay with exp(-duration/T1) and the coherences with exp(-duration/T2).
+    /// The damping probability is 1 - exp(-duration/T1), the remaining pure dephasing with
+    /// 1/T_phi = 1/T2 - 1/(2 T1) is applied with the probability (1 - exp(-duration/T_phi))/2.
+    /// Only possible for density matrix quantum registers and requires T2 <= 2 T1.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit` - The qubit the noise acts on.
+    /// * `t1` - The relaxation time T1.
+    /// * `t2` - The dephasing time T2.
+    /// * `duration` - The duration of the time evolution.
+    pub fn apply_t1_t2_noise(
+        &mut self,
+        qubit: usize,
+        t1: f64,
+        t2: f64,
+        duration: f64,
+    ) -> Result<(), RoqoqoBackendError> {
+        if !self.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "T1 and T2 noise can only be applied to a density matrix quantum register"
+                    .to_string(),
+            });
+        }
+        self.check_subsystem(&[qubit])?;
+        if !(t1 > 0.0 && t2 > 0.0 && duration >= 0.0 && t2 <= 2.0 * t1) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Invalid T1 and T2 noise with T1 {}, T2 {} and duration {}, T1 and T2 must be positive with T2 <= 2 T1",
+                    t1, t2, duration
+                ),
+            });
+        }
+        let damping_probability = 1.0 - (-duration / t1).exp();
+        let dephasing_rate = (1.0 / t2 - 0.5 / t1).max(0.0);
+        let dephasing_probability = 0.5 * (1.0 - (-duration * dephasing_rate).exp());
+        unsafe {
+            quest_sys::mixDamping(
+                self.quest_qureg,
+                qubit as ::std::os::raw::c_int,
+                damping_probability as qreal,
+            );
+            quest_sys::mixDephasing(
+                self.quest_qureg,
+                qubit as ::std::os::raw::c_int,
+                dephasing_probability as qreal,
+            );
+        }
+        Ok(())
+    }
+
+    /// Applies a depolarising channel to the whole quantum register.
+    ///
+    /// The channel rho -> (1 - probability) rho + probability 1/2^n mixes the state with the
+    /// maximally mixed state, a probability of one fully depolarises the register.
+    /// The maximally mixed state is prepared by fully depolarising each qubit of a workspace register
+    /// and mixed into the state with QuEST's mixDensityMatrix.
+    /// Only possible for density matrix quantum registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `probability` - The probability of replacing the state with the maximally mixed state, between 0 and 1.
+    pub fn mix_global_depolarising(&mut self, probability: f64) -> Result<(), RoqoqoBackendError> {
+        if !self.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Global depolarising noise can only be applied to a density matrix quantum register"
+                    .to_string(),
+            });
+        }
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Probability {} of global depolarising noise must be between 0 and 1",
+                    probability
+                ),
+            });
+        }
+        let workspace = self.new_workspace(true);
+        unsafe {
+            for qubit in 0..self.number_qubits() {
+                // Depolarising probability 3/4 fully mixes a qubit
+                quest_sys::mixDepolarising(
+                    workspace.quest_qureg,
+                    qubit as ::std::os::raw::c_int,
+                    0.75 as qreal,
+                );
+            }
+            quest_sys::mixDensityMatrix(
+                self.quest_qureg,
+                probability as qreal,
+                workspace.quest_qureg,
+            );
+        }
+        Ok(())
+    }
+
+    /// Applies a correlated two-qubit noise channel given by its Kraus operators.
+    ///
+    /// The channel rho -> sum_k K_k rho K_k^dagger is applied with QuEST's mixTwoQubitKrausMap.
+    /// In the 4x4 Kraus operators `qubit_a` corresponds to the least significant bit of the basis state index.
+    /// The Kraus operators must be trace preserving (sum_k K_k^dagger K_k = 1).
+    /// Only possible for density matrix quantum registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubit_a` - The first qubit the channel acts on.
+    /// * `qubit_b` - The second qubit the channel acts on.
+    /// * `kraus_operators` - The 4x4 Kraus operators of the channel, at most 16.
+    pub fn mix_two_qubit_kraus_map(
+        &mut self,
+        qubit_a: usize,
+        qubit_b: usize,
+        kraus_operators: &[Array2<Complex64>],
+    ) -> Result<(), RoqoqoBackendError> {
+        if !self.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Kraus maps can only be applied to a density matrix quantum register"
+                    .to_string(),
+            });
+        }
+        self.check_subsystem(&[qubit_a, qubit_b])?;
+        if kraus_operators.is_empty()
+            || kraus_operators.len() > 16
+            || kraus_operators.iter().any(|op| op.dim() != (4, 4))
+        {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "A two-qubit Kraus map needs between 1 and 16 Kraus operators of size 4x4, got {} operators",
+                    kraus_operators.len()
+                ),
+            });
+        }
+        let completeness = kraus_operators
+            .iter()
+            .fold(Array2::<Complex64>::zeros((4, 4)), |acc, op| {
+                acc + op.t().mapv(|value| value.conj()).dot(op)
+            });
+        let deviation = (completeness - Array2::<Complex64>::eye(4))
+            .iter()
+            .map(|value| value.norm())
+            .fold(0.0, f64::max);
+        if deviation > VALIDATION_TOLERANCE {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Kraus operators are not trace preserving, sum of K^dagger K deviates from identity by {}",
+                    deviation
+                ),
+            });
+        }
+        let mut matrices: Vec<quest_sys::ComplexMatrix4> = kraus_operators
+            .iter()
+            .map(|op| quest_sys::ComplexMatrix4 {
+                real: qreal_matrix(
+                    [0, 1, 2, 3].map(|row| [0, 1, 2, 3].map(|column| op[(row, column)].re)),
+                ),
+                imag: qreal_matrix(
+                    [0, 1, 2, 3].map(|row| [0, 1, 2, 3].map(|column| op[(row, column)].im)),
+                ),
+            })
+            .collect();
+        unsafe {
+            quest_sys::mixTwoQubitKrausMap(
+                self.quest_qureg,
+                qubit_a as ::std::os::raw::c_int,
+                qubit_b as ::std::os::raw::c_int,
+                matrices.as_mut_ptr(),
+                matrices.len() as ::std::os::raw::c_int,
+            )
+        }
+        Ok(())
+    }
+
+    /// Applies a unitary to target qubits conditioned on all control qubits being in state |1>.
+    ///
+    /// The gate is applied natively with QuEST's multiControlledMultiQubitUnitary
+    /// (multiQubitUnitary without control qubits). Controlled single qubit unitaries,
+    /// such as a doubly-controlled Hadamard, use the dedicated multiControlledUnitary.
+    /// The n-th target qubit corresponds to the n-th bit of the row and column indices of the unitary.
+    ///
+    /// # Arguments
+    ///
+    /// * `controls` - The control qubits.
+    /// * `targets` - The target qubits of the unitary, at least one.
+    /// * `unitary` - The 2^n x 2^n unitary matrix applied to the n target qubits.
+    pub fn apply_multi_controlled_unitary(
+        &mut self,
+        controls: &[usize],
+        targets: &[usize],
+        unitary: &Array2<Complex64>,
+    ) -> Result<(), RoqoqoBackendError> {
+        let qubits: Vec<usize> = controls.iter().chain(targets.iter()).cloned().collect();
+        self.check_subsystem(&qubits)?;
+        check_unitary(targets.len(), unitary)?;
+        let mut controls: Vec<i32> = controls.iter().map(|qubit| *qubit as i32).collect();
+        if targets.len() == 1 && !controls.is_empty() {
+            let complex_matrix = quest_sys::ComplexMatrix2 {
+                real: qreal_matrix(
+                    [0, 1].map(|row| [0, 1].map(|column| unitary[(row, column)].re)),
+                ),
+                imag: qreal_matrix(
+                    [0, 1].map(|row| [0, 1].map(|column| unitary[(row, column)].im)),
+                ),
+            };
+            unsafe {
+                quest_sys::multiControlledUnitary(
+                    self.quest_qureg,
+                    controls.as_mut_ptr(),
+                    controls.len() as ::std::os::raw::c_int,
+                    targets[0] as ::std::os::raw::c_int,
+                    complex_matrix,
+                )
+            }
+            return Ok(());
+        }
+        let mut complex_matrix = ComplexMatrixN::new(targets.len() as u32);
+        for ((row, column), value) in unitary.indexed_iter() {
+            complex_matrix.set(row, column, *value).map_err(|err| {
+                RoqoqoBackendError::GenericError {
+                    msg: err.to_string(),
+                }
+            })?;
+        }
+        let mut targets: Vec<i32> = targets.iter().map(|qubit| *qubit as i32).collect();
+        unsafe {
+            if controls.is_empty() {
+                quest_sys::multiQubitUnitary(
+                    self.quest_qureg,
+                    targets.as_mut_ptr(),
+                    targets.len() as ::std::os::raw::c_int,
+                    complex_matrix.complex_matrix,
+                )
+            } else {
+                quest_sys::multiControlledMultiQubitUnitary(
+                    self.quest_qureg,
+                    controls.as_mut_ptr(),
+                    controls.len() as ::std::os::raw::c_int,
+                    targets.as_mut_ptr(),
+                    targets.len() as ::std::os::raw::c_int,
+                    complex_matrix.complex_matrix,
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a user provided unitary matrix to one or two qubits.
+    ///
+    /// Single qubit matrices are applied with QuEST's unitary and two qubit matrices with twoQubitUnitary,
+    /// the same functions used for the gate operations of roqoqo.
+    /// The n-th qubit corresponds to the n-th bit of the row and column indices of the unitary.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The one or two qubits the unitary acts on.
+    /// * `unitary` - The 2x2 or 4x4 unitary matrix.
+    pub fn apply_unitary(
+        &mut self,
+        qubits: &[usize],
+        unitary: &Array2<Complex64>,
+    ) -> Result<(), RoqoqoBackendError> {
+        self.check_subsystem(qubits)?;
+        check_unitary(qubits.len(), unitary)?;
+        match qubits {
+            [qubit] => {
+                let complex_matrix = quest_sys::ComplexMatrix2 {
+                    real: qreal_matrix(
+                        [0, 1].map(|row| [0, 1].map(|column| unitary[(row, column)].re)),
+                    ),
+                    imag: qreal_matrix(
+                        [0, 1].map(|row| [0, 1].map(|column| unitary[(row, column)].im)),
+                    ),
+                };
                 unsafe {
-                    probabilites.push(
-                        quest_sys::getDensityAmp(self.quest_qureg, index.into(), index.into()).real,
+                    quest_sys::unitary(
+                        self.quest_qureg,
+                        *qubit as ::std::os::raw::c_int,
+                        complex_matrix,
                     )
+                }
+            }
+            [qubit_0, qubit_1] => {
+                let complex_matrix = quest_sys::ComplexMatrix4 {
+                    real: qreal_matrix(
+                        [0, 1, 2, 3]
+                            .map(|row| [0, 1, 2, 3].map(|column| unitary[(row, column)].re)),
+                    ),
+                    imag: qreal_matrix(
+                        [0, 1, 2, 3]
+                            .map(|row| [0, 1, 2, 3].map(|column| unitary[(row, column)].im)),
+                    ),
                 };
+                unsafe {
+                    quest_sys::twoQubitUnitary(
+                        self.quest_qureg,
+                        *qubit_0 as ::std::os::raw::c_int,
+                        *qubit_1 as ::std::os::raw::c_int,
+                        complex_matrix,
+                    )
+                }
+            }
+            _ => {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "A unitary can only be applied to one or two qubits, got {}",
+                        qubits.len()
+                    ),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the amplitudes of a contiguous range of basis states of a state vector.
+    ///
+    /// Only the requested amplitudes are read out, the full state vector is not copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The index of the first basis state in the range.
+    /// * `len` - The number of amplitudes that are read out.
+    pub fn amplitudes_range(
+        &self,
+        start: usize,
+        len: usize,
+    ) -> Result<Vec<Complex64>, RoqoqoBackendError> {
+        if self.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Trying to obtain amplitudes from density matrix quantum register".to_string(),
+            });
+        }
+        let dimension = self.number_amplitudes()?;
+        match start.checked_add(len) {
+            Some(end) if end <= dimension => Ok((start..end)
+                .map(|index| {
+                    let amp = unsafe { quest_sys::getAmp(self.quest_qureg, index as i64) };
+                    Complex64::new(amp.real as f64, amp.imag as f64)
+                })
+                .collect()),
+            _ => Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Amplitude range starting at {} with length {} exceeds the {} amplitudes of the quantum register",
+                    start, len, dimension
+                ),
+            }),
+        }
+    }
+
+    /// Returns the amplitudes of a state vector with a magnitude above a threshold.
+    ///
+    /// The amplitudes are read out one by one and only the (basis state index, amplitude)
+    /// pairs above the threshold are kept, the full state vector is not copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Amplitudes with a magnitude not above the threshold are skipped.
+    pub fn sparse_state_vector(
+        &self,
+        threshold: f64,
+    ) -> Result<Vec<(usize, Complex64)>, RoqoqoBackendError> {
+        if self.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "Trying to obtain amplitudes from density matrix quantum register".to_string(),
+            });
+        }
+        let dimension = self.number_amplitudes()?;
+        Ok((0..dimension)
+            .filter_map(|index| {
+                let amp = unsafe { quest_sys::getAmp(self.quest_qureg, index as i64) };
+                let amplitude = Complex64::new(amp.real as f64, amp.imag as f64);
+                (amplitude.norm() > threshold).then(|| (index, amplitude))
+            })
+            .collect())
+    }
+
+    /// Returns the joint probability of measuring the given outcomes on the given qubits.
+    ///
+    /// The quantum register is not changed (no collapse).
+    ///
+    /// # Arguments
+    ///
+    /// * `outcomes` - The pairs of qubit and outcome (true for |1>) that are measured.
+    pub fn outcome_probability(
+        &self,
+        outcomes: &[(usize, bool)],
+    ) -> Result<f64, RoqoqoBackendError> {
+        let qubits: Vec<usize> = outcomes.iter().map(|(qubit, _)| *qubit).collect();
+        self.check_subsystem(&qubits)?;
+        if qubits.is_empty() {
+            return Ok(1.0);
+        }
+        let mut quest_qubits: Vec<::std::os::raw::c_int> = qubits
+            .iter()
+            .map(|qubit| *qubit as ::std::os::raw::c_int)
+            .collect();
+        let mut outcome_probabilities: Vec<qreal> = vec![0.0; 1 << qubits.len()];
+        unsafe {
+            quest_sys::calcProbOfAllOutcomes(
+                outcome_probabilities.as_mut_ptr(),
+                self.quest_qureg,
+                quest_qubits.as_mut_ptr(),
+                quest_qubits.len() as ::std::os::raw::c_int,
+            )
+        }
+        // The n-th qubit in qubits corresponds to the n-th bit of the outcome index
+        let index = outcomes
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (bit, (_, outcome))| {
+                acc | ((*outcome as usize) << bit)
+            });
+        Ok(outcome_probabilities[index] as f64)
+    }
+
+    /// Returns the purity Tr(rho^2) of the density matrix in the quantum register.
+    pub fn purity(&self) -> Result<f64, RoqoqoBackendError> {
+        self.check_density_matrix_mode("purity")?;
+        Ok(unsafe { quest_sys::calcPurity(self.quest_qureg) } as f64)
+    }
+
+    /// Returns the Hilbert-Schmidt distance between this and another density matrix.
+    ///
+    /// Follows the convention of QuEST: the distance is the square root of the sum of the
+    /// absolute values squared of the element-wise differences, sqrt(Tr[(rho_a - rho_b)^2]).
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The quantum register of the other density matrix.
+    pub fn hilbert_schmidt_distance(&self, other: &Qureg) -> Result<f64, RoqoqoBackendError> {
+        self.check_density_matrix_mode("hilbert_schmidt_distance")?;
+        other.check_density_matrix_mode("hilbert_schmidt_distance")?;
+        if self.number_qubits() != other.number_qubits() {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Hilbert-Schmidt distance requires equally sized quantum registers, got {} and {} qubits",
+                    self.number_qubits(),
+                    other.number_qubits()
+                ),
+            });
+        }
+        Ok(
+            unsafe { quest_sys::calcHilbertSchmidtDistance(self.quest_qureg, other.quest_qureg) }
+                as f64,
+        )
+    }
+
+    /// Returns the inner product <self|other> of two state vector quantum registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The quantum register of the ket state.
+    pub fn inner_product(&self, other: &Qureg) -> Result<Complex64, RoqoqoBackendError> {
+        if self.is_density_matrix || other.is_density_matrix {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: "The inner product can only be calculated for state vector quantum registers"
+                    .to_string(),
+            });
+        }
+        if self.number_qubits() != other.number_qubits() {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "Inner product requires equally sized quantum registers, got {} and {} qubits",
+                    self.number_qubits(),
+                    other.number_qubits()
+                ),
+            });
+        }
+        let product = unsafe { quest_sys::calcInnerProduct(self.quest_qureg, other.quest_qureg) };
+        Ok(Complex64::new(product.real as f64, product.imag as f64))
+    }
+
+    /// Returns the reduced density matrix of a subsystem of the quantum register.
+    ///
+    /// All qubits not in `qubits` are traced out.
+    /// The n-th qubit in `qubits` corresponds to the n-th bit of the row and column indices
+    /// of the reduced density matrix.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits of the subsystem.
+    pub fn reduced_density_matrix(
+        &self,
+        qubits: &[usize],
+    ) -> Result<Array2<Complex64>, RoqoqoBackendError> {
+        self.check_density_matrix_mode("reduced density matrix")?;
+        self.check_subsystem(qubits)?;
+        let number_qubits = self.number_qubits() as usize;
+        let traced_qubits: Vec<usize> = (0..number_qubits)
+            .filter(|qubit| !qubits.contains(qubit))
+            .collect();
+        let dimension = 1_usize << qubits.len();
+        let mut reduced_density_matrix = Array2::<Complex64>::zeros((dimension, dimension));
+        for row in 0..dimension {
+            for column in 0..dimension {
+                for traced_index in 0..(1_usize << traced_qubits.len()) {
+                    let traced_part = spread_bits(traced_index, &traced_qubits);
+                    let full_row = spread_bits(row, qubits) | traced_part;
+                    let full_column = spread_bits(column, qubits) | traced_part;
+                    reduced_density_matrix[(row, column)] +=
+                        self.density_amp(full_row, full_column);
+                }
+            }
+        }
+        Ok(reduced_density_matrix)
+    }
+
+    /// Returns the von Neumann entropy -Tr(rho_A ln(rho_A)) of a subsystem of the quantum register.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits of the subsystem A.
+    pub fn von_neumann_entropy(&self, qubits: &[usize]) -> Result<f64, RoqoqoBackendError> {
+        let eigenvalues = hermitian_eigenvalues(&self.reduced_density_matrix(qubits)?);
+        Ok(eigenvalues
+            .iter()
+            .filter(|x| **x > f64::EPSILON)
+            .map(|x| -x * x.ln())
+            .sum())
+    }
+
+    /// Returns the second Rényi entropy -ln Tr(rho_A^2) of a subsystem of the quantum register.
+    ///
+    /// Tr(rho_A^2) is the expectation value of the swap operator on two copies of the subsystem,
+    /// which a swap test estimates. It is calculated from the reduced density matrix without diagonalization.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits of the subsystem A.
+    pub fn renyi_2_entropy(&self, qubits: &[usize]) -> Result<f64, RoqoqoBackendError> {
+        // The reduced density matrix is hermitian, so Tr(rho_A^2) is the sum of its absolute values squared
+        let purity: f64 = self
+            .reduced_density_matrix(qubits)?
+            .iter()
+            .map(|x| x.norm_sqr())
+            .sum();
+        Ok(-purity.ln())
+    }
+
+    /// Returns the negativity of the bipartition of the quantum register into `qubits` and the remaining qubits.
+    ///
+    /// The negativity is the absolute sum of the negative eigenvalues
+    /// of the density matrix partially transposed with respect to `qubits`.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits of the subsystem that is transposed.
+    pub fn negativity(&self, qubits: &[usize]) -> Result<f64, RoqoqoBackendError> {
+        self.check_density_matrix_mode("negativity")?;
+        self.check_subsystem(qubits)?;
+        let dimension = self.number_amplitudes()?;
+        let mask = spread_bits((1_usize << qubits.len()) - 1, qubits);
+        let mut partial_transpose = Array2::<Complex64>::zeros((dimension, dimension));
+        for row in 0..dimension {
+            for column in 0..dimension {
+                // Exchange the bits of the transposed subsystem between row and column index
+                let transposed_row = (row & !mask) | (column & mask);
+                let transposed_column = (column & !mask) | (row & mask);
+                partial_transpose[(row, column)] =
+                    self.density_amp(transposed_row, transposed_column);
+            }
+        }
+        Ok(hermitian_eigenvalues(&partial_transpose)
+            .iter()
+            .filter(|x| **x < 0.0)
+            .map(|x| -x)
+            .sum())
+    }
+
+    /// Returns the logarithmic negativity log2(2N + 1) of the bipartition of the quantum register into `qubits` and the remaining qubits.
+    ///
+    /// # Arguments
+    ///
+    /// * `qubits` - The qubits of the subsystem that is transposed.
+    pub fn logarithmic_negativity(&self, qubits: &[usize]) -> Result<f64, RoqoqoBackendError> {
+        Ok((2.0 * self.negativity(qubits)? + 1.0).log2())
+    }
+
+    /// Writes the amplitudes of the quantum register to a file in the format of QuEST's reportState.
+    ///
+    /// The file starts with the header line `real, imag` followed by one line `real, imag`
+    /// per stored amplitude. A density matrix is stored as its 4^N entries in QuEST's order,
+    /// the entry (row, column) is at index row + column * 2^N.
+    /// The values are written with full precision so the state is restored exactly when it is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file that is written.
+    pub fn write_state_to_file(&self, path: &Path) -> Result<(), RoqoqoBackendError> {
+        let (reals, imags) = self.stored_amplitudes();
+        let mut content = String::from("real, imag\n");
+        for (real, imag) in reals.iter().zip(imags.iter()) {
+            content.push_str(&format!("{}, {}\n", real, imag));
+        }
+        std::fs::write(path, content).map_err(|err| RoqoqoBackendError::GenericError {
+            msg: format!("State could not be written to file {:?}: {}", path, err),
+        })
+    }
+
+    /// Reads the amplitudes of the quantum register from a file in the format of QuEST's reportState.
+    ///
+    /// Empty lines, lines starting with `#` and the header line `real, imag` are skipped, every
+    /// other line holds the real and imaginary part of one amplitude separated by a comma.
+    /// The file must contain exactly the 2^N amplitudes of a state vector, or the 4^N entries
+    /// of a density matrix in QuEST's order, of the quantum register. The state is not renormalized.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file that is read.
+    pub fn read_state_from_file(&mut self, path: &Path) -> Result<(), RoqoqoBackendError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|err| RoqoqoBackendError::GenericError {
+                msg: format!("State could not be read from file {:?}: {}", path, err),
+            })?;
+        let mut amplitudes: Vec<(f64, f64)> = Vec::new();
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "real, imag" {
+                continue;
             }
+            let amplitude = line.split_once(',').and_then(|(real, imag)| {
+                Some((real.trim().parse().ok()?, imag.trim().parse().ok()?))
+            });
+            match amplitude {
+                Some(amplitude) => amplitudes.push(amplitude),
+                None => {
+                    return Err(RoqoqoBackendError::GenericError {
+                        msg: format!(
+                            "Line {} of state file {:?} is not a pair of real and imaginary parts: {:?}",
+                            line_number + 1,
+                            path,
+                            line
+                        ),
+                    })
+                }
+            }
+        }
+        let number_amplitudes = self.quest_qureg.numAmpsPerChunk as usize;
+        if amplitudes.len() != number_amplitudes {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "State file {:?} contains {} amplitudes, the {} quantum register with {} qubits requires {}",
+                    path,
+                    amplitudes.len(),
+                    if self.is_density_matrix { "density matrix" } else { "state vector" },
+                    self.number_qubits(),
+                    number_amplitudes
+                ),
+            });
+        }
+        let (reals, imags) = unsafe {
+            (
+                std::slice::from_raw_parts_mut(self.quest_qureg.stateVec.real, number_amplitudes),
+                std::slice::from_raw_parts_mut(self.quest_qureg.stateVec.imag, number_amplitudes),
+            )
+        };
+        for ((real, imag), (real_part, imag_part)) in
+            reals.iter_mut().zip(imags.iter_mut()).zip(amplitudes)
+        {
+            *real = real_part as qreal;
+            *imag = imag_part as qreal;
+        }
+        Ok(())
+    }
+
+    // Returns the real and imaginary parts of all amplitudes stored in the quantum register
+    fn stored_amplitudes(&self) -> (&[qreal], &[qreal]) {
+        let number_amplitudes = self.quest_qureg.numAmpsPerChunk as usize;
+        unsafe {
+            (
+                std::slice::from_raw_parts(self.quest_qureg.stateVec.real, number_amplitudes),
+                std::slice::from_raw_parts(self.quest_qureg.stateVec.imag, number_amplitudes),
+            )
+        }
+    }
+
+    // Returns the entry of the density matrix in the quantum register at (row, column)
+    fn density_amp(&self, row: usize, column: usize) -> Complex64 {
+        let amp = unsafe { quest_sys::getDensityAmp(self.quest_qureg, row as i64, column as i64) };
+        Complex64::new(amp.real as f64, amp.imag as f64)
+    }
+
+    // Returns an error if the quantum register is not a density matrix
+    fn check_density_matrix_mode(&self, quantity: &str) -> Result<(), RoqoqoBackendError> {
+        if self.is_density_matrix {
+            Ok(())
         } else {
-            for index in 0..dimension {
-                unsafe { probabilites.push(quest_sys::getProbAmp(self.quest_qureg, index.into())) };
+            Err(RoqoqoBackendError::GenericError {
+                msg: format!(
+                    "The {} can only be calculated for a density matrix quantum register",
+                    quantity
+                ),
+            })
+        }
+    }
+
+    // Returns an error if the subsystem contains qubits outside of the register or duplicated qubits
+    fn check_subsystem(&self, qubits: &[usize]) -> Result<(), RoqoqoBackendError> {
+        for (index, qubit) in qubits.iter().enumerate() {
+            if *qubit >= self.number_qubits() as usize || qubits[..index].contains(qubit) {
+                return Err(RoqoqoBackendError::GenericError {
+                    msg: format!(
+                        "Invalid subsystem {:?} for quantum register with {} qubits",
+                        qubits,
+                        self.number_qubits()
+                    ),
+                });
             }
         }
-        probabilites
+        Ok(())
+    }
+}
+
+// Returns an error if the matrix is not a unitary acting on the given number of qubits
+fn check_unitary(
+    number_qubits: usize,
+    unitary: &Array2<Complex64>,
+) -> Result<(), RoqoqoBackendError> {
+    let dimension = 1_usize << number_qubits;
+    if number_qubits == 0 || unitary.dim() != (dimension, dimension) {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "A unitary on {} target qubits must have size {}x{}, got {}x{}",
+                number_qubits,
+                dimension,
+                dimension,
+                unitary.nrows(),
+                unitary.ncols()
+            ),
+        });
+    }
+    let deviation = (unitary.t().mapv(|value| value.conj()).dot(unitary)
+        - Array2::<Complex64>::eye(dimension))
+    .iter()
+    .map(|value| value.norm())
+    .fold(0.0, f64::max);
+    if deviation > VALIDATION_TOLERANCE {
+        return Err(RoqoqoBackendError::GenericError {
+            msg: format!(
+                "Matrix is not unitary, U^dagger U deviates from identity by {}",
+                deviation
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Converts a matrix of f64 entries to the floating point type of QuEST
+pub(crate) fn qreal_matrix<const N: usize>(matrix: [[f64; N]; N]) -> [[qreal; N]; N] {
+    matrix.map(|row| row.map(|value| value as qreal))
+}
+
+// Converts a real and imaginary part in the floating point type of QuEST to a complex number,
+// the casts are only no-ops in double precision
+#[allow(clippy::unnecessary_cast)]
+fn complex_amplitude(real: qreal, imag: qreal) -> Complex64 {
+    Complex64::new(real as f64, imag as f64)
+}
+
+// Collects the bits of index at the positions given by qubits, the n-th qubit becomes the n-th bit
+fn gather_bits(index: usize, qubits: &[usize]) -> usize {
+    qubits
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (bit, qubit)| acc | (((index >> qubit) & 1) << bit))
+}
+
+// Places the n-th bit of index at the position given by the n-th entry of qubits
+fn spread_bits(index: usize, qubits: &[usize]) -> usize {
+    qubits
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (bit, qubit)| acc | (((index >> bit) & 1) << qubit))
+}
+
+// Returns the eigenvalues of a hermitian matrix
+fn hermitian_eigenvalues(matrix: &Array2<Complex64>) -> Vec<f64> {
+    let (rows, columns) = matrix.dim();
+    nalgebra::DMatrix::from_fn(rows, columns, |row, column| matrix[(row, column)])
+        .symmetric_eigenvalues()
+        .iter()
+        .cloned()
+        .collect()
+}
+
+impl std::fmt::Debug for Qureg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The amplitudes and the probability buffer grow exponentially with the number of qubits,
+        // only the number of stored amplitudes is shown
+        f.debug_struct("Qureg")
+            .field("number_qubits", &self.number_qubits())
+            .field("is_density_matrix", &self.is_density_matrix)
+            .field("number_stored_amplitudes", &self.quest_qureg.numAmpsTotal)
+            .field("noise_boost", &self.noise_boost)
+            .field("gate_repetitions", &self.gate_repetitions)
+            .field("skip_unsupported", &self.skip_unsupported)
+            .finish_non_exhaustive()
     }
 }
 
@@ -133,8 +1789,8 @@ impl ComplexMatrixN {
         if row >= self.dimension || column >= self.dimension {
             return Err("Row or column index out of bounds");
         }
-        let real = value.re;
-        let imag = value.im;
+        let real = value.re as qreal;
+        let imag = value.im as qreal;
         unsafe {
             let real_pointer = self.complex_matrix.real;
             let real_row_pointer = *real_pointer.add(row);
@@ -173,7 +1829,11 @@ impl Vector {
     ///
     /// * `number_qubits` - The number of qubits that determine the dimension of the matrix (2**number_qubits).
     pub fn new(x: f64, y: f64, z: f64) -> Self {
-        let vector = quest_sys::Vector { x, y, z };
+        let vector = quest_sys::Vector {
+            x: x as qreal,
+            y: y as qreal,
+            z: z as qreal,
+        };
         Vector { vector }
     }
 }
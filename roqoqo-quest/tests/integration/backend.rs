@@ -10,10 +10,23 @@
 // express or implied. See the License for the specific language governing permissions and
 // limitations under the License.
 
+use ndarray::{array, Array2};
+use num_complex::Complex64;
 use roqoqo::backends::EvaluatingBackend;
+use roqoqo::devices::Device;
 use roqoqo::operations;
+use roqoqo::operations::{Operate, OperatePragmaNoiseProba};
 use roqoqo::Circuit;
-use roqoqo_quest::Backend;
+use roqoqo::RoqoqoBackendError;
+use roqoqo_quest::{
+    call_circuit, call_parity_conditional, Backend, BackendBuilder, Qureg, ReadoutModel,
+    RegisterShapes,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use test_case::test_case;
 
 #[test]
 fn test_circuit_with_repeated_measurement() {
@@ -61,3 +74,2455 @@ fn test_circuit_with_set_measurement_number() {
         assert_eq!(repetition[2], false);
     }
 }
+
+#[test]
+fn test_run_measurement_registers_indexed() {
+    use roqoqo::measurements::ClassicalRegister;
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit_a += operations::PauliX::new(0);
+    circuit_a += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 3, None);
+    let mut circuit_b = Circuit::new();
+    circuit_b += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit_b += operations::PauliX::new(1);
+    circuit_b += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 4, None);
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::PauliX::new(0);
+    constant_circuit += operations::PauliX::new(0);
+    let measurement = ClassicalRegister {
+        constant_circuit: Some(constant_circuit),
+        circuits: vec![circuit_a, circuit_b],
+    };
+    let backend = Backend::new(2);
+    let indexed_result = backend
+        .run_measurement_registers_indexed(&measurement)
+        .unwrap();
+    assert_eq!(indexed_result.len(), 2);
+    assert_eq!(
+        indexed_result[0].0.get("ro").unwrap(),
+        &vec![vec![true, false]; 3]
+    );
+    assert_eq!(
+        indexed_result[1].0.get("ro").unwrap(),
+        &vec![vec![false, true]; 4]
+    );
+
+    // The concatenated registers contain the results of both circuits in order
+    let (bit_result, _, _) = backend.run_measurement_registers(&measurement).unwrap();
+    let mut concatenated = indexed_result[0].0.get("ro").unwrap().clone();
+    concatenated.extend(indexed_result[1].0.get("ro").unwrap().clone());
+    assert_eq!(bit_result.get("ro").unwrap(), &concatenated);
+}
+
+/// Test that reusing the state of the constant circuit gives the same results as prepending it
+#[test]
+fn test_reuse_constant_circuit() {
+    use roqoqo::measurements::ClassicalRegister;
+    let mut constant_circuit = Circuit::new();
+    for j in 0..10 {
+        for i in 0..3 {
+            constant_circuit += operations::RotateX::new(i, (0.1 * j as f64).into());
+        }
+        constant_circuit += operations::CNOT::new(0, 1);
+        constant_circuit += operations::CNOT::new(1, 2);
+    }
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    circuit_a += operations::DefinitionComplex::new("state".to_string(), 8, true);
+    circuit_a += operations::Hadamard::new(0);
+    circuit_a += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit_a += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 20, None);
+    let mut circuit_b = Circuit::new();
+    circuit_b += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    circuit_b += operations::DefinitionComplex::new("state".to_string(), 64, true);
+    circuit_b += operations::PragmaDamping::new(1, 1.0.into(), 0.1.into());
+    circuit_b += operations::PragmaGetDensityMatrix::new("state".to_string(), None);
+    circuit_b += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 20, None);
+    let measurement = ClassicalRegister {
+        constant_circuit: Some(constant_circuit),
+        circuits: vec![circuit_a, circuit_b],
+    };
+    let backend = Backend::new(3).set_random_seed(vec![11]);
+    let reused_backend = backend.clone().set_reuse_constant_circuit(true);
+    let result = backend
+        .run_measurement_registers_indexed(&measurement)
+        .unwrap();
+    let reused_result = reused_backend
+        .run_measurement_registers_indexed(&measurement)
+        .unwrap();
+    assert_eq!(result.len(), reused_result.len());
+    for (registers, reused_registers) in result.iter().zip(reused_result.iter()) {
+        assert_eq!(registers.0, reused_registers.0);
+        let state = &registers.2.get("state").unwrap()[0];
+        let reused_state = &reused_registers.2.get("state").unwrap()[0];
+        assert_eq!(state.len(), reused_state.len());
+        for (value, reused_value) in state.iter().zip(reused_state.iter()) {
+            assert!((value - reused_value).norm() < 1e-12);
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_run_measurement_registers() {
+    use roqoqo::measurements::ClassicalRegister;
+    let mut circuit_a = Circuit::new();
+    circuit_a += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit_a += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit_a += operations::PauliX::new(0);
+    circuit_a += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit_a += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 5, None);
+    let mut circuit_b = Circuit::new();
+    circuit_b += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit_b += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit_b += operations::PauliX::new(1);
+    circuit_b += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit_b += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 5, None);
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::Hadamard::new(0);
+    constant_circuit += operations::Hadamard::new(0);
+    let measurement = ClassicalRegister {
+        constant_circuit: Some(constant_circuit),
+        circuits: vec![circuit_a, circuit_b],
+    };
+    let backend = Backend::new(2);
+    let sync_result = backend.run_measurement_registers(&measurement).unwrap();
+    let async_result =
+        futures::executor::block_on(backend.async_run_measurement_registers(&measurement)).unwrap();
+    assert_eq!(sync_result.0, async_result.0);
+    assert_eq!(sync_result.1, async_result.1);
+    for (key, values) in sync_result.2.iter() {
+        let async_values = async_result.2.get(key).unwrap();
+        assert_eq!(values.len(), async_values.len());
+        for (value, async_value) in values.iter().zip(async_values.iter()) {
+            for (a, b) in value.iter().zip(async_value.iter()) {
+                assert!((a - b).norm() < 1e-10);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cancel_long_running_circuit() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.1, 0.1);
+    circuit += operations::RotateX::new(0, 1.0.into());
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let cancellation_flag = Arc::new(AtomicBool::new(false));
+    let backend = Backend::new(2)
+        .set_repetitions(usize::MAX)
+        .set_cancellation_flag(cancellation_flag.clone());
+    let start = Instant::now();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        cancellation_flag.store(true, Ordering::SeqCst);
+    });
+    let result = backend.run_circuit(&circuit);
+    handle.join().unwrap();
+    assert_eq!(
+        result,
+        Err(RoqoqoBackendError::GenericError {
+            msg: "cancelled".to_string()
+        })
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+    // The shared flag of the caller is not reset by the backend
+    assert!(backend.cancellation_flag.is_cancelled());
+}
+
+#[test]
+fn test_cancel_long_running_circuit_internal_flag() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.1, 0.1);
+    circuit += operations::RotateX::new(0, 1.0.into());
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let backend = Backend::new(2).set_repetitions(usize::MAX);
+    let cancellation_flag = backend.cancellation_flag.clone();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        cancellation_flag.cancel();
+    });
+    let result = backend.run_circuit(&circuit);
+    handle.join().unwrap();
+    assert_eq!(
+        result,
+        Err(RoqoqoBackendError::GenericError {
+            msg: "cancelled".to_string()
+        })
+    );
+    // The cancellation only applies to the simulations that were running
+    let backend = backend.set_repetitions(1);
+    assert!(backend.run_circuit(&circuit).is_ok());
+    assert!(!backend.cancellation_flag.is_cancelled());
+}
+
+#[test]
+fn test_number_amplitudes_overflow() {
+    // 2^70 amplitudes do not fit in usize, the backend returns an error instead of overflowing
+    let backend = Backend::new(70);
+    let error = Err(RoqoqoBackendError::GenericError {
+        msg:
+            "Number of amplitudes of a quantum register with 70 qubits exceeds the addressable size"
+                .to_string(),
+    });
+    let circuit = Circuit::new();
+    assert_eq!(backend.density_matrix(&circuit).map(|_| ()), error);
+    assert_eq!(
+        backend
+            .expectation_value_diagonal(&circuit, &[1.0])
+            .map(|_| ()),
+        error
+    );
+    assert_eq!(
+        backend.run_circuit_trajectories(&circuit, 1).map(|_| ()),
+        error
+    );
+    assert_eq!(
+        backend
+            .clone()
+            .set_initial_density_matrix(Some(Array2::zeros((1, 1))))
+            .map(|_| ()),
+        error
+    );
+}
+
+#[test]
+fn test_cancel_before_run() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PauliX::new(0);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let cancellation_flag = Arc::new(AtomicBool::new(true));
+    let backend = Backend::new(1).set_cancellation_flag(cancellation_flag.clone());
+    // A shared flag set before the run aborts the simulation and is left unchanged
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "cancelled".to_string()
+        })
+    );
+    assert!(cancellation_flag.load(Ordering::SeqCst));
+    cancellation_flag.store(false, Ordering::SeqCst);
+    // A call to cancel while no simulation is running does not abort a later one
+    backend.cancellation_flag.cancel();
+    let (bit_registers, _, _) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(bit_registers["ro"], vec![vec![true]]);
+    assert!(!cancellation_flag.load(Ordering::SeqCst));
+
+    // The flag is not part of the configuration of the backend
+    assert_eq!(backend, Backend::new(1));
+    assert_ne!(backend, Backend::new(1).set_repetitions(2));
+}
+
+#[cfg(feature = "async")]
+#[test_case(false; "circuits in parallel")]
+#[test_case(true; "reused constant circuit")]
+fn test_async_run_measurement_registers_seeded(reuse_constant_circuit: bool) {
+    use roqoqo::measurements::ClassicalRegister;
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    for qubit in 0..3 {
+        constant_circuit += operations::Hadamard::new(qubit);
+    }
+    let mut circuit = Circuit::new();
+    for qubit in 0..3 {
+        circuit += operations::MeasureQubit::new(qubit, "ro".to_string(), qubit);
+    }
+    let measurement = ClassicalRegister {
+        constant_circuit: Some(constant_circuit),
+        circuits: vec![circuit.clone(), circuit.clone(), circuit],
+    };
+    let backend = Backend::new(3).set_reuse_constant_circuit(reuse_constant_circuit);
+    // Unseeded circuits are simulated in parallel
+    let (bit_registers, _, _) =
+        futures::executor::block_on(backend.async_run_measurement_registers(&measurement)).unwrap();
+    assert_eq!(bit_registers["ro"].len(), 3);
+
+    // Seeded simulations reproduce the synchronous run
+    let backend = backend.set_random_seed_single(7);
+    let sync_result = backend.run_measurement_registers(&measurement).unwrap();
+    let async_result =
+        futures::executor::block_on(backend.async_run_measurement_registers(&measurement)).unwrap();
+    assert_eq!(sync_result.0, async_result.0);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_cancel_all_circuits() {
+    use roqoqo::measurements::ClassicalRegister;
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.1, 0.1);
+    circuit += operations::RotateX::new(0, 1.0.into());
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let measurement = ClassicalRegister {
+        constant_circuit: None,
+        circuits: vec![circuit.clone(), circuit.clone(), circuit],
+    };
+    let cancellation_flag = Arc::new(AtomicBool::new(false));
+    let backend = Backend::new(1)
+        .set_repetitions(usize::MAX)
+        .set_cancellation_flag(cancellation_flag.clone());
+    let start = Instant::now();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        cancellation_flag.store(true, Ordering::SeqCst);
+    });
+    // The run only returns once every circuit of the measurement has been aborted
+    let result = futures::executor::block_on(backend.async_run_measurement_registers(&measurement));
+    handle.join().unwrap();
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    // The shared flag of the caller is not reset by the backend
+    assert!(backend.cancellation_flag.is_cancelled());
+}
+
+#[test_case(false, Complex64::new(1.0, 0.0); "ignored")]
+#[test_case(true, Complex64::new(0.0, 1.0); "tracked")]
+fn test_global_phase_tracking(track_global_phase: bool, expected: Complex64) {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 2, true);
+    circuit += operations::PragmaGlobalPhase::new(std::f64::consts::FRAC_PI_2.into());
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let backend = Backend::new(1).set_track_global_phase(track_global_phase);
+    let (_, _, complex_result) = backend.run_circuit(&circuit).unwrap();
+    let state = &complex_result.get("state").unwrap()[0];
+    assert!((state[0] - expected).norm() < 1e-10);
+    assert!(state[1].norm() < 1e-10);
+}
+
+#[test]
+fn test_decomposition_block_reordering() {
+    let mut reordering_dictionary: HashMap<usize, usize> = HashMap::new();
+    reordering_dictionary.insert(0, 1);
+    reordering_dictionary.insert(1, 0);
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 8, true);
+    circuit += operations::PragmaStartDecompositionBlock::new(vec![0, 1], reordering_dictionary);
+    circuit += operations::PauliX::new(0);
+    circuit += operations::RotateY::new(1, 0.3.into());
+    circuit += operations::CNOT::new(0, 2);
+    circuit += operations::PragmaStopDecompositionBlock::new(vec![0, 1]);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+
+    let mut remapped_circuit = Circuit::new();
+    remapped_circuit += operations::DefinitionComplex::new("state".to_string(), 8, true);
+    remapped_circuit += operations::PauliX::new(1);
+    remapped_circuit += operations::RotateY::new(0, 0.3.into());
+    remapped_circuit += operations::CNOT::new(1, 2);
+    remapped_circuit += operations::Hadamard::new(0);
+    remapped_circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+
+    let backend = Backend::new(3);
+    let (_, _, complex_result) = backend.run_circuit(&circuit).unwrap();
+    let (_, _, remapped_complex_result) = backend.run_circuit(&remapped_circuit).unwrap();
+    let state = &complex_result.get("state").unwrap()[0];
+    let remapped_state = &remapped_complex_result.get("state").unwrap()[0];
+    for (value, remapped_value) in state.iter().zip(remapped_state.iter()) {
+        assert!((value - remapped_value).norm() < 1e-10);
+    }
+}
+
+#[test_case(false; "measure qubit")]
+#[test_case(true; "repeated measurement")]
+fn test_random_seed_reproducibility(repeated_measurement: bool) {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    // Random noise triggers the repetition of the simulation with one trajectory per repetition
+    for qubit in 0..3 {
+        circuit += operations::Hadamard::new(qubit);
+        circuit += operations::PragmaRandomNoise::new(qubit, 1.0.into(), 0.5.into(), 0.5.into());
+    }
+    if repeated_measurement {
+        circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 50, None);
+    } else {
+        for qubit in 0..3 {
+            circuit += operations::MeasureQubit::new(qubit, "ro".to_string(), qubit);
+        }
+    }
+
+    let backend = Backend::new(3).set_repetitions(50);
+    let (first_result, _, _) = backend
+        .clone()
+        .set_random_seed(vec![1, 2])
+        .run_circuit(&circuit)
+        .unwrap();
+    let (second_result, _, _) = backend
+        .clone()
+        .set_random_seed(vec![1, 2])
+        .run_circuit(&circuit)
+        .unwrap();
+    let (other_result, _, _) = backend
+        .set_random_seed(vec![3])
+        .run_circuit(&circuit)
+        .unwrap();
+    assert_eq!(first_result.get("ro"), second_result.get("ro"));
+    assert_ne!(first_result.get("ro"), other_result.get("ro"));
+}
+
+#[test_case(operations::PragmaGetPauliProduct::new([(0, 3)].into_iter().collect(), "pp".to_string(), Circuit::new()).into(); "PragmaGetPauliProduct")]
+#[test_case(operations::PragmaGetOccupationProbability::new("pp".to_string(), None).into(); "PragmaGetOccupationProbability")]
+fn test_random_seed_readout_pragma(readout: operations::Operation) {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 10, true);
+    circuit += operations::DefinitionFloat::new("pp".to_string(), 1024, true);
+    for qubit in 0..10 {
+        circuit += operations::Hadamard::new(qubit);
+    }
+    let mut readout_circuit = circuit.clone();
+    readout_circuit += readout;
+    for qubit in 0..10 {
+        circuit += operations::MeasureQubit::new(qubit, "ro".to_string(), qubit);
+        readout_circuit += operations::MeasureQubit::new(qubit, "ro".to_string(), qubit);
+    }
+    // Workspace registers of readout pragmas do not reseed the random number generator of QuEST
+    let backend = Backend::new(10).set_random_seed(vec![4, 2]);
+    let (bit_registers, _, _) = backend.run_circuit(&circuit).unwrap();
+    let (readout_bit_registers, _, _) = backend.run_circuit(&readout_circuit).unwrap();
+    assert_eq!(bit_registers["ro"], readout_bit_registers["ro"]);
+}
+
+#[test_case(0.0, 0.4; "dephasing")]
+#[test_case(0.4, 0.0; "depolarising")]
+fn test_random_noise_average(depolarising_rate: f64, dephasing_rate: f64) {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::RotateZ::new(0, 0.3.into());
+    let mut channel_circuit = circuit.clone();
+    circuit += operations::PragmaRandomNoise::new(
+        0,
+        1.0.into(),
+        depolarising_rate.into(),
+        dephasing_rate.into(),
+    );
+    if depolarising_rate > 0.0 {
+        channel_circuit +=
+            operations::PragmaDepolarising::new(0, 1.0.into(), depolarising_rate.into());
+    } else {
+        channel_circuit += operations::PragmaDephasing::new(0, 1.0.into(), dephasing_rate.into());
+    }
+    // The trajectories of the repetitions average to the noise channel
+    let density_matrix = Backend::new(1)
+        .set_repetitions(4000)
+        .set_random_seed(vec![9])
+        .density_matrix(&circuit)
+        .unwrap();
+    let channel_density_matrix = Backend::new(1).density_matrix(&channel_circuit).unwrap();
+    for (value, expected) in density_matrix.iter().zip(channel_density_matrix.iter()) {
+        assert!((value - expected).norm() < 0.03);
+    }
+}
+
+#[test]
+fn test_random_noise_negative_rate() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PragmaRandomNoise::new(0, 1.0.into(), (-0.1).into(), 0.0.into());
+    assert!(Backend::new(1).run_circuit(&circuit).is_err());
+}
+
+#[test]
+fn test_random_seed_density_matrix_reconstruction() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    for qubit in 0..2 {
+        circuit += operations::RotateX::new(qubit, 1.0.into());
+        circuit += operations::PragmaRandomNoise::new(qubit, 1.0.into(), 0.3.into(), 0.2.into());
+    }
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    // Averages the projectors onto the state vectors of all trajectories
+    let reconstruct = |seed: Vec<u64>| -> Array2<Complex64> {
+        let (_, _, complex_registers) = Backend::new(2)
+            .set_repetitions(20)
+            .set_random_seed(seed)
+            .run_circuit(&circuit)
+            .unwrap();
+        let trajectories = &complex_registers["state"];
+        assert_eq!(trajectories.len(), 20);
+        let mut density_matrix = Array2::<Complex64>::zeros((4, 4));
+        for state in trajectories.iter() {
+            for row in 0..4 {
+                for column in 0..4 {
+                    density_matrix[[row, column]] += state[row] * state[column].conj() / 20.0;
+                }
+            }
+        }
+        density_matrix
+    };
+
+    let first_density_matrix = reconstruct(vec![5, 6]);
+    assert_eq!(first_density_matrix, reconstruct(vec![5, 6]));
+    let other_density_matrix = reconstruct(vec![7]);
+    assert!(first_density_matrix
+        .iter()
+        .zip(other_density_matrix.iter())
+        .any(|(a, b)| (a - b).norm() > 1e-6));
+}
+
+#[test]
+fn test_overrotation_reproducibility() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("z".to_string(), 1, true);
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 1.0, 0.5);
+    circuit += operations::RotateX::new(0, 0.0.into());
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "z".to_string(),
+        Circuit::new(),
+    );
+
+    let backend = Backend::new(1).set_repetitions(20);
+    let (_, first_floats, _) = backend
+        .clone()
+        .set_random_seed_single(11)
+        .run_circuit(&circuit)
+        .unwrap();
+    let (_, second_floats, _) = backend
+        .clone()
+        .set_random_seed_single(11)
+        .run_circuit(&circuit)
+        .unwrap();
+    let (_, other_floats, _) = backend
+        .set_random_seed_single(12)
+        .run_circuit(&circuit)
+        .unwrap();
+    assert_eq!(first_floats, second_floats);
+    assert_ne!(first_floats, other_floats);
+    // Each repetition rotates by a different random angle, <Z> = cos(offset)
+    let expectation_values: Vec<f64> = first_floats["z"].iter().map(|z| z[0]).collect();
+    assert!(expectation_values.iter().all(|z| *z < 1.0 - 1e-12));
+    assert!(expectation_values
+        .iter()
+        .any(|z| (z - expectation_values[0]).abs() > 1e-6));
+}
+
+#[test]
+fn test_overrotation_targets() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("z".to_string(), 1, true);
+    // The overrotation only applies to RotateX gates acting on qubit 1
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![1], 1.0, 0.5);
+    circuit += operations::RotateX::new(0, 0.0.into());
+    circuit += operations::RotateY::new(1, 0.0.into());
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "z".to_string(),
+        Circuit::new(),
+    );
+    let backend = Backend::new(2).set_repetitions(5).set_random_seed_single(3);
+    let (_, float_registers, _) = backend.run_circuit(&circuit).unwrap();
+    assert!(float_registers["z"]
+        .iter()
+        .all(|z| (z[0] - 1.0).abs() < 1e-12));
+
+    let mut circuit = Circuit::new();
+    circuit += operations::PragmaOverrotation::new("Hadamard".to_string(), vec![0], 1.0, 0.5);
+    circuit += operations::Hadamard::new(0);
+    assert!(Backend::new(1)
+        .set_repetitions(2)
+        .run_circuit(&circuit)
+        .is_err());
+}
+
+#[test]
+fn test_random_seed_single() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    for qubit in 0..3 {
+        circuit += operations::Hadamard::new(qubit);
+    }
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 50, None);
+
+    let (single_result, _, _) = Backend::new(3)
+        .set_random_seed_single(5)
+        .run_circuit(&circuit)
+        .unwrap();
+    let (list_result, _, _) = Backend::new(3)
+        .set_random_seed(vec![5])
+        .run_circuit(&circuit)
+        .unwrap();
+    assert_eq!(single_result.get("ro"), list_result.get("ro"));
+    assert_eq!(
+        Backend::new(3).set_random_seed_single(5),
+        Backend::new(3).set_random_seed(vec![5])
+    );
+}
+
+#[test]
+fn test_density_matrix() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(0);
+    let backend = Backend::new(1);
+    let density_matrix = backend.density_matrix(&circuit).unwrap();
+    let expected = array![
+        [Complex64::new(0.0, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)]
+    ];
+    assert_eq!(density_matrix, expected);
+
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let backend = Backend::new(2);
+    let density_matrix = backend.density_matrix(&circuit).unwrap();
+    assert_eq!(density_matrix.dim(), (4, 4));
+    for ((row, column), value) in density_matrix.indexed_iter() {
+        let expected_value = if (row == 0 || row == 3) && (column == 0 || column == 3) {
+            0.5
+        } else {
+            0.0
+        };
+        assert!((value - Complex64::new(expected_value, 0.0)).norm() < 1e-10);
+    }
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_renormalize_set_state(density_matrix: bool) {
+    let mut circuit = Circuit::new();
+    if density_matrix {
+        circuit += operations::PragmaSetDensityMatrix::new(array![
+            [Complex64::new(2.0, 0.0), Complex64::new(0.0, 0.0)],
+            [Complex64::new(0.0, 0.0), Complex64::new(2.0, 0.0)]
+        ]);
+    } else {
+        circuit += operations::PragmaSetStateVector::new(array![
+            Complex64::new(1.0, 0.0),
+            Complex64::new(1.0, 0.0)
+        ]);
+    }
+    let backend = Backend::new(1);
+    assert!(backend.run_circuit(&circuit).is_err());
+
+    let backend = backend.set_renormalize_set_state(true);
+    let result = backend.density_matrix(&circuit).unwrap();
+    let expected = if density_matrix {
+        array![
+            [Complex64::new(0.5, 0.0), Complex64::new(0.0, 0.0)],
+            [Complex64::new(0.0, 0.0), Complex64::new(0.5, 0.0)]
+        ]
+    } else {
+        array![
+            [Complex64::new(0.5, 0.0), Complex64::new(0.5, 0.0)],
+            [Complex64::new(0.5, 0.0), Complex64::new(0.5, 0.0)]
+        ]
+    };
+    for (value, expected_value) in result.iter().zip(expected.iter()) {
+        assert!((value - expected_value).norm() < 1e-10);
+    }
+}
+
+// Logger that stores all log messages to check emitted warnings
+struct CaptureLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.messages
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE_LOGGER: CaptureLogger = CaptureLogger {
+    messages: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn test_ignored_circuit_warning() {
+    log::set_logger(&CAPTURE_LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+    let mut preparation_circuit = Circuit::new();
+    preparation_circuit += operations::PauliX::new(0);
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 2, true);
+    circuit +=
+        operations::PragmaGetStateVector::new("state".to_string(), Some(preparation_circuit));
+    let backend = Backend::new(1);
+    backend.run_circuit(&circuit).unwrap();
+    let expected = "Circuit parameter of PragmaGetStateVector with readout state is ignored by the QuEST backend".to_string();
+    assert_eq!(
+        roqoqo_quest::ignored_circuit_warnings(circuit.iter()),
+        vec![expected.clone()]
+    );
+    assert!(CAPTURE_LOGGER.messages.lock().unwrap().contains(&expected));
+}
+
+#[test_case(true; "repeated measurement")]
+#[test_case(false; "set number of measurements")]
+fn test_measurement_override(repeated_measurement: bool) {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PauliX::new(1);
+    if repeated_measurement {
+        circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+    } else {
+        circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+        circuit += operations::MeasureQubit::new(1, "ro".to_string(), 1);
+        circuit += operations::PragmaSetNumberOfMeasurements::new(10, "ro".to_string());
+    }
+    let backend = Backend::new(2);
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(bit_result.get("ro").unwrap().len(), 10);
+
+    let backend = backend.with_measurement_override(Some(25));
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    let nested_vec = bit_result.get("ro").unwrap();
+    assert_eq!(nested_vec.len(), 25);
+    for repetition in nested_vec {
+        assert_eq!(repetition, &vec![false, true]);
+    }
+
+    let backend = backend.with_measurement_override(None);
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(bit_result.get("ro").unwrap().len(), 10);
+}
+
+#[test]
+fn test_initial_state() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let backend = Backend::new(2).with_initial_state(Some(3));
+    let (_, _, complex_result) = backend.run_circuit(&circuit).unwrap();
+    let state = &complex_result.get("state").unwrap()[0];
+    let expected = [0.0, 0.0, 0.0, 1.0];
+    for (value, expected_value) in state.iter().zip(expected.iter()) {
+        assert!((value - Complex64::new(*expected_value, 0.0)).norm() < 1e-10);
+    }
+
+    let backend = backend.with_initial_state(Some(4));
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Basis state 4 does not exist in a quantum register with 2 qubits".to_string()
+        })
+    );
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_new_plus(density_matrix: bool) {
+    let mut circuit = Circuit::new();
+    if density_matrix {
+        circuit += operations::PragmaDamping::new(0, 0.0.into(), 0.0.into());
+    }
+    let backend = Backend::new_plus(3);
+    let density_matrix = backend.density_matrix(&circuit).unwrap();
+    // Uniform occupation probabilities 1/2^n on the diagonal
+    for index in 0..8 {
+        assert!((density_matrix[[index, index]] - Complex64::new(1.0 / 8.0, 0.0)).norm() < 1e-10);
+    }
+}
+
+#[test]
+fn test_measure_stream() {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::RotateY::new(1, 1.2.into());
+    let backend = Backend::new(2).set_random_seed(vec![5]);
+    let number_shots = 20000;
+    let mut counts = [0_usize; 4];
+    for shot in backend.measure_stream(&circuit).unwrap().take(number_shots) {
+        assert_eq!(shot.len(), 2);
+        counts[shot[0] as usize + 2 * shot[1] as usize] += 1;
+    }
+    let probability_one = (0.6_f64).sin().powi(2);
+    let expected = [
+        0.5 * (1.0 - probability_one),
+        0.5 * (1.0 - probability_one),
+        0.5 * probability_one,
+        0.5 * probability_one,
+    ];
+    for (count, expected_probability) in counts.iter().zip(expected.iter()) {
+        assert!((*count as f64 / number_shots as f64 - expected_probability).abs() < 0.02);
+    }
+
+    // Seeded streams are reproducible
+    let first: Vec<Vec<bool>> = backend.measure_stream(&circuit).unwrap().take(50).collect();
+    let second: Vec<Vec<bool>> = backend.measure_stream(&circuit).unwrap().take(50).collect();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_run_circuit_counts_deterministic() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 100, None);
+    let counts = Backend::new(2).run_circuit_counts(&circuit).unwrap();
+    let expected: HashMap<Vec<bool>, usize> = [(vec![false, true], 100)].into_iter().collect();
+    assert_eq!(counts, [("ro".to_string(), expected)].into_iter().collect());
+}
+
+#[test_case(vec![
+    operations::DefinitionBit::new("ro".to_string(), 2, true).into(),
+    operations::Hadamard::new(0).into(),
+    operations::CNOT::new(0, 1).into(),
+    operations::PragmaRepeatedMeasurement::new("ro".to_string(), 200, Some([(0, 1), (1, 0)].into_iter().collect())).into(),
+]; "sampled")]
+#[test_case(vec![
+    operations::DefinitionBit::new("ro".to_string(), 2, true).into(),
+    operations::Hadamard::new(0).into(),
+    operations::MeasureQubit::new(0, "ro".to_string(), 0).into(),
+    operations::CNOT::new(0, 1).into(),
+    operations::PragmaRepeatedMeasurement::new("ro".to_string(), 200, None).into(),
+]; "run")]
+fn test_run_circuit_counts(operations: Vec<operations::Operation>) {
+    let mut circuit = Circuit::new();
+    for operation in operations {
+        circuit += operation;
+    }
+    let backend = Backend::new(2).set_random_seed(vec![11]);
+    let counts = backend.run_circuit_counts(&circuit).unwrap();
+    let (bit_registers, _, _) = backend.run_circuit(&circuit).unwrap();
+    let mut expected: HashMap<Vec<bool>, usize> = HashMap::new();
+    for shot in bit_registers["ro"].iter() {
+        *expected.entry(shot.clone()).or_default() += 1;
+    }
+    assert_eq!(counts["ro"], expected);
+    assert_eq!(counts["ro"].values().sum::<usize>(), 200);
+}
+
+#[test]
+fn test_run_circuit_counts_and_probabilities() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::RotateX::new(0, std::f64::consts::FRAC_PI_3.into());
+    circuit += operations::Hadamard::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 20000, None);
+    let backend = Backend::new(2).set_random_seed(vec![5]);
+    let (counts, probabilities) = backend
+        .run_circuit_counts_and_probabilities(&circuit)
+        .unwrap();
+    // |0> and |1> of qubit 0 with probabilities 3/4 and 1/4, qubit 1 in an equal superposition
+    let expected_probabilities = [0.375, 0.125, 0.375, 0.125];
+    assert_eq!(probabilities.len(), 4);
+    for (probability, expected) in probabilities.iter().zip(expected_probabilities) {
+        assert!((probability - expected).abs() < 1e-10);
+    }
+    // The counts are sampled from the same final state
+    assert_eq!(counts, backend.run_circuit_counts(&circuit).unwrap(),);
+    assert_eq!(counts["ro"].values().sum::<usize>(), 20000);
+    for (index, probability) in probabilities.iter().enumerate() {
+        let bits = vec![index & 1 == 1, index & 2 == 2];
+        let count = counts["ro"].get(&bits).copied().unwrap_or_default();
+        assert!((count as f64 / 20000.0 - probability).abs() < 0.02);
+    }
+}
+
+#[test]
+fn test_run_circuit_counts_and_probabilities_error() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    assert!(Backend::new(1)
+        .run_circuit_counts_and_probabilities(&circuit)
+        .is_err());
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+    assert!(Backend::new(1)
+        .run_circuit_counts_and_probabilities(&circuit)
+        .is_err());
+}
+
+#[test_case(operations::Hadamard::new(0).into(), true; "Hadamard")]
+#[test_case(operations::CNOT::new(0, 1).into(), true; "CNOT")]
+#[test_case(operations::MultiQubitMS::new(vec![0, 1], 0.5.into()).into(), true; "MultiQubitMS")]
+#[test_case(operations::PragmaDamping::new(0, 0.1.into(), 0.1.into()).into(), true; "PragmaDamping")]
+#[test_case(operations::PragmaGlobalPhase::new(0.5.into()).into(), true; "PragmaGlobalPhase")]
+#[test_case(operations::DefinitionUsize::new("usize".to_string(), 1, false).into(), true; "DefinitionUsize")]
+#[test_case(operations::PragmaSleep::new(vec![0, 1], 0.5.into()).into(), false; "PragmaSleep")]
+#[test_case(operations::PragmaStartDecompositionBlock::new(vec![0], HashMap::new()).into(), true; "PragmaStartDecompositionBlock")]
+#[test_case(operations::PragmaStopParallelBlock::new(vec![0], 0.1.into()).into(), true; "PragmaStopParallelBlock")]
+#[test_case(operations::PragmaRandomNoise::new(0, 0.1.into(), 0.1.into(), 0.1.into()).into(), true; "PragmaRandomNoise")]
+fn test_supports_operation(operation: operations::Operation, supported: bool) {
+    let backend = Backend::new(2);
+    assert_eq!(backend.supports_operation(&operation), supported);
+    // Stays in sync with the simulation of the operation
+    let mut circuit = Circuit::new();
+    circuit += operation;
+    assert_eq!(backend.run_circuit(&circuit).is_ok(), supported);
+}
+
+#[test]
+fn test_repetitions_start_in_zero_state() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PauliX::new(0);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    // Overrotations force one simulation per repetition
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.0, 0.0);
+    let backend = Backend::new(1).set_repetitions(4);
+    let (bit_registers, _, _) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(bit_registers["ro"], vec![vec![true]; 4]);
+}
+
+#[test]
+fn test_circuits_without_qubits() {
+    // Circuits that use no qubits are simulated on all qubits of the backend
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let (_, _, complex_registers) = Backend::new(2).run_circuit(&circuit).unwrap();
+    assert_eq!(complex_registers["state"][0].len(), 4);
+
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    let (bit_registers, float_registers, complex_registers) =
+        Backend::new(1).run_circuit(&circuit).unwrap();
+    assert_eq!(bit_registers["ro"], vec![vec![false; 3]]);
+    assert!(float_registers.is_empty());
+    assert!(complex_registers.is_empty());
+
+    let error = || RoqoqoBackendError::GenericError {
+        msg: "The QuEST backend needs at least one qubit to run a circuit".to_string(),
+    };
+    assert_eq!(Backend::new(0).run_circuit(&Circuit::new()), Err(error()));
+    assert_eq!(
+        Backend::new(0).sparse_state_vector(&Circuit::new(), 0.0),
+        Err(error())
+    );
+    assert!(Backend::builder().number_qubits(0).build().is_err());
+}
+
+#[test]
+fn test_run_circuit_trajectories() {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PragmaRandomNoise::new(0, 1.0.into(), 0.0.into(), 0.5.into());
+    let backend = Backend::new(1).set_random_seed(vec![42]);
+    let trajectories = backend.run_circuit_trajectories(&circuit, 200).unwrap();
+    assert_eq!(trajectories.len(), 200);
+    assert_eq!(
+        trajectories,
+        backend.run_circuit_trajectories(&circuit, 200).unwrap()
+    );
+
+    // Each trajectory ends in |+> or in |-> after a phase flip, their average is the dephased state
+    let mut average = Array2::<Complex64>::zeros((2, 2));
+    for trajectory in trajectories.iter() {
+        assert!((trajectory[0].norm() - trajectory[1].norm()).abs() < 1e-10);
+        for row in 0..2 {
+            for column in 0..2 {
+                average[[row, column]] += trajectory[row] * trajectory[column].conj() / 200.0;
+            }
+        }
+    }
+    assert!(trajectories
+        .iter()
+        .any(|trajectory| (trajectory[0] - trajectory[1]).norm() < 1e-10));
+    assert!(trajectories
+        .iter()
+        .any(|trajectory| (trajectory[0] + trajectory[1]).norm() < 1e-10));
+    let mut dephased_circuit = Circuit::new();
+    dephased_circuit += operations::Hadamard::new(0);
+    dephased_circuit += operations::PragmaDephasing::new(0, 1.0.into(), 0.5.into());
+    let density_matrix = backend.density_matrix(&dephased_circuit).unwrap();
+    for (value, expected) in average.iter().zip(density_matrix.iter()) {
+        assert!((value - expected).norm() < 0.1);
+    }
+
+    let mut noisy_circuit = Circuit::new();
+    noisy_circuit += operations::PragmaDamping::new(0, 1.0.into(), 1.0.into());
+    assert!(backend.run_circuit_trajectories(&noisy_circuit, 2).is_err());
+}
+
+#[test]
+fn test_probability_tolerance() {
+    // Trace one density matrix with a slightly negative probability for |1>
+    let density_matrix = array![
+        [Complex64::new(1.0 + 1e-10, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(-1e-10, 0.0)]
+    ];
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PragmaSetDensityMatrix::new(density_matrix);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+
+    let backend = Backend::new(1);
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Negative probability -0.0000000001 of basis state 1 exceeds the tolerance 0.00000000000001".to_string()
+        })
+    );
+
+    let backend = backend.set_probability_tolerance(1e-9);
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    for repetition in bit_result.get("ro").unwrap() {
+        assert_eq!(repetition, &vec![false]);
+    }
+}
+
+#[test]
+fn test_parameter_shift_gradient() {
+    use roqoqo::measurements::{CheatedPauliZProduct, CheatedPauliZProductInput};
+    let mut constant_circuit = Circuit::new();
+    constant_circuit += operations::RotateX::new(0, "theta".into());
+    constant_circuit += operations::RotateY::new(1, "phi".into());
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("z0".to_string(), 1, true);
+    circuit += operations::DefinitionFloat::new("z1".to_string(), 1, true);
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 3)].into_iter().collect(),
+        "z0".to_string(),
+        Circuit::new(),
+    );
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(1, 3)].into_iter().collect(),
+        "z1".to_string(),
+        Circuit::new(),
+    );
+    let mut input = CheatedPauliZProductInput::new();
+    let z0 = input.add_pauliz_product("z0".to_string());
+    let z1 = input.add_pauliz_product("z1".to_string());
+    input
+        .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+        .unwrap();
+    input
+        .add_linear_exp_val("z1".to_string(), [(z1, 1.0)].into_iter().collect())
+        .unwrap();
+    let measurement = CheatedPauliZProduct {
+        constant_circuit: Some(constant_circuit),
+        circuits: vec![circuit],
+        input,
+    };
+
+    // <Z> = cos(angle) for both rotations, so the derivative is -sin(angle)
+    let (theta, phi) = (0.4, 1.3);
+    let parameters: HashMap<String, f64> = [("theta".to_string(), theta), ("phi".to_string(), phi)]
+        .into_iter()
+        .collect();
+    let backend = Backend::new(2);
+    let gradients = backend
+        .parameter_shift_gradient(&measurement, &parameters)
+        .unwrap();
+    assert!((gradients["z0"]["theta"] + f64::sin(theta)).abs() < 1e-10);
+    assert!(gradients["z0"]["phi"].abs() < 1e-10);
+    assert!((gradients["z1"]["phi"] + f64::sin(phi)).abs() < 1e-10);
+    assert!(gradients["z1"]["theta"].abs() < 1e-10);
+}
+
+#[test]
+fn test_backend_builder() {
+    let confusion_matrix = [[0.9, 0.1], [0.2, 0.8]];
+    let backend = Backend::builder()
+        .number_qubits(3)
+        .repetitions(20)
+        .random_seed(vec![1, 2])
+        .readout_error(1, confusion_matrix)
+        .build()
+        .unwrap();
+    assert_eq!(backend.number_qubits, 3);
+    assert_eq!(backend.repetitions, 20);
+    assert_eq!(backend.random_seed, Some(vec![1, 2]));
+    assert_eq!(backend.readout_errors.len(), 1);
+    assert_eq!(backend.readout_errors.get(&1), Some(&confusion_matrix));
+    assert_eq!(
+        backend,
+        Backend::new(3)
+            .set_repetitions(20)
+            .set_random_seed(vec![1, 2])
+            .set_readout_error(1, confusion_matrix)
+            .unwrap()
+    );
+
+    // Unset options keep the defaults
+    assert_eq!(
+        BackendBuilder::new().number_qubits(2).build().unwrap(),
+        Backend::new(2)
+    );
+    assert!(BackendBuilder::new().repetitions(2).build().is_err());
+
+    // The readout model replaces the readout errors of all qubits
+    let readout_model: ReadoutModel = [(0, confusion_matrix), (2, [[1.0, 0.0], [0.3, 0.7]])]
+        .into_iter()
+        .collect();
+    let backend = Backend::builder()
+        .number_qubits(3)
+        .readout_error(1, confusion_matrix)
+        .imperfect_readout_model(readout_model.clone())
+        .build()
+        .unwrap();
+    assert_eq!(backend.readout_errors, readout_model);
+    assert_eq!(
+        backend,
+        Backend::new(3)
+            .set_imperfect_readout_model(readout_model)
+            .unwrap()
+    );
+    let invalid_model: ReadoutModel = [(0, [[0.5, 0.6], [0.0, 1.0]])].into_iter().collect();
+    assert!(Backend::builder()
+        .number_qubits(2)
+        .imperfect_readout_model(invalid_model.clone())
+        .build()
+        .is_err());
+    assert!(Backend::new(2)
+        .set_imperfect_readout_model(invalid_model)
+        .is_err());
+    assert!(Backend::builder()
+        .number_qubits(2)
+        .readout_error(0, [[0.5, 0.6], [0.0, 1.0]])
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_readout_error() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10000, None);
+
+    // Asymmetric readout error: 0 is detected as 1 with 0.1, 1 is detected as 0 with 0.3
+    let confusion_matrix = [[0.9, 0.1], [0.3, 0.7]];
+    let backend = Backend::new(2)
+        .set_random_seed(vec![5])
+        .set_readout_error(0, confusion_matrix)
+        .unwrap()
+        .set_readout_error(1, confusion_matrix)
+        .unwrap();
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    let measurements = bit_result.get("ro").unwrap();
+    let frequency_0_as_1 =
+        measurements.iter().filter(|bits| bits[0]).count() as f64 / measurements.len() as f64;
+    let frequency_1_as_0 =
+        measurements.iter().filter(|bits| !bits[1]).count() as f64 / measurements.len() as f64;
+    assert!((frequency_0_as_1 - 0.1).abs() < 0.02);
+    assert!((frequency_1_as_0 - 0.3).abs() < 0.02);
+
+    let flip_backend = Backend::new(2)
+        .set_readout_error_probabilities(0, 0.1, 0.3)
+        .unwrap();
+    assert_eq!(flip_backend.readout_errors.get(&0), Some(&confusion_matrix));
+
+    assert!(Backend::new(2)
+        .set_readout_error(0, [[0.9, 0.2], [0.3, 0.7]])
+        .is_err());
+}
+
+#[test]
+fn test_sparse_state_vector() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(2);
+    circuit += operations::Hadamard::new(0);
+    let backend = Backend::new(3);
+    let sparse = backend.sparse_state_vector(&circuit, 1e-10).unwrap();
+    assert_eq!(sparse.len(), 2);
+    assert_eq!(sparse[0].0, 4);
+    assert_eq!(sparse[1].0, 5);
+
+    circuit += operations::PragmaDamping::new(0, 1.0.into(), 0.1.into());
+    assert!(backend.sparse_state_vector(&circuit, 1e-10).is_err());
+}
+
+#[test]
+fn test_state_vector_readout_in_density_matrix_mode() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 2, true);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PragmaDephasing::new(0, 1.0.into(), 0.1.into());
+
+    let backend = Backend::new(1);
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Circuit contains PragmaGetStateVector but is simulated as a density matrix because of operation PragmaDephasing, use PragmaGetDensityMatrix instead".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_apply_diagonal() {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    let backend = Backend::new(1);
+    let state_vector = backend
+        .apply_diagonal(&circuit, &[0.0, std::f64::consts::PI])
+        .unwrap();
+    let half_sqrt = std::f64::consts::FRAC_1_SQRT_2;
+    assert!((state_vector[0] - Complex64::new(half_sqrt, 0.0)).norm() < 1e-12);
+    assert!((state_vector[1] - Complex64::new(-half_sqrt, 0.0)).norm() < 1e-12);
+    assert!(backend.apply_diagonal(&circuit, &[0.0]).is_err());
+}
+
+#[test]
+fn test_apply_unitary() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(0);
+    let one = Complex64::new(1.0, 0.0);
+    let zero = Complex64::new(0.0, 0.0);
+    let swap = ndarray::array![
+        [one, zero, zero, zero],
+        [zero, zero, one, zero],
+        [zero, one, zero, zero],
+        [zero, zero, zero, one]
+    ];
+    let backend = Backend::new(2);
+    // The custom SWAP moves the excitation from qubit 0 to qubit 1 like the built-in SWAP
+    let state_vector = backend.apply_unitary(&circuit, &[0, 1], &swap).unwrap();
+    assert_eq!(state_vector, vec![zero, zero, one, zero]);
+    assert!(backend.apply_unitary(&circuit, &[0], &swap).is_err());
+}
+
+#[test_case(0.0; "zero")]
+#[test_case(std::f64::consts::FRAC_PI_3; "pi_over_three")]
+#[test_case(std::f64::consts::FRAC_PI_2; "pi_over_two")]
+#[test_case(std::f64::consts::PI; "pi")]
+fn test_expectation_value_diagonal(theta: f64) {
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateY::new(0, theta.into());
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::Hadamard::new(2);
+    let backend = Backend::new(3);
+    // Full parity operator Z0 Z1 Z2 vanishes because qubit 2 is in the |+> state
+    let parity: Vec<f64> = (0..8_usize)
+        .map(|index| {
+            if index.count_ones() % 2 == 0 {
+                1.0
+            } else {
+                -1.0
+            }
+        })
+        .collect();
+    assert!(
+        backend
+            .expectation_value_diagonal(&circuit, &parity)
+            .unwrap()
+            .abs()
+            < 1e-12
+    );
+    // Parity operator Z0 Z1 is one for cos(theta/2)|00> + sin(theta/2)|11>
+    let parity_01: Vec<f64> = (0..8_usize)
+        .map(|index| {
+            if (index & 0b011).count_ones() % 2 == 0 {
+                1.0
+            } else {
+                -1.0
+            }
+        })
+        .collect();
+    assert!(
+        (backend
+            .expectation_value_diagonal(&circuit, &parity_01)
+            .unwrap()
+            - 1.0)
+            .abs()
+            < 1e-12
+    );
+    // Z0 distinguishes the two branches of the superposition
+    let z_0: Vec<f64> = (0..8_usize)
+        .map(|index| if index & 1 == 0 { 1.0 } else { -1.0 })
+        .collect();
+    assert!(
+        (backend.expectation_value_diagonal(&circuit, &z_0).unwrap() - theta.cos()).abs() < 1e-12
+    );
+    assert!(backend
+        .expectation_value_diagonal(&circuit, &[1.0, -1.0])
+        .is_err());
+}
+
+#[test]
+fn test_expectation_value_diagonal_density_matrix() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(0);
+    circuit += operations::PragmaDamping::new(0, 1.0.into(), 1.0.into());
+    let backend = Backend::new(1);
+    let expectation_value = backend
+        .expectation_value_diagonal(&circuit, &[1.0, -1.0])
+        .unwrap();
+    assert!((expectation_value - (1.0 - 2.0 * (-1.0_f64).exp())).abs() < 1e-12);
+}
+
+#[test]
+fn test_skip_unsupported() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PauliX::new(0);
+    circuit += operations::PragmaSleep::new(vec![0, 1], 0.5.into());
+    circuit += operations::PauliX::new(1);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 5, None);
+
+    let backend = Backend::new(2);
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::OperationNotInBackend {
+            backend: "QuEST",
+            hqslang: "PragmaSleep",
+        })
+    );
+
+    // The supported gates before and after the unsupported pragma are still applied
+    let (bit_result, _, _) = backend
+        .set_skip_unsupported(true)
+        .run_circuit(&circuit)
+        .unwrap();
+    assert_eq!(bit_result.get("ro").unwrap(), &vec![vec![true, true]; 5]);
+}
+
+#[test]
+fn test_force_statevector_overrotation() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state_vector".to_string(), 2, true);
+    circuit += operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.5, 0.1);
+    circuit += operations::RotateX::new(0, 0.0.into());
+    circuit += operations::PragmaGetStateVector::new("state_vector".to_string(), None);
+
+    // Overrotations are sampled in each repetition and keep the state vector simulation
+    // The seed keeps the sampled overrotations away from zero
+    let backend = Backend::new(1)
+        .set_force_statevector(true)
+        .set_repetitions(3)
+        .set_random_seed_single(1);
+    let (_, _, complex_registers) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(complex_registers["state_vector"].len(), 3);
+    for state_vector in complex_registers["state_vector"].iter() {
+        assert_eq!(state_vector.len(), 2);
+        let norm: f64 = state_vector
+            .iter()
+            .map(|amplitude| amplitude.norm_sqr())
+            .sum();
+        assert!((norm - 1.0).abs() < 1e-10);
+        assert!(state_vector[1].norm() > 1e-3);
+    }
+}
+
+#[test_case(operations::PragmaDamping::new(0, 1.0.into(), 0.1.into()).into(); "PragmaDamping")]
+#[test_case(operations::PragmaDephasing::new(0, 1.0.into(), 0.1.into()).into(); "PragmaDephasing")]
+#[test_case(operations::PragmaDepolarising::new(0, 1.0.into(), 0.1.into()).into(); "PragmaDepolarising")]
+fn test_force_statevector_density_matrix_noise(noise: operations::Operation) {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PauliX::new(0);
+    circuit += noise.clone();
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+
+    // Without the flag the circuit is promoted to a density matrix simulation
+    assert!(Backend::new(1).run_circuit(&circuit).is_ok());
+
+    let backend = Backend::new(1).set_force_statevector(true);
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: format!("Operation {} requires a density matrix simulation but the backend is forced to simulate a state vector", noise.hqslang()),
+        })
+    );
+    assert!(backend.run_circuit_counts(&circuit).is_err());
+}
+
+#[test]
+fn test_non_collapsing_measurement() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 200, true);
+    circuit += operations::DefinitionComplex::new("state_vector".to_string(), 2, true);
+    circuit += operations::Hadamard::new(0);
+    for index in 0..200 {
+        circuit += operations::MeasureQubit::new(0, "ro".to_string(), index);
+    }
+    circuit += operations::PragmaGetStateVector::new("state_vector".to_string(), None);
+    let half_sqrt = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+
+    // The first measurement collapses the state, all later measurements repeat its result
+    let backend = Backend::new(1).set_random_seed(vec![3]);
+    let (bit_registers, _, complex_registers) = backend.run_circuit(&circuit).unwrap();
+    assert!((complex_registers["state_vector"][0][0] - half_sqrt).norm() > 0.1);
+    let number_ones = bit_registers["ro"][0].iter().filter(|bit| **bit).count();
+    assert!(number_ones == 0 || number_ones == 200);
+
+    // Without collapse every measurement samples the superposition, which is left unchanged
+    let (bit_registers, _, complex_registers) = backend
+        .set_non_collapsing_measurement(true)
+        .run_circuit(&circuit)
+        .unwrap();
+    assert!((complex_registers["state_vector"][0][0] - half_sqrt).norm() < 1e-12);
+    assert!((complex_registers["state_vector"][0][1] - half_sqrt).norm() < 1e-12);
+    let number_ones = bit_registers["ro"][0].iter().filter(|bit| **bit).count();
+    assert!(number_ones > 50 && number_ones < 150);
+}
+
+#[test_case(0.3, false; "mostly zero")]
+#[test_case(std::f64::consts::FRAC_PI_4 - 0.01, false; "slightly zero")]
+#[test_case(1.2, true; "mostly one")]
+fn test_deterministic_measurement(theta: f64, expected: bool) {
+    // Prepares cos(theta)|0> + sin(theta)|1> before measuring
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::DefinitionComplex::new("state_vector".to_string(), 2, true);
+    circuit += operations::RotateY::new(0, (2.0 * theta).into());
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    circuit += operations::PragmaGetStateVector::new("state_vector".to_string(), None);
+
+    for seed in 0..20 {
+        let backend = Backend::new(1)
+            .set_random_seed(vec![seed])
+            .set_deterministic_measurement(true);
+        let (bit_registers, _, complex_registers) = backend.run_circuit(&circuit).unwrap();
+        assert_eq!(bit_registers["ro"], vec![vec![expected]]);
+        // The state is collapsed onto the measured value and renormalized
+        let state_vector = &complex_registers["state_vector"][0];
+        assert!((state_vector[expected as usize].norm() - 1.0).abs() < 1e-12);
+        assert!(state_vector[!expected as usize].norm() < 1e-12);
+    }
+}
+
+#[test]
+fn test_initial_density_matrix() {
+    let c = |re: f64, im: f64| Complex64::new(re, im);
+    let initial_density_matrix: Array2<Complex64> = array![
+        [c(0.5, 0.0), c(0.0, 0.0), c(0.1, 0.1), c(0.0, 0.0)],
+        [c(0.0, 0.0), c(0.25, 0.0), c(0.0, 0.0), c(0.0, 0.0)],
+        [c(0.1, -0.1), c(0.0, 0.0), c(0.25, 0.0), c(0.0, 0.0)],
+        [c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)],
+    ];
+    let backend = Backend::new(2)
+        .set_initial_density_matrix(Some(initial_density_matrix.clone()))
+        .unwrap();
+
+    // A circuit without gates returns the initial mixed state
+    let density_matrix = backend.density_matrix(&Circuit::new()).unwrap();
+    for (value, expected) in density_matrix.iter().zip(initial_density_matrix.iter()) {
+        assert!((value - expected).norm() < 1e-12);
+    }
+    // Every run starts from the initial density matrix
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(1);
+    for _ in 0..2 {
+        let density_matrix = backend.density_matrix(&circuit).unwrap();
+        assert!((density_matrix[(2, 2)] - c(0.5, 0.0)).norm() < 1e-12);
+        assert!((density_matrix[(3, 3)] - c(0.25, 0.0)).norm() < 1e-12);
+        assert!((density_matrix[(0, 0)] - c(0.25, 0.0)).norm() < 1e-12);
+    }
+
+    // Without the initial density matrix the simulation starts in |00> again
+    let density_matrix = backend
+        .set_initial_density_matrix(None)
+        .unwrap()
+        .density_matrix(&circuit)
+        .unwrap();
+    assert!((density_matrix[(2, 2)] - c(1.0, 0.0)).norm() < 1e-12);
+}
+
+// Circuit preparing RotateY(theta)|0> on the first qubit with the exact Pauli product
+// expectation value <X0 Z1> = sin(theta)
+fn pauli_product_circuit(theta: f64) -> Circuit {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionFloat::new("pp".to_string(), 1, true);
+    circuit += operations::RotateY::new(0, theta.into());
+    circuit += operations::PragmaGetPauliProduct::new(
+        [(0, 1), (1, 3)].into_iter().collect(),
+        "pp".to_string(),
+        Circuit::new(),
+    );
+    circuit
+}
+
+#[test_case(0.4; "small angle")]
+#[test_case(1.3; "large angle")]
+#[test_case(-2.0; "negative expectation")]
+fn test_pauli_product_shots_converge(theta: f64) {
+    let circuit = pauli_product_circuit(theta);
+    let backend = Backend::new(2)
+        .set_random_seed_single(5)
+        .set_pauli_product_shots(Some(1_000_000))
+        .unwrap();
+    let (_, float_registers, _) = backend.run_circuit(&circuit).unwrap();
+    // The standard deviation of the estimate is below 1e-3
+    assert!((float_registers["pp"][0][0] - theta.sin()).abs() < 5e-3);
+}
+
+#[test]
+fn test_pauli_product_shots_variance() {
+    let theta: f64 = 0.9;
+    let shots = 10;
+    let circuit = pauli_product_circuit(theta);
+    let number_samples = 2000;
+    let estimates: Vec<f64> = (0..number_samples)
+        .map(|seed| {
+            let backend = Backend::new(2)
+                .set_random_seed_single(seed)
+                .set_pauli_product_shots(Some(shots))
+                .unwrap();
+            let (_, float_registers, _) = backend.run_circuit(&circuit).unwrap();
+            float_registers["pp"][0][0]
+        })
+        .collect();
+    // Each estimate is the mean of shots outcomes of +1 or -1
+    for estimate in estimates.iter() {
+        let number_plus = (estimate + 1.0) * shots as f64 / 2.0;
+        assert!((number_plus - number_plus.round()).abs() < 1e-9);
+    }
+    let mean = estimates.iter().sum::<f64>() / number_samples as f64;
+    let variance =
+        estimates.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (number_samples - 1) as f64;
+    let expected_variance = (1.0 - theta.sin().powi(2)) / shots as f64;
+    assert!((mean - theta.sin()).abs() < 0.05);
+    assert!((variance - expected_variance).abs() < 0.15 * expected_variance);
+
+    // Without shots the exact expectation value is returned
+    let (_, float_registers, _) = Backend::new(2).run_circuit(&circuit).unwrap();
+    assert!((float_registers["pp"][0][0] - theta.sin()).abs() < 1e-12);
+}
+
+#[test_case(false, false, false; "parity 0 from 00")]
+#[test_case(true, false, true; "parity 1 from 10")]
+#[test_case(false, true, true; "parity 1 from 01")]
+#[test_case(true, true, false; "parity 0 from 11")]
+fn test_parity_conditional_correction(first: bool, second: bool, corrected: bool) {
+    // Qubits 1 and 2 are syndrome qubits that are measured into the syndrome register
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("syndrome".to_string(), 2, true);
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    if first {
+        circuit += operations::PauliX::new(1);
+    }
+    if second {
+        circuit += operations::PauliX::new(2);
+    }
+    circuit += operations::MeasureQubit::new(1, "syndrome".to_string(), 0);
+    circuit += operations::MeasureQubit::new(2, "syndrome".to_string(), 1);
+    let mut correction = Circuit::new();
+    correction += operations::PauliX::new(0);
+    let mut readout = Circuit::new();
+    readout += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+
+    let mut qureg = Qureg::new(3, false);
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) = (
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+    );
+    call_circuit(
+        &circuit,
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    )
+    .unwrap();
+    call_parity_conditional(
+        "syndrome",
+        &[0, 1],
+        &correction,
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    )
+    .unwrap();
+    call_circuit(
+        &readout,
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    )
+    .unwrap();
+    assert_eq!(bit_registers["syndrome"], vec![first, second]);
+    assert_eq!(bit_registers["ro"], vec![corrected]);
+}
+
+#[test]
+fn test_parity_conditional_readout_error() {
+    // The parity is calculated from the detected syndrome bits, including readout errors
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("syndrome".to_string(), 2, true);
+    circuit += operations::MeasureQubit::new(1, "syndrome".to_string(), 0);
+    circuit += operations::MeasureQubit::new(2, "syndrome".to_string(), 1);
+    let mut correction = Circuit::new();
+    correction += operations::PauliX::new(0);
+    let mut qureg = Qureg::new(3, false);
+    qureg.readout_errors.insert(1, [[0.0, 1.0], [1.0, 0.0]]);
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) = (
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+    );
+    call_circuit(
+        &circuit,
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    )
+    .unwrap();
+    call_parity_conditional(
+        "syndrome",
+        &[0, 1],
+        &correction,
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    )
+    .unwrap();
+    assert_eq!(bit_registers["syndrome"], vec![true, false]);
+    assert!((qureg.probabilites()[1] - 1.0).abs() < 1e-10);
+}
+
+#[test_case("syndrome", &[0, 2]; "index out of range")]
+#[test_case("syndrome", &[]; "no indices")]
+#[test_case("missing", &[0]; "missing register")]
+fn test_parity_conditional_error(register: &str, indices: &[usize]) {
+    let mut qureg = Qureg::new(1, false);
+    let mut bit_registers: HashMap<String, Vec<bool>> =
+        [("syndrome".to_string(), vec![true, false])]
+            .into_iter()
+            .collect();
+    let mut correction = Circuit::new();
+    correction += operations::PauliX::new(0);
+    assert!(matches!(
+        call_parity_conditional(
+            register,
+            indices,
+            &correction,
+            &mut qureg,
+            &mut bit_registers,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+        ),
+        Err(RoqoqoBackendError::GenericError { .. })
+    ));
+}
+
+#[test]
+fn test_conditional_index_out_of_range() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("syndrome".to_string(), 2, true);
+    let mut correction = Circuit::new();
+    correction += operations::PauliX::new(0);
+    circuit += operations::PragmaConditional::new("syndrome".to_string(), 2, correction);
+    assert!(matches!(
+        Backend::new(1).run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError { .. })
+    ));
+}
+
+#[test]
+fn test_pauli_product_zero_shots() {
+    let result = Backend::new(1).set_pauli_product_shots(Some(0));
+    assert!(matches!(
+        result,
+        Err(RoqoqoBackendError::GenericError { .. })
+    ));
+}
+
+#[test]
+fn test_initial_density_matrix_error() {
+    assert!(Backend::new(2)
+        .set_initial_density_matrix(Some(Array2::zeros((2, 2))))
+        .is_err());
+    assert!(Backend::new(1)
+        .set_initial_density_matrix(Some(Array2::zeros((2, 3))))
+        .is_err());
+    let mut circuit = Circuit::new();
+    circuit += operations::PauliX::new(0);
+    // The trace of the density matrix is checked when running a circuit
+    let unnormalized = Backend::new(1)
+        .set_initial_density_matrix(Some(Array2::zeros((2, 2))))
+        .unwrap();
+    assert!(unnormalized.run_circuit(&circuit).is_err());
+    let forced_statevector = Backend::new(1)
+        .set_force_statevector(true)
+        .set_initial_density_matrix(Some(Array2::eye(2) / Complex64::new(2.0, 0.0)))
+        .unwrap();
+    assert!(forced_statevector.run_circuit(&circuit).is_err());
+}
+
+#[test]
+fn test_unsubstituted_symbolic_parameter() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::RotateX::new(0, "theta".into());
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+
+    let backend = Backend::new(1);
+    assert_eq!(
+        backend.run_circuit(&circuit),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Operation RotateX has the unresolved symbolic parameter theta, substitute symbolic parameters before running the circuit".to_string()
+        })
+    );
+}
+
+/// Test that a fixed seed and fixed probabilities always sample the same basis states
+#[test]
+fn test_repeated_measurement_sampling_sequence() {
+    // Probabilities 0.1, 0.0, 0.6 and 0.3 of the basis states 0 to 3
+    let state_vector = array![
+        Complex64::new(0.1_f64.sqrt(), 0.0),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.6_f64.sqrt(), 0.0),
+        Complex64::new(0.3_f64.sqrt(), 0.0)
+    ];
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PragmaSetStateVector::new(state_vector);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 12, None);
+
+    let backend = Backend::new(2).set_random_seed(vec![7]);
+    let (bit_result, _, _) = backend.run_circuit(&circuit).unwrap();
+    let indices: Vec<usize> = bit_result
+        .get("ro")
+        .unwrap()
+        .iter()
+        .map(|bits| {
+            bits.iter()
+                .enumerate()
+                .map(|(qubit, bit)| (*bit as usize) << qubit)
+                .sum()
+        })
+        .collect();
+    assert_eq!(indices, vec![2, 2, 3, 0, 2, 3, 3, 0, 2, 2, 3, 2]);
+}
+
+#[test]
+fn test_boost_noise() {
+    let damping = operations::PragmaDamping::new(0, 1.0.into(), 0.1.into());
+    let probability: f64 = *damping.probability().float().unwrap();
+    let mut boosted_circuit = Circuit::new();
+    boosted_circuit += operations::PauliX::new(0);
+    boosted_circuit += operations::PragmaBoostNoise::new(2.0.into());
+    boosted_circuit += damping.clone();
+
+    // Damping with twice the probability p = 1 - exp(-gate_time * rate)
+    let doubled_gate_time = -(1.0 - 2.0 * probability).ln() / 0.1;
+    let mut doubled_circuit = Circuit::new();
+    doubled_circuit += operations::PauliX::new(0);
+    doubled_circuit += operations::PragmaDamping::new(0, doubled_gate_time.into(), 0.1.into());
+
+    let backend = Backend::new(1);
+    let boosted_density_matrix = backend.density_matrix(&boosted_circuit).unwrap();
+    let doubled_density_matrix = backend.density_matrix(&doubled_circuit).unwrap();
+    for (boosted, doubled) in boosted_density_matrix
+        .iter()
+        .zip(doubled_density_matrix.iter())
+    {
+        assert!((boosted - doubled).norm() < 1e-10);
+    }
+    assert!(
+        (boosted_density_matrix[(1, 1)] - Complex64::new(1.0 - 2.0 * probability, 0.0)).norm()
+            < 1e-10
+    );
+
+    let mut invalid_circuit = Circuit::new();
+    invalid_circuit += operations::PragmaBoostNoise::new(100.0.into());
+    invalid_circuit += damping;
+    assert!(backend.run_circuit(&invalid_circuit).is_err());
+}
+
+// Device on which only RotateX and CNOT are available
+struct RotateXDevice {
+    number_qubits: usize,
+}
+
+impl Device for RotateXDevice {
+    fn single_qubit_gate_time(&self, hqslang: &str, qubit: &usize) -> Option<f64> {
+        (hqslang == "RotateX" && *qubit < self.number_qubits).then(|| 1.0)
+    }
+
+    fn two_qubit_gate_time(&self, hqslang: &str, control: &usize, target: &usize) -> Option<f64> {
+        (hqslang == "CNOT" && *control < self.number_qubits && *target < self.number_qubits)
+            .then(|| 1.0)
+    }
+
+    fn multi_qubit_gate_time(&self, _hqslang: &str, _qubits: &[usize]) -> Option<f64> {
+        None
+    }
+
+    fn qubit_decoherence_rates(&self, _qubit: &usize) -> Option<Array2<f64>> {
+        None
+    }
+
+    fn number_qubits(&self) -> usize {
+        self.number_qubits
+    }
+
+    fn two_qubit_edges(&self) -> Vec<(usize, usize)> {
+        vec![(0, 1)]
+    }
+}
+
+#[test]
+fn test_backend_from_device() {
+    let backend = Backend::from_device(Box::new(RotateXDevice { number_qubits: 2 }), Some(vec![1]));
+    assert_eq!(backend.number_qubits, 2);
+    assert_eq!(backend.random_seed, Some(vec![1]));
+
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateX::new(0, 0.5.into());
+    circuit += operations::CNOT::new(0, 1);
+    assert!(backend.run_circuit(&circuit).is_ok());
+
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    assert!(backend.run_circuit(&circuit).is_err());
+    assert!(Backend::new(2).run_circuit(&circuit).is_ok());
+}
+
+struct DecoheringDevice {
+    number_qubits: usize,
+}
+
+impl Device for DecoheringDevice {
+    fn single_qubit_gate_time(&self, hqslang: &str, qubit: &usize) -> Option<f64> {
+        (hqslang == "RotateX" && *qubit < self.number_qubits).then(|| 1.0)
+    }
+
+    fn two_qubit_gate_time(
+        &self,
+        _hqslang: &str,
+        _control: &usize,
+        _target: &usize,
+    ) -> Option<f64> {
+        None
+    }
+
+    fn multi_qubit_gate_time(&self, _hqslang: &str, _qubits: &[usize]) -> Option<f64> {
+        None
+    }
+
+    fn qubit_decoherence_rates(&self, qubit: &usize) -> Option<Array2<f64>> {
+        (*qubit < self.number_qubits)
+            .then(|| array![[0.0, 0.0, 0.0], [0.0, 0.3, 0.0], [0.0, 0.0, 0.1]])
+    }
+
+    fn number_qubits(&self) -> usize {
+        self.number_qubits
+    }
+
+    fn two_qubit_edges(&self) -> Vec<(usize, usize)> {
+        vec![(0, 1)]
+    }
+}
+
+#[test]
+fn test_auto_idle_noise() {
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateX::new(1, std::f64::consts::FRAC_PI_2.into());
+    circuit += operations::RotateX::new(0, std::f64::consts::PI.into());
+    circuit += operations::PragmaStopParallelBlock::new(vec![0], 0.5.into());
+
+    let mut manual_circuit = circuit.clone();
+    manual_circuit += operations::PragmaDamping::new(1, 0.5.into(), 0.3.into());
+    manual_circuit += operations::PragmaDephasing::new(1, 0.5.into(), 0.1.into());
+
+    let auto_backend = Backend::from_device(Box::new(DecoheringDevice { number_qubits: 2 }), None)
+        .set_auto_idle_noise(true);
+    let manual_backend =
+        Backend::from_device(Box::new(DecoheringDevice { number_qubits: 2 }), None);
+    let auto_density_matrix = auto_backend.density_matrix(&circuit).unwrap();
+    let manual_density_matrix = manual_backend.density_matrix(&manual_circuit).unwrap();
+    for (auto_value, manual_value) in auto_density_matrix.iter().zip(manual_density_matrix.iter()) {
+        assert!((auto_value - manual_value).norm() < 1e-10);
+    }
+
+    // Without the flag the idle qubit stays in a pure state
+    let noiseless_density_matrix = manual_backend.density_matrix(&circuit).unwrap();
+    assert!((noiseless_density_matrix[[3, 3]] - auto_density_matrix[[3, 3]]).norm() > 1e-3);
+}
+
+#[test_case(1; "one_step")]
+#[test_case(3; "three_steps")]
+#[test_case(6; "six_steps")]
+fn test_continuous_decoherence(number_steps: usize) {
+    // Qubit 1 is excited and stays idle while gates of duration 1.0 are applied to qubit 0
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateX::new(1, std::f64::consts::PI.into());
+    for _ in 0..number_steps {
+        circuit += operations::RotateX::new(0, 0.0.into());
+    }
+    let backend = Backend::from_device(Box::new(DecoheringDevice { number_qubits: 2 }), None)
+        .set_continuous_decoherence(true);
+    let density_matrix = backend.density_matrix(&circuit).unwrap();
+    let excited_population = (density_matrix[[2, 2]] + density_matrix[[3, 3]]).re;
+    // Amplitude damping with rate 0.3 during the preparation and all following gates
+    let expected = (-0.3 * (number_steps + 1) as f64).exp();
+    assert!((excited_population - expected).abs() < 1e-10);
+
+    // Without the flag and without a device the qubit does not decay
+    let density_matrix =
+        Backend::from_device(Box::new(DecoheringDevice { number_qubits: 2 }), None)
+            .density_matrix(&circuit)
+            .unwrap();
+    assert!((density_matrix[[2, 2]].re - 1.0).abs() < 1e-10);
+    let density_matrix = Backend::new(2)
+        .set_continuous_decoherence(true)
+        .density_matrix(&circuit)
+        .unwrap();
+    assert!((density_matrix[[2, 2]].re - 1.0).abs() < 1e-10);
+}
+
+// A device with a damping rate that is recalibrated to the rate in percent wrapped in a PragmaChangeDevice
+struct RecalibratedDevice {
+    damping_rate: f64,
+}
+
+impl Device for RecalibratedDevice {
+    fn single_qubit_gate_time(&self, hqslang: &str, qubit: &usize) -> Option<f64> {
+        (hqslang == "RotateX" && *qubit < 2).then(|| 1.0)
+    }
+
+    fn two_qubit_gate_time(
+        &self,
+        _hqslang: &str,
+        _control: &usize,
+        _target: &usize,
+    ) -> Option<f64> {
+        None
+    }
+
+    fn multi_qubit_gate_time(&self, _hqslang: &str, _qubits: &[usize]) -> Option<f64> {
+        None
+    }
+
+    fn qubit_decoherence_rates(&self, qubit: &usize) -> Option<Array2<f64>> {
+        (*qubit < 2).then(|| {
+            array![
+                [0.0, 0.0, 0.0],
+                [0.0, self.damping_rate, 0.0],
+                [0.0, 0.0, 0.0]
+            ]
+        })
+    }
+
+    fn number_qubits(&self) -> usize {
+        2
+    }
+
+    fn two_qubit_edges(&self) -> Vec<(usize, usize)> {
+        vec![(0, 1)]
+    }
+
+    fn change_device(&mut self, hqslang: &str, operation: &[u8]) -> Result<(), RoqoqoBackendError> {
+        if hqslang != "PragmaRecalibrate" {
+            return Err(RoqoqoBackendError::GenericError {
+                msg: format!("Can not change device with {}", hqslang),
+            });
+        }
+        self.damping_rate = operation[0] as f64 / 100.0;
+        Ok(())
+    }
+}
+
+#[test_case(false; "continuous_decoherence")]
+#[test_case(true; "auto_idle_noise")]
+fn test_change_device_noise(idle_blocks: bool) {
+    // Qubit 1 is excited and stays idle for two periods of duration 2.0,
+    // the device is recalibrated from a damping rate of 0.1 to 0.4 between the periods
+    let idle_period = |circuit: &mut Circuit| {
+        for _ in 0..2 {
+            *circuit += operations::RotateX::new(0, 0.0.into());
+            if idle_blocks {
+                *circuit += operations::PragmaStopParallelBlock::new(vec![0], 1.0.into());
+            }
+        }
+    };
+    let mut circuit = Circuit::new();
+    circuit += operations::RotateX::new(1, std::f64::consts::PI.into());
+    idle_period(&mut circuit);
+    circuit += operations::PragmaChangeDevice {
+        wrapped_tags: vec![],
+        wrapped_hqslang: "PragmaRecalibrate".to_string(),
+        wrapped_operation: vec![40],
+    };
+    idle_period(&mut circuit);
+
+    let backend = Backend::from_device(Box::new(RecalibratedDevice { damping_rate: 0.1 }), None);
+    let backend = if idle_blocks {
+        backend.set_auto_idle_noise(true)
+    } else {
+        backend.set_continuous_decoherence(true)
+    };
+    let density_matrix = backend.density_matrix(&circuit).unwrap();
+    let excited_population = (density_matrix[[2, 2]] + density_matrix[[3, 3]]).re;
+    // With continuous decoherence qubit 1 is also damped during its own preparation
+    let initial_time: f64 = if idle_blocks { 2.0 } else { 3.0 };
+    let expected = (-0.1 * initial_time - 0.4 * 2.0).exp();
+    assert!((excited_population - expected).abs() < 1e-10);
+    // The recalibrated device damps more than the initial calibration
+    assert!(excited_population < (-0.1 * (initial_time + 2.0)).exp() - 1e-3);
+
+    let backend = Backend::from_device(Box::new(RecalibratedDevice { damping_rate: 0.1 }), None)
+        .set_continuous_decoherence(true)
+        .set_force_statevector(true);
+    assert!(backend.run_circuit(&circuit).is_err());
+}
+
+#[test]
+fn test_hilbert_schmidt_distance() {
+    let backend = Backend::new(2);
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let distance = backend
+        .hilbert_schmidt_distance(&circuit, &circuit)
+        .unwrap();
+    assert!(distance.abs() < 1e-10);
+
+    let mut orthogonal_circuit = Circuit::new();
+    orthogonal_circuit += operations::PauliX::new(0);
+    let distance = backend
+        .hilbert_schmidt_distance(&orthogonal_circuit, &Circuit::new())
+        .unwrap();
+    assert!((distance - 2.0).abs() < 1e-10);
+
+    let mut mixed_circuit = Circuit::new();
+    mixed_circuit += operations::Hadamard::new(0);
+    mixed_circuit += operations::PragmaDephasing::new(0, 1.0.into(), 100.0.into());
+    let distance = backend
+        .hilbert_schmidt_distance(&mixed_circuit, &Circuit::new())
+        .unwrap();
+    assert!((distance - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_renyi_2_entropy() {
+    let backend = Backend::new(3);
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::Hadamard::new(2);
+    // One half of a Bell pair is maximally mixed
+    let entropy = backend.renyi_2_entropy(&circuit, &[0]).unwrap();
+    assert!((entropy - 2.0_f64.ln()).abs() < 1e-10);
+    // The Bell pair and the product qubit are pure
+    assert!(backend.renyi_2_entropy(&circuit, &[0, 1]).unwrap().abs() < 1e-10);
+    assert!(backend.renyi_2_entropy(&circuit, &[2]).unwrap().abs() < 1e-10);
+    let entropy = backend.renyi_2_entropy(&circuit, &[1, 2]).unwrap();
+    assert!((entropy - 2.0_f64.ln()).abs() < 1e-10);
+
+    // A partially dephased qubit has purity (1 + e^(-2 * rate * time)) / 2
+    let mut mixed_circuit = Circuit::new();
+    mixed_circuit += operations::Hadamard::new(0);
+    mixed_circuit += operations::PragmaDephasing::new(0, 1.0.into(), 0.5.into());
+    let entropy = backend.renyi_2_entropy(&mixed_circuit, &[0]).unwrap();
+    let expected = -((1.0 + (-2.0_f64).exp()) / 2.0).ln();
+    assert!((entropy - expected).abs() < 1e-10);
+}
+
+#[test]
+fn test_state_file() {
+    let path = std::env::temp_dir().join(format!(
+        "roqoqo_quest_backend_state_{}.csv",
+        std::process::id()
+    ));
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::RotateZ::new(1, 0.4.into());
+    let backend = Backend::new(2);
+    backend.write_state_to_file(&circuit, &path).unwrap();
+    let state_vector = backend.read_state_from_file(&path).unwrap();
+
+    // The state read from the file is the final state of the circuit
+    let mut state_circuit = Circuit::new();
+    state_circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    state_circuit += circuit.clone();
+    state_circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let (_, _, complex_registers) = backend.run_circuit(&state_circuit).unwrap();
+    for (value, expected) in state_vector
+        .iter()
+        .zip(complex_registers["state"][0].iter())
+    {
+        assert!((value - expected).norm() < 1e-12);
+    }
+
+    // The number of qubits of the file and the backend must match
+    assert!(Backend::new(3).read_state_from_file(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+    let mut noisy_circuit = circuit;
+    noisy_circuit += operations::PragmaDamping::new(0, 1.0.into(), 0.1.into());
+    assert!(backend.write_state_to_file(&noisy_circuit, &path).is_err());
+}
+
+#[test]
+fn test_run_circuit_timed() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    for qubit in 0..3 {
+        circuit += operations::Hadamard::new(qubit);
+        circuit += operations::RotateZ::new(qubit, 0.3.into());
+    }
+    circuit += operations::CNOT::new(0, 1);
+    for qubit in 0..3 {
+        circuit += operations::MeasureQubit::new(qubit, "ro".to_string(), qubit);
+    }
+    let backend = Backend::new(3).set_repetitions(5);
+    let ((bit_registers, _, _), timing) = backend.run_circuit_timed(&circuit).unwrap();
+    assert_eq!(bit_registers["ro"].len(), 1);
+    assert!(timing.total > Duration::ZERO);
+    assert!(timing.gate_operations > Duration::ZERO);
+    assert!(timing.measurements > Duration::ZERO);
+    assert!(timing.gate_operations + timing.measurements <= timing.total);
+}
+
+#[test]
+fn test_output_registers() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 3, true);
+    circuit += operations::DefinitionBit::new("internal".to_string(), 2, false);
+    circuit += operations::DefinitionFloat::new("pauli".to_string(), 1, true);
+    circuit += operations::DefinitionComplex::new("state".to_string(), 8, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 10, None);
+    let backend = Backend::new(3);
+    let shapes = backend.output_registers(&circuit);
+    assert_eq!(
+        shapes,
+        RegisterShapes {
+            bit_registers: [("ro".to_string(), 3)].into_iter().collect(),
+            float_registers: [("pauli".to_string(), 1)].into_iter().collect(),
+            complex_registers: [("state".to_string(), 8)].into_iter().collect(),
+        }
+    );
+    // The reported shapes match the registers of a run
+    let (bit_registers, _, complex_registers) = backend.run_circuit(&circuit).unwrap();
+    assert_eq!(bit_registers["ro"][0].len(), 3);
+    assert_eq!(complex_registers["state"][0].len(), 8);
+    assert!(!bit_registers.contains_key("internal"));
+}
+
+#[test]
+fn test_inner_product() {
+    let backend = Backend::new(2);
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::CNOT::new(0, 1);
+    let identical = backend.inner_product(&circuit, &circuit).unwrap();
+    assert!((identical - Complex64::new(1.0, 0.0)).norm() < 1e-10);
+
+    let mut orthogonal_circuit = Circuit::new();
+    orthogonal_circuit += operations::PauliX::new(0);
+    let orthogonal = backend
+        .inner_product(&orthogonal_circuit, &Circuit::new())
+        .unwrap();
+    assert!(orthogonal.norm() < 1e-10);
+
+    // PauliY prepares i|1>, its overlap with |+> is i/sqrt(2)
+    let mut phase_circuit = Circuit::new();
+    phase_circuit += operations::Hadamard::new(0);
+    let mut y_circuit = Circuit::new();
+    y_circuit += operations::PauliY::new(0);
+    let overlap = backend.inner_product(&phase_circuit, &y_circuit).unwrap();
+    assert!((overlap - Complex64::new(0.0, 1.0 / 2.0_f64.sqrt())).norm() < 1e-10);
+
+    let mut mixed_circuit = Circuit::new();
+    mixed_circuit += operations::PragmaDephasing::new(0, 1.0.into(), 100.0.into());
+    assert!(backend.inner_product(&mixed_circuit, &circuit).is_err());
+}
+
+#[test]
+fn test_prepared_state_matches_run_circuit() {
+    // The global phase and the reordering of the decomposition block change the final state
+    let mut reordering_dictionary: HashMap<usize, usize> = HashMap::new();
+    reordering_dictionary.insert(0, 1);
+    reordering_dictionary.insert(1, 0);
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::PragmaStartDecompositionBlock::new(vec![0, 1], reordering_dictionary);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::RotateY::new(1, 0.3.into());
+    circuit += operations::PragmaStopDecompositionBlock::new(vec![0, 1]);
+    circuit += operations::PragmaGlobalPhase::new(std::f64::consts::FRAC_PI_3.into());
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+
+    let backend = Backend::new(2).set_track_global_phase(true);
+    let (_, _, complex_registers) = backend.run_circuit(&circuit).unwrap();
+    let state = &complex_registers["state"][0];
+
+    let sparse = backend.sparse_state_vector(&circuit, 0.0).unwrap();
+    assert_eq!(sparse.len(), 4);
+    for (index, amplitude) in sparse {
+        assert!((amplitude - state[index]).norm() < 1e-10);
+    }
+    let overlap = backend.inner_product(&Circuit::new(), &circuit).unwrap();
+    assert!((overlap - state[0]).norm() < 1e-10);
+}
+
+#[test]
+fn test_group_diagonal_gates() {
+    let mut circuit = Circuit::new();
+    for qubit in 0..3 {
+        circuit += operations::Hadamard::new(qubit);
+    }
+    circuit += operations::PragmaDamping::new(2, 0.1.into(), 1.0.into());
+    circuit += operations::RotateZ::new(0, 0.3.into());
+    circuit += operations::PhaseShiftState1::new(1, 0.7.into());
+    circuit += operations::ControlledPhaseShift::new(0, 2, 1.1.into());
+    circuit += operations::RotateZ::new(2, (-0.4).into());
+    circuit += operations::RotateX::new(1, 0.2.into());
+    circuit += operations::ControlledPhaseShift::new(1, 2, 0.5.into());
+    circuit += operations::PhaseShiftState1::new(0, 0.9.into());
+
+    let grouped = Backend::new(3).density_matrix(&circuit).unwrap();
+    let ungrouped = Backend::new(3)
+        .set_group_diagonal_gates(false)
+        .density_matrix(&circuit)
+        .unwrap();
+    for (grouped_value, ungrouped_value) in grouped.iter().zip(ungrouped.iter()) {
+        assert!((grouped_value - ungrouped_value).norm() < 1e-10);
+    }
+}
+
+#[test]
+fn test_precision_agrees_with_double_precision() {
+    let info = roqoqo_quest::environment_info();
+    assert!(info.precision == 1 || info.precision == 2);
+
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionComplex::new("state".to_string(), 4, true);
+    circuit += operations::RotateX::new(0, 0.3.into());
+    circuit += operations::RotateY::new(1, 0.7.into());
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    let (_, _, complex_registers) = Backend::new(2).run_circuit(&circuit).unwrap();
+    let state = &complex_registers["state"][0];
+
+    let qubit_0 = [
+        Complex64::new(0.15_f64.cos(), 0.0),
+        Complex64::new(0.0, -0.15_f64.sin()),
+    ];
+    let qubit_1 = [
+        Complex64::new(0.35_f64.cos(), 0.0),
+        Complex64::new(0.35_f64.sin(), 0.0),
+    ];
+    for (index, value) in state.iter().enumerate() {
+        let expected = qubit_0[index % 2] * qubit_1[index / 2];
+        assert!((value - expected).norm() < 1e-6);
+    }
+}
+
+#[test]
+fn test_run_measurement_with_uncertainty() {
+    use roqoqo::measurements::{PauliZProduct, PauliZProductInput};
+    let measurement = |number_measurements: usize| -> PauliZProduct {
+        let mut constant_circuit = Circuit::new();
+        constant_circuit += operations::RotateX::new(0, 1.0.into());
+        let mut circuit = Circuit::new();
+        circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+        circuit +=
+            operations::PragmaRepeatedMeasurement::new("ro".to_string(), number_measurements, None);
+        let mut input = PauliZProductInput::new(2, false);
+        let z0 = input.add_pauliz_product("ro".to_string(), vec![0]).unwrap();
+        let z1 = input.add_pauliz_product("ro".to_string(), vec![1]).unwrap();
+        input
+            .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+            .unwrap();
+        input
+            .add_linear_exp_val(
+                "z0_plus_z1".to_string(),
+                [(z0, 1.0), (z1, 1.0)].into_iter().collect(),
+            )
+            .unwrap();
+        PauliZProduct {
+            constant_circuit: Some(constant_circuit),
+            circuits: vec![circuit],
+            input,
+        }
+    };
+    let backend = Backend::new(2).set_random_seed(vec![3]);
+    let few_shots = backend
+        .run_measurement_with_uncertainty(&measurement(100))
+        .unwrap();
+    let many_shots = backend
+        .run_measurement_with_uncertainty(&measurement(10000))
+        .unwrap();
+
+    // The standard error of <Z> = cos(1) is sin(1) / sqrt(number_measurements)
+    for (results, number_measurements) in [(&few_shots, 100.0), (&many_shots, 10000.0)] {
+        let (value, uncertainty) = results["z0"];
+        let expected_uncertainty = f64::sin(1.0) / f64::sqrt(number_measurements);
+        assert!((uncertainty - expected_uncertainty).abs() < 0.2 * expected_uncertainty);
+        assert!((value - f64::cos(1.0)).abs() < 5.0 * expected_uncertainty);
+        // Qubit 1 is always measured in 0, so it does not add to the uncertainty
+        let (value_sum, uncertainty_sum) = results["z0_plus_z1"];
+        assert!((value_sum - value - 1.0).abs() < 1e-10);
+        assert!((uncertainty_sum - uncertainty).abs() < 1e-10);
+    }
+    assert!(many_shots["z0"].1 < few_shots["z0"].1 / 5.0);
+}
+
+#[test]
+fn test_run_measurement_with_uncertainty_single_shot() {
+    use roqoqo::measurements::{PauliZProduct, PauliZProductInput};
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+    let mut input = PauliZProductInput::new(1, false);
+    let z0 = input.add_pauliz_product("ro".to_string(), vec![0]).unwrap();
+    input
+        .add_linear_exp_val("z0".to_string(), [(z0, 1.0)].into_iter().collect())
+        .unwrap();
+    let measurement = PauliZProduct {
+        constant_circuit: None,
+        circuits: vec![circuit],
+        input,
+    };
+    let backend = Backend::new(1);
+    assert!(backend
+        .run_measurement_with_uncertainty(&measurement)
+        .is_err());
+}
+
+#[test]
+fn test_set_num_threads() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PauliX::new(0);
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+
+    let backend = Backend::new(1).set_num_threads(1).unwrap();
+    assert_eq!(backend.get_num_threads(), 1);
+    backend.run_circuit(&circuit).unwrap();
+    assert_eq!(roqoqo_quest::environment_info().number_threads, 1);
+
+    let backend = Backend::new(1).set_num_threads(2);
+    if roqoqo_quest::environment_info().multithreaded {
+        let backend = backend.unwrap();
+        assert_eq!(backend.get_num_threads(), 2);
+        // Configuring the backend does not change the threads of the calling thread
+        assert_eq!(roqoqo_quest::environment_info().number_threads, 1);
+        backend.run_circuit(&circuit).unwrap();
+        assert_eq!(roqoqo_quest::environment_info().number_threads, 2);
+    } else {
+        assert!(backend.is_err());
+    }
+    assert!(Backend::new(1).set_num_threads(0).is_err());
+}
+
+#[test]
+fn test_marginal_probabilities() {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::RotateX::new(2, std::f64::consts::FRAC_PI_3.into());
+    let backend = Backend::new(3);
+    let marginals = backend
+        .marginal_probabilities(&circuit, &[2, 0, 1])
+        .unwrap();
+    let expected = [0.25, 0.5, 1.0];
+    for (marginal, expected) in marginals.iter().zip(expected.iter()) {
+        assert!((marginal - expected).abs() < 1e-12);
+    }
+
+    // Noisy circuits are simulated as density matrices
+    let mut noisy_circuit = circuit.clone();
+    noisy_circuit += operations::PragmaDamping::new(1, 1.0.into(), 0.5.into());
+    let marginals = backend
+        .marginal_probabilities(&noisy_circuit, &[1])
+        .unwrap();
+    assert!((marginals[0] - (-0.5_f64).exp()).abs() < 1e-12);
+
+    assert!(backend.marginal_probabilities(&circuit, &[3]).is_err());
+}
+
+#[test_case(None; "fallback")]
+#[test_case(Some(operations::PragmaOverrotation::new("RotateX".to_string(), vec![0], 0.1, 0.1).into()); "overrotation")]
+#[test_case(Some(operations::PragmaRandomNoise::new(0, 1.0.into(), 0.1.into(), 0.1.into()).into()); "random noise")]
+fn test_simulate_noisy_number_shots(stochastic_noise: Option<operations::Operation>) {
+    // A mid-circuit measurement can not be replaced by the repeated measurement,
+    // so the circuit is repeated once for each measurement.
+    // Stochastic noise is sampled in one repetition per shot.
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("mid".to_string(), 1, true);
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    if let Some(noise) = stochastic_noise {
+        circuit += noise;
+    }
+    circuit += operations::RotateX::new(0, std::f64::consts::PI.into());
+    circuit += operations::MeasureQubit::new(1, "mid".to_string(), 0);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 1, None);
+    let repetitions = 7;
+    let (bit_registers, _, _) = Backend::new(2)
+        .simulate_noisy(
+            &circuit,
+            Box::new(DecoheringDevice { number_qubits: 2 }),
+            HashMap::new(),
+            repetitions,
+        )
+        .unwrap();
+    assert_eq!(bit_registers["ro"].len(), repetitions);
+    assert_eq!(bit_registers["mid"].len(), repetitions);
+}
+
+#[test]
+fn test_simulate_noisy() {
+    // Both qubits are excited, qubit 0 then idles for several gates on qubit 1
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::RotateX::new(0, std::f64::consts::PI.into());
+    circuit += operations::RotateX::new(1, std::f64::consts::PI.into());
+    for _ in 0..3 {
+        circuit += operations::RotateX::new(1, 0.0.into());
+    }
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 1, None);
+    let repetitions = 400;
+    let fraction_excited = |results: &Vec<Vec<bool>>, qubit: usize| -> f64 {
+        results.iter().filter(|shot| shot[qubit]).count() as f64 / results.len() as f64
+    };
+
+    let backend = Backend::new(2).set_random_seed(vec![11]);
+    let (noiseless, _, _) = backend
+        .clone()
+        .with_measurement_override(Some(repetitions))
+        .run_circuit(&circuit)
+        .unwrap();
+    assert_eq!(noiseless["ro"].len(), repetitions);
+    assert_eq!(fraction_excited(&noiseless["ro"], 0), 1.0);
+    assert_eq!(fraction_excited(&noiseless["ro"], 1), 1.0);
+
+    let (noisy, _, _) = backend
+        .simulate_noisy(
+            &circuit,
+            Box::new(DecoheringDevice { number_qubits: 2 }),
+            HashMap::new(),
+            repetitions,
+        )
+        .unwrap();
+    assert_eq!(noisy["ro"].len(), repetitions);
+    // Amplitude damping with rate 0.3 during the five gates of duration 1.0
+    let expected = (-0.3 * 5.0_f64).exp();
+    assert!((fraction_excited(&noisy["ro"], 0) - expected).abs() < 0.1);
+    assert!((fraction_excited(&noisy["ro"], 1) - expected).abs() < 0.1);
+
+    // A readout error detecting 1 as 0 lowers the excited fraction of qubit 0 further
+    let (noisy_readout, _, _) = backend
+        .simulate_noisy(
+            &circuit,
+            Box::new(DecoheringDevice { number_qubits: 2 }),
+            [(0, [[1.0, 0.0], [0.5, 0.5]])].into_iter().collect(),
+            repetitions,
+        )
+        .unwrap();
+    assert!((fraction_excited(&noisy_readout["ro"], 0) - 0.5 * expected).abs() < 0.1);
+
+    let invalid_readout = backend.simulate_noisy(
+        &circuit,
+        Box::new(DecoheringDevice { number_qubits: 2 }),
+        [(0, [[0.5, 0.6], [0.0, 1.0]])].into_iter().collect(),
+        repetitions,
+    );
+    assert!(invalid_readout.is_err());
+}
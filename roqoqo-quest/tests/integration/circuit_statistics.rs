@@ -0,0 +1,56 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use roqoqo::operations;
+use roqoqo::Circuit;
+use roqoqo_quest::{circuit_statistics, CircuitStats};
+
+#[test]
+fn test_circuit_statistics() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 4, true);
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::PauliX::new(1);
+    circuit += operations::CNOT::new(0, 1);
+    circuit += operations::RotateZ::new(3, 0.5.into());
+    circuit += operations::MultiQubitMS::new(vec![0, 1, 2], 1.0.into());
+    circuit += operations::MultiQubitMS::new(vec![0, 1, 2, 3], 1.0.into());
+    circuit += operations::PragmaDamping::new(2, 0.01.into(), 2.0.into());
+    circuit += operations::MeasureQubit::new(0, "ro".to_string(), 0);
+
+    let statistics = circuit_statistics(&circuit);
+    assert_eq!(
+        statistics,
+        CircuitStats {
+            number_single_qubit_gates: 3,
+            number_two_qubit_gates: 1,
+            number_three_qubit_gates: 1,
+            number_multi_qubit_gates: 1,
+            number_noise_pragmas: 1,
+            depth: 4,
+            number_qubits: 4,
+            is_density_matrix: true,
+        }
+    );
+}
+
+#[test]
+fn test_circuit_statistics_state_vector() {
+    let mut circuit = Circuit::new();
+    circuit += operations::Hadamard::new(0);
+    circuit += operations::Hadamard::new(5);
+    let statistics = circuit_statistics(&circuit);
+    assert_eq!(statistics.number_single_qubit_gates, 2);
+    assert_eq!(statistics.depth, 1);
+    assert_eq!(statistics.number_qubits, 6);
+    assert!(!statistics.is_density_matrix);
+}
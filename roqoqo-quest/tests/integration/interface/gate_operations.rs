@@ -129,6 +129,48 @@ fn test_single_qubit_gate(operation: operations::SingleQubitGateOperation) {
     }
 }
 
+// Native gates used for device calibration must match the roqoqo matrices exactly, including the global phase
+#[test_case(operations::SingleQubitGateOperation::from(operations::RotateX::new(0, 0.3.into()));"RotateX")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::RotateZ::new(0, 2.0.into()));"RotateZ")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::PhaseShiftState0::new(0, 3.0.into()));"PhaseShiftState0")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::PhaseShiftState1::new(0, 4.0.into()));"PhaseShiftState1")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::PhaseShiftState1::new(0, std::f64::consts::FRAC_PI_2.into()));"virtual Z")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::SGate::new(0)); "SGate")]
+#[test_case(operations::SingleQubitGateOperation::from(operations::TGate::new(0)); "TGate")]
+fn test_single_qubit_gate_exact_phase(operation: operations::SingleQubitGateOperation) {
+    let unitary_matrix = operation.unitary_matrix().unwrap();
+    for column in 0..2 {
+        let (
+            mut bit_registers,
+            mut float_registers,
+            mut complex_registers,
+            mut bit_registers_output,
+        ) = create_empty_registers();
+        let mut qureg = Qureg::new(1, false);
+        qureg.init_classical_state(column).unwrap();
+        call_operation(
+            &operation.clone().into(),
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+        let state = qureg.amplitudes_range(0, 2).unwrap();
+        for (row, check_value) in unitary_matrix.column(column).iter().enumerate() {
+            assert!(
+                is_close(state[row], *check_value),
+                "row: {}, column: {}, simulated: {} target: {}",
+                row,
+                column,
+                state[row],
+                check_value
+            );
+        }
+    }
+}
+
 #[test_case(operations::TwoQubitGateOperation::from(operations::CNOT::new(1,0)); "CNOT")]
 #[test_case(operations::TwoQubitGateOperation::from(operations::SWAP::new(1,0)); "SWAP")]
 #[test_case(operations::TwoQubitGateOperation::from(operations::FSwap::new(1,0)); "FSwap")]
@@ -216,7 +258,154 @@ fn test_two_qubit_gate(operation: operations::TwoQubitGateOperation) {
     }
 }
 
+#[test_case(operations::TwoQubitGateOperation::from(operations::Fsim::new(1,0, 0.5.into(), 1.0.into(), 0.5.into())); "Fsim")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::Fsim::new(0,1, 1e-9.into(), (-1e-9).into(), 1e-9.into())); "Fsim_near_identity")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::Fsim::new(1,0, std::f64::consts::FRAC_PI_2.into(), std::f64::consts::PI.into(), (std::f64::consts::FRAC_PI_2 - 1e-9).into())); "Fsim_near_degenerate")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(1,0, std::f64::consts::FRAC_PI_4.into())); "XY_quarter_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(1,0, std::f64::consts::FRAC_PI_2.into())); "XY_half_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(0,1, std::f64::consts::PI.into())); "XY_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(1,0, (1.5 * std::f64::consts::PI).into())); "XY_three_half_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(0,1, 1e-9.into())); "XY_near_identity")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::XY::new(0,1, (-0.7).into())); "XY_negative")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::Qsim::new(1,0, 0.5.into(), 1.0.into(), 0.5.into())); "Qsim")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::Qsim::new(0,1, 1e-9.into(), 1e-9.into(), (-1e-9).into())); "Qsim_near_identity")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::Qsim::new(1,0, (std::f64::consts::FRAC_PI_4 + 1e-9).into(), std::f64::consts::FRAC_PI_4.into(), std::f64::consts::PI.into())); "Qsim_near_degenerate")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::PMInteraction::new(1,0, 1.0.into())); "PMInteraction")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::PMInteraction::new(0,1, (-0.3).into())); "PMInteraction_reversed")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::PMInteraction::new(1,0, std::f64::consts::FRAC_PI_2.into())); "PMInteraction_half_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::ComplexPMInteraction::new(1,0, 1.0.into(), 2.0.into())); "ComplexPMInteraction")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::ComplexPMInteraction::new(1,0, 0.0.into(), (-1.0).into())); "ComplexPMInteraction_imaginary")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::ComplexPMInteraction::new(1,0, 0.0.into(), 0.0.into())); "ComplexPMInteraction_zero")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::MolmerSorensenXX::new(1,0)); "MolmerSorensenXX")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::MolmerSorensenXX::new(0,1)); "MolmerSorensenXX_reversed")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::VariableMSXX::new(1,0, 0.3.into())); "VariableMSXX")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::VariableMSXX::new(0,1, (-1.2).into())); "VariableMSXX_negative")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::VariableMSXX::new(1,0, std::f64::consts::PI.into())); "VariableMSXX_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::VariableMSXX::new(0,1, (2.0 * std::f64::consts::PI - 1e-9).into())); "VariableMSXX_near_two_pi")]
+#[test_case(operations::TwoQubitGateOperation::from(operations::VariableMSXX::new(1,0, 1e-9.into())); "VariableMSXX_near_identity")]
+fn test_fsim_qsim_precision(operation: operations::TwoQubitGateOperation) {
+    let c0: Complex64 = Complex::new(0.0, 0.0);
+    let c1: Complex64 = Complex::new(1.0, 0.0);
+    let basis_states: Vec<Array1<Complex64>> = vec![
+        array![c1, c0, c0, c0],
+        array![c0, c1, c0, c0],
+        array![c0, c0, c1, c0],
+        array![c0, c0, c0, c1],
+    ];
+    let unitary_matrix = operation.unitary_matrix().unwrap();
+    for (column, basis) in basis_states.into_iter().enumerate() {
+        // Create the readout registers
+        let (
+            mut bit_registers,
+            mut float_registers,
+            mut complex_registers,
+            mut bit_registers_output,
+        ) = create_empty_registers();
+        // Register for state_vector readout
+        complex_registers.insert("state_vec".to_string(), Vec::new());
+        // initialize with basis vector to reconstruct unitary gate
+        let mut qureg = Qureg::new(2, false);
+        let set_basis_operation: operations::Operation = PragmaSetStateVector::new(basis).into();
+        call_operation(
+            &set_basis_operation,
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+        // Apply tested operation to output
+        call_operation(
+            &operation.clone().into(),
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+        // Extract state vector
+        let extract_state_vector_operation: operations::Operation =
+            PragmaGetStateVector::new("state_vec".to_string(), None).into();
+        call_operation(
+            &extract_state_vector_operation,
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+        for (row, check_value) in unitary_matrix.column(column).into_iter().enumerate() {
+            let value = complex_registers.get("state_vec").unwrap()[row];
+            // Compare with the analytic matrix without global phase tolerance
+            if (value - check_value).norm() > 1e-13 {
+                panic!("Reconstructed matrix entry does not match analytic matrix, row: {}, column: {}, reconstructed: {} target: {} ",
+                    row, column, value, check_value)
+            }
+        }
+    }
+}
+
+#[test_case(operations::PMInteraction::new(1, 0, (-0.3).into()).into(), operations::PMInteraction::new(0, 1, (-0.3).into()).into(); "PMInteraction")]
+#[test_case(operations::ComplexPMInteraction::new(1, 0, (-0.5).into(), 0.25.into()).into(), operations::ComplexPMInteraction::new(0, 1, (-0.5).into(), 0.25.into()).into(); "ComplexPMInteraction")]
+fn test_two_qubit_gate_reversed_qubits(
+    operation: operations::Operation,
+    reversed_operation: operations::Operation,
+) {
+    // Applying the gate to swapped qubits is the same as swapping the qubits around the gate
+    let swap: operations::Operation = operations::SWAP::new(0, 1).into();
+    let c0: Complex64 = Complex::new(0.0, 0.0);
+    let c1: Complex64 = Complex::new(1.0, 0.0);
+    let basis_states: Vec<Array1<Complex64>> = vec![
+        array![c1, c0, c0, c0],
+        array![c0, c1, c0, c0],
+        array![c0, c0, c1, c0],
+        array![c0, c0, c0, c1],
+    ];
+    for basis in basis_states {
+        let run = |operations: Vec<&operations::Operation>| -> Vec<Complex64> {
+            let (
+                mut bit_registers,
+                mut float_registers,
+                mut complex_registers,
+                mut bit_registers_output,
+            ) = create_empty_registers();
+            complex_registers.insert("state_vec".to_string(), Vec::new());
+            let mut qureg = Qureg::new(2, false);
+            let set_basis_operation: operations::Operation =
+                PragmaSetStateVector::new(basis.clone()).into();
+            let extract_state_vector_operation: operations::Operation =
+                PragmaGetStateVector::new("state_vec".to_string(), None).into();
+            let mut all_operations = vec![&set_basis_operation];
+            all_operations.extend(operations);
+            all_operations.push(&extract_state_vector_operation);
+            for operation in all_operations {
+                call_operation(
+                    operation,
+                    &mut qureg,
+                    &mut bit_registers,
+                    &mut float_registers,
+                    &mut complex_registers,
+                    &mut bit_registers_output,
+                )
+                .unwrap();
+            }
+            complex_registers["state_vec"].clone()
+        };
+        let reversed = run(vec![&reversed_operation]);
+        let swapped = run(vec![&swap, &operation, &swap]);
+        for (value, check_value) in reversed.iter().zip(swapped.iter()) {
+            assert!((value - check_value).norm() < 1e-13);
+        }
+    }
+}
+
 #[test_case(operations::MultiQubitGateOperation::from(operations::MultiQubitMS::new(vec![0,1,2,3], 1.0.into())); "MultiQubitMS")]
+#[test_case(operations::MultiQubitGateOperation::from(operations::MultiQubitMS::new(vec![0,1], 1.0.into())); "MultiQubitMS_two_qubits")]
+#[test_case(operations::MultiQubitGateOperation::from(operations::MultiQubitZZ::new(vec![0,1,2,3], 1.0.into())); "MultiQubitZZ")]
+#[test_case(operations::MultiQubitGateOperation::from(operations::MultiQubitZZ::new(vec![0,1], 1.0.into())); "MultiQubitZZ_two_qubits")]
 fn test_multi_qubit_gate(operation: operations::MultiQubitGateOperation) {
     let c1: Complex64 = Complex::new(1.0, 0.0);
     let mut basis_states: Vec<Array1<Complex64>> = Vec::new();
@@ -14,6 +14,7 @@
 
 use ndarray::{array, Array1, Array2};
 use num_complex::{Complex, Complex64};
+use quest_sys::qreal;
 use roqoqo::operations::{
     self, PragmaGetDensityMatrix, PragmaGetStateVector, PragmaNoiseOperation,
     PragmaSetDensityMatrix, PragmaSetStateVector,
@@ -451,8 +452,8 @@ fn test_statevec_multiplication_quest() {
     for (test_number, density_matrix) in density_matrices.into_iter().enumerate() {
         for unitary_matrix in unitary_matrices.clone().into_iter() {
             let qureg = Qureg::new(1, true);
-            let mut reals: Vec<f64> = density_matrix.iter().map(|x| x.re).collect();
-            let mut imags: Vec<f64> = density_matrix.iter().map(|x| x.im).collect();
+            let mut reals: Vec<qreal> = density_matrix.iter().map(|x| x.re as qreal).collect();
+            let mut imags: Vec<qreal> = density_matrix.iter().map(|x| x.im as qreal).collect();
             unsafe {
                 quest_sys::initStateFromAmps(
                     qureg.quest_qureg,
@@ -465,28 +466,28 @@ fn test_statevec_multiplication_quest() {
                 // Row major version
                 real: [
                     [
-                        unitary_matrix[(0, 0)].re,
-                        unitary_matrix[(0, 1)].re,
-                        unitary_matrix[(0, 2)].re,
-                        unitary_matrix[(0, 3)].re,
+                        unitary_matrix[(0, 0)].re as qreal,
+                        unitary_matrix[(0, 1)].re as qreal,
+                        unitary_matrix[(0, 2)].re as qreal,
+                        unitary_matrix[(0, 3)].re as qreal,
                     ],
                     [
-                        unitary_matrix[(1, 0)].re,
-                        unitary_matrix[(1, 1)].re,
-                        unitary_matrix[(1, 2)].re,
-                        unitary_matrix[(1, 3)].re,
+                        unitary_matrix[(1, 0)].re as qreal,
+                        unitary_matrix[(1, 1)].re as qreal,
+                        unitary_matrix[(1, 2)].re as qreal,
+                        unitary_matrix[(1, 3)].re as qreal,
                     ],
                     [
-                        unitary_matrix[(2, 0)].re,
-                        unitary_matrix[(2, 1)].re,
-                        unitary_matrix[(2, 2)].re,
-                        unitary_matrix[(2, 3)].re,
+                        unitary_matrix[(2, 0)].re as qreal,
+                        unitary_matrix[(2, 1)].re as qreal,
+                        unitary_matrix[(2, 2)].re as qreal,
+                        unitary_matrix[(2, 3)].re as qreal,
                     ],
                     [
-                        unitary_matrix[(3, 0)].re,
-                        unitary_matrix[(3, 1)].re,
-                        unitary_matrix[(3, 2)].re,
-                        unitary_matrix[(3, 3)].re,
+                        unitary_matrix[(3, 0)].re as qreal,
+                        unitary_matrix[(3, 1)].re as qreal,
+                        unitary_matrix[(3, 2)].re as qreal,
+                        unitary_matrix[(3, 3)].re as qreal,
                     ],
                 ],
                 imag: [
@@ -506,7 +507,7 @@ fn test_statevec_multiplication_quest() {
                     unsafe {
                         comparison_matrix[(row, column)] =
                             quest_sys::getDensityAmp(qureg.quest_qureg, column as i64, row as i64)
-                                .real
+                                .real as f64
                     }
                 }
             }
@@ -781,6 +782,36 @@ fn test_set_density_matrix_error_1() {
     );
 }
 
+#[test]
+fn test_set_density_matrix_not_square() {
+    // The number of rows matches the quantum register, but there are too few entries for QuEST to read
+    let pragma = operations::PragmaSetDensityMatrix::new(array![
+        [Complex64::new(0.5, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(0.5, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(0.0, 0.0)]
+    ]);
+    let mut qureg = Qureg::new(2, true);
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) =
+        create_empty_registers();
+    let error = call_operation(
+        &pragma.into(),
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    );
+    assert_eq!(
+        error,
+        Err(RoqoqoBackendError::GenericError {
+            msg:
+                "Can not set 8 real and 8 imaginary parts on a quantum register with 16 amplitudes"
+                    .to_string()
+        })
+    );
+}
+
 #[test]
 fn test_set_density_matrix_error_2() {
     let pragma = operations::PragmaSetDensityMatrix::new(array![
@@ -834,6 +865,97 @@ fn test_set_state_vector_error() {
     );
 }
 
+#[test]
+fn test_set_state_vector_too_many_qubits() {
+    let pragma = operations::PragmaSetStateVector::new(array![
+        Complex64::new(1.0, 0.0),
+        Complex64::new(0.0, 0.0)
+    ]);
+    let mut qureg = Qureg::new(1, false);
+    // Pretend the quantum register is too large for its amplitudes to be addressed
+    qureg.quest_qureg.numQubitsRepresented = 70;
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) =
+        create_empty_registers();
+    let error = call_operation(
+        &pragma.into(),
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    );
+    assert_eq!(
+        error,
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Number of amplitudes of a quantum register with 70 qubits exceeds the addressable size".to_string()
+        })
+    );
+    assert!(qureg.number_amplitudes().is_err());
+    qureg.quest_qureg.numQubitsRepresented = 1;
+    assert_eq!(qureg.number_amplitudes(), Ok(2));
+}
+
+#[test_case(true; "is_density_matrix")]
+#[test_case(false; "is_state_vector")]
+fn test_set_state_vector_normalization_error(density: bool) {
+    let pragma = operations::PragmaSetStateVector::new(array![
+        Complex64::new(1.0, 0.0),
+        Complex64::new(1.0, 0.0)
+    ]);
+    let mut qureg = Qureg::new(1, density);
+    // Create the readout registers
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) =
+        create_empty_registers();
+    // Apply tested operation to output
+    let error = call_operation(
+        &pragma.clone().into(),
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    );
+    assert!(error.is_err());
+    assert_eq!(
+        error,
+        Err(RoqoqoBackendError::GenericError {
+            msg:
+                "Can not set state vector that is not normalized, squared norm of statevector is 2"
+                    .to_string()
+        })
+    );
+}
+
+#[test]
+fn test_set_density_matrix_normalization_error() {
+    let pragma = operations::PragmaSetDensityMatrix::new(array![
+        [Complex64::new(1.0, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)]
+    ]);
+    let mut qureg = Qureg::new(1, true);
+    // Create the readout registers
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) =
+        create_empty_registers();
+    // Apply tested operation to output
+    let error = call_operation(
+        &pragma.clone().into(),
+        &mut qureg,
+        &mut bit_registers,
+        &mut float_registers,
+        &mut complex_registers,
+        &mut bit_registers_output,
+    );
+    assert!(error.is_err());
+    assert_eq!(
+        error,
+        Err(RoqoqoBackendError::GenericError {
+            msg:
+                "Can not set density matrix that is not normalized, trace of density matrix is 2+0i"
+                    .to_string()
+        })
+    );
+}
+
 #[test]
 fn test_get_state_vector_error() {
     let pragma = operations::PragmaGetStateVector::new("ro".into(), None);
@@ -862,3 +984,32 @@ fn test_get_state_vector_error() {
 fn is_close(a: Complex64, b: Complex64) -> bool {
     (a - b).norm() < 1e-10
 }
+
+#[test_case(3, 1.0; "odd repetitions")]
+#[test_case(2, 0.0; "even repetitions")]
+#[test_case(0, 0.0; "zero repetitions")]
+fn test_pragma_repeat_gate(repetitions: usize, probability_one: f64) {
+    let (mut bit_registers, mut float_registers, mut complex_registers, mut bit_registers_output) =
+        create_empty_registers();
+    let mut qureg = Qureg::new(2, false);
+    let operations: Vec<operations::Operation> = vec![
+        operations::PragmaRepeatGate::new(repetitions).into(),
+        operations::PauliX::new(0).into(),
+        operations::PauliX::new(1).into(),
+    ];
+    for operation in operations.iter() {
+        call_operation(
+            operation,
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+    }
+    let probabilities = qureg.probabilites();
+    // Only the gate directly following the pragma is repeated, qubit 1 is always flipped once
+    assert!((probabilities[3] - probability_one).abs() < 1e-10);
+    assert!((probabilities[2] - (1.0 - probability_one)).abs() < 1e-10);
+}
@@ -15,3 +15,12 @@ mod interface;
 
 #[cfg(test)]
 mod backend;
+
+#[cfg(test)]
+mod quest_bindings;
+
+#[cfg(test)]
+mod circuit_statistics;
+
+#[cfg(test)]
+mod qasm;
@@ -0,0 +1,147 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use roqoqo::backends::EvaluatingBackend;
+use roqoqo::operations;
+use roqoqo::Circuit;
+use roqoqo::RoqoqoBackendError;
+use roqoqo_quest::{circuit_to_qasm, Backend};
+use test_case::test_case;
+
+// Parses the single and two qubit gates of a QASM program back into a circuit
+fn parse_qasm_gates(qasm: &str) -> Circuit {
+    let qubit = |argument: &str| -> usize {
+        argument
+            .trim_start_matches("q[")
+            .trim_end_matches(']')
+            .parse()
+            .unwrap()
+    };
+    let mut circuit = Circuit::new();
+    for line in qasm.lines() {
+        let (gate, arguments) = line.trim_end_matches(';').split_once(' ').unwrap();
+        if gate == "creg" || gate == "measure" {
+            continue;
+        }
+        let qubits: Vec<usize> = arguments.split(',').map(qubit).collect();
+        match gate {
+            "h" => circuit += operations::Hadamard::new(qubits[0]),
+            "x" => circuit += operations::PauliX::new(qubits[0]),
+            "cx" => circuit += operations::CNOT::new(qubits[0], qubits[1]),
+            _ if gate.starts_with("rx(") => {
+                let theta: f64 = gate[3..gate.len() - 1].parse().unwrap();
+                circuit += operations::RotateX::new(qubits[0], theta.into())
+            }
+            _ => {}
+        }
+    }
+    circuit
+}
+
+#[test]
+fn test_qasm_round_trip() {
+    let mut gates = Circuit::new();
+    gates += operations::Hadamard::new(0);
+    gates += operations::CNOT::new(0, 1);
+    gates += operations::RotateX::new(2, 0.25.into());
+    gates += operations::PauliX::new(1);
+    gates += operations::RotateZ::new(0, 0.5.into());
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::DefinitionComplex::new("state".to_string(), 8, true);
+    circuit += gates.clone();
+    circuit += operations::MeasureQubit::new(1, "ro".to_string(), 1);
+
+    let qasm = circuit_to_qasm(&circuit, 3).unwrap();
+    let lines: Vec<&str> = qasm.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "OPENQASM 2.0;",
+            "include \"qelib1.inc\";",
+            "qreg q[3];",
+            "creg ro[2];",
+            "h q[0];",
+            "cx q[0],q[1];",
+            "rx(0.25) q[2];",
+            "x q[1];",
+            "h q[0];",
+            "rx(0.5) q[0];",
+            "h q[0];",
+            "measure q[1] -> ro[1];",
+        ]
+    );
+
+    // The gates parsed back from the QASM program prepare the same state as the original gates,
+    // including the global phase
+    let state_circuit = |gates: Circuit| -> Circuit {
+        let mut circuit = Circuit::new();
+        circuit += operations::DefinitionComplex::new("state".to_string(), 8, true);
+        circuit += gates;
+        circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+        circuit
+    };
+    let backend = Backend::new(3);
+    let (_, _, original_registers) = backend.run_circuit(&state_circuit(gates)).unwrap();
+    let (_, _, parsed_registers) = backend
+        .run_circuit(&state_circuit(parse_qasm_gates(&lines[4..].join("\n"))))
+        .unwrap();
+    for (a, b) in original_registers["state"][0]
+        .iter()
+        .zip(parsed_registers["state"][0].iter())
+    {
+        assert!((a - b).norm() < 1e-10);
+    }
+}
+
+#[test]
+fn test_qasm_repeated_measurement() {
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 2, true);
+    circuit += operations::PragmaRepeatedMeasurement::new("ro".to_string(), 100, None);
+    let qasm = circuit_to_qasm(&circuit, 2).unwrap();
+    assert!(qasm.ends_with("measure q[0] -> ro[0];\nmeasure q[1] -> ro[1];\n"));
+
+    let mut circuit = Circuit::new();
+    circuit += operations::DefinitionBit::new("ro".to_string(), 1, true);
+    circuit += operations::PragmaRepeatedMeasurement::new(
+        "ro".to_string(),
+        100,
+        Some([(1, 0)].into_iter().collect()),
+    );
+    let qasm = circuit_to_qasm(&circuit, 2).unwrap();
+    assert!(qasm.ends_with("creg ro[1];\nmeasure q[1] -> ro[0];\n"));
+}
+
+#[test_case(operations::PragmaDamping::new(0, 1.0.into(), 0.1.into()).into(); "noise pragma")]
+#[test_case(operations::PhaseShiftState0::new(0, 0.1.into()).into(); "global phase differs")]
+#[test_case(operations::RotateX::new(0, "theta".into()).into(); "symbolic angle")]
+#[test_case(operations::PauliX::new(2).into(); "qubit out of range")]
+fn test_qasm_error(operation: operations::Operation) {
+    let mut circuit = Circuit::new();
+    circuit += operation;
+    let result = circuit_to_qasm(&circuit, 2);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_qasm_unsupported_operation() {
+    let mut circuit = Circuit::new();
+    circuit += operations::PragmaGetStateVector::new("state".to_string(), None);
+    assert_eq!(
+        circuit_to_qasm(&circuit, 1),
+        Err(RoqoqoBackendError::OperationNotInBackend {
+            backend: "QASM",
+            hqslang: "PragmaGetStateVector",
+        })
+    );
+}
@@ -0,0 +1,932 @@
+// Copyright © 2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ndarray::{array, Array2};
+use num_complex::Complex64;
+use roqoqo::operations::{
+    ControlledPhaseShift, Hadamard, Operation, PauliX, PragmaGetDensityMatrix, RotateX, RotateY,
+    SGate, TGate, CNOT, SWAP,
+};
+use roqoqo::RoqoqoBackendError;
+use roqoqo_quest::{call_operation, Qureg};
+use std::collections::HashMap;
+use test_case::test_case;
+
+fn bell_state_qureg(number_qubits: u32) -> Qureg {
+    let operations: Vec<Operation> = vec![Hadamard::new(0).into(), CNOT::new(0, 1).into()];
+    prepare_qureg(number_qubits, true, operations)
+}
+
+fn prepare_qureg(number_qubits: u32, is_density_matrix: bool, operations: Vec<Operation>) -> Qureg {
+    let mut qureg = Qureg::new(number_qubits, is_density_matrix);
+    let mut bit_registers = HashMap::new();
+    let mut float_registers = HashMap::new();
+    let mut complex_registers = HashMap::new();
+    let mut bit_registers_output = HashMap::new();
+    for operation in operations {
+        call_operation(
+            &operation,
+            &mut qureg,
+            &mut bit_registers,
+            &mut float_registers,
+            &mut complex_registers,
+            &mut bit_registers_output,
+        )
+        .unwrap();
+    }
+    qureg
+}
+
+#[test]
+fn test_bell_state_entanglement() {
+    let qureg = bell_state_qureg(2);
+    assert!((qureg.purity().unwrap() - 1.0).abs() < 1e-10);
+    let reduced = qureg.reduced_density_matrix(&[0]).unwrap();
+    let expected = array![
+        [Complex64::new(0.5, 0.0), Complex64::new(0.0, 0.0)],
+        [Complex64::new(0.0, 0.0), Complex64::new(0.5, 0.0)]
+    ];
+    for (value, expected_value) in reduced.iter().zip(expected.iter()) {
+        assert!((value - expected_value).norm() < 1e-10);
+    }
+    assert!((qureg.von_neumann_entropy(&[0]).unwrap() - 2.0_f64.ln()).abs() < 1e-10);
+    assert!((qureg.von_neumann_entropy(&[0, 1]).unwrap()).abs() < 1e-10);
+    assert!((qureg.renyi_2_entropy(&[0]).unwrap() - 2.0_f64.ln()).abs() < 1e-10);
+    assert!((qureg.renyi_2_entropy(&[0, 1]).unwrap()).abs() < 1e-10);
+    assert!((qureg.negativity(&[1]).unwrap() - 0.5).abs() < 1e-10);
+    assert!((qureg.logarithmic_negativity(&[1]).unwrap() - 1.0).abs() < 1e-10);
+}
+
+#[test]
+fn test_product_state_entanglement() {
+    let qureg = bell_state_qureg(3);
+    // Qubit 2 is not entangled with the Bell pair
+    assert!((qureg.von_neumann_entropy(&[2]).unwrap()).abs() < 1e-10);
+    assert!((qureg.negativity(&[2]).unwrap()).abs() < 1e-10);
+    assert!((qureg.von_neumann_entropy(&[1, 2]).unwrap() - 2.0_f64.ln()).abs() < 1e-10);
+}
+
+#[test_case(&[2]; "out of range")]
+#[test_case(&[0, 0]; "duplicate")]
+fn test_invalid_subsystem(qubits: &[usize]) {
+    let qureg = bell_state_qureg(2);
+    assert!(qureg.reduced_density_matrix(qubits).is_err());
+    assert!(qureg.von_neumann_entropy(qubits).is_err());
+    assert!(qureg.renyi_2_entropy(qubits).is_err());
+    assert!(qureg.negativity(qubits).is_err());
+}
+
+#[test]
+fn test_state_vector_error() {
+    let qureg = Qureg::new(2, false);
+    assert!(qureg.purity().is_err());
+    assert!(qureg.von_neumann_entropy(&[0]).is_err());
+    assert!(qureg.negativity(&[0]).is_err());
+}
+
+#[test_case(true; "density matrix")]
+#[test_case(false; "state vector")]
+fn test_outcome_probability(is_density_matrix: bool) {
+    let qureg = prepare_qureg(1, is_density_matrix, vec![Hadamard::new(0).into()]);
+    assert!((qureg.outcome_probability(&[(0, false)]).unwrap() - 0.5).abs() < 1e-10);
+    assert!((qureg.outcome_probability(&[(0, true)]).unwrap() - 0.5).abs() < 1e-10);
+
+    let operations: Vec<Operation> = vec![Hadamard::new(0).into(), CNOT::new(0, 1).into()];
+    let qureg = prepare_qureg(2, is_density_matrix, operations);
+    assert!(
+        (qureg
+            .outcome_probability(&[(0, false), (1, false)])
+            .unwrap()
+            - 0.5)
+            .abs()
+            < 1e-10
+    );
+    assert!((qureg.outcome_probability(&[(0, false), (1, true)]).unwrap()).abs() < 1e-10);
+    assert!((qureg.outcome_probability(&[(1, true), (0, true)]).unwrap() - 0.5).abs() < 1e-10);
+    assert!((qureg.outcome_probability(&[(1, true)]).unwrap() - 0.5).abs() < 1e-10);
+}
+
+#[test]
+fn test_outcome_probability_error() {
+    let qureg = Qureg::new(2, false);
+    assert_eq!(
+        qureg.outcome_probability(&[(2, true)]),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Invalid subsystem [2] for quantum register with 2 qubits".to_string()
+        })
+    );
+    assert!(qureg.outcome_probability(&[(0, true), (0, false)]).is_err());
+}
+
+#[test_case(true; "density matrix")]
+#[test_case(false; "state vector")]
+fn test_probabilities_into(is_density_matrix: bool) {
+    let operations: Vec<Operation> = vec![Hadamard::new(0).into(), CNOT::new(0, 2).into()];
+    let qureg = prepare_qureg(3, is_density_matrix, operations);
+    let mut buffer = vec![0.0; 8];
+    qureg.probabilities_into(&mut buffer).unwrap();
+    assert_eq!(buffer, qureg.probabilites());
+    assert!((buffer[0] - 0.5).abs() < 1e-10);
+    assert!((buffer[5] - 0.5).abs() < 1e-10);
+
+    let mut wrong_buffer = vec![0.0; 4];
+    assert_eq!(
+        qureg.probabilities_into(&mut wrong_buffer),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Buffer of length 4 can not hold the 8 probabilities of the quantum register"
+                .to_string()
+        })
+    );
+}
+
+#[test]
+fn test_amplitudes_range() {
+    let operations: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        Hadamard::new(1).into(),
+        CNOT::new(1, 2).into(),
+    ];
+    let qureg = prepare_qureg(3, false, operations);
+    let full_state = qureg.amplitudes_range(0, 8).unwrap();
+    for (index, value) in full_state.iter().enumerate() {
+        let expected = if [0, 1, 6, 7].contains(&index) {
+            0.5
+        } else {
+            0.0
+        };
+        assert!((value - Complex64::new(expected, 0.0)).norm() < 1e-10);
+    }
+    assert_eq!(qureg.amplitudes_range(2, 5).unwrap(), full_state[2..7]);
+    assert_eq!(qureg.amplitudes_range(8, 0).unwrap(), vec![]);
+    assert_eq!(
+        qureg.amplitudes_range(6, 3),
+        Err(RoqoqoBackendError::GenericError {
+            msg: "Amplitude range starting at 6 with length 3 exceeds the 8 amplitudes of the quantum register".to_string()
+        })
+    );
+    assert!(qureg.amplitudes_range(usize::MAX, 2).is_err());
+    assert!(Qureg::new(1, true).amplitudes_range(0, 1).is_err());
+}
+
+#[test]
+fn test_hilbert_schmidt_distance_requires_density_matrix() {
+    let density_qureg = Qureg::new(1, true);
+    let state_qureg = Qureg::new(1, false);
+    assert!(density_qureg
+        .hilbert_schmidt_distance(&Qureg::new(1, true))
+        .is_ok());
+    assert!(density_qureg
+        .hilbert_schmidt_distance(&state_qureg)
+        .is_err());
+    assert!(density_qureg
+        .hilbert_schmidt_distance(&Qureg::new(2, true))
+        .is_err());
+}
+
+#[test]
+fn test_apply_diagonal_phases() {
+    let operations: Vec<Operation> = vec![Hadamard::new(0).into(), Hadamard::new(2).into()];
+    let mut qureg = prepare_qureg(3, false, operations);
+    let phases = [0.0, std::f64::consts::FRAC_PI_2, 0.0, std::f64::consts::PI];
+    qureg.apply_diagonal_phases(&[0, 2], &phases).unwrap();
+    let state = qureg.amplitudes_range(0, 8).unwrap();
+    let expected = [
+        Complex64::new(0.5, 0.0),
+        Complex64::new(0.0, 0.5),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.5, 0.0),
+        Complex64::new(-0.5, 0.0),
+        Complex64::new(0.0, 0.0),
+        Complex64::new(0.0, 0.0),
+    ];
+    for (value, expected_value) in state.iter().zip(expected.iter()) {
+        assert!((value - expected_value).norm() < 1e-10);
+    }
+
+    assert!(qureg.apply_diagonal_phases(&[0], &phases).is_err());
+    assert!(qureg.apply_diagonal_phases(&[0, 0], &phases).is_err());
+    assert!(qureg.apply_diagonal_phases(&[0, 3], &phases).is_err());
+}
+
+// Returns the matrix element <row|P|column> of a product of Pauli operators on consecutive qubits
+fn pauli_product_element(paulis: &str, row: usize, column: usize) -> Complex64 {
+    paulis
+        .chars()
+        .enumerate()
+        .map(|(qubit, pauli)| {
+            let (row_bit, column_bit) = ((row >> qubit) & 1, (column >> qubit) & 1);
+            match (pauli, row_bit, column_bit) {
+                ('I', r, c) | ('Z', r, c) if r != c => Complex64::new(0.0, 0.0),
+                ('I', _, _) => Complex64::new(1.0, 0.0),
+                ('Z', r, _) => Complex64::new(1.0 - 2.0 * r as f64, 0.0),
+                ('X', r, c) if r != c => Complex64::new(1.0, 0.0),
+                ('Y', 0, 1) => Complex64::new(0.0, -1.0),
+                ('Y', 1, 0) => Complex64::new(0.0, 1.0),
+                _ => Complex64::new(0.0, 0.0),
+            }
+        })
+        .product()
+}
+
+#[test_case("ZZZ"; "ZZZ")]
+#[test_case("XXX"; "XXX")]
+#[test_case("XYZ"; "XYZ")]
+#[test_case("YIX"; "YIX")]
+fn test_multi_rotate_pauli(paulis: &str) {
+    let angle = 0.7;
+    let operations: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        RotateY::new(1, 0.3.into()).into(),
+        RotateX::new(2, 1.1.into()).into(),
+    ];
+    let mut qureg = prepare_qureg(3, false, operations);
+    let initial_state = qureg.amplitudes_range(0, 8).unwrap();
+    qureg.multi_rotate_pauli(&[0, 1, 2], paulis, angle).unwrap();
+    let state = qureg.amplitudes_range(0, 8).unwrap();
+    for (row, value) in state.iter().enumerate() {
+        // exp(-i angle/2 P) = cos(angle/2) - i sin(angle/2) P
+        let expected: Complex64 = (0..8)
+            .map(|column| {
+                let identity = if row == column { 1.0 } else { 0.0 };
+                (Complex64::new((angle / 2.0).cos() * identity, 0.0)
+                    - Complex64::new(0.0, (angle / 2.0).sin())
+                        * pauli_product_element(paulis, row, column))
+                    * initial_state[column]
+            })
+            .sum();
+        assert!((value - expected).norm() < 1e-10);
+    }
+}
+
+#[test]
+fn test_multi_rotate_pauli_error() {
+    let mut qureg = Qureg::new(3, false);
+    assert!(qureg.multi_rotate_pauli(&[0, 1], "Z", 0.4).is_err());
+    assert!(qureg.multi_rotate_pauli(&[0, 1], "ZA", 0.4).is_err());
+    assert!(qureg.multi_rotate_pauli(&[0, 3], "ZZ", 0.4).is_err());
+    assert!(qureg.multi_rotate_pauli(&[0, 0], "ZZ", 0.4).is_err());
+    assert!(qureg.multi_rotate_pauli(&[], "", 0.4).is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_single_qubit_pauli_expectations(is_density_matrix: bool) {
+    // Qubit 0 in |+>, qubit 1 in the T-state T|+> and qubit 2 in |0>
+    let operations: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        Hadamard::new(1).into(),
+        TGate::new(1).into(),
+    ];
+    let qureg = prepare_qureg(3, is_density_matrix, operations);
+    let expectations = qureg.single_qubit_pauli_expectations();
+    let half_sqrt = std::f64::consts::FRAC_1_SQRT_2;
+    let expected = [
+        [1.0, 0.0, 0.0],
+        [half_sqrt, half_sqrt, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+    assert_eq!(expectations.len(), 3);
+    for (bloch_vector, expected_vector) in expectations.iter().zip(expected.iter()) {
+        for (value, expected_value) in bloch_vector.iter().zip(expected_vector.iter()) {
+            assert!((value - expected_value).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_sparse_state_vector() {
+    let basis_state = prepare_qureg(3, false, vec![]);
+    assert_eq!(
+        basis_state.sparse_state_vector(1e-10).unwrap(),
+        vec![(0, Complex64::new(1.0, 0.0))]
+    );
+
+    let superposition = prepare_qureg(3, false, vec![Hadamard::new(1).into()]);
+    let sparse = superposition.sparse_state_vector(1e-10).unwrap();
+    assert_eq!(sparse.len(), 2);
+    assert_eq!(sparse[0].0, 0);
+    assert_eq!(sparse[1].0, 2);
+    for (_, amplitude) in sparse {
+        assert!((amplitude - Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0)).norm() < 1e-12);
+    }
+
+    let density_matrix = prepare_qureg(3, true, vec![]);
+    assert!(density_matrix.sparse_state_vector(1e-10).is_err());
+}
+
+#[test_case(10.0, 5.0, 1.0; "T2 below T1")]
+#[test_case(10.0, 20.0, 3.0; "T2 equal to 2 T1")]
+#[test_case(2.0, 3.0, 0.5; "T2 between T1 and 2 T1")]
+fn test_apply_t1_t2_noise(t1: f64, t2: f64, duration: f64) {
+    let theta = 1.2;
+    let mut qureg = prepare_qureg(1, true, vec![RotateY::new(0, theta.into()).into()]);
+    let initial = qureg.reduced_density_matrix(&[0]).unwrap();
+    qureg.apply_t1_t2_noise(0, t1, t2, duration).unwrap();
+    let evolved = qureg.reduced_density_matrix(&[0]).unwrap();
+
+    // Populations decay with exp(-t/T1), coherences with exp(-t/T2)
+    let expected_population = initial[(1, 1)] * (-duration / t1).exp();
+    let expected_coherence = initial[(0, 1)] * (-duration / t2).exp();
+    assert!((evolved[(1, 1)] - expected_population).norm() < 1e-12);
+    assert!((evolved[(0, 0)] - (1.0 - expected_population)).norm() < 1e-12);
+    assert!((evolved[(0, 1)] - expected_coherence).norm() < 1e-12);
+    assert!((evolved[(1, 0)] - expected_coherence.conj()).norm() < 1e-12);
+}
+
+#[test]
+fn test_apply_t1_t2_noise_error() {
+    let mut qureg = Qureg::new(1, false);
+    assert!(qureg.apply_t1_t2_noise(0, 1.0, 1.0, 1.0).is_err());
+    let mut qureg = Qureg::new(1, true);
+    assert!(qureg.apply_t1_t2_noise(0, 1.0, 3.0, 1.0).is_err());
+    assert!(qureg.apply_t1_t2_noise(0, -1.0, 1.0, 1.0).is_err());
+    assert!(qureg.apply_t1_t2_noise(1, 1.0, 1.0, 1.0).is_err());
+}
+
+#[test]
+fn test_mix_two_qubit_kraus_map() {
+    // Correlated two-qubit dephasing rho -> (1 - p) rho + p (Z x Z) rho (Z x Z)
+    let probability = 0.2_f64;
+    let zz = ndarray::Array2::from_diag(&array![
+        Complex64::new(1.0, 0.0),
+        Complex64::new(-1.0, 0.0),
+        Complex64::new(-1.0, 0.0),
+        Complex64::new(1.0, 0.0)
+    ]);
+    let kraus_operators = vec![
+        ndarray::Array2::<Complex64>::eye(4) * Complex64::new((1.0 - probability).sqrt(), 0.0),
+        zz * Complex64::new(probability.sqrt(), 0.0),
+    ];
+    let operations: Vec<Operation> = vec![Hadamard::new(0).into(), Hadamard::new(1).into()];
+    let mut qureg = prepare_qureg(2, true, operations);
+    qureg
+        .mix_two_qubit_kraus_map(0, 1, &kraus_operators)
+        .unwrap();
+    let density_matrix = qureg.reduced_density_matrix(&[0, 1]).unwrap();
+
+    // Coherences between basis states of different ZZ parity decay with 1 - 2p
+    let parity = |index: usize| index.count_ones() % 2;
+    for row in 0..4 {
+        for column in 0..4 {
+            let expected = if parity(row) == parity(column) {
+                0.25
+            } else {
+                0.25 * (1.0 - 2.0 * probability)
+            };
+            assert!((density_matrix[(row, column)] - Complex64::new(expected, 0.0)).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_mix_two_qubit_kraus_map_error() {
+    let identity = vec![ndarray::Array2::<Complex64>::eye(4)];
+    let mut state_vector = Qureg::new(2, false);
+    assert!(state_vector
+        .mix_two_qubit_kraus_map(0, 1, &identity)
+        .is_err());
+    let mut qureg = Qureg::new(2, true);
+    assert!(qureg.mix_two_qubit_kraus_map(0, 0, &identity).is_err());
+    assert!(qureg.mix_two_qubit_kraus_map(0, 1, &[]).is_err());
+    let not_trace_preserving =
+        vec![ndarray::Array2::<Complex64>::eye(4) * Complex64::new(0.9, 0.0)];
+    assert!(qureg
+        .mix_two_qubit_kraus_map(0, 1, &not_trace_preserving)
+        .is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_apply_diagonal_op(is_density_matrix: bool) {
+    // Phases [0, pi] negate the |1> amplitude, turning |+> into |->
+    let mut qureg = prepare_qureg(1, is_density_matrix, vec![Hadamard::new(0).into()]);
+    qureg
+        .apply_diagonal_op(&[0.0, std::f64::consts::PI])
+        .unwrap();
+    let mut expected = prepare_qureg(
+        1,
+        is_density_matrix,
+        vec![RotateY::new(0, (-std::f64::consts::FRAC_PI_2).into()).into()],
+    );
+    if is_density_matrix {
+        assert!(qureg.hilbert_schmidt_distance(&expected).unwrap() < 1e-12);
+    } else {
+        let amplitudes = qureg.amplitudes_range(0, 2).unwrap();
+        let half_sqrt = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((amplitudes[0] - Complex64::new(half_sqrt, 0.0)).norm() < 1e-12);
+        assert!((amplitudes[1] - Complex64::new(-half_sqrt, 0.0)).norm() < 1e-12);
+    }
+    assert!(expected.apply_diagonal_op(&[0.0]).is_err());
+}
+
+// Returns the state vector of a quantum register
+fn state_vector(qureg: &Qureg) -> Vec<Complex64> {
+    qureg
+        .amplitudes_range(0, 1 << qureg.number_qubits())
+        .unwrap()
+}
+
+#[test_case(vec![0, 1, 2]; "all qubits")]
+#[test_case(vec![2, 0, 1]; "permuted qubits")]
+fn test_apply_qft(qubits: Vec<usize>) {
+    // Arbitrary input state
+    let preparation: Vec<Operation> = vec![
+        RotateX::new(0, 0.3.into()).into(),
+        RotateY::new(1, 1.1.into()).into(),
+        Hadamard::new(2).into(),
+        CNOT::new(2, 0).into(),
+    ];
+    let mut qureg = prepare_qureg(3, false, preparation.clone());
+    qureg.apply_qft(&qubits).unwrap();
+
+    // Hand-built QFT circuit, qubits[n] is the n-th bit of the transformed index
+    let mut qft_circuit = preparation;
+    for target in (0..3).rev() {
+        qft_circuit.push(Hadamard::new(qubits[target]).into());
+        for control in (0..target).rev() {
+            let angle = std::f64::consts::PI / (1 << (target - control)) as f64;
+            qft_circuit.push(
+                ControlledPhaseShift::new(qubits[control], qubits[target], angle.into()).into(),
+            );
+        }
+    }
+    qft_circuit.push(SWAP::new(qubits[0], qubits[2]).into());
+    let expected = prepare_qureg(3, false, qft_circuit);
+
+    // Compare up to the global phase of the gate implementations
+    let overlap: Complex64 = state_vector(&expected)
+        .iter()
+        .zip(state_vector(&qureg).iter())
+        .map(|(expected, value)| expected.conj() * value)
+        .sum();
+    assert!((overlap.norm() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_apply_qft_error() {
+    let mut qureg = Qureg::new(2, false);
+    assert!(qureg.apply_qft(&[]).is_err());
+    assert!(qureg.apply_qft(&[0, 2]).is_err());
+    assert!(qureg.apply_qft(&[1, 1]).is_err());
+}
+
+#[test]
+fn test_apply_multi_controlled_unitary() {
+    let pauli_x = array![
+        [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)],
+        [Complex64::new(1.0, 0.0), Complex64::new(0.0, 0.0)]
+    ];
+    // Explicit unitary of the 3-control NOT, flips qubit 3 when qubits 0, 1 and 2 are |1>
+    let mut expected = Array2::<Complex64>::zeros((16, 16));
+    for column in 0..16 {
+        let row = if column & 0b0111 == 0b0111 {
+            column ^ 0b1000
+        } else {
+            column
+        };
+        expected[[row, column]] = Complex64::new(1.0, 0.0);
+    }
+    for column in 0..16 {
+        let mut qureg = Qureg::new(4, false);
+        qureg.init_classical_state(column).unwrap();
+        qureg
+            .apply_multi_controlled_unitary(&[0, 1, 2], &[3], &pauli_x)
+            .unwrap();
+        let state = qureg.amplitudes_range(0, 16).unwrap();
+        for (row, value) in state.iter().enumerate() {
+            assert!((value - expected[[row, column]]).norm() < 1e-12);
+        }
+    }
+}
+
+#[test_case([0, 1], 2; "target_last")]
+#[test_case([2, 0], 1; "target_middle")]
+#[test_case([1, 2], 0; "target_first")]
+fn test_apply_doubly_controlled_hadamard(controls: [usize; 2], target: usize) {
+    let half_sqrt = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+    let hadamard = array![[half_sqrt, half_sqrt], [half_sqrt, -half_sqrt]];
+    // Explicit 8x8 unitary, a Hadamard on the target when both controls are |1>
+    let control_mask = (1 << controls[0]) | (1 << controls[1]);
+    let target_mask = 1 << target;
+    let mut expected = Array2::<Complex64>::zeros((8, 8));
+    for column in 0..8_usize {
+        if column & control_mask == control_mask {
+            let target_bit = (column & target_mask != 0) as usize;
+            for row_bit in 0..2_usize {
+                let row = (column & !target_mask) | (row_bit * target_mask);
+                expected[[row, column]] = hadamard[[row_bit, target_bit]];
+            }
+        } else {
+            expected[[column, column]] = Complex64::new(1.0, 0.0);
+        }
+    }
+    for column in 0..8 {
+        let mut qureg = Qureg::new(3, false);
+        qureg.init_classical_state(column).unwrap();
+        qureg
+            .apply_multi_controlled_unitary(&controls, &[target], &hadamard)
+            .unwrap();
+        let state = qureg.amplitudes_range(0, 8).unwrap();
+        for (row, value) in state.iter().enumerate() {
+            assert!((value - expected[[row, column]]).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_apply_multi_controlled_unitary_error() {
+    let pauli_x = array![
+        [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)],
+        [Complex64::new(1.0, 0.0), Complex64::new(0.0, 0.0)]
+    ];
+    let mut qureg = Qureg::new(3, false);
+    // Target in controls, qubit out of range, no target
+    assert!(qureg
+        .apply_multi_controlled_unitary(&[0, 1], &[1], &pauli_x)
+        .is_err());
+    assert!(qureg
+        .apply_multi_controlled_unitary(&[0, 1], &[3], &pauli_x)
+        .is_err());
+    assert!(qureg
+        .apply_multi_controlled_unitary(&[0, 1], &[], &pauli_x)
+        .is_err());
+    // Wrong size and not unitary
+    assert!(qureg
+        .apply_multi_controlled_unitary(&[0], &[1, 2], &pauli_x)
+        .is_err());
+    let not_unitary = pauli_x.mapv(|value| value * 2.0);
+    assert!(qureg
+        .apply_multi_controlled_unitary(&[0, 1], &[2], &not_unitary)
+        .is_err());
+}
+
+#[test]
+fn test_apply_unitary() {
+    let one = Complex64::new(1.0, 0.0);
+    let zero = Complex64::new(0.0, 0.0);
+    let half_sqrt = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+    let swap = array![
+        [one, zero, zero, zero],
+        [zero, zero, one, zero],
+        [zero, one, zero, zero],
+        [zero, zero, zero, one]
+    ];
+    // The first qubit is the least significant bit of the matrix indices
+    let cnot = array![
+        [one, zero, zero, zero],
+        [zero, zero, zero, one],
+        [zero, zero, one, zero],
+        [zero, one, zero, zero]
+    ];
+    let hadamard = array![[half_sqrt, half_sqrt], [half_sqrt, -half_sqrt]];
+    let cases: Vec<(Vec<usize>, Array2<Complex64>, Operation)> = vec![
+        (vec![0, 2], swap, SWAP::new(0, 2).into()),
+        (vec![2, 1], cnot, CNOT::new(2, 1).into()),
+        (vec![1], hadamard, Hadamard::new(1).into()),
+    ];
+    let preparation: Vec<Operation> = vec![
+        RotateX::new(0, 0.3.into()).into(),
+        Hadamard::new(1).into(),
+        RotateY::new(2, 1.1.into()).into(),
+        TGate::new(0).into(),
+    ];
+    for (qubits, unitary, operation) in cases {
+        let mut qureg = prepare_qureg(3, false, preparation.clone());
+        qureg.apply_unitary(&qubits, &unitary).unwrap();
+        let mut operations = preparation.clone();
+        operations.push(operation);
+        let expected = prepare_qureg(3, false, operations);
+        let state = qureg.amplitudes_range(0, 8).unwrap();
+        let expected_state = expected.amplitudes_range(0, 8).unwrap();
+        for (value, expected_value) in state.iter().zip(expected_state.iter()) {
+            assert!((value - expected_value).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_apply_unitary_error() {
+    let one = Complex64::new(1.0, 0.0);
+    let zero = Complex64::new(0.0, 0.0);
+    let pauli_x = array![[zero, one], [one, zero]];
+    let mut qureg = Qureg::new(3, false);
+    // Duplicated qubits, qubit out of range, too many or too few qubits for the matrix
+    assert!(qureg.apply_unitary(&[1, 1], &Array2::eye(4)).is_err());
+    assert!(qureg.apply_unitary(&[3], &pauli_x).is_err());
+    assert!(qureg.apply_unitary(&[0, 1], &pauli_x).is_err());
+    assert!(qureg.apply_unitary(&[], &pauli_x).is_err());
+    assert!(qureg.apply_unitary(&[0, 1, 2], &Array2::eye(8)).is_err());
+    // Not unitary
+    assert!(qureg
+        .apply_unitary(&[0], &pauli_x.mapv(|value| value * 2.0))
+        .is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_reset_to_zero(is_density_matrix: bool) {
+    let mut qureg = prepare_qureg(
+        3,
+        is_density_matrix,
+        vec![
+            Hadamard::new(0).into(),
+            CNOT::new(0, 1).into(),
+            RotateX::new(2, 0.7.into()).into(),
+        ],
+    );
+    qureg.reset_to_zero();
+    let mut expected = vec![0.0; 8];
+    expected[0] = 1.0;
+    assert_eq!(qureg.probabilites(), expected);
+}
+
+#[test_case(1.0, [0.25, 0.25, 0.25, 0.25]; "full")]
+#[test_case(0.4, [0.1, 0.1, 0.1, 0.7]; "partial")]
+#[test_case(0.0, [0.0, 0.0, 0.0, 1.0]; "none")]
+fn test_mix_global_depolarising(probability: f64, expected: [f64; 4]) {
+    let mut qureg = prepare_qureg(2, true, vec![PauliX::new(0).into(), PauliX::new(1).into()]);
+    qureg.mix_global_depolarising(probability).unwrap();
+    for (value, expected_value) in qureg.probabilites().iter().zip(expected.iter()) {
+        assert!((value - expected_value).abs() < 1e-12);
+    }
+    // Full depolarisation gives the maximally mixed state with purity 1/4
+    if probability == 1.0 {
+        assert!((qureg.purity().unwrap() - 0.25).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_mix_global_depolarising_error() {
+    assert!(Qureg::new(2, false).mix_global_depolarising(0.5).is_err());
+    assert!(Qureg::new(2, true).mix_global_depolarising(1.5).is_err());
+    assert!(Qureg::new(2, true).mix_global_depolarising(-0.1).is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_sample_qubit(is_density_matrix: bool) {
+    // Qubit 0 is in |1> with probability 1/4
+    let mut qureg = prepare_qureg(
+        2,
+        is_density_matrix,
+        vec![
+            RotateX::new(0, (2.0 * std::f64::consts::FRAC_PI_6).into()).into(),
+            PauliX::new(1).into(),
+        ],
+    );
+    qureg.set_random_seed(&[7]);
+    let probabilities = qureg.probabilites();
+    let number_samples = 4000;
+    let mut number_ones = 0;
+    for _ in 0..number_samples {
+        if qureg.sample_qubit(0).unwrap() {
+            number_ones += 1;
+        }
+        assert!(qureg.sample_qubit(1).unwrap());
+    }
+    assert!((number_ones as f64 / number_samples as f64 - 0.25).abs() < 0.03);
+    // Sampling does not collapse the state
+    assert_eq!(qureg.probabilites(), probabilities);
+    assert!(qureg.sample_qubit(2).is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_collapse_to_most_likely(is_density_matrix: bool) {
+    // Qubit 0 is in |1> with probability 1/4, qubit 1 with probability 3/4
+    let mut qureg = prepare_qureg(
+        2,
+        is_density_matrix,
+        vec![
+            RotateX::new(0, (2.0 * std::f64::consts::FRAC_PI_6).into()).into(),
+            RotateX::new(1, (4.0 * std::f64::consts::FRAC_PI_6).into()).into(),
+        ],
+    );
+    assert!(!qureg.collapse_to_most_likely(0).unwrap());
+    assert!(qureg.collapse_to_most_likely(1).unwrap());
+    let probabilities = qureg.probabilites();
+    assert!((probabilities[2] - 1.0).abs() < 1e-12);
+    assert!(qureg.collapse_to_most_likely(2).is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_snapshot_measurement(is_density_matrix: bool) {
+    let ghz_preparation: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        CNOT::new(0, 1).into(),
+        CNOT::new(1, 2).into(),
+    ];
+    let number_samples = 400;
+    let mut number_ones = 0;
+    for seed in 0..number_samples {
+        let mut qureg = prepare_qureg(3, is_density_matrix, ghz_preparation.clone());
+        qureg.set_random_seed(&[seed]);
+        let measured = qureg.snapshot_measurement().unwrap();
+        // The GHZ state only produces all-zeros or all-ones bitstrings
+        assert!(measured == vec![false; 3] || measured == vec![true; 3]);
+        if measured[0] {
+            number_ones += 1;
+        }
+        // The state collapsed onto the measured basis state
+        assert_eq!(qureg.snapshot_measurement().unwrap(), measured);
+    }
+    assert!((number_ones as f64 / number_samples as f64 - 0.5).abs() < 0.1);
+}
+
+#[test]
+fn test_promote_to_density_matrix() {
+    let mut qureg = prepare_qureg(
+        2,
+        false,
+        vec![
+            Hadamard::new(0).into(),
+            CNOT::new(0, 1).into(),
+            RotateY::new(1, 0.4.into()).into(),
+            SGate::new(0).into(),
+        ],
+    );
+    let state_vector = qureg.amplitudes_range(0, 4).unwrap();
+    qureg.promote_to_density_matrix();
+    assert!(qureg.is_density_matrix());
+    assert_eq!(qureg.number_qubits(), 2);
+
+    let mut complex_registers = HashMap::new();
+    call_operation(
+        &PragmaGetDensityMatrix::new("density_matrix".to_string(), None).into(),
+        &mut qureg,
+        &mut HashMap::new(),
+        &mut HashMap::new(),
+        &mut complex_registers,
+        &mut HashMap::new(),
+    )
+    .unwrap();
+    // The density matrix is the outer product of the state vector, flattened in row-major order
+    let density_matrix = &complex_registers["density_matrix"];
+    for row in 0..4 {
+        for column in 0..4 {
+            let expected = state_vector[row] * state_vector[column].conj();
+            assert!((density_matrix[4 * row + column] - expected).norm() < 1e-12);
+        }
+    }
+
+    // Promoting a density matrix does not change it
+    qureg.promote_to_density_matrix();
+    assert!(qureg.is_density_matrix());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_marginal_probabilities(is_density_matrix: bool) {
+    let mut qureg = Qureg::new(3, is_density_matrix);
+    qureg.init_plus_state();
+    let probabilities = qureg.probabilites();
+    let marginals = qureg.marginal_probabilities(&[0, 1, 2]).unwrap();
+    for marginal in marginals {
+        assert!((marginal - 0.5).abs() < 1e-12);
+    }
+    // The state does not collapse
+    assert_eq!(qureg.probabilites(), probabilities);
+
+    let qureg = prepare_qureg(2, is_density_matrix, vec![PauliX::new(1).into()]);
+    assert_eq!(
+        qureg.marginal_probabilities(&[1, 0]).unwrap(),
+        vec![1.0, 0.0]
+    );
+    assert!(qureg.marginal_probabilities(&[2]).is_err());
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_measure_subset_to_int(is_density_matrix: bool) {
+    let ghz_preparation: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        CNOT::new(0, 1).into(),
+        CNOT::new(1, 2).into(),
+        PauliX::new(3).into(),
+    ];
+    let number_samples = 400;
+    let mut number_ones = 0;
+    for seed in 0..number_samples {
+        let mut qureg = prepare_qureg(4, is_density_matrix, ghz_preparation.clone());
+        qureg.set_random_seed(&[seed]);
+        // The GHZ state only produces the all-zeros or the all-ones integer
+        let outcome = qureg.measure_subset_to_int(&[0, 1, 2]).unwrap();
+        assert!(outcome == 0 || outcome == 0b111);
+        if outcome == 0b111 {
+            number_ones += 1;
+        }
+        // The first qubit is the least significant bit
+        assert_eq!(
+            qureg.measure_subset_to_int(&[3, 0]).unwrap(),
+            1 + 2 * (outcome & 1)
+        );
+    }
+    assert!((number_ones as f64 / number_samples as f64 - 0.5).abs() < 0.1);
+
+    let mut qureg = Qureg::new(2, is_density_matrix);
+    assert!(qureg.measure_subset_to_int(&[2]).is_err());
+    assert!(qureg.measure_subset_to_int(&[0; 65]).is_err());
+    assert_eq!(qureg.measure_subset_to_int(&[]).unwrap(), 0);
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_debug_summary(is_density_matrix: bool) {
+    let small = Qureg::new(1, is_density_matrix);
+    let small_debug = format!("{:?}", small);
+    assert!(small_debug.contains("number_qubits: 1"));
+    assert!(small_debug.contains(&format!("is_density_matrix: {}", is_density_matrix)));
+    assert_eq!(small.is_density_matrix(), is_density_matrix);
+
+    let mut large = Qureg::new(10, is_density_matrix);
+    large.probability_buffer = vec![0.5; large.number_amplitudes().unwrap()];
+    let large_debug = format!("{:?}", large);
+    let stored_amplitudes = if is_density_matrix { 1 << 20 } else { 1 << 10 };
+    assert!(large_debug.contains(&format!("number_stored_amplitudes: {}", stored_amplitudes)));
+    // The output does not grow with the number of amplitudes
+    assert!(large_debug.len() < 250);
+    assert!(large_debug.len() <= small_debug.len() + 10);
+}
+
+#[test_case(false; "state vector")]
+#[test_case(true; "density matrix")]
+fn test_state_file_round_trip(is_density_matrix: bool) {
+    let path = std::env::temp_dir().join(format!(
+        "roqoqo_quest_state_round_trip_{}_{}.csv",
+        is_density_matrix,
+        std::process::id()
+    ));
+    let operations: Vec<Operation> = vec![
+        Hadamard::new(0).into(),
+        RotateY::new(1, 0.3.into()).into(),
+        TGate::new(0).into(),
+        CNOT::new(0, 2).into(),
+    ];
+    let qureg = prepare_qureg(3, is_density_matrix, operations);
+    qureg.write_state_to_file(&path).unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("real, imag\n"));
+    let number_lines = if is_density_matrix { 64 } else { 8 };
+    assert_eq!(content.lines().count(), number_lines + 1);
+
+    let mut loaded = Qureg::new(3, is_density_matrix);
+    loaded.read_state_from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    if is_density_matrix {
+        assert!(loaded.hilbert_schmidt_distance(&qureg).unwrap() < 1e-12);
+    } else {
+        assert!((loaded.inner_product(&qureg).unwrap().norm() - 1.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_read_state_in_quest_format() {
+    let path = std::env::temp_dir().join(format!(
+        "roqoqo_quest_state_quest_format_{}.csv",
+        std::process::id()
+    ));
+    // Format written by QuEST's reportState, with a comment line
+    std::fs::write(
+        &path,
+        "real, imag\n# comment\n0.600000000000, 0.000000000000\n0.000000000000, 0.000000000000\n0.000000000000, 0.000000000000\n0.000000000000, 0.800000000000\n",
+    )
+    .unwrap();
+    let mut qureg = Qureg::new(2, false);
+    qureg.read_state_from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let amplitudes = qureg.amplitudes_range(0, 4).unwrap();
+    assert!((amplitudes[0] - Complex64::new(0.6, 0.0)).norm() < 1e-12);
+    assert!((amplitudes[3] - Complex64::new(0.0, 0.8)).norm() < 1e-12);
+}
+
+#[test_case("real, imag\n1.0, 0.0\n0.0, 0.0\n"; "too few amplitudes")]
+#[test_case("1.0, 0.0\n0.0, 0.0\n0.0, 0.0\n0.0, 0.0\n0.0, 0.0\n"; "too many amplitudes")]
+#[test_case("1.0; 0.0\n0.0, 0.0\n0.0, 0.0\n0.0, 0.0\n"; "invalid line")]
+fn test_read_state_error(content: &str) {
+    let path = std::env::temp_dir().join(format!(
+        "roqoqo_quest_state_error_{}_{}.csv",
+        content.len(),
+        std::process::id()
+    ));
+    std::fs::write(&path, content).unwrap();
+    let mut qureg = Qureg::new(2, false);
+    let result = qureg.read_state_from_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(RoqoqoBackendError::GenericError { .. })
+    ));
+    // A missing file returns an error as well
+    assert!(qureg.read_state_from_file(&path).is_err());
+}